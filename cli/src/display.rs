@@ -0,0 +1,107 @@
+use anchor_lang::prelude::Pubkey;
+use stache_client::stache::account::{Auto, CurrentStache, SpendingLimit, TriggerType, Vault, VaultType};
+
+pub fn print_stache(address: &Pubkey, stache: &CurrentStache) {
+    println!("stache {} ({})", stache.stacheid, address);
+    println!("  keychain:     {}", stache.keychain);
+    println!("  version:      {}", stache.version);
+    println!("  vaults:       {:?}", stache.vaults);
+    println!("  automations:  {:?}", stache.autos);
+    print_limits(&stache.spending_limits);
+}
+
+pub fn print_vault(address: &Pubkey, vault: &Vault) {
+    println!("vault {}: {} ({})", vault.index, vault.name, address);
+    println!("  type:            {}", vault_type(&vault.vault_type));
+    println!("  locked:          {}{}", vault.locked,
+             if vault.locked_until > 0 { format!(" (time-locked until {})", vault.locked_until) } else { String::new() });
+    println!("  withdraw delay:  {}s", vault.withdraw_delay);
+    if !vault.mints.is_empty() {
+        println!("  mints:           {:?}", vault.mints.iter().map(|mint| mint.to_string()).collect::<Vec<_>>());
+    }
+    print_limits(&vault.spending_limits);
+    for authorized in vault.authorized_autos.iter() {
+        println!("  authorized automation: {} ({} -> {})", authorized.auto, authorized.action.from, authorized.action.to);
+    }
+    for action in vault.actions.iter() {
+        println!("  action {}: {:?}, approvals: {}, executable at: {}{}",
+                 action.action_index,
+                 action.action_type,
+                 action.approvers.len(),
+                 action.executable_at,
+                 if action.over_limit { " (over limit)" } else { "" });
+    }
+}
+
+pub fn print_auto(address: &Pubkey, auto: &Auto) {
+    println!("automation {}: {} ({})", auto.index, auto.name, address);
+    println!("  status:      {}", auto_status(auto));
+    if let Some(thread) = auto.thread {
+        println!("  thread:      {}", thread);
+    }
+    println!("  trigger:     {}", trigger(auto));
+    println!("  fired:       {} times (last at {}), executed {} times, {} moved",
+             auto.num_triggers, auto.last_fired_at, auto.num_execs, auto.total_amount);
+    if let Some(max_execs) = auto.max_execs {
+        println!("  max execs:   {}", max_execs);
+    }
+    if let Some(max_total_amount) = auto.max_total_amount {
+        println!("  max amount:  {}", max_total_amount);
+    }
+    if let Some(expires_at) = auto.expires_at {
+        println!("  expires at:  {}", expires_at);
+    }
+    for (i, action) in auto.actions.iter().enumerate() {
+        println!("  action {}: {:?} {} of {} from {}{} to {}",
+                 i,
+                 action.mode,
+                 action.amount,
+                 action.mint,
+                 action.from,
+                 action.vault.map_or(String::new(), |vault| format!(" (vault {})", vault)),
+                 action.to);
+    }
+}
+
+pub fn vault_type(vault_type: &VaultType) -> String {
+    match vault_type {
+        VaultType::Easy => "easy".to_string(),
+        VaultType::TwoSig => "2-sig".to_string(),
+        VaultType::Squads { multisig, sigs } => format!("squads ({} sigs, multisig {})", sigs, multisig),
+        VaultType::Threshold { approvers, threshold, .. } => format!("{} of {}", threshold, approvers.len()),
+    }
+}
+
+pub fn auto_status(auto: &Auto) -> &'static str {
+    match (auto.active, auto.paused, auto.thread.is_some()) {
+        (true, false, _) => "active",
+        (true, true, _) => "paused",
+        (false, _, true) => "stopped",
+        (false, _, false) => "inactive",
+    }
+}
+
+fn trigger(auto: &Auto) -> String {
+    let mut auto = auto.clone();
+    match auto.trigger_type {
+        None => "none".to_string(),
+        Some(TriggerType::Balance) => match auto.balance_trigger() {
+            Ok(trigger) => format!("balance of {} {} {}", trigger.account, if trigger.above { "above" } else { "below" }, trigger.trigger_balance),
+            Err(_) => "invalid balance trigger".to_string(),
+        },
+        Some(TriggerType::Cron) => match auto.cron_trigger() {
+            Ok(trigger) => format!("cron \"{}\"", trigger.schedule),
+            Err(_) => "invalid cron trigger".to_string(),
+        },
+        Some(TriggerType::Interval) => match auto.interval_trigger() {
+            Ok(trigger) => format!("every {}s from {}", trigger.seconds, trigger.start),
+            Err(_) => "invalid interval trigger".to_string(),
+        },
+    }
+}
+
+fn print_limits(limits: &[SpendingLimit]) {
+    for limit in limits {
+        println!("  spending limit: {} of {} per {}s ({} spent this period)", limit.amount, limit.mint, limit.period_seconds, limit.spent);
+    }
+}
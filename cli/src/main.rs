@@ -0,0 +1,685 @@
+// stache: operate a stache from the command line. amounts are always in base units (lamports, or the token's smallest
+// unit), and the signer (--keypair) has to be a key on the keychain
+
+mod display;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use anchor_spl::token::{self, spl_token::native_mint};
+use anchor_spl::token_interface::TokenAccount;
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+use stache_client::{auto_version, decode, decode_auto_v0, fetch, fetch_autos, fetch_multisig, fetch_stache, fetch_vaults, find_squads_transaction, instruction, stache_version, vault_version, StacheKeys};
+use stache_client::instruction::NftAccounts;
+use stache_client::stache::migration::clockwork_v1;
+use stache_client::stache::account::{ActionType, Approver, Auto, CurrentStache, Submitter, TransferMode, Vault, VaultAction, VaultType};
+
+#[derive(Parser)]
+#[command(name = "stache", about = "Operate a stache from the command line")]
+struct Cli {
+    /// keychain name (= the stache id)
+    #[arg(short = 'n', long)]
+    keychain: String,
+
+    /// keychain domain
+    #[arg(short, long)]
+    domain: String,
+
+    /// rpc url (defaults to the solana cli config)
+    #[arg(short, long)]
+    url: Option<String>,
+
+    /// signer keypair file (defaults to the solana cli config)
+    #[arg(short, long)]
+    keypair: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// create the stache for the keychain
+    Create,
+    /// print the stache, its vaults and its automations
+    Show,
+    /// stash tokens from the signer's ata
+    Stash { mint: Pubkey, amount: u64 },
+    /// wrap + stash sol from the signer
+    StashSol { lamports: u64 },
+    /// unstash tokens, to the signer's ata by default
+    Unstash {
+        mint: Pubkey,
+        amount: u64,
+        #[arg(long)]
+        to: Option<Pubkey>,
+    },
+    /// unwrap stashed sol back to the signer
+    UnstashSol { lamports: u64 },
+    /// migrate the stache, its vaults and its automations to the current account layouts (the signer pays for the space)
+    Migrate,
+    #[command(subcommand)]
+    Vault(VaultCommand),
+    #[command(subcommand)]
+    Auto(AutoCommand),
+}
+
+#[derive(Subcommand)]
+enum VaultCommand {
+    List,
+    Show { index: u8 },
+    Create {
+        name: String,
+        #[arg(long, value_enum, default_value_t = VaultKind::Easy)]
+        kind: VaultKind,
+        /// squads vaults: the squads multisig
+        #[arg(long, required_if_eq("kind", "squads"))]
+        multisig: Option<Pubkey>,
+        /// squads vaults: approvals needed
+        #[arg(long, required_if_eq("kind", "squads"))]
+        sigs: Option<u8>,
+        /// threshold vaults: a key that can approve (repeatable)
+        #[arg(long = "approver", required_if_eq("kind", "threshold"))]
+        approvers: Vec<Pubkey>,
+        /// threshold vaults: a key that can submit withdrawals (repeatable)
+        #[arg(long = "submitter", required_if_eq("kind", "threshold"))]
+        submitters: Vec<Pubkey>,
+        /// threshold vaults: approvals needed
+        #[arg(long, required_if_eq("kind", "threshold"))]
+        threshold: Option<u8>,
+    },
+    /// deposit tokens from the signer's ata
+    Deposit { index: u8, mint: Pubkey, amount: u64 },
+    /// withdraw tokens (to the signer's ata by default); proposes an action unless the vault doesn't need approvals
+    Withdraw {
+        index: u8,
+        mint: Pubkey,
+        amount: u64,
+        #[arg(long)]
+        to: Option<Pubkey>,
+    },
+    /// withdraw sol held by the vault account (to the signer by default)
+    WithdrawSol {
+        index: u8,
+        lamports: u64,
+        #[arg(long)]
+        to: Option<Pubkey>,
+    },
+    /// approve an action, executing it if that was the last approval it needed
+    Approve {
+        index: u8,
+        action: u8,
+        /// nft transfers of programmable nfts
+        #[arg(long)]
+        programmable: bool,
+    },
+    /// execute an approved action once its withdraw delay is up
+    Execute {
+        index: u8,
+        action: u8,
+        #[arg(long)]
+        programmable: bool,
+    },
+    Deny { index: u8, action: u8 },
+    /// put an action on a squads vault up for approval in squads (members approve + execute it there)
+    ProposeToSquads {
+        index: u8,
+        action: u8,
+        #[arg(long)]
+        programmable: bool,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum VaultKind {
+    Easy,
+    TwoSig,
+    Squads,
+    Threshold,
+}
+
+#[derive(Subcommand)]
+enum AutoCommand {
+    List,
+    Show { index: u8 },
+    Create { name: String },
+    /// fire when the token account's balance goes above (or below) a balance
+    BalanceTrigger {
+        index: u8,
+        token: Pubkey,
+        balance: u64,
+        #[arg(long)]
+        below: bool,
+    },
+    /// fire on a clockwork cron schedule (sec min hour day month weekday year)
+    CronTrigger { index: u8, schedule: String },
+    /// fire every `seconds` seconds from `start` (unix timestamp)
+    IntervalTrigger {
+        index: u8,
+        seconds: i64,
+        #[arg(long, default_value_t = 0)]
+        start: i64,
+    },
+    /// add a transfer from the stache ata (or a vault's ata) to a token account
+    AddAction {
+        index: u8,
+        mint: Pubkey,
+        to: Pubkey,
+        amount: u64,
+        #[arg(long, value_enum, default_value_t = Mode::Fixed)]
+        mode: Mode,
+        #[arg(long)]
+        from_vault: Option<u8>,
+    },
+    ClearActions { index: u8 },
+    Limits {
+        index: u8,
+        #[arg(long)]
+        max_execs: Option<u32>,
+        #[arg(long)]
+        max_total_amount: Option<u64>,
+        #[arg(long)]
+        expires_at: Option<i64>,
+    },
+    Activate { index: u8 },
+    Pause { index: u8 },
+    Resume { index: u8 },
+    Deactivate { index: u8 },
+    Destroy { index: u8 },
+    /// fire it by hand (it has to be active + not paused)
+    Fire { index: u8 },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Mode {
+    Fixed,
+    Percent,
+    SweepAbove,
+    TopUp,
+}
+
+impl From<Mode> for TransferMode {
+    fn from(mode: Mode) -> Self {
+        match mode {
+            Mode::Fixed => TransferMode::Fixed,
+            Mode::Percent => TransferMode::Percent,
+            Mode::SweepAbove => TransferMode::SweepAbove,
+            Mode::TopUp => TransferMode::TopUp,
+        }
+    }
+}
+
+struct Ctx {
+    rpc: RpcClient,
+    signer: Keypair,
+    keys: StacheKeys,
+}
+
+impl Ctx {
+    fn authority(&self) -> Pubkey {
+        self.signer.pubkey()
+    }
+
+    fn send(&self, instructions: &[Instruction]) -> Result<()> {
+        let blockhash = self.rpc.get_latest_blockhash()?;
+        let tx = Transaction::new_signed_with_payer(instructions, Some(&self.signer.pubkey()), &[&self.signer], blockhash);
+        let signature = self.rpc.send_and_confirm_transaction_with_spinner(&tx)?;
+        println!("signature: {}", signature);
+        Ok(())
+    }
+
+    // token or token-2022, whichever owns the mint
+    fn token_program(&self, mint: &Pubkey) -> Result<Pubkey> {
+        Ok(self.rpc.get_account(mint).with_context(|| format!("mint {}", mint))?.owner)
+    }
+
+    fn ata(&self, owner: &Pubkey, mint: &Pubkey) -> Result<Pubkey> {
+        Ok(get_associated_token_address_with_program_id(owner, mint, &self.token_program(mint)?))
+    }
+
+    fn vault(&self, index: u8) -> Result<Vault> {
+        fetch(&self.rpc, &self.keys.vault(index)).with_context(|| format!("vault {}", index))
+    }
+
+    fn auto(&self, index: u8) -> Result<Auto> {
+        fetch(&self.rpc, &self.keys.auto(index)).with_context(|| format!("automation {}", index))
+    }
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    let config = match solana_cli_config::CONFIG_FILE.as_ref() {
+        Some(config_file) => solana_cli_config::Config::load(config_file).unwrap_or_default(),
+        None => solana_cli_config::Config::default(),
+    };
+    let url = cli.url.unwrap_or(config.json_rpc_url);
+    let keypair = cli.keypair.unwrap_or(config.keypair_path);
+
+    let ctx = Ctx {
+        rpc: RpcClient::new_with_commitment(url, CommitmentConfig::confirmed()),
+        signer: read_keypair_file(&keypair).map_err(|e| anyhow!("couldn't read keypair {}: {}", keypair, e))?,
+        keys: StacheKeys::new(&cli.keychain, &cli.domain),
+    };
+
+    match cli.command {
+        Command::Create => ctx.send(&[instruction::create_stache(&ctx.keys, &ctx.authority())]),
+        Command::Show => show(&ctx),
+        Command::Stash { mint, amount } => {
+            let token_program = ctx.token_program(&mint)?;
+            let from = get_associated_token_address_with_program_id(&ctx.authority(), &mint, &token_program);
+            ctx.send(&[instruction::stash(&ctx.keys, &ctx.authority(), &mint, &token_program, Some(from), amount)])
+        }
+        Command::StashSol { lamports } => {
+            ctx.send(&[instruction::stash(&ctx.keys, &ctx.authority(), &native_mint::ID, &token::ID, None, lamports)])
+        }
+        Command::Unstash { mint, amount, to } => {
+            let token_program = ctx.token_program(&mint)?;
+            let to = match to {
+                Some(to) => to,
+                None => get_associated_token_address_with_program_id(&ctx.authority(), &mint, &token_program),
+            };
+            ctx.send(&[instruction::unstash(&ctx.keys, &ctx.authority(), &mint, &token_program, Some(to), false, amount)])
+        }
+        Command::UnstashSol { lamports } => {
+            // unwrapping everything just closes the stache's wsol ata
+            let stache_ata = ctx.keys.ata(&native_mint::ID, &token::ID);
+            let wrapped = fetch::<TokenAccount>(&ctx.rpc, &stache_ata).context("stache wsol ata")?.amount;
+            let partial = lamports < wrapped;
+            ctx.send(&[instruction::unstash(&ctx.keys, &ctx.authority(), &native_mint::ID, &token::ID, None, partial, lamports)])
+        }
+        Command::Migrate => migrate(&ctx),
+        Command::Vault(command) => vault(&ctx, command),
+        Command::Auto(command) => auto(&ctx, command),
+    }
+}
+
+// one transaction per account that's still in an older layout
+fn migrate(ctx: &Ctx) -> Result<()> {
+    let authority = ctx.authority();
+    let keys = &ctx.keys;
+
+    let data = ctx.rpc.get_account_data(&keys.stache).context("stache")?;
+    if stache_version(&data).context("stache")? < CurrentStache::CURRENT_VERSION {
+        println!("migrating stache {}", keys.stache);
+        ctx.send(&[instruction::migrate_stache(keys, &authority)])?;
+    }
+
+    let stache = fetch_stache(&ctx.rpc, keys).context("stache")?;
+    for index in &stache.vaults {
+        let data = ctx.rpc.get_account_data(&keys.vault(*index)).with_context(|| format!("vault {}", index))?;
+        if vault_version(&data).with_context(|| format!("vault {}", index))? < Vault::CURRENT_VERSION {
+            println!("migrating vault {}", index);
+            ctx.send(&[instruction::migrate_vault(keys, &authority, *index)])?;
+        }
+    }
+    for index in &stache.autos {
+        let data = ctx.rpc.get_account_data(&keys.auto(*index)).with_context(|| format!("automation {}", index))?;
+        if auto_version(&data).with_context(|| format!("automation {}", index))? < Auto::CURRENT_VERSION {
+            let auto = decode_auto_v0(&data)?;
+            let from_token = auto.transfer_action().map_err(|e| anyhow!("automation {}: {}", index, e))?.map(|transfer| transfer.from);
+            // the old thread goes through whichever clockwork program owns it (1.x for threads from before clockwork
+            // 2.0). one that's already gone gets skipped, so the program doesn't matter then
+            let thread = auto.thread.map(|thread| {
+                let owner = ctx.rpc.get_account(&thread).map(|account| account.owner).unwrap_or(clockwork_v1::ID);
+                (thread, owner)
+            });
+            println!("migrating automation {}", index);
+            ctx.send(&[instruction::migrate_auto(keys, &authority, *index, from_token, thread)])?;
+            if thread.is_some() {
+                println!("automation {}'s thread was deleted, activate it again", index);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn show(ctx: &Ctx) -> Result<()> {
+    let stache = fetch_stache(&ctx.rpc, &ctx.keys).context("stache")?;
+    display::print_stache(&ctx.keys.stache, &stache);
+    for (address, vault) in fetch_vaults(&ctx.rpc, &ctx.keys, &stache)? {
+        println!();
+        display::print_vault(&address, &vault);
+    }
+    for (address, auto) in fetch_autos(&ctx.rpc, &ctx.keys, &stache)? {
+        println!();
+        display::print_auto(&address, &auto);
+    }
+    Ok(())
+}
+
+fn vault(ctx: &Ctx, command: VaultCommand) -> Result<()> {
+    let authority = ctx.authority();
+    match command {
+        VaultCommand::List => {
+            let stache = fetch_stache(&ctx.rpc, &ctx.keys).context("stache")?;
+            for (address, vault) in fetch_vaults(&ctx.rpc, &ctx.keys, &stache)? {
+                println!("{:>3}  {:<32}  {}  {}", vault.index, vault.name, display::vault_type(&vault.vault_type), address);
+            }
+            Ok(())
+        }
+        VaultCommand::Show { index } => {
+            display::print_vault(&ctx.keys.vault(index), &ctx.vault(index)?);
+            Ok(())
+        }
+        VaultCommand::Create { name, kind, multisig, sigs, approvers, submitters, threshold } => {
+            let vault_type = vault_type(kind, multisig, sigs, approvers, submitters, threshold)?;
+            let stache = fetch_stache(&ctx.rpc, &ctx.keys).context("stache")?;
+            println!("creating vault {} at {}", stache.next_vault_index, ctx.keys.vault(stache.next_vault_index));
+            ctx.send(&[instruction::create_vault(&ctx.keys, &authority, stache.next_vault_index, &name, vault_type)])
+        }
+        VaultCommand::Deposit { index, mint, amount } => {
+            let token_program = ctx.token_program(&mint)?;
+            let from = get_associated_token_address_with_program_id(&authority, &mint, &token_program);
+            ctx.send(&[instruction::deposit_to_vault(&ctx.keys, &authority, index, &mint, &token_program, &from, amount)])
+        }
+        VaultCommand::Withdraw { index, mint, amount, to } => {
+            let token_program = ctx.token_program(&mint)?;
+            let to = match to {
+                Some(to) => to,
+                None => get_associated_token_address_with_program_id(&authority, &mint, &token_program),
+            };
+            let multisig = multisig(&ctx.vault(index)?);
+            ctx.send(&[instruction::withdraw_from_vault(&ctx.keys, &authority, index, &mint, &token_program, &to, multisig, amount)])
+        }
+        VaultCommand::WithdrawSol { index, lamports, to } => {
+            let multisig = multisig(&ctx.vault(index)?);
+            ctx.send(&[instruction::withdraw_sol_from_vault(&ctx.keys, &authority, index, &to.unwrap_or(authority), multisig, lamports)])
+        }
+        VaultCommand::Approve { index, action, programmable } => {
+            let vault = ctx.vault(index)?;
+            let (remaining, token_program) = action_accounts(ctx, index, &vault, action, programmable)?;
+            ctx.send(&[instruction::approve_action(&ctx.keys, &authority, index, action, &token_program, multisig(&vault), remaining)])
+        }
+        VaultCommand::Execute { index, action, programmable } => {
+            let vault = ctx.vault(index)?;
+            let (remaining, token_program) = action_accounts(ctx, index, &vault, action, programmable)?;
+            ctx.send(&[instruction::execute_action(&ctx.keys, &authority, index, action, &token_program, multisig(&vault), remaining)])
+        }
+        VaultCommand::Deny { index, action } => {
+            let multisig = multisig(&ctx.vault(index)?);
+            ctx.send(&[instruction::deny_action(&ctx.keys, &authority, index, action, multisig)])
+        }
+        VaultCommand::ProposeToSquads { index, action, programmable } => {
+            let vault = ctx.vault(index)?;
+            let multisig = multisig(&vault).ok_or(anyhow!("vault {} isn't a squads vault", index))?;
+            let ms = fetch_multisig(&ctx.rpc, &multisig).context("multisig")?;
+            let (remaining, token_program) = action_accounts(ctx, index, &vault, action, programmable)?;
+            let transaction_index = ms.transaction_index + 1;
+            ctx.send(&[instruction::propose_to_squads(&ctx.keys, &authority, index, action, &multisig, transaction_index, &token_program, remaining)])?;
+            println!("squads transaction: {}", find_squads_transaction(&multisig, transaction_index));
+            Ok(())
+        }
+    }
+}
+
+// clap already insists on the args each kind needs, this just puts them together
+fn vault_type(kind: VaultKind,
+              multisig: Option<Pubkey>,
+              sigs: Option<u8>,
+              approvers: Vec<Pubkey>,
+              submitters: Vec<Pubkey>,
+              threshold: Option<u8>) -> Result<VaultType> {
+    Ok(match kind {
+        VaultKind::Easy => VaultType::Easy,
+        VaultKind::TwoSig => VaultType::TwoSig,
+        VaultKind::Squads => VaultType::Squads {
+            multisig: multisig.ok_or(anyhow!("--multisig is required"))?,
+            sigs: sigs.ok_or(anyhow!("--sigs is required"))?,
+        },
+        VaultKind::Threshold => VaultType::Threshold {
+            approvers: approvers.into_iter().map(|key| Approver { key, enabled: true }).collect(),
+            submitters: submitters.into_iter().map(|key| Submitter { key, enabled: true }).collect(),
+            threshold: threshold.ok_or(anyhow!("--threshold is required"))?,
+        },
+    })
+}
+
+fn multisig(vault: &Vault) -> Option<Pubkey> {
+    match &vault.vault_type {
+        VaultType::Squads { multisig, .. } => Some(*multisig),
+        _ => None,
+    }
+}
+
+// what executing the action needs passed in, and the token program it goes through
+fn action_accounts(ctx: &Ctx, index: u8, vault: &Vault, action_index: u8, programmable: bool) -> Result<(Vec<AccountMeta>, Pubkey)> {
+    let mut action: VaultAction = vault.actions.iter()
+        .find(|action| action.action_index == action_index)
+        .cloned()
+        .ok_or(anyhow!("vault {} has no action {}", index, action_index))?;
+
+    match action.action_type {
+        ActionType::Transfer => {
+            let transfer = action.transfer_action()?;
+            let from = ctx.rpc.get_account(&transfer.from).context("vault token account")?;
+            let from_token: TokenAccount = decode(&from.data)?;
+            Ok((instruction::transfer_action_accounts(&transfer.from, &transfer.to, &from_token.mint), from.owner))
+        }
+        ActionType::SolTransfer => {
+            let transfer = action.sol_transfer_action()?;
+            Ok((instruction::sol_transfer_action_accounts(&transfer.to), token::ID))
+        }
+        ActionType::NftTransfer => {
+            let transfer = action.nft_transfer_action()?;
+            let token_program = ctx.token_program(&transfer.mint)?;
+            let vault_token = ctx.keys.vault_ata(index, &transfer.mint, &token_program);
+            let destination_token = ctx.ata(&transfer.to, &transfer.mint)?;
+            let nft = NftAccounts::new(&transfer.mint, &vault_token, &destination_token, programmable);
+            Ok((nft.action_accounts(&vault_token, &destination_token, &transfer.to), token_program))
+        }
+        // the proof has to come from a das api (getAssetProof), which the cli doesn't talk to
+        ActionType::CnftTransfer => Err(anyhow!("cnft actions need the asset's proof, see stache_client::instruction::CnftAccounts")),
+        ActionType::SetDelay | ActionType::Unlock | ActionType::AuthorizeAuto | ActionType::LockUntil => Ok((Vec::new(), token::ID)),
+    }
+}
+
+fn auto(ctx: &Ctx, command: AutoCommand) -> Result<()> {
+    let authority = ctx.authority();
+    let keys = &ctx.keys;
+    match command {
+        AutoCommand::List => {
+            let stache = fetch_stache(&ctx.rpc, keys).context("stache")?;
+            for (address, auto) in fetch_autos(&ctx.rpc, keys, &stache)? {
+                println!("{:>3}  {:<32}  {}  {}", auto.index, auto.name, display::auto_status(&auto), address);
+            }
+            Ok(())
+        }
+        AutoCommand::Show { index } => {
+            display::print_auto(&keys.auto(index), &ctx.auto(index)?);
+            Ok(())
+        }
+        AutoCommand::Create { name } => {
+            let stache = fetch_stache(&ctx.rpc, keys).context("stache")?;
+            println!("creating automation {} at {}", stache.next_auto_index, keys.auto(stache.next_auto_index));
+            ctx.send(&[instruction::create_auto(keys, &authority, stache.next_auto_index, &name)])
+        }
+        AutoCommand::BalanceTrigger { index, token, balance, below } => {
+            ctx.send(&[instruction::set_auto_balance_trigger(keys, &authority, index, &token, balance, !below)])
+        }
+        AutoCommand::CronTrigger { index, schedule } => {
+            ctx.send(&[instruction::set_auto_cron_trigger(keys, &authority, index, &schedule)])
+        }
+        AutoCommand::IntervalTrigger { index, seconds, start } => {
+            ctx.send(&[instruction::set_auto_interval_trigger(keys, &authority, index, seconds, start)])
+        }
+        AutoCommand::AddAction { index, mint, to, amount, mode, from_vault } => {
+            let token_program = ctx.token_program(&mint)?;
+            ctx.send(&[instruction::add_auto_action(keys, &authority, index, from_vault, &to, &mint, &token_program, amount, mode.into())])
+        }
+        AutoCommand::ClearActions { index } => {
+            ctx.send(&[instruction::clear_auto_actions(keys, &authority, index)])
+        }
+        AutoCommand::Limits { index, max_execs, max_total_amount, expires_at } => {
+            ctx.send(&[instruction::set_auto_limits(keys, &authority, index, max_execs, max_total_amount, expires_at)])
+        }
+        AutoCommand::Activate { index } => {
+            let auto = ctx.auto(index)?;
+            ctx.send(&[instruction::activate_auto(keys, &authority, index, &auto)])
+        }
+        AutoCommand::Pause { index } => {
+            let auto = ctx.auto(index)?;
+            ctx.send(&[instruction::pause_auto(keys, &authority, index, &auto.name)])
+        }
+        AutoCommand::Resume { index } => {
+            let auto = ctx.auto(index)?;
+            ctx.send(&[instruction::resume_auto(keys, &authority, index, &auto.name)])
+        }
+        AutoCommand::Deactivate { index } => {
+            let auto = ctx.auto(index)?;
+            if auto.thread.is_none() {
+                bail!("automation {} doesn't have a thread", index);
+            }
+            ctx.send(&[instruction::deactivate_auto(keys, &authority, index, &auto.name)])
+        }
+        AutoCommand::Destroy { index } => {
+            let auto = ctx.auto(index)?;
+            let thread_name = auto.thread.map(|_| auto.name.as_str());
+            ctx.send(&[instruction::destroy_auto(keys, &authority, index, thread_name)])
+        }
+        AutoCommand::Fire { index } => {
+            let auto = ctx.auto(index)?;
+            ctx.send(&[instruction::fire_auto(keys, &authority, index, &auto)])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    fn parse(args: &[&str]) -> clap::error::Result<Command> {
+        let base = ["stache", "-n", "testuser", "-d", "testdomain"];
+        Cli::try_parse_from(base.iter().chain(args)).map(|cli| cli.command)
+    }
+
+    #[test]
+    fn verifies_the_cli() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn parses_vault_creation() {
+        let multisig = Pubkey::new_unique();
+        let (key1, key2) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        let Ok(Command::Vault(VaultCommand::Create { name, kind, multisig: None, .. })) = parse(&["vault", "create", "savings"]) else {
+            panic!("expected an easy vault");
+        };
+        assert_eq!(name, "savings");
+        assert!(kind == VaultKind::Easy);
+
+        let Ok(Command::Vault(VaultCommand::Create { kind, multisig: ms, sigs, approvers, submitters, threshold, .. })) =
+            parse(&["vault", "create", "squad", "--kind", "squads", "--multisig", &multisig.to_string(), "--sigs", "2"]) else {
+            panic!("expected a squads vault");
+        };
+        assert_eq!(vault_type(kind, ms, sigs, approvers, submitters, threshold).unwrap(), VaultType::Squads { multisig, sigs: 2 });
+
+        let Ok(Command::Vault(VaultCommand::Create { kind, multisig: ms, sigs, approvers, submitters, threshold, .. })) =
+            parse(&["vault", "create", "board", "--kind", "threshold", "--approver", &key1.to_string(), "--approver", &key2.to_string(),
+                    "--submitter", &key1.to_string(), "--threshold", "2"]) else {
+            panic!("expected a threshold vault");
+        };
+        assert_eq!(vault_type(kind, ms, sigs, approvers, submitters, threshold).unwrap(), VaultType::Threshold {
+            approvers: vec![Approver { key: key1, enabled: true }, Approver { key: key2, enabled: true }],
+            submitters: vec![Submitter { key: key1, enabled: true }],
+            threshold: 2,
+        });
+
+        // each kind's args are required
+        assert!(parse(&["vault", "create", "squad", "--kind", "squads", "--sigs", "2"]).is_err());
+        assert!(parse(&["vault", "create", "board", "--kind", "threshold", "--approver", &key1.to_string(), "--threshold", "1"]).is_err());
+        assert!(vault_type(VaultKind::Squads, None, Some(2), vec![], vec![], None).is_err());
+        assert!(parse(&["vault", "create", "savings", "--kind", "three-sig"]).is_err());
+    }
+
+    #[test]
+    fn parses_vault_actions() {
+        let mint = Pubkey::new_unique();
+        let to = Pubkey::new_unique();
+
+        let Ok(Command::Vault(VaultCommand::Withdraw { index: 1, mint: m, amount: 100, to: Some(t) })) =
+            parse(&["vault", "withdraw", "1", &mint.to_string(), "100", "--to", &to.to_string()]) else {
+            panic!("expected a withdrawal");
+        };
+        assert_eq!((m, t), (mint, to));
+        assert!(matches!(parse(&["vault", "withdraw-sol", "1", "5000"]), Ok(Command::Vault(VaultCommand::WithdrawSol { index: 1, lamports: 5000, to: None }))));
+
+        assert!(matches!(parse(&["vault", "approve", "1", "3"]), Ok(Command::Vault(VaultCommand::Approve { index: 1, action: 3, programmable: false }))));
+        assert!(matches!(parse(&["vault", "execute", "1", "3", "--programmable"]), Ok(Command::Vault(VaultCommand::Execute { index: 1, action: 3, programmable: true }))));
+        assert!(matches!(parse(&["vault", "deny", "1", "3"]), Ok(Command::Vault(VaultCommand::Deny { index: 1, action: 3 }))));
+        assert!(matches!(parse(&["vault", "propose-to-squads", "1", "3"]), Ok(Command::Vault(VaultCommand::ProposeToSquads { index: 1, action: 3, programmable: false }))));
+
+        // indexes are u8s, amounts are base units
+        assert!(parse(&["vault", "approve", "256", "3"]).is_err());
+        assert!(parse(&["vault", "withdraw", "1", &mint.to_string(), "1.5"]).is_err());
+        assert!(parse(&["vault", "withdraw", "1", "not-a-mint", "100"]).is_err());
+    }
+
+    #[test]
+    fn parses_automation_triggers() {
+        let token = Pubkey::new_unique();
+
+        let Ok(Command::Auto(AutoCommand::BalanceTrigger { index: 0, token: t, balance: 600, below })) =
+            parse(&["auto", "balance-trigger", "0", &token.to_string(), "600"]) else {
+            panic!("expected a balance trigger");
+        };
+        assert_eq!(t, token);
+        assert!(!below);
+        assert!(matches!(parse(&["auto", "balance-trigger", "0", &token.to_string(), "600", "--below"]),
+                         Ok(Command::Auto(AutoCommand::BalanceTrigger { below: true, .. }))));
+
+        let Ok(Command::Auto(AutoCommand::CronTrigger { index: 2, schedule })) = parse(&["auto", "cron-trigger", "2", "0 0 12 1 * * *"]) else {
+            panic!("expected a cron trigger");
+        };
+        assert_eq!(schedule, "0 0 12 1 * * *");
+
+        assert!(matches!(parse(&["auto", "interval-trigger", "2", "3600"]), Ok(Command::Auto(AutoCommand::IntervalTrigger { index: 2, seconds: 3600, start: 0 }))));
+        assert!(matches!(parse(&["auto", "interval-trigger", "2", "3600", "--start", "1700000000"]),
+                         Ok(Command::Auto(AutoCommand::IntervalTrigger { start: 1_700_000_000, .. }))));
+    }
+
+    #[test]
+    fn parses_automation_actions_and_limits() {
+        let mint = Pubkey::new_unique();
+        let to = Pubkey::new_unique();
+
+        let Ok(Command::Auto(AutoCommand::AddAction { index: 0, amount: 100, mode, from_vault: None, .. })) =
+            parse(&["auto", "add-action", "0", &mint.to_string(), &to.to_string(), "100"]) else {
+            panic!("expected an action");
+        };
+        assert_eq!(TransferMode::from(mode), TransferMode::Fixed);
+
+        let Ok(Command::Auto(AutoCommand::AddAction { mint: m, to: t, mode, from_vault: Some(1), .. })) =
+            parse(&["auto", "add-action", "0", &mint.to_string(), &to.to_string(), "100", "--mode", "sweep-above", "--from-vault", "1"]) else {
+            panic!("expected an action pulling from a vault");
+        };
+        assert_eq!((m, t), (mint, to));
+        assert_eq!(TransferMode::from(mode), TransferMode::SweepAbove);
+        assert_eq!(TransferMode::from(Mode::Percent), TransferMode::Percent);
+        assert_eq!(TransferMode::from(Mode::TopUp), TransferMode::TopUp);
+        assert!(parse(&["auto", "add-action", "0", &mint.to_string(), &to.to_string(), "100", "--mode", "all"]).is_err());
+
+        assert!(matches!(parse(&["auto", "limits", "0", "--max-execs", "5", "--expires-at", "1700000000"]),
+                         Ok(Command::Auto(AutoCommand::Limits { index: 0, max_execs: Some(5), max_total_amount: None, expires_at: Some(1_700_000_000) }))));
+        assert!(matches!(parse(&["auto", "limits", "0"]),
+                         Ok(Command::Auto(AutoCommand::Limits { max_execs: None, max_total_amount: None, expires_at: None, .. }))));
+    }
+
+    #[test]
+    fn parses_automation_lifecycle() {
+        assert!(matches!(parse(&["auto", "activate", "3"]), Ok(Command::Auto(AutoCommand::Activate { index: 3 }))));
+        assert!(matches!(parse(&["auto", "pause", "3"]), Ok(Command::Auto(AutoCommand::Pause { index: 3 }))));
+        assert!(matches!(parse(&["auto", "resume", "3"]), Ok(Command::Auto(AutoCommand::Resume { index: 3 }))));
+        assert!(matches!(parse(&["auto", "deactivate", "3"]), Ok(Command::Auto(AutoCommand::Deactivate { index: 3 }))));
+        assert!(matches!(parse(&["auto", "destroy", "3"]), Ok(Command::Auto(AutoCommand::Destroy { index: 3 }))));
+        assert!(matches!(parse(&["auto", "fire", "3"]), Ok(Command::Auto(AutoCommand::Fire { index: 3 }))));
+        assert!(parse(&["auto", "fire"]).is_err());
+    }
+}
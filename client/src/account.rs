@@ -0,0 +1,112 @@
+use anchor_lang::{AccountDeserialize, AnchorDeserialize, prelude::Pubkey};
+use keychain::account::CurrentKeyChain;
+use solana_client::rpc_client::RpcClient;
+use stache::account::{Auto, CurrentStache, Vault};
+use stache::migration::{AutoV0, VaultV0};
+use stache::squads::Ms;
+
+use crate::error::{ClientError, Result};
+use crate::pda::StacheKeys;
+
+// decodes an account's data (including the discriminator check)
+pub fn decode<T: AccountDeserialize>(data: &[u8]) -> Result<T> {
+    let mut data = data;
+    Ok(T::try_deserialize(&mut data)?)
+}
+
+pub fn decode_stache(data: &[u8]) -> Result<CurrentStache> {
+    decode(data)
+}
+
+pub fn decode_vault(data: &[u8]) -> Result<Vault> {
+    decode(data)
+}
+
+pub fn decode_auto(data: &[u8]) -> Result<Auto> {
+    decode(data)
+}
+
+// layout versions from an account's raw data. vaults + automations only got a version (right after the stache) in v1,
+// but every v0 one was created at its v0 size
+pub fn stache_version(data: &[u8]) -> Result<u8> {
+    data.get(8).copied().ok_or(ClientError::AccountTooSmall(data.len()))
+}
+
+pub fn vault_version(data: &[u8]) -> Result<u8> {
+    sub_account_version(data, VaultV0::MAX_SIZE)
+}
+
+pub fn auto_version(data: &[u8]) -> Result<u8> {
+    sub_account_version(data, AutoV0::MAX_SIZE)
+}
+
+fn sub_account_version(data: &[u8], v0_size: usize) -> Result<u8> {
+    if data.len() == 8 + v0_size {
+        return Ok(0);
+    }
+    data.get(8 + 32).copied().ok_or(ClientError::AccountTooSmall(data.len()))
+}
+
+// a v0 automation: migrate_auto needs its transfer's from account, and its thread if it still has one
+pub fn decode_auto_v0(data: &[u8]) -> Result<AutoV0> {
+    let mut data = data.get(8..).ok_or(ClientError::AccountTooSmall(data.len()))?;
+    Ok(AutoV0::deserialize(&mut data).map_err(anchor_lang::error::Error::from)?)
+}
+
+// a squads multisig (squads accounts aren't ours, so this checks the discriminator by hand)
+pub fn decode_multisig(data: &[u8]) -> Result<Ms> {
+    if data.len() <= 8 || data[..8] != Ms::discriminator() {
+        return Err(anchor_lang::error::Error::from(anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch).into());
+    }
+    Ok(Ms::deserialize(&mut &data[8..]).map_err(anchor_lang::error::Error::from)?)
+}
+
+pub fn fetch<T: AccountDeserialize>(rpc: &RpcClient, address: &Pubkey) -> Result<T> {
+    let account = rpc.get_account_with_commitment(address, rpc.commitment())?
+        .value
+        .ok_or(ClientError::AccountNotFound(*address))?;
+    decode(&account.data)
+}
+
+pub fn fetch_stache(rpc: &RpcClient, keys: &StacheKeys) -> Result<CurrentStache> {
+    fetch(rpc, &keys.stache)
+}
+
+pub fn fetch_keychain(rpc: &RpcClient, keys: &StacheKeys) -> Result<CurrentKeyChain> {
+    fetch(rpc, &keys.keychain)
+}
+
+pub fn fetch_multisig(rpc: &RpcClient, multisig: &Pubkey) -> Result<Ms> {
+    let account = rpc.get_account_with_commitment(multisig, rpc.commitment())?
+        .value
+        .ok_or(ClientError::AccountNotFound(*multisig))?;
+    decode_multisig(&account.data)
+}
+
+pub fn fetch_vault(rpc: &RpcClient, keys: &StacheKeys, index: u8) -> Result<Vault> {
+    fetch(rpc, &keys.vault(index))
+}
+
+pub fn fetch_auto(rpc: &RpcClient, keys: &StacheKeys, index: u8) -> Result<Auto> {
+    fetch(rpc, &keys.auto(index))
+}
+
+// the stache's active vaults, w/their addresses
+pub fn fetch_vaults(rpc: &RpcClient, keys: &StacheKeys, stache: &CurrentStache) -> Result<Vec<(Pubkey, Vault)>> {
+    stache.vaults.iter()
+        .map(|index| {
+            let address = keys.vault(*index);
+            Ok((address, fetch(rpc, &address)?))
+        })
+        .collect()
+}
+
+// the stache's active automations, w/their addresses
+pub fn fetch_autos(rpc: &RpcClient, keys: &StacheKeys, stache: &CurrentStache) -> Result<Vec<(Pubkey, Auto)>> {
+    stache.autos.iter()
+        .map(|index| {
+            let address = keys.auto(*index);
+            Ok((address, fetch(rpc, &address)?))
+        })
+        .collect()
+}
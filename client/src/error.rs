@@ -0,0 +1,18 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("rpc error: {0}")]
+    Rpc(#[from] solana_client::client_error::ClientError),
+
+    #[error("couldn't decode account: {0}")]
+    Decode(#[from] anchor_lang::error::Error),
+
+    #[error("account not found: {0}")]
+    AccountNotFound(anchor_lang::prelude::Pubkey),
+
+    #[error("account data too short: {0} bytes")]
+    AccountTooSmall(usize),
+}
+
+pub type Result<T> = std::result::Result<T, ClientError>;
@@ -0,0 +1,917 @@
+// one builder per stache instruction. signers (authority/owner/cosigner) are passed in, everything derivable from the
+// keychain comes from StacheKeys. optional accounts are options, like on the program side
+
+use anchor_lang::{InstructionData, ToAccountMetas, system_program};
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::hash::hash;
+use anchor_lang::solana_program::sysvar;
+use anchor_spl::associated_token::{self, get_associated_token_address_with_program_id};
+use mpl_bubblegum::accounts::TreeConfig;
+use mpl_token_metadata::accounts::{MasterEdition, Metadata, TokenRecord};
+use stache::{accounts, instruction as ix};
+use stache::account::{Auto, TransferMode, VaultType};
+use stache::squads;
+use stache::nft::{spl_account_compression, spl_noop, CnftArgs, BUBBLEGUM_ID, TOKEN_METADATA_ID};
+
+use crate::pda::{find_squads_instruction, find_squads_transaction, find_thread, StacheKeys};
+
+fn build(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: stache::ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+fn build_with_remaining(accounts: impl ToAccountMetas, data: impl InstructionData, remaining: Vec<AccountMeta>) -> Instruction {
+    let mut instruction = build(accounts, data);
+    instruction.accounts.extend(remaining);
+    instruction
+}
+
+/////// STACHE ///////
+
+pub fn create_stache(keys: &StacheKeys, authority: &Pubkey) -> Instruction {
+    build(accounts::CreateStache {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        authority: *authority,
+        system_program: system_program::ID,
+        keychain_program: keychain::ID,
+    }, ix::CreateStache {})
+}
+
+// sweep = (stache ata, mint) for each token account to empty into the destination's ata + close
+pub fn destroy_stache(keys: &StacheKeys,
+                      authority: &Pubkey,
+                      cosigner: &Pubkey,
+                      destination: &Pubkey,
+                      token_program: &Pubkey,
+                      sweep: &[(Pubkey, Pubkey)]) -> Instruction {
+    let remaining = sweep.iter()
+        .flat_map(|(stache_ata, mint)| [
+            AccountMeta::new(*stache_ata, false),
+            AccountMeta::new(get_associated_token_address_with_program_id(destination, mint, token_program), false),
+            AccountMeta::new_readonly(*mint, false),
+        ])
+        .collect();
+
+    build_with_remaining(accounts::DestroyStache {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        authority: *authority,
+        cosigner: *cosigner,
+        destination: Some(*destination),
+        token_program: Some(*token_program),
+        system_program: system_program::ID,
+        keychain_program: keychain::ID,
+    }, ix::DestroyStache { sweep: !sweep.is_empty() }, remaining)
+}
+
+// no from token = wrap the owner's sol (native mint only)
+pub fn stash(keys: &StacheKeys, owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey, from_token: Option<Pubkey>, amount: u64) -> Instruction {
+    build(accounts::Stash {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        stache_ata: keys.ata(mint, token_program),
+        mint: *mint,
+        owner: *owner,
+        from_token,
+        system_program: system_program::ID,
+        token_program: *token_program,
+        associated_token_program: associated_token::ID,
+    }, ix::Stash { amount })
+}
+
+// lamports held by the stache account itself
+pub fn unstash_sol(keys: &StacheKeys, owner: &Pubkey, lamports: u64) -> Instruction {
+    build(accounts::UnstashSol {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        owner: *owner,
+        rent: sysvar::rent::ID,
+        system_program: system_program::ID,
+    }, ix::UnstashSol { lamports })
+}
+
+// no to token = unwrap wsol to the owner: partial unwraps go through the unwrap account, unwrapping everything
+// closes the stache ata
+pub fn unstash(keys: &StacheKeys,
+               owner: &Pubkey,
+               mint: &Pubkey,
+               token_program: &Pubkey,
+               to_token: Option<Pubkey>,
+               partial_unwrap: bool,
+               amount: u64) -> Instruction {
+    build(accounts::Unstash {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        stache_ata: keys.ata(mint, token_program),
+        mint: *mint,
+        owner: *owner,
+        to_token,
+        unwrap_token: if to_token.is_none() && partial_unwrap { Some(keys.unwrap()) } else { None },
+        system_program: system_program::ID,
+        token_program: *token_program,
+        associated_token_program: associated_token::ID,
+    }, ix::Unstash { amount })
+}
+
+/////// NFTS ///////
+
+// the metaplex accounts for moving an nft between two token accounts; programmable nfts need the token records
+#[derive(Clone, Debug)]
+pub struct NftAccounts {
+    pub mint: Pubkey,
+    pub metadata: Pubkey,
+    pub edition: Option<Pubkey>,
+    pub token_record: Option<Pubkey>,
+    pub destination_token_record: Option<Pubkey>,
+    pub authorization_rules: Option<Pubkey>,
+    pub authorization_rules_program: Option<Pubkey>,
+}
+
+impl NftAccounts {
+
+    pub fn new(mint: &Pubkey, token: &Pubkey, destination_token: &Pubkey, programmable: bool) -> Self {
+        NftAccounts {
+            mint: *mint,
+            metadata: Metadata::find_pda(mint).0,
+            edition: Some(MasterEdition::find_pda(mint).0),
+            token_record: programmable.then(|| TokenRecord::find_pda(mint, token).0),
+            destination_token_record: programmable.then(|| TokenRecord::find_pda(mint, destination_token).0),
+            authorization_rules: None,
+            authorization_rules_program: None,
+        }
+    }
+
+    // the accounts the nft instructions share
+    pub fn metaplex(&self) -> accounts::MetaplexAccounts {
+        accounts::MetaplexAccounts {
+            metadata: self.metadata,
+            edition: self.edition,
+            token_record: self.token_record,
+            destination_token_record: self.destination_token_record,
+            authorization_rules: self.authorization_rules,
+            authorization_rules_program: self.authorization_rules_program,
+            token_metadata_program: TOKEN_METADATA_ID,
+            sysvar_instructions: sysvar::instructions::ID,
+            system_program: system_program::ID,
+            associated_token_program: associated_token::ID,
+        }
+    }
+
+    // remaining accounts for an nft vault action (see NftTransfer::from_remaining_accounts on the program side)
+    pub fn action_accounts(&self, vault_token: &Pubkey, destination_token: &Pubkey, destination_owner: &Pubkey) -> Vec<AccountMeta> {
+        let optional = |account: Option<Pubkey>| account.unwrap_or(TOKEN_METADATA_ID);
+        vec![
+            AccountMeta::new(*vault_token, false),
+            AccountMeta::new(*destination_token, false),
+            AccountMeta::new_readonly(*destination_owner, false),
+            AccountMeta::new_readonly(self.mint, false),
+            AccountMeta::new(self.metadata, false),
+            AccountMeta::new_readonly(optional(self.edition), false),
+            AccountMeta::new(optional(self.token_record), false),
+            AccountMeta::new(optional(self.destination_token_record), false),
+            AccountMeta::new_readonly(TOKEN_METADATA_ID, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(sysvar::instructions::ID, false),
+            AccountMeta::new_readonly(associated_token::ID, false),
+            AccountMeta::new_readonly(optional(self.authorization_rules_program), false),
+            AccountMeta::new_readonly(optional(self.authorization_rules), false),
+        ]
+    }
+}
+
+// into the stache, or straight into one of its vaults
+pub fn stash_nft(keys: &StacheKeys, owner: &Pubkey, vault_index: Option<u8>, token_program: &Pubkey, mint: &Pubkey, programmable: bool) -> Instruction {
+    let vault = vault_index.map(|index| keys.vault(index));
+    let from_token = get_associated_token_address_with_program_id(owner, mint, token_program);
+    let destination_token = get_associated_token_address_with_program_id(&vault.unwrap_or(keys.stache), mint, token_program);
+    let nft = NftAccounts::new(mint, &from_token, &destination_token, programmable);
+
+    build(accounts::StashNft {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        vault,
+        owner: *owner,
+        from_token,
+        destination_token,
+        mint: *mint,
+        metaplex: nft.metaplex(),
+        token_program: *token_program,
+    }, ix::StashNft {})
+}
+
+pub fn unstash_nft(keys: &StacheKeys, owner: &Pubkey, token_program: &Pubkey, mint: &Pubkey, programmable: bool) -> Instruction {
+    let stache_token = keys.ata(mint, token_program);
+    let destination_token = get_associated_token_address_with_program_id(owner, mint, token_program);
+    let nft = NftAccounts::new(mint, &stache_token, &destination_token, programmable);
+
+    build(accounts::UnstashNft {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        owner: *owner,
+        stache_token,
+        destination_token,
+        mint: *mint,
+        metaplex: nft.metaplex(),
+        token_program: *token_program,
+    }, ix::UnstashNft {})
+}
+
+// the bubblegum accounts for moving a compressed nft. proof = the proof nodes from the das api (getAssetProof), minus
+// whatever the tree's canopy already holds; leaf_delegate is the owner unless the cnft has been delegated
+#[derive(Clone, Debug)]
+pub struct CnftAccounts {
+    pub merkle_tree: Pubkey,
+    pub leaf_delegate: Pubkey,
+    pub proof: Vec<Pubkey>,
+}
+
+impl CnftAccounts {
+
+    pub fn new(merkle_tree: &Pubkey, leaf_delegate: &Pubkey, proof: Vec<Pubkey>) -> Self {
+        CnftAccounts {
+            merkle_tree: *merkle_tree,
+            leaf_delegate: *leaf_delegate,
+            proof,
+        }
+    }
+
+    // the accounts the cnft instructions share
+    pub fn bubblegum(&self) -> accounts::BubblegumAccounts {
+        accounts::BubblegumAccounts {
+            tree_config: TreeConfig::find_pda(&self.merkle_tree).0,
+            merkle_tree: self.merkle_tree,
+            leaf_delegate: self.leaf_delegate,
+            bubblegum_program: BUBBLEGUM_ID,
+            log_wrapper: spl_noop::ID,
+            compression_program: spl_account_compression::ID,
+            system_program: system_program::ID,
+        }
+    }
+
+    // the proof, as remaining accounts
+    pub fn proof_accounts(&self) -> Vec<AccountMeta> {
+        self.proof.iter().map(|node| AccountMeta::new_readonly(*node, false)).collect()
+    }
+
+    // remaining accounts for a cnft vault action (see CnftTransfer::from_remaining_accounts on the program side). the
+    // proof has to be against the tree's current root, so fetch it right before approving/executing
+    pub fn action_accounts(&self, to: &Pubkey) -> Vec<AccountMeta> {
+        let mut accounts = vec![
+            AccountMeta::new_readonly(*to, false),
+            AccountMeta::new_readonly(TreeConfig::find_pda(&self.merkle_tree).0, false),
+            AccountMeta::new(self.merkle_tree, false),
+            AccountMeta::new_readonly(self.leaf_delegate, false),
+            AccountMeta::new_readonly(spl_noop::ID, false),
+            AccountMeta::new_readonly(spl_account_compression::ID, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(BUBBLEGUM_ID, false),
+        ];
+        accounts.extend(self.proof_accounts());
+        accounts
+    }
+}
+
+// into the stache, or straight into one of its vaults
+pub fn stash_cnft(keys: &StacheKeys, owner: &Pubkey, vault_index: Option<u8>, cnft: &CnftAccounts, args: CnftArgs) -> Instruction {
+    build_with_remaining(accounts::StashCnft {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        vault: vault_index.map(|index| keys.vault(index)),
+        owner: *owner,
+        bubblegum: cnft.bubblegum(),
+    }, ix::StashCnft { args }, cnft.proof_accounts())
+}
+
+pub fn unstash_cnft(keys: &StacheKeys, owner: &Pubkey, cnft: &CnftAccounts, args: CnftArgs) -> Instruction {
+    build_with_remaining(accounts::UnstashCnft {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        owner: *owner,
+        bubblegum: cnft.bubblegum(),
+    }, ix::UnstashCnft { args }, cnft.proof_accounts())
+}
+
+/////// VAULTS ///////
+
+// index = the stache's next_vault_index; squads vaults need the multisig
+pub fn create_vault(keys: &StacheKeys, authority: &Pubkey, index: u8, name: &str, vault_type: VaultType) -> Instruction {
+    let multisig = match &vault_type {
+        VaultType::Squads { multisig, .. } => Some(*multisig),
+        _ => None,
+    };
+    build(accounts::CreateVault {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        vault: keys.vault(index),
+        authority: *authority,
+        multisig,
+        system_program: system_program::ID,
+    }, ix::CreateVault { name: name.to_string(), vault_type })
+}
+
+pub fn lock_vault(keys: &StacheKeys, authority: &Pubkey, index: u8) -> Instruction {
+    build(lock_vault_accounts(keys, authority, index), ix::LockVault {})
+}
+
+pub fn lock_vault_until(keys: &StacheKeys, authority: &Pubkey, index: u8, unlock_at: i64) -> Instruction {
+    build(lock_vault_accounts(keys, authority, index), ix::LockVaultUntil { unlock_at })
+}
+
+fn lock_vault_accounts(keys: &StacheKeys, authority: &Pubkey, index: u8) -> accounts::LockVault {
+    accounts::LockVault {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        vault: keys.vault(index),
+        authority: *authority,
+    }
+}
+
+pub fn unlock_vault(keys: &StacheKeys, authority: &Pubkey, index: u8, multisig: Option<Pubkey>) -> Instruction {
+    build(accounts::UnlockVault {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        vault: keys.vault(index),
+        authority: *authority,
+        multisig,
+    }, ix::UnlockVault {})
+}
+
+pub fn set_vault_delay(keys: &StacheKeys, authority: &Pubkey, index: u8, withdraw_delay: u32) -> Instruction {
+    build(accounts::SetVaultDelay {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        vault: keys.vault(index),
+        authority: *authority,
+    }, ix::SetVaultDelay { withdraw_delay })
+}
+
+pub fn set_vault_mints(keys: &StacheKeys, authority: &Pubkey, index: u8, mints: Vec<Pubkey>) -> Instruction {
+    build(accounts::SetVaultMints {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        vault: keys.vault(index),
+        authority: *authority,
+    }, ix::SetVaultMints { mints })
+}
+
+pub fn authorize_vault_auto(keys: &StacheKeys, authority: &Pubkey, vault_index: u8, auto_index: u8, action_index: u8, multisig: Option<Pubkey>) -> Instruction {
+    build(accounts::AuthorizeVaultAuto {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        vault: keys.vault(vault_index),
+        auto: keys.auto(auto_index),
+        authority: *authority,
+        multisig,
+    }, ix::AuthorizeVaultAuto { action_index })
+}
+
+pub fn revoke_vault_auto(keys: &StacheKeys, authority: &Pubkey, vault_index: u8, auto: &Pubkey) -> Instruction {
+    build(accounts::RevokeVaultAuto {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        vault: keys.vault(vault_index),
+        authority: *authority,
+    }, ix::RevokeVaultAuto { auto: *auto })
+}
+
+// from = a token account owned by the authority, or the stache ata
+pub fn deposit_to_vault(keys: &StacheKeys, authority: &Pubkey, index: u8, mint: &Pubkey, token_program: &Pubkey, from_token: &Pubkey, amount: u64) -> Instruction {
+    build(accounts::DepositToVault {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        vault: keys.vault(index),
+        vault_ata: keys.vault_ata(index, mint, token_program),
+        mint: *mint,
+        authority: *authority,
+        from_token: *from_token,
+        system_program: system_program::ID,
+        token_program: *token_program,
+        associated_token_program: associated_token::ID,
+    }, ix::DepositToVault { amount })
+}
+
+pub fn deposit_sol_to_vault(keys: &StacheKeys, authority: &Pubkey, index: u8, lamports: u64) -> Instruction {
+    build(accounts::DepositSolToVault {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        vault: keys.vault(index),
+        authority: *authority,
+        system_program: system_program::ID,
+    }, ix::DepositSolToVault { lamports })
+}
+
+// no vault index = the stache ata
+pub fn internal_transfer(keys: &StacheKeys,
+                         authority: &Pubkey,
+                         from_vault: Option<u8>,
+                         to_vault: Option<u8>,
+                         mint: &Pubkey,
+                         token_program: &Pubkey,
+                         multisig: Option<Pubkey>,
+                         amount: u64) -> Instruction {
+    let ata = |vault: Option<u8>| match vault {
+        Some(index) => keys.vault_ata(index, mint, token_program),
+        None => keys.ata(mint, token_program),
+    };
+    build(accounts::InternalTransfer {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        from_vault: from_vault.map(|index| keys.vault(index)),
+        to_vault: to_vault.map(|index| keys.vault(index)),
+        from_token: ata(from_vault),
+        to_token: ata(to_vault),
+        mint: *mint,
+        authority: *authority,
+        multisig,
+        token_program: *token_program,
+    }, ix::InternalTransfer { amount })
+}
+
+pub fn withdraw_from_vault(keys: &StacheKeys,
+                           authority: &Pubkey,
+                           index: u8,
+                           mint: &Pubkey,
+                           token_program: &Pubkey,
+                           to_token: &Pubkey,
+                           multisig: Option<Pubkey>,
+                           amount: u64) -> Instruction {
+    build(accounts::WithdrawFromVault {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        vault: keys.vault(index),
+        vault_ata: keys.vault_ata(index, mint, token_program),
+        to_token: *to_token,
+        mint: *mint,
+        authority: *authority,
+        multisig,
+        token_program: *token_program,
+        associated_token_program: associated_token::ID,
+    }, ix::WithdrawFromVault { amount })
+}
+
+pub fn withdraw_sol_from_vault(keys: &StacheKeys, authority: &Pubkey, index: u8, to: &Pubkey, multisig: Option<Pubkey>, lamports: u64) -> Instruction {
+    build(accounts::WithdrawSolFromVault {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        vault: keys.vault(index),
+        to: *to,
+        authority: *authority,
+        multisig,
+    }, ix::WithdrawSolFromVault { lamports })
+}
+
+// the nft goes to the to wallet's ata
+pub fn withdraw_nft_from_vault(keys: &StacheKeys,
+                               authority: &Pubkey,
+                               index: u8,
+                               to: &Pubkey,
+                               mint: &Pubkey,
+                               token_program: &Pubkey,
+                               programmable: bool,
+                               multisig: Option<Pubkey>) -> Instruction {
+    let vault_token = keys.vault_ata(index, mint, token_program);
+    let destination_token = get_associated_token_address_with_program_id(to, mint, token_program);
+    let nft = NftAccounts::new(mint, &vault_token, &destination_token, programmable);
+
+    build(accounts::WithdrawNftFromVault {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        vault: keys.vault(index),
+        authority: *authority,
+        to: *to,
+        vault_token,
+        destination_token,
+        multisig,
+        mint: *mint,
+        metaplex: nft.metaplex(),
+        token_program: *token_program,
+    }, ix::WithdrawNftFromVault {})
+}
+
+// the cnft goes straight to the to wallet
+pub fn withdraw_cnft_from_vault(keys: &StacheKeys,
+                                authority: &Pubkey,
+                                index: u8,
+                                to: &Pubkey,
+                                cnft: &CnftAccounts,
+                                args: CnftArgs,
+                                multisig: Option<Pubkey>) -> Instruction {
+    build_with_remaining(accounts::WithdrawCnftFromVault {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        vault: keys.vault(index),
+        authority: *authority,
+        to: *to,
+        multisig,
+        bubblegum: cnft.bubblegum(),
+    }, ix::WithdrawCnftFromVault { args }, cnft.proof_accounts())
+}
+
+// remaining accounts for executing a transfer action
+pub fn transfer_action_accounts(from: &Pubkey, to: &Pubkey, mint: &Pubkey) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new(*from, false),
+        AccountMeta::new(*to, false),
+        AccountMeta::new_readonly(*mint, false),
+    ]
+}
+
+// remaining accounts for executing a sol transfer action
+pub fn sol_transfer_action_accounts(to: &Pubkey) -> Vec<AccountMeta> {
+    vec![AccountMeta::new(*to, false)]
+}
+
+// remaining = the accounts the action needs if this approval executes it (see the *_action_accounts helpers)
+pub fn approve_action(keys: &StacheKeys,
+                      authority: &Pubkey,
+                      vault_index: u8,
+                      action_index: u8,
+                      token_program: &Pubkey,
+                      multisig: Option<Pubkey>,
+                      remaining: Vec<AccountMeta>) -> Instruction {
+    build_with_remaining(accounts::ApproveVaultAction {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        vault: keys.vault(vault_index),
+        authority: *authority,
+        multisig,
+        token_program: *token_program,
+        associated_token_program: associated_token::ID,
+    }, ix::ApproveAction { action_index }, remaining)
+}
+
+pub fn execute_action(keys: &StacheKeys,
+                      authority: &Pubkey,
+                      vault_index: u8,
+                      action_index: u8,
+                      token_program: &Pubkey,
+                      multisig: Option<Pubkey>,
+                      remaining: Vec<AccountMeta>) -> Instruction {
+    build_with_remaining(accounts::ExecuteVaultAction {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        vault: keys.vault(vault_index),
+        authority: *authority,
+        multisig,
+        token_program: *token_program,
+    }, ix::ExecuteAction { action_index }, remaining)
+}
+
+pub fn deny_action(keys: &StacheKeys, authority: &Pubkey, vault_index: u8, action_index: u8, multisig: Option<Pubkey>) -> Instruction {
+    build(accounts::DenyVaultAction {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        vault: keys.vault(vault_index),
+        authority: *authority,
+        multisig,
+    }, ix::DenyAction { action_index })
+}
+
+// no vault index = the stache's limits. loosening (or removing) a limit needs a cosigner
+pub fn set_spending_limit(keys: &StacheKeys,
+                          authority: &Pubkey,
+                          vault_index: Option<u8>,
+                          cosigner: Option<Pubkey>,
+                          mint: &Pubkey,
+                          amount: u64,
+                          period_seconds: u32) -> Instruction {
+    build(spending_limit_accounts(keys, authority, vault_index, cosigner),
+          ix::SetSpendingLimit { mint: *mint, amount, period_seconds })
+}
+
+pub fn remove_spending_limit(keys: &StacheKeys, authority: &Pubkey, vault_index: Option<u8>, cosigner: &Pubkey, mint: &Pubkey) -> Instruction {
+    build(spending_limit_accounts(keys, authority, vault_index, Some(*cosigner)),
+          ix::RemoveSpendingLimit { mint: *mint })
+}
+
+fn spending_limit_accounts(keys: &StacheKeys, authority: &Pubkey, vault_index: Option<u8>, cosigner: Option<Pubkey>) -> accounts::SetSpendingLimit {
+    accounts::SetSpendingLimit {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        vault: vault_index.map(|index| keys.vault(index)),
+        authority: *authority,
+        cosigner,
+    }
+}
+
+pub fn destroy_vault(keys: &StacheKeys, authority: &Pubkey, index: u8) -> Instruction {
+    build(accounts::DestroyVault {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        vault: keys.vault(index),
+        authority: *authority,
+    }, ix::DestroyVault {})
+}
+
+/////// SQUADS ///////
+
+// the instruction squads calls when it executes a transaction from propose_to_squads. remaining = the action's accounts
+// (same as approve_action)
+pub fn execute_squads_action(keys: &StacheKeys,
+                             vault_index: u8,
+                             action_index: u8,
+                             multisig: &Pubkey,
+                             transaction: &Pubkey,
+                             token_program: &Pubkey,
+                             remaining: Vec<AccountMeta>) -> Instruction {
+    build_with_remaining(accounts::ExecuteSquadsAction {
+        stache: keys.stache,
+        vault: keys.vault(vault_index),
+        multisig: *multisig,
+        transaction: *transaction,
+        squads_authority: squads::find_authority(multisig).0,
+        token_program: *token_program,
+        sysvar_instructions: sysvar::instructions::ID,
+    }, ix::ExecuteSquadsAction { action_index }, remaining)
+}
+
+// transaction_index = the multisig's transaction_index + 1 (the transaction squads is about to create)
+pub fn propose_to_squads(keys: &StacheKeys,
+                         authority: &Pubkey,
+                         vault_index: u8,
+                         action_index: u8,
+                         multisig: &Pubkey,
+                         transaction_index: u32,
+                         token_program: &Pubkey,
+                         remaining: Vec<AccountMeta>) -> Instruction {
+    let transaction = find_squads_transaction(multisig, transaction_index);
+    build_with_remaining(accounts::ProposeToSquads {
+        stache: keys.stache,
+        vault: keys.vault(vault_index),
+        multisig: *multisig,
+        transaction,
+        instruction: find_squads_instruction(&transaction, 1),
+        authority: *authority,
+        token_program: *token_program,
+        squads_program: squads::ID,
+        system_program: system_program::ID,
+    }, ix::ProposeToSquads { action_index }, remaining)
+}
+
+// squads' own instructions, for the rest of the flow (members can just as well use the squads app)
+fn squads_ix(name: &str, accounts: Vec<AccountMeta>) -> Instruction {
+    Instruction {
+        program_id: squads::ID,
+        accounts,
+        data: hash(format!("global:{}", name).as_bytes()).to_bytes()[..8].to_vec(),
+    }
+}
+
+pub fn approve_squads_transaction(multisig: &Pubkey, transaction: &Pubkey, member: &Pubkey) -> Instruction {
+    squads_ix("approve_transaction", vec![
+        AccountMeta::new(*multisig, false),
+        AccountMeta::new(*transaction, false),
+        AccountMeta::new(*member, true),
+    ])
+}
+
+// executes the transaction's (only) instruction, ie: the execute_squads_action it was proposed w/
+pub fn execute_squads_transaction(multisig: &Pubkey, transaction: &Pubkey, member: &Pubkey, execute: &Instruction) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*multisig, false),
+        AccountMeta::new(*transaction, false),
+        AccountMeta::new(find_squads_instruction(transaction, 1), false),
+        AccountMeta::new(*member, true),
+        AccountMeta::new_readonly(execute.program_id, false),
+    ];
+    // squads signs for its authority itself
+    accounts.extend(execute.accounts.iter().map(|meta| AccountMeta { is_signer: false, ..meta.clone() }));
+    squads_ix("execute_instruction", accounts)
+}
+
+/////// AUTOMATIONS ///////
+
+// index = the stache's next_auto_index
+pub fn create_auto(keys: &StacheKeys, authority: &Pubkey, index: u8, name: &str) -> Instruction {
+    build(accounts::CreateAutomation {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        auto: keys.auto(index),
+        authority: *authority,
+        system_program: system_program::ID,
+    }, ix::CreateAuto { name: name.to_string() })
+}
+
+// thread name = the automation's name, only needed if it still has a thread
+pub fn destroy_auto(keys: &StacheKeys, authority: &Pubkey, index: u8, thread_name: Option<&str>) -> Instruction {
+    let auto = keys.auto(index);
+    build(accounts::DestroyAutomation {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        auto,
+        authority: *authority,
+        thread: thread_name.map(|name| find_thread(&auto, name)),
+        clockwork_program: thread_name.map(|_| clockwork_sdk::ID),
+    }, ix::DestroyAuto {})
+}
+
+pub fn set_auto_balance_trigger(keys: &StacheKeys, authority: &Pubkey, index: u8, token: &Pubkey, trigger_balance: u64, above: bool) -> Instruction {
+    build(trigger_accounts(keys, authority, index, Some(*token)), ix::SetAutoBalanceTrigger { trigger_balance, above })
+}
+
+pub fn set_auto_cron_trigger(keys: &StacheKeys, authority: &Pubkey, index: u8, schedule: &str) -> Instruction {
+    build(trigger_accounts(keys, authority, index, None), ix::SetAutoCronTrigger { schedule: schedule.to_string() })
+}
+
+pub fn set_auto_interval_trigger(keys: &StacheKeys, authority: &Pubkey, index: u8, seconds: i64, start: i64) -> Instruction {
+    build(trigger_accounts(keys, authority, index, None), ix::SetAutoIntervalTrigger { seconds, start })
+}
+
+fn trigger_accounts(keys: &StacheKeys, authority: &Pubkey, index: u8, token: Option<Pubkey>) -> accounts::SetAutomationTrigger {
+    accounts::SetAutomationTrigger {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        auto: keys.auto(index),
+        authority: *authority,
+        token,
+    }
+}
+
+pub fn set_auto_limits(keys: &StacheKeys,
+                       authority: &Pubkey,
+                       index: u8,
+                       max_execs: Option<u32>,
+                       max_total_amount: Option<u64>,
+                       expires_at: Option<i64>) -> Instruction {
+    build(accounts::SetAutomationLimits {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        auto: keys.auto(index),
+        authority: *authority,
+    }, ix::SetAutoLimits { max_execs, max_total_amount, expires_at })
+}
+
+// from = the stache ata, or the vault's ata if pulling from a vault
+pub fn add_auto_action(keys: &StacheKeys,
+                       authority: &Pubkey,
+                       index: u8,
+                       from_vault: Option<u8>,
+                       to_token: &Pubkey,
+                       mint: &Pubkey,
+                       token_program: &Pubkey,
+                       amount: u64,
+                       mode: TransferMode) -> Instruction {
+    let from_token = match from_vault {
+        Some(vault_index) => keys.vault_ata(vault_index, mint, token_program),
+        None => keys.ata(mint, token_program),
+    };
+    build(accounts::SetAutomationAction {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        auto: keys.auto(index),
+        authority: *authority,
+        from_token: Some(from_token),
+        to_token: Some(*to_token),
+        mint: *mint,
+        token_program: *token_program,
+        associated_token_program: Some(associated_token::ID),
+        vault: from_vault.map(|vault_index| keys.vault(vault_index)),
+    }, ix::AddAutoAction { amount, mode })
+}
+
+pub fn clear_auto_actions(keys: &StacheKeys, authority: &Pubkey, index: u8) -> Instruction {
+    build(accounts::ClearAutomationActions {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        auto: keys.auto(index),
+        authority: *authority,
+    }, ix::ClearAutoActions {})
+}
+
+// creates the automation's thread
+pub fn activate_auto(keys: &StacheKeys, authority: &Pubkey, index: u8, auto: &Auto) -> Instruction {
+    let auto_key = keys.auto(index);
+    // the vaults the actions pull from
+    let vaults = auto.actions.iter()
+        .filter_map(|action| action.vault)
+        .map(|vault| AccountMeta::new_readonly(vault, false))
+        .collect();
+
+    build_with_remaining(accounts::ActivateAutomation {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        auto: auto_key,
+        authority: *authority,
+        thread: find_thread(&auto_key, &auto.name),
+        clockwork_program: clockwork_sdk::ID,
+        system_program: system_program::ID,
+    }, ix::ActivateAuto {}, vaults)
+}
+
+pub fn deactivate_auto(keys: &StacheKeys, authority: &Pubkey, index: u8, thread_name: &str) -> Instruction {
+    let auto = keys.auto(index);
+    build(accounts::DeactivateAutomation {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        auto,
+        authority: *authority,
+        thread: find_thread(&auto, thread_name),
+        clockwork_program: clockwork_sdk::ID,
+    }, ix::DeactivateAuto {})
+}
+
+pub fn pause_auto(keys: &StacheKeys, authority: &Pubkey, index: u8, thread_name: &str) -> Instruction {
+    let auto = keys.auto(index);
+    build(accounts::ToggleAutomation {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        auto,
+        authority: *authority,
+        thread: find_thread(&auto, thread_name),
+        clockwork_program: clockwork_sdk::ID,
+    }, ix::PauseAuto {})
+}
+
+pub fn resume_auto(keys: &StacheKeys, authority: &Pubkey, index: u8, thread_name: &str) -> Instruction {
+    let auto = keys.auto(index);
+    build(accounts::ToggleAutomation {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        auto,
+        authority: *authority,
+        thread: find_thread(&auto, thread_name),
+        clockwork_program: clockwork_sdk::ID,
+    }, ix::ResumeAuto {})
+}
+
+// the remaining accounts fire_auto/dry_run_auto need for an automation: the balance trigger's token account, then
+// from, to, mint, token program (+ vault) for each action. same as what activate_auto gives the thread
+pub fn fire_accounts(auto: &Auto) -> Vec<AccountMeta> {
+    let mut accounts = Vec::new();
+    if let Ok(trigger) = auto.clone().balance_trigger() {
+        accounts.push(AccountMeta::new_readonly(trigger.account, false));
+    }
+    for action in auto.actions.iter() {
+        accounts.push(AccountMeta::new(action.from, false));
+        accounts.push(AccountMeta::new(action.to, false));
+        accounts.push(AccountMeta::new_readonly(action.mint, false));
+        accounts.push(AccountMeta::new_readonly(action.token_program, false));
+        if let Some(vault) = action.vault {
+            accounts.push(AccountMeta::new(vault, false));
+        }
+    }
+    accounts
+}
+
+// fires the automation by hand (authority = a key on the keychain)
+pub fn fire_auto(keys: &StacheKeys, authority: &Pubkey, index: u8, auto: &Auto) -> Instruction {
+    build_with_remaining(accounts::FireAutomation {
+        stache: keys.stache,
+        auto: keys.auto(index),
+        thread: None,
+        keychain: Some(keys.keychain),
+        authority: Some(*authority),
+    }, ix::FireAuto {}, fire_accounts(auto))
+}
+
+// simulate this and read the AutoDryRun event out of the logs
+pub fn dry_run_auto(keys: &StacheKeys, authority: &Pubkey, index: u8, auto: &Auto) -> Instruction {
+    build_with_remaining(accounts::DryRunAutomation {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        auto: keys.auto(index),
+        authority: *authority,
+    }, ix::DryRunAuto {}, fire_accounts(auto))
+}
+
+/////// MIGRATIONS ///////
+
+// authority pays for the extra space the current layout needs
+pub fn migrate_stache(keys: &StacheKeys, authority: &Pubkey) -> Instruction {
+    build(accounts::MigrateStache {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        authority: *authority,
+        system_program: system_program::ID,
+    }, ix::MigrateStache {})
+}
+
+pub fn migrate_vault(keys: &StacheKeys, authority: &Pubkey, index: u8) -> Instruction {
+    build(accounts::MigrateVault {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        vault: keys.vault(index),
+        authority: *authority,
+        system_program: system_program::ID,
+    }, ix::MigrateVault {})
+}
+
+// from token = the from account of a v0 transfer, thread name = the automation's name if it still has a v0 thread
+// thread = the v0 automation's thread + the clockwork program that owns it (v0 threads can be clockwork 1.x threads)
+pub fn migrate_auto(keys: &StacheKeys, authority: &Pubkey, index: u8, from_token: Option<Pubkey>, thread: Option<(Pubkey, Pubkey)>) -> Instruction {
+    build(accounts::MigrateAutomation {
+        stache: keys.stache,
+        keychain: keys.keychain,
+        auto: keys.auto(index),
+        authority: *authority,
+        system_program: system_program::ID,
+        from_token,
+        thread: thread.map(|(thread, _)| thread),
+        clockwork_program: thread.map(|(_, clockwork_program)| clockwork_program),
+    }, ix::MigrateAuto {})
+}
@@ -0,0 +1,17 @@
+// off-chain client for the stache program: pda derivation, instruction builders and account fetching/decoding.
+// builders return plain instructions, so they work w/whatever signs + sends the transaction
+
+pub mod error;
+pub mod pda;
+pub mod instruction;
+pub mod account;
+
+pub use error::*;
+pub use pda::*;
+pub use account::*;
+
+// the program crate, for its account + arg types (Vault, VaultType, TransferMode, ...)
+pub use stache;
+pub use stache::ID as STACHE_PROGRAM_ID;
+pub use keychain::ID as KEYCHAIN_PROGRAM_ID;
+pub use clockwork_sdk::ID as THREAD_PROGRAM_ID;
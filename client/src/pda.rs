@@ -0,0 +1,134 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use clockwork_sdk::state::Thread;
+use stache::constant::*;
+use stache::migration::clockwork_v1;
+use stache::squads::{self, SQUAD, TRANSACTION};
+
+// keychain program seeds
+pub const KEYCHAIN: &str = "keychain";
+pub const KEYCHAIN_SPACE: &str = "keychains";
+pub const KEYCHAIN_STATE_SPACE: &str = "keychain_states";
+pub const KEY_SPACE: &str = "keys";
+pub const DOMAIN_STATE: &str = "domain_state";
+
+/////// KEYCHAIN ///////
+
+pub fn find_domain(domain: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[domain.as_bytes(), KEYCHAIN.as_bytes()], &keychain::ID)
+}
+
+pub fn find_domain_state(domain: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[DOMAIN_STATE.as_bytes(), domain.as_bytes(), KEYCHAIN.as_bytes()], &keychain::ID)
+}
+
+pub fn find_keychain(name: &str, domain: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[name.as_bytes(), KEYCHAIN_SPACE.as_bytes(), domain.as_bytes(), KEYCHAIN.as_bytes()],
+                                 &keychain::ID)
+}
+
+pub fn find_keychain_state(keychain: &Pubkey, domain: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[keychain.as_ref(), KEYCHAIN_STATE_SPACE.as_bytes(), domain.as_bytes(), KEYCHAIN.as_bytes()],
+                                 &keychain::ID)
+}
+
+// the "pointer" account from a wallet to its keychain
+pub fn find_keychain_key(wallet: &Pubkey, domain: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[wallet.as_ref(), KEY_SPACE.as_bytes(), domain.as_bytes(), KEYCHAIN.as_bytes()],
+                                 &keychain::ID)
+}
+
+/////// STACHE ///////
+
+// the stacheid is the keychain name, and domain is the keychain's domain account (not the domain name)
+pub fn find_stache(stacheid: &str, domain: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[stacheid.as_bytes(), BEARD_SPACE.as_bytes(), domain.as_ref(), STACHE.as_bytes()],
+                                 &stache::ID)
+}
+
+pub fn find_vault(index: u8, stacheid: &str, domain: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[&index.to_le_bytes(), VAULT_SPACE.as_bytes(), stacheid.as_bytes(), BEARD_SPACE.as_bytes(),
+                                   domain.as_ref(), STACHE.as_bytes()],
+                                 &stache::ID)
+}
+
+pub fn find_auto(index: u8, stacheid: &str, domain: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[&index.to_le_bytes(), AUTO_SPACE.as_bytes(), stacheid.as_bytes(), BEARD_SPACE.as_bytes(),
+                                   domain.as_ref(), STACHE.as_bytes()],
+                                 &stache::ID)
+}
+
+// temporary wsol account for partially unwrapping sol out of the stache
+pub fn find_unwrap(stache: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[UNWRAP.as_bytes(), stache.as_ref()], &stache::ID)
+}
+
+// an automation's clockwork thread: the automation is the thread authority and its name is the thread id
+pub fn find_thread(auto: &Pubkey, name: &str) -> Pubkey {
+    Thread::pubkey(*auto, name.as_bytes().to_vec())
+}
+
+// the same thread under clockwork 1.x, which v0 automations were set up with
+pub fn find_v1_thread(auto: &Pubkey, name: &str) -> Pubkey {
+    Pubkey::find_program_address(&[b"thread", auto.as_ref(), name.as_bytes()], &clockwork_v1::ID).0
+}
+
+// a squads transaction of the multisig (squads numbers them from 1)
+pub fn find_squads_transaction(multisig: &Pubkey, transaction_index: u32) -> Pubkey {
+    Pubkey::find_program_address(
+        &[SQUAD.as_bytes(), multisig.as_ref(), &transaction_index.to_le_bytes(), TRANSACTION.as_bytes()],
+        &squads::ID,
+    ).0
+}
+
+// an instruction of a squads transaction (also numbered from 1)
+pub fn find_squads_instruction(transaction: &Pubkey, instruction_index: u8) -> Pubkey {
+    Pubkey::find_program_address(&[SQUAD.as_bytes(), transaction.as_ref(), &[instruction_index], b"instruction"], &squads::ID).0
+}
+
+// everything that's derived from the keychain a stache belongs to, so the instruction builders only need the
+// accounts specific to each instruction
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StacheKeys {
+    pub stacheid: String,
+    pub domain_name: String,
+    pub domain: Pubkey,
+    pub keychain: Pubkey,
+    pub stache: Pubkey,
+}
+
+impl StacheKeys {
+
+    // name = keychain name (= stacheid), domain = keychain domain name
+    pub fn new(name: &str, domain: &str) -> Self {
+        let domain_pda = find_domain(domain).0;
+        StacheKeys {
+            stacheid: name.to_string(),
+            domain_name: domain.to_string(),
+            domain: domain_pda,
+            keychain: find_keychain(name, domain).0,
+            stache: find_stache(name, &domain_pda).0,
+        }
+    }
+
+    pub fn vault(&self, index: u8) -> Pubkey {
+        find_vault(index, &self.stacheid, &self.domain).0
+    }
+
+    pub fn auto(&self, index: u8) -> Pubkey {
+        find_auto(index, &self.stacheid, &self.domain).0
+    }
+
+    pub fn unwrap(&self) -> Pubkey {
+        find_unwrap(&self.stache).0
+    }
+
+    // the stache's ata for a mint
+    pub fn ata(&self, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(&self.stache, mint, token_program)
+    }
+
+    pub fn vault_ata(&self, index: u8, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(&self.vault(index), mint, token_program)
+    }
+}
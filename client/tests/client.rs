@@ -0,0 +1,376 @@
+use anchor_lang::{InstructionData, ToAccountMetas, system_program};
+use anchor_lang::solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, instruction::{AccountMeta, Instruction}, native_token::LAMPORTS_PER_SOL, sysvar};
+use anchor_spl::associated_token;
+use anchor_spl::token::{self, spl_token::native_mint, TokenAccount};
+use mpl_bubblegum::accounts::TreeConfig;
+use solana_program_test::{processor, BanksClient, ProgramTest};
+use solana_sdk::{pubkey::Pubkey, signature::{Keypair, Signer}, transaction::Transaction};
+use stache::account::{TransferMode, VaultType};
+use stache::migration::clockwork_v1;
+use stache::nft::{spl_account_compression, spl_noop, CnftArgs, BUBBLEGUM_ID};
+use stache::squads;
+use stache_client::{decode, decode_auto, decode_stache, decode_vault, instruction, pda::*};
+use stache_client::instruction::CnftAccounts;
+
+// anchor's entrypoints want the accounts to live as long as the account infos, which processor! can't promise
+fn stache_entry(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    stache::entry(program_id, accounts, data)
+}
+
+fn keychain_entry(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    keychain::entry(program_id, accounts, data)
+}
+
+const DOMAIN: &str = "testdomain";
+const NAME: &str = "testuser";
+
+async fn send(banks: &mut BanksClient, payer: &Keypair, instructions: &[Instruction]) {
+    let blockhash = banks.get_latest_blockhash().await.unwrap();
+    let tx = Transaction::new_signed_with_payer(instructions, Some(&payer.pubkey()), &[payer], blockhash);
+    banks.process_transaction(tx).await.unwrap();
+}
+
+async fn account_data(banks: &mut BanksClient, address: &Pubkey) -> Vec<u8> {
+    banks.get_account(*address).await.unwrap().expect("account exists").data
+}
+
+// (address, writable, signer) for each account, in order
+fn metas(accounts: &[AccountMeta]) -> Vec<(Pubkey, bool, bool)> {
+    accounts.iter().map(|meta| (meta.pubkey, meta.is_writable, meta.is_signer)).collect()
+}
+
+fn readonly(address: Pubkey) -> (Pubkey, bool, bool) {
+    (address, false, false)
+}
+
+fn writable(address: Pubkey) -> (Pubkey, bool, bool) {
+    (address, true, false)
+}
+
+fn signer(address: Pubkey) -> (Pubkey, bool, bool) {
+    (address, true, true)
+}
+
+// a domain + a keychain for the payer, and the stache keys for it
+async fn setup() -> (BanksClient, Keypair, StacheKeys) {
+    let mut program_test = ProgramTest::new("stache", stache::ID, processor!(stache_entry));
+    program_test.add_program("keychain", keychain::ID, processor!(keychain_entry));
+    let (mut banks, payer, _) = program_test.start().await;
+
+    let keys = StacheKeys::new(NAME, DOMAIN);
+    let treasury = Keypair::new();
+
+    let create_domain = Instruction {
+        program_id: keychain::ID,
+        accounts: keychain::accounts::CreateDomain {
+            domain: keys.domain,
+            domain_state: find_domain_state(DOMAIN).0,
+            authority: payer.pubkey(),
+            system_program: system_program::ID,
+            treasury: treasury.pubkey(),
+        }.to_account_metas(None),
+        data: keychain::instruction::CreateDomain { name: DOMAIN.to_string(), keychain_cost: 0 }.data(),
+    };
+    let create_keychain = Instruction {
+        program_id: keychain::ID,
+        accounts: keychain::accounts::CreateKeychain {
+            keychain: keys.keychain,
+            keychain_state: find_keychain_state(&keys.keychain, DOMAIN).0,
+            key: find_keychain_key(&payer.pubkey(), DOMAIN).0,
+            domain: keys.domain,
+            wallet: payer.pubkey(),
+            authority: payer.pubkey(),
+            system_program: system_program::ID,
+        }.to_account_metas(None),
+        data: keychain::instruction::CreateKeychain { keychain_name: NAME.to_string() }.data(),
+    };
+    send(&mut banks, &payer, &[create_domain, create_keychain]).await;
+
+    (banks, payer, keys)
+}
+
+#[tokio::test]
+async fn derives_the_program_addresses() {
+    let (mut banks, payer, keys) = setup().await;
+    let authority = payer.pubkey();
+
+    send(&mut banks, &payer, &[instruction::create_stache(&keys, &authority)]).await;
+    let stache = decode_stache(&account_data(&mut banks, &keys.stache).await).unwrap();
+    assert_eq!(stache.stacheid, NAME);
+    assert_eq!(stache.keychain, keys.keychain);
+    assert_eq!(stache.domain, keys.domain);
+
+    // the builders derive the vault + automation addresses the program expects from the next indexes
+    send(&mut banks, &payer, &[
+        instruction::create_vault(&keys, &authority, stache.next_vault_index, "savings", VaultType::Easy),
+        instruction::create_auto(&keys, &authority, stache.next_auto_index, "payday"),
+    ]).await;
+
+    let vault = decode_vault(&account_data(&mut banks, &keys.vault(1)).await).unwrap();
+    assert_eq!(vault.name, "savings");
+    assert_eq!(vault.stache, keys.stache);
+
+    let auto = decode_auto(&account_data(&mut banks, &keys.auto(1)).await).unwrap();
+    assert_eq!(auto.name, "payday");
+    assert_eq!(find_thread(&keys.auto(1), "payday"), clockwork_sdk::state::Thread::pubkey(keys.auto(1), "payday".into()));
+
+    let stache = decode_stache(&account_data(&mut banks, &keys.stache).await).unwrap();
+    assert_eq!(stache.vaults, vec![1]);
+    assert_eq!(stache.autos, vec![1]);
+}
+
+#[tokio::test]
+async fn stashes_and_moves_sol_into_a_vault() {
+    let (mut banks, payer, keys) = setup().await;
+    let authority = payer.pubkey();
+
+    send(&mut banks, &payer, &[
+        instruction::create_stache(&keys, &authority),
+        instruction::create_vault(&keys, &authority, 1, "savings", VaultType::Easy),
+    ]).await;
+
+    // no from token = wrap sol into the stache's wsol ata
+    send(&mut banks, &payer, &[
+        instruction::stash(&keys, &authority, &native_mint::ID, &token::ID, None, LAMPORTS_PER_SOL),
+    ]).await;
+    let stache_ata: TokenAccount = decode(&account_data(&mut banks, &keys.ata(&native_mint::ID, &token::ID)).await).unwrap();
+    assert_eq!(stache_ata.amount, LAMPORTS_PER_SOL);
+
+    send(&mut banks, &payer, &[
+        instruction::internal_transfer(&keys, &authority, None, Some(1), &native_mint::ID, &token::ID, None, LAMPORTS_PER_SOL / 2),
+    ]).await;
+    let vault_ata: TokenAccount = decode(&account_data(&mut banks, &keys.vault_ata(1, &native_mint::ID, &token::ID)).await).unwrap();
+    assert_eq!(vault_ata.amount, LAMPORTS_PER_SOL / 2);
+}
+
+#[tokio::test]
+async fn builds_fire_accounts_from_the_automation() {
+    let (mut banks, payer, keys) = setup().await;
+    let authority = payer.pubkey();
+    let stache_ata = keys.ata(&native_mint::ID, &token::ID);
+    let vault_ata = keys.vault_ata(1, &native_mint::ID, &token::ID);
+
+    send(&mut banks, &payer, &[
+        instruction::create_stache(&keys, &authority),
+        instruction::create_vault(&keys, &authority, 1, "savings", VaultType::Easy),
+        instruction::stash(&keys, &authority, &native_mint::ID, &token::ID, None, LAMPORTS_PER_SOL),
+        instruction::deposit_to_vault(&keys, &authority, 1, &native_mint::ID, &token::ID, &stache_ata, 1),
+        instruction::create_auto(&keys, &authority, 1, "sweep"),
+        instruction::set_auto_balance_trigger(&keys, &authority, 1, &stache_ata, 1, true),
+        instruction::add_auto_action(&keys, &authority, 1, None, &vault_ata, &native_mint::ID, &token::ID, 1000, TransferMode::Fixed),
+    ]).await;
+
+    let auto = decode_auto(&account_data(&mut banks, &keys.auto(1)).await).unwrap();
+    let accounts = instruction::fire_accounts(&auto);
+    let keys_only: Vec<Pubkey> = accounts.iter().map(|account| account.pubkey).collect();
+    assert_eq!(keys_only, vec![stache_ata, stache_ata, vault_ata, native_mint::ID, token::ID]);
+
+    // fire it by hand
+    send(&mut banks, &payer, &[instruction::fire_auto(&keys, &authority, 1, &auto)]).await;
+    let auto = decode_auto(&account_data(&mut banks, &keys.auto(1)).await).unwrap();
+    assert_eq!(auto.num_execs, 1);
+    let vault_token: TokenAccount = decode(&account_data(&mut banks, &vault_ata).await).unwrap();
+    assert_eq!(vault_token.amount, 1001);
+}
+
+#[test]
+fn builds_vault_action_accounts() {
+    let keys = StacheKeys::new(NAME, DOMAIN);
+    let authority = Pubkey::new_unique();
+    let (from, to, mint) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let remaining = instruction::transfer_action_accounts(&from, &to, &mint);
+    assert_eq!(metas(&remaining), vec![writable(from), writable(to), readonly(mint)]);
+    assert_eq!(metas(&instruction::sol_transfer_action_accounts(&to)), vec![writable(to)]);
+
+    // missing optional accounts (the multisig) are the program id, and the action's accounts come after the fixed ones
+    let approve = instruction::approve_action(&keys, &authority, 2, 7, &token::ID, None, remaining.clone());
+    assert_eq!(metas(&approve.accounts), vec![
+        writable(keys.stache),
+        readonly(keys.keychain),
+        writable(keys.vault(2)),
+        signer(authority),
+        readonly(stache::ID),
+        readonly(token::ID),
+        readonly(associated_token::ID),
+        writable(from),
+        writable(to),
+        readonly(mint),
+    ]);
+
+    let multisig = Pubkey::new_unique();
+    let execute = instruction::execute_action(&keys, &authority, 2, 7, &token::ID, Some(multisig), remaining);
+    assert_eq!(metas(&execute.accounts), vec![
+        writable(keys.stache),
+        readonly(keys.keychain),
+        writable(keys.vault(2)),
+        signer(authority),
+        readonly(multisig),
+        readonly(token::ID),
+        writable(from),
+        writable(to),
+        readonly(mint),
+    ]);
+
+    let deny = instruction::deny_action(&keys, &authority, 2, 7, Some(multisig));
+    assert_eq!(metas(&deny.accounts), vec![
+        writable(keys.stache),
+        readonly(keys.keychain),
+        writable(keys.vault(2)),
+        signer(authority),
+        readonly(multisig),
+    ]);
+    let deny = instruction::deny_action(&keys, &authority, 2, 7, None);
+    assert_eq!(deny.accounts[4].pubkey, stache::ID);
+}
+
+#[test]
+fn builds_squads_action_accounts() {
+    let keys = StacheKeys::new(NAME, DOMAIN);
+    let authority = Pubkey::new_unique();
+    let multisig = Pubkey::new_unique();
+    let (from, to, mint) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let remaining = instruction::transfer_action_accounts(&from, &to, &mint);
+
+    // proposing creates the multisig's next transaction + its one instruction
+    let transaction = find_squads_transaction(&multisig, 4);
+    let propose = instruction::propose_to_squads(&keys, &authority, 2, 7, &multisig, 4, &token::ID, remaining.clone());
+    assert_eq!(metas(&propose.accounts), vec![
+        readonly(keys.stache),
+        readonly(keys.vault(2)),
+        writable(multisig),
+        writable(transaction),
+        writable(find_squads_instruction(&transaction, 1)),
+        signer(authority),
+        readonly(token::ID),
+        readonly(squads::ID),
+        readonly(system_program::ID),
+        writable(from),
+        writable(to),
+        readonly(mint),
+    ]);
+
+    let squads_authority = squads::find_authority(&multisig).0;
+    let execute = instruction::execute_squads_action(&keys, 2, 7, &multisig, &transaction, &token::ID, remaining);
+    assert_eq!(metas(&execute.accounts), vec![
+        readonly(keys.stache),
+        writable(keys.vault(2)),
+        readonly(multisig),
+        readonly(transaction),
+        signer(squads_authority),
+        readonly(token::ID),
+        readonly(sysvar::instructions::ID),
+        writable(from),
+        writable(to),
+        readonly(mint),
+    ]);
+
+    // squads signs for its authority, so the member is the only signer on the way in
+    let member = Pubkey::new_unique();
+    let squads_execute = instruction::execute_squads_transaction(&multisig, &transaction, &member, &execute);
+    assert_eq!(squads_execute.program_id, squads::ID);
+    let mut expected = vec![
+        writable(multisig),
+        writable(transaction),
+        writable(find_squads_instruction(&transaction, 1)),
+        signer(member),
+        readonly(stache::ID),
+    ];
+    expected.extend(execute.accounts.iter().map(|meta| (meta.pubkey, meta.is_writable, false)));
+    assert_eq!(metas(&squads_execute.accounts), expected);
+}
+
+#[test]
+fn builds_cnft_action_accounts() {
+    let keys = StacheKeys::new(NAME, DOMAIN);
+    let authority = Pubkey::new_unique();
+    let to = Pubkey::new_unique();
+    let merkle_tree = Pubkey::new_unique();
+    let proof = vec![Pubkey::new_unique(), Pubkey::new_unique()];
+    let cnft = CnftAccounts::new(&merkle_tree, &keys.vault(2), proof.clone());
+    let tree_config = TreeConfig::find_pda(&merkle_tree).0;
+
+    // only the tree changes, and the proof goes last
+    assert_eq!(metas(&cnft.action_accounts(&to)), vec![
+        readonly(to),
+        readonly(tree_config),
+        writable(merkle_tree),
+        readonly(keys.vault(2)),
+        readonly(spl_noop::ID),
+        readonly(spl_account_compression::ID),
+        readonly(system_program::ID),
+        readonly(BUBBLEGUM_ID),
+        readonly(proof[0]),
+        readonly(proof[1]),
+    ]);
+
+    let args = CnftArgs { root: [1; 32], data_hash: [2; 32], creator_hash: [3; 32], nonce: 4, index: 4 };
+    let withdraw = instruction::withdraw_cnft_from_vault(&keys, &authority, 2, &to, &cnft, args, None);
+    assert_eq!(metas(&withdraw.accounts), vec![
+        readonly(keys.stache),
+        readonly(keys.keychain),
+        writable(keys.vault(2)),
+        (authority, false, true),
+        readonly(to),
+        readonly(stache::ID),
+        readonly(tree_config),
+        writable(merkle_tree),
+        readonly(keys.vault(2)),
+        readonly(BUBBLEGUM_ID),
+        readonly(spl_noop::ID),
+        readonly(spl_account_compression::ID),
+        readonly(system_program::ID),
+        readonly(proof[0]),
+        readonly(proof[1]),
+    ]);
+}
+
+#[test]
+fn builds_migration_accounts() {
+    let keys = StacheKeys::new(NAME, DOMAIN);
+    let authority = Pubkey::new_unique();
+
+    assert_eq!(metas(&instruction::migrate_stache(&keys, &authority).accounts), vec![
+        writable(keys.stache),
+        readonly(keys.keychain),
+        signer(authority),
+        readonly(system_program::ID),
+    ]);
+    assert_eq!(metas(&instruction::migrate_vault(&keys, &authority, 3).accounts), vec![
+        readonly(keys.stache),
+        readonly(keys.keychain),
+        writable(keys.vault(3)),
+        signer(authority),
+        readonly(system_program::ID),
+    ]);
+
+    // no from token or thread: the optional accounts are the program id
+    let migrate = instruction::migrate_auto(&keys, &authority, 3, None, None);
+    assert_eq!(metas(&migrate.accounts), vec![
+        readonly(keys.stache),
+        readonly(keys.keychain),
+        writable(keys.auto(3)),
+        signer(authority),
+        readonly(system_program::ID),
+        readonly(stache::ID),
+        readonly(stache::ID),
+        readonly(stache::ID),
+    ]);
+
+    // the thread goes back through whichever clockwork program owns it
+    let from_token = Pubkey::new_unique();
+    let thread = Pubkey::new_unique();
+    let migrate = instruction::migrate_auto(&keys, &authority, 3, Some(from_token), Some((thread, clockwork_v1::ID)));
+    assert_eq!(metas(&migrate.accounts[5..]), vec![
+        readonly(from_token),
+        writable(thread),
+        readonly(clockwork_v1::ID),
+    ]);
+    let migrate = instruction::migrate_auto(&keys, &authority, 3, None, Some((thread, clockwork_sdk::ID)));
+    assert_eq!(metas(&migrate.accounts[5..]), vec![
+        readonly(stache::ID),
+        writable(thread),
+        readonly(clockwork_sdk::ID),
+    ]);
+}
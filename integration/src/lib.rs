@@ -0,0 +1,552 @@
+// test harness for running stache in-process w/solana-program-test: loads the stache + keychain programs, plus the
+// local stand-ins for clockwork (clockwork-mock) and squads (squads-mock) at their real program addresses (and metaplex,
+// when it's been dumped into tests/fixtures), and sets up a domain + keychain w/a couple of keys to work with. tests are
+// in tests/, one file per area
+
+use anchor_lang::{AccountDeserialize, AccountSerialize, AnchorSerialize, InstructionData, ToAccountMetas, system_program};
+use anchor_lang::solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    hash::hash,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    native_token::LAMPORTS_PER_SOL,
+    program_error::ProgramError,
+    program_pack::Pack,
+    system_instruction,
+    sysvar::clock::Clock,
+};
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use anchor_spl::token::spl_token;
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{transfer_fee, transfer_hook, ExtensionType, StateWithExtensions},
+};
+use clockwork_sdk::state::Thread;
+use mpl_token_metadata::{
+    accounts::{MasterEdition, Metadata},
+    instructions::{CreateV1Builder, MintV1Builder},
+    types::{PrintSupply, TokenStandard},
+};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::{Account, AccountSharedData},
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use stache::account::{Auto, CurrentStache, Vault, VaultType};
+use stache::error::StacheError;
+use stache::migration::clockwork_v1;
+use stache::nft::TOKEN_METADATA_ID;
+use std::path::Path;
+use stache_client::{instruction, pda::*};
+
+pub const DOMAIN: &str = "testdomain";
+pub const NAME: &str = "testuser";
+
+// anchor's entrypoints want the accounts to live as long as the account infos, which processor! can't promise
+fn stache_entry(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    stache::entry(program_id, accounts, data)
+}
+
+fn keychain_entry(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    keychain::entry(program_id, accounts, data)
+}
+
+fn clockwork_entry(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    clockwork_mock::entry(program_id, accounts, data)
+}
+
+fn squads_entry(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    squads_mock::entry(program_id, accounts, data)
+}
+
+// clockwork 1.x only comes up when migrating v0 automations, which only ever delete their old threads, so this stands
+// in for its thread program w/just thread_delete: the thread's authority (the first field of a 1.x thread) has to sign,
+// and the thread's lamports go to close_to
+fn clockwork_v1_entry(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [authority, close_to, thread] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    if data.len() < 8 || data[..8] != hash(b"global:thread_delete").to_bytes()[..8] {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if thread.owner != program_id {
+        return Err(ProgramError::IllegalOwner);
+    }
+    if !authority.is_signer || thread.data.borrow()[8..40] != authority.key.to_bytes() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    **close_to.lamports.borrow_mut() += thread.lamports();
+    **thread.lamports.borrow_mut() = 0;
+    thread.data.borrow_mut().fill(0);
+    Ok(())
+}
+
+pub fn program_test() -> ProgramTest {
+    let mut program_test = ProgramTest::new("stache", stache::ID, processor!(stache_entry));
+    program_test.add_program("keychain", keychain::ID, processor!(keychain_entry));
+    program_test.add_program("clockwork_mock", clockwork_mock::ID, processor!(clockwork_entry));
+    program_test.add_program("clockwork_v1_mock", clockwork_v1::ID, processor!(clockwork_v1_entry));
+    program_test.add_program("squads_mock", squads_mock::ID, processor!(squads_entry));
+    // metaplex has no stand-in: the nft happy paths need the real program dumped into tests/fixtures (see has_metaplex)
+    if has_metaplex() {
+        program_test.add_program("mpl_token_metadata", TOKEN_METADATA_ID, None);
+    }
+    program_test
+}
+
+// solana program dump -u m metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s tests/fixtures/mpl_token_metadata.so
+pub fn has_metaplex() -> bool {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/mpl_token_metadata.so").exists()
+}
+
+pub struct TestEnv {
+    pub ctx: ProgramTestContext,
+    pub keys: StacheKeys,
+    // created the keychain, and pays for everything
+    pub owner: Keypair,
+    // 2nd (verified) key on the keychain
+    pub key2: Keypair,
+    // added to the keychain, but never verified
+    pub pending: Keypair,
+    // not on the keychain
+    pub outsider: Keypair,
+}
+
+impl TestEnv {
+
+    // a domain + a keychain w/the owner, key2 (verified) and pending (unverified) on it
+    pub async fn new() -> Self {
+        let ctx = program_test().start_with_context().await;
+        let owner = copy_keypair(&ctx.payer);
+        let mut env = TestEnv {
+            ctx,
+            keys: StacheKeys::new(NAME, DOMAIN),
+            owner,
+            key2: Keypair::new(),
+            pending: Keypair::new(),
+            outsider: Keypair::new(),
+        };
+
+        let treasury = Keypair::new();
+        let owner = env.owner.pubkey();
+        let key2 = env.key2.pubkey();
+        let pending = env.pending.pubkey();
+        let keychain = env.keys.keychain;
+        let domain = env.keys.domain;
+
+        env.send_ok(&[
+            system_instruction::transfer(&owner, &treasury.pubkey(), LAMPORTS_PER_SOL),
+            system_instruction::transfer(&owner, &key2, 10 * LAMPORTS_PER_SOL),
+            system_instruction::transfer(&owner, &pending, LAMPORTS_PER_SOL),
+            system_instruction::transfer(&owner, &env.outsider.pubkey(), 10 * LAMPORTS_PER_SOL),
+            keychain_ix(keychain::accounts::CreateDomain {
+                domain,
+                domain_state: find_domain_state(DOMAIN).0,
+                authority: owner,
+                system_program: system_program::ID,
+                treasury: treasury.pubkey(),
+            }, keychain::instruction::CreateDomain { name: DOMAIN.to_string(), keychain_cost: 0 }),
+            keychain_ix(keychain::accounts::CreateKeychain {
+                keychain,
+                keychain_state: find_keychain_state(&keychain, DOMAIN).0,
+                key: find_keychain_key(&owner, DOMAIN).0,
+                domain,
+                wallet: owner,
+                authority: owner,
+                system_program: system_program::ID,
+            }, keychain::instruction::CreateKeychain { keychain_name: NAME.to_string() }),
+            keychain_ix(keychain::accounts::AddKey { keychain, domain, authority: owner },
+                        keychain::instruction::AddKey { pubkey: key2 }),
+            keychain_ix(keychain::accounts::AddKey { keychain, domain, authority: owner },
+                        keychain::instruction::AddKey { pubkey: pending }),
+        ], &[]).await;
+
+        let verify_key = keychain_ix(keychain::accounts::VerifyKey {
+            keychain,
+            key: find_keychain_key(&key2, DOMAIN).0,
+            user_key: key2,
+            authority: key2,
+            domain,
+            treasury: treasury.pubkey(),
+            system_program: system_program::ID,
+        }, keychain::instruction::VerifyKey {});
+        let key2_signer = copy_keypair(&env.key2);
+        env.send_ok(&[verify_key], &[&key2_signer]).await;
+
+        env
+    }
+
+    // + a stache for the keychain
+    pub async fn with_stache() -> Self {
+        let mut env = Self::new().await;
+        let create_stache = instruction::create_stache(&env.keys, &env.owner.pubkey());
+        env.send_ok(&[create_stache], &[]).await;
+        env
+    }
+
+    // + 1000 tokens stashed, and a vault of the given type holding 500 of them. returns the mint + vault index
+    pub async fn funded_vault(vault_type: VaultType) -> (Self, Pubkey, u8) {
+        let mut env = Self::with_stache().await;
+        let mint = env.funded_mint(1000).await;
+        env.stash(&mint, 1000).await;
+        let vault = env.create_vault("savings", vault_type).await;
+        env.fund_vault(vault, &mint, 500).await;
+        (env, mint, vault)
+    }
+
+    /////// TRANSACTIONS ///////
+
+    // the owner pays; signers = any other keys that need to sign. every transaction gets a fresh blockhash so sending
+    // the same instructions again (ie: retrying after a failure) isn't dropped as a duplicate
+    pub async fn send(&mut self, instructions: &[Instruction], signers: &[&Keypair]) -> Result<(), BanksClientError> {
+        let blockhash = self.ctx.get_new_latest_blockhash().await.unwrap();
+        let mut all_signers = vec![&self.owner];
+        all_signers.extend(signers.iter().copied().filter(|signer| signer.pubkey() != self.owner.pubkey()));
+        let tx = Transaction::new_signed_with_payer(instructions, Some(&self.owner.pubkey()), &all_signers, blockhash);
+        self.ctx.banks_client.process_transaction(tx).await
+    }
+
+    pub async fn send_ok(&mut self, instructions: &[Instruction], signers: &[&Keypair]) {
+        if let Err(err) = self.send(instructions, signers).await {
+            panic!("transaction failed: {:?}", err);
+        }
+    }
+
+    /////// ACCOUNTS ///////
+
+    pub async fn exists(&mut self, address: &Pubkey) -> bool {
+        self.ctx.banks_client.get_account(*address).await.unwrap().is_some()
+    }
+
+    pub async fn lamports(&mut self, address: &Pubkey) -> u64 {
+        self.ctx.banks_client.get_balance(*address).await.unwrap()
+    }
+
+    pub async fn account<T: AccountDeserialize>(&mut self, address: &Pubkey) -> T {
+        let account = self.ctx.banks_client.get_account(*address).await.unwrap()
+            .unwrap_or_else(|| panic!("account {} doesn't exist", address));
+        T::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    // overwrites an account's data in place (same owner, lamports + size), ie: to push a counter to its limit
+    pub async fn set_account<T: AccountSerialize>(&mut self, address: &Pubkey, value: &T) {
+        let mut account = self.ctx.banks_client.get_account(*address).await.unwrap().unwrap();
+        let mut data = Vec::new();
+        value.try_serialize(&mut data).unwrap();
+        data.resize(account.data.len(), 0);
+        account.data = data;
+        self.ctx.set_account(address, &AccountSharedData::from(account));
+    }
+
+    // rewrites an account in an older layout, at that layout's size + rent, ie: to test migrating it
+    pub async fn set_legacy_account<T: AnchorSerialize>(&mut self, address: &Pubkey, discriminator: [u8; 8], value: &T, size: usize) {
+        let mut account = self.ctx.banks_client.get_account(*address).await.unwrap().unwrap();
+        let mut data = discriminator.to_vec();
+        value.serialize(&mut data).unwrap();
+        data.resize(size, 0);
+        account.data = data;
+        account.lamports = Rent::default().minimum_balance(size);
+        self.ctx.set_account(address, &AccountSharedData::from(account));
+    }
+
+    pub async fn stache(&mut self) -> CurrentStache {
+        let stache = self.keys.stache;
+        self.account(&stache).await
+    }
+
+    pub async fn vault(&mut self, index: u8) -> Vault {
+        let vault = self.keys.vault(index);
+        self.account(&vault).await
+    }
+
+    pub async fn auto(&mut self, index: u8) -> Auto {
+        let auto = self.keys.auto(index);
+        self.account(&auto).await
+    }
+
+    pub async fn thread(&mut self, index: u8) -> Thread {
+        let auto = self.auto(index).await;
+        self.account(&find_thread(&self.keys.auto(index), &auto.name)).await
+    }
+
+    /////// CLOCK ///////
+
+    pub async fn now(&mut self) -> i64 {
+        self.ctx.banks_client.get_sysvar::<Clock>().await.unwrap().unix_timestamp
+    }
+
+    // moves the clock forward (it can't go back)
+    pub async fn warp(&mut self, seconds: i64) {
+        let mut clock = self.ctx.banks_client.get_sysvar::<Clock>().await.unwrap();
+        clock.unix_timestamp += seconds;
+        self.ctx.set_sysvar(&clock);
+    }
+
+    /////// TOKENS ///////
+
+    // a new spl mint, w/the owner as mint authority
+    pub async fn create_mint(&mut self, decimals: u8) -> Pubkey {
+        let mint = Keypair::new();
+        let owner = self.owner.pubkey();
+        let rent = self.ctx.banks_client.get_rent().await.unwrap();
+        self.send_ok(&[
+            system_instruction::create_account(&owner,
+                                               &mint.pubkey(),
+                                               rent.minimum_balance(spl_token::state::Mint::LEN),
+                                               spl_token::state::Mint::LEN as u64,
+                                               &spl_token::ID),
+            spl_token::instruction::initialize_mint2(&spl_token::ID, &mint.pubkey(), &owner, None, decimals).unwrap(),
+        ], &[&mint]).await;
+        mint.pubkey()
+    }
+
+    // a token-2022 mint w/a transfer fee of basis_points (capped at max_fee); the owner is every authority
+    pub async fn create_fee_mint(&mut self, decimals: u8, basis_points: u16, max_fee: u64) -> Pubkey {
+        let mint = Keypair::new();
+        let owner = self.owner.pubkey();
+        let init_fee = transfer_fee::instruction::initialize_transfer_fee_config(
+            &spl_token_2022::ID, &mint.pubkey(), Some(&owner), Some(&owner), basis_points, max_fee).unwrap();
+        self.create_mint_2022(mint, decimals, ExtensionType::TransferFeeConfig, init_fee).await
+    }
+
+    // a token-2022 mint w/a transfer hook into hook_program (which never runs: stache turns these mints away)
+    pub async fn create_hook_mint(&mut self, decimals: u8, hook_program: &Pubkey) -> Pubkey {
+        let mint = Keypair::new();
+        let owner = self.owner.pubkey();
+        let init_hook = transfer_hook::instruction::initialize(
+            &spl_token_2022::ID, &mint.pubkey(), Some(owner), Some(*hook_program)).unwrap();
+        self.create_mint_2022(mint, decimals, ExtensionType::TransferHook, init_hook).await
+    }
+
+    // extensions have to be initialized before the mint itself
+    async fn create_mint_2022(&mut self, mint: Keypair, decimals: u8, extension: ExtensionType, init_extension: Instruction) -> Pubkey {
+        let owner = self.owner.pubkey();
+        let space = ExtensionType::get_account_len::<spl_token_2022::state::Mint>(&[extension]);
+        let rent = self.ctx.banks_client.get_rent().await.unwrap();
+        self.send_ok(&[
+            system_instruction::create_account(&owner,
+                                               &mint.pubkey(),
+                                               rent.minimum_balance(space),
+                                               space as u64,
+                                               &spl_token_2022::ID),
+            init_extension,
+            spl_token_2022::instruction::initialize_mint2(&spl_token_2022::ID, &mint.pubkey(), &owner, None, decimals).unwrap(),
+        ], &[&mint]).await;
+        mint.pubkey()
+    }
+
+    // token or token-2022, whichever owns the mint
+    pub async fn token_program(&mut self, mint: &Pubkey) -> Pubkey {
+        self.ctx.banks_client.get_account(*mint).await.unwrap()
+            .unwrap_or_else(|| panic!("mint {} doesn't exist", mint))
+            .owner
+    }
+
+    // the wallet's ata for the mint (created if needed)
+    pub async fn create_ata(&mut self, wallet: &Pubkey, mint: &Pubkey) -> Pubkey {
+        let token_program = self.token_program(mint).await;
+        let ata = get_associated_token_address_with_program_id(wallet, mint, &token_program);
+        if !self.exists(&ata).await {
+            let owner = self.owner.pubkey();
+            self.send_ok(&[spl_associated_token_account::instruction::create_associated_token_account(&owner, wallet, mint, &token_program)], &[]).await;
+        }
+        ata
+    }
+
+    pub async fn mint_to(&mut self, mint: &Pubkey, token: &Pubkey, amount: u64) {
+        let owner = self.owner.pubkey();
+        let token_program = self.token_program(mint).await;
+        self.send_ok(&[spl_token_2022::instruction::mint_to(&token_program, mint, token, &owner, &[], amount).unwrap()], &[]).await;
+    }
+
+    // a new mint w/amount tokens in the owner's ata
+    pub async fn funded_mint(&mut self, amount: u64) -> Pubkey {
+        let mint = self.create_mint(6).await;
+        let owner = self.owner.pubkey();
+        let owner_ata = self.create_ata(&owner, &mint).await;
+        self.mint_to(&mint, &owner_ata, amount).await;
+        mint
+    }
+
+    // works for token-2022 accounts (w/extensions) too
+    pub async fn token_balance(&mut self, token: &Pubkey) -> u64 {
+        let account = self.ctx.banks_client.get_account(*token).await.unwrap()
+            .unwrap_or_else(|| panic!("token account {} doesn't exist", token));
+        StateWithExtensions::<spl_token_2022::state::Account>::unpack(&account.data).unwrap().base.amount
+    }
+
+    // a metaplex nft (non-programmable) in the owner's ata. needs metaplex loaded, see has_metaplex
+    pub async fn create_nft(&mut self) -> Pubkey {
+        let mint = Keypair::new();
+        let owner = self.owner.pubkey();
+        let metadata = Metadata::find_pda(&mint.pubkey()).0;
+        let edition = MasterEdition::find_pda(&mint.pubkey()).0;
+        let owner_ata = ata(&owner, &mint.pubkey());
+        let create = CreateV1Builder::new()
+            .metadata(metadata)
+            .master_edition(Some(edition))
+            .mint(mint.pubkey(), true)
+            .authority(owner)
+            .payer(owner)
+            .update_authority(owner, true)
+            .spl_token_program(spl_token::ID)
+            .name("stache nft".to_string())
+            .uri("https://example.com/nft.json".to_string())
+            .seller_fee_basis_points(0)
+            .token_standard(TokenStandard::NonFungible)
+            .print_supply(PrintSupply::Zero)
+            .instruction();
+        let mint_to = MintV1Builder::new()
+            .token(owner_ata)
+            .token_owner(Some(owner))
+            .metadata(metadata)
+            .master_edition(Some(edition))
+            .mint(mint.pubkey())
+            .authority(owner)
+            .payer(owner)
+            .spl_token_program(spl_token::ID)
+            .amount(1)
+            .instruction();
+        self.send_ok(&[create, mint_to], &[&mint]).await;
+        mint.pubkey()
+    }
+
+    /////// STACHE ///////
+
+    // creates a vault off the stache's next index, returns the index
+    pub async fn create_vault(&mut self, name: &str, vault_type: VaultType) -> u8 {
+        let index = self.stache().await.next_vault_index;
+        let create_vault = instruction::create_vault(&self.keys, &self.owner.pubkey(), index, name, vault_type);
+        self.send_ok(&[create_vault], &[]).await;
+        index
+    }
+
+    // creates an automation off the stache's next index, returns the index
+    pub async fn create_auto(&mut self, name: &str) -> u8 {
+        let index = self.stache().await.next_auto_index;
+        let create_auto = instruction::create_auto(&self.keys, &self.owner.pubkey(), index, name);
+        self.send_ok(&[create_auto], &[]).await;
+        index
+    }
+
+    // stashes amount of the mint from the owner's ata
+    pub async fn stash(&mut self, mint: &Pubkey, amount: u64) {
+        let owner = self.owner.pubkey();
+        let token_program = self.token_program(mint).await;
+        let owner_ata = get_associated_token_address_with_program_id(&owner, mint, &token_program);
+        let stash = instruction::stash(&self.keys, &owner, mint, &token_program, Some(owner_ata), amount);
+        self.send_ok(&[stash], &[]).await;
+    }
+
+    // moves amount of the mint from the stache ata into the vault (creating the vault ata)
+    pub async fn fund_vault(&mut self, index: u8, mint: &Pubkey, amount: u64) {
+        let owner = self.owner.pubkey();
+        let token_program = self.token_program(mint).await;
+        let stache_ata = self.keys.ata(mint, &token_program);
+        let deposit = instruction::deposit_to_vault(&self.keys, &owner, index, mint, &token_program, &stache_ata, amount);
+        self.send_ok(&[deposit], &[]).await;
+    }
+
+    /////// MOCKS ///////
+
+    // a squads multisig (squads-mock) w/the given members
+    pub async fn create_multisig(&mut self, threshold: u16, members: Vec<Pubkey>) -> Pubkey {
+        let create_key = Pubkey::new_unique();
+        let multisig = Pubkey::find_program_address(&[b"squad", create_key.as_ref(), b"multisig"], &squads_mock::ID).0;
+        let create = Instruction {
+            program_id: squads_mock::ID,
+            accounts: squads_mock::accounts::Create {
+                multisig,
+                creator: self.owner.pubkey(),
+                system_program: system_program::ID,
+            }.to_account_metas(None),
+            data: squads_mock::instruction::Create { threshold, create_key, members }.data(),
+        };
+        self.send_ok(&[create], &[]).await;
+        multisig
+    }
+
+    // the clockwork 1.x thread a v0 automation would have had (see clockwork_v1_entry), authority first like 1.x has it
+    pub async fn create_v1_thread(&mut self, auto_index: u8, name: &str) -> Pubkey {
+        let auto = self.keys.auto(auto_index);
+        let thread = find_v1_thread(&auto, name);
+        let mut data = hash(b"account:Thread").to_bytes()[..8].to_vec();
+        data.extend_from_slice(auto.as_ref());
+        data.resize(256, 0);
+        let account = Account {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner: clockwork_v1::ID,
+            executable: false,
+            rent_epoch: 0,
+        };
+        self.ctx.set_account(&thread, &AccountSharedData::from(account));
+        thread
+    }
+
+    // what a clockwork worker does when the automation's thread triggers (see clockwork-mock's thread_exec)
+    pub async fn exec_thread(&mut self, auto_index: u8) -> Result<(), BanksClientError> {
+        let auto = self.auto(auto_index).await;
+        let thread_key = find_thread(&self.keys.auto(auto_index), &auto.name);
+        let thread: Thread = self.account(&thread_key).await;
+
+        // the thread signs inside the mock, so nothing in the transaction is a signer
+        let fire = &thread.instructions[0];
+        let mut accounts = clockwork_mock::accounts::ThreadExec { thread: thread_key }.to_account_metas(None);
+        accounts.extend(fire.accounts.iter()
+            .filter(|account| account.pubkey != thread_key)
+            .map(|account| if account.is_writable {
+                AccountMeta::new(account.pubkey, false)
+            } else {
+                AccountMeta::new_readonly(account.pubkey, false)
+            }));
+        accounts.push(AccountMeta::new_readonly(fire.program_id, false));
+
+        let exec = Instruction {
+            program_id: clockwork_mock::ID,
+            accounts,
+            data: clockwork_mock::instruction::ThreadExec {}.data(),
+        };
+        self.send(&[exec], &[]).await
+    }
+}
+
+// env.send borrows the env mutably, so signers held by the env get copied out first
+pub fn copy_keypair(keypair: &Keypair) -> Keypair {
+    Keypair::from_bytes(&keypair.to_bytes()).unwrap()
+}
+
+pub fn keychain_ix(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: keychain::ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+// spl token atas (for token-2022 mints, create_ata hands back the right one)
+pub fn ata(wallet: &Pubkey, mint: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(wallet, mint, &spl_token::ID)
+}
+
+fn error_code(result: Result<(), BanksClientError>) -> u32 {
+    match result.expect_err("transaction should have failed").unwrap() {
+        TransactionError::InstructionError(_, InstructionError::Custom(code)) => code,
+        err => panic!("expected a program error, got: {:?}", err),
+    }
+}
+
+pub fn assert_stache_error(result: Result<(), BanksClientError>, error: StacheError) {
+    assert_eq!(error_code(result), u32::from(error), "expected {:?}", error);
+}
+
+pub fn assert_anchor_error(result: Result<(), BanksClientError>, error: anchor_lang::error::ErrorCode) {
+    assert_eq!(error_code(result), u32::from(error), "expected {:?}", error);
+}
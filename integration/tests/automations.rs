@@ -0,0 +1,465 @@
+use anchor_lang::error::ErrorCode;
+use anchor_spl::token::spl_token;
+use clockwork_sdk::state::Trigger;
+use solana_sdk::{instruction::AccountMeta, pubkey::Pubkey, signature::Signer};
+use stache::account::{TransferMode, VaultType};
+use stache::error::StacheError;
+use stache_client::instruction;
+use stache_client::pda::find_thread;
+use stache_integration::*;
+
+// a stache holding 700 tokens, and an automation paying 100 of them to key2 when it fires (no trigger yet)
+async fn payday() -> (TestEnv, Pubkey, Pubkey, u8) {
+    let mut env = TestEnv::with_stache().await;
+    let owner = env.owner.pubkey();
+    let mint = env.funded_mint(1000).await;
+    env.stash(&mint, 700).await;
+    let payee = env.create_ata(&env.key2.pubkey(), &mint).await;
+
+    let auto = env.create_auto("payday").await;
+    env.send_ok(&[instruction::add_auto_action(&env.keys, &owner, auto, None, &payee, &mint, &spl_token::ID, 100, TransferMode::Fixed)], &[]).await;
+    (env, mint, payee, auto)
+}
+
+// fires while the stache has at least 600
+async fn set_balance_trigger(env: &mut TestEnv, mint: &Pubkey, auto: u8) {
+    let stache_ata = env.keys.ata(mint, &spl_token::ID);
+    let trigger = instruction::set_auto_balance_trigger(&env.keys, &env.owner.pubkey(), auto, &stache_ata, 600, true);
+    env.send_ok(&[trigger], &[]).await;
+}
+
+async fn activate(env: &mut TestEnv, auto: u8) {
+    let auto_account = env.auto(auto).await;
+    let activate = instruction::activate_auto(&env.keys, &env.owner.pubkey(), auto, &auto_account);
+    env.send_ok(&[activate], &[]).await;
+}
+
+#[tokio::test]
+async fn validates_automations() {
+    let (mut env, mint, payee, auto) = payday().await;
+    let owner = env.owner.pubkey();
+    let add_action = |env: &TestEnv, amount: u64, mode: TransferMode| {
+        instruction::add_auto_action(&env.keys, &env.owner.pubkey(), auto, None, &payee, &mint, &spl_token::ID, amount, mode)
+    };
+
+    let result = env.send(&[instruction::create_auto(&env.keys, &owner, 2, "not valid")], &[]).await;
+    assert_stache_error(result, StacheError::InvalidName);
+
+    let result = env.send(&[add_action(&env, 0, TransferMode::Percent)], &[]).await;
+    assert_stache_error(result, StacheError::InvalidAction);
+    let result = env.send(&[add_action(&env, 10001, TransferMode::Percent)], &[]).await;
+    assert_stache_error(result, StacheError::InvalidAction);
+    env.send_ok(&[add_action(&env, 5000, TransferMode::Percent)], &[]).await;
+    env.send_ok(&[add_action(&env, 100, TransferMode::TopUp)], &[]).await;
+    let result = env.send(&[add_action(&env, 100, TransferMode::SweepAbove)], &[]).await;
+    assert_stache_error(result, StacheError::HitLimit);
+
+    env.send_ok(&[instruction::clear_auto_actions(&env.keys, &owner, auto)], &[]).await;
+    let auto_account = env.auto(auto).await;
+    assert!(auto_account.actions.is_empty());
+    let result = env.send(&[instruction::activate_auto(&env.keys, &owner, auto, &auto_account)], &[]).await;
+    assert_stache_error(result, StacheError::MissingAction);
+
+    env.send_ok(&[add_action(&env, 100, TransferMode::Fixed)], &[]).await;
+    let auto_account = env.auto(auto).await;
+    let result = env.send(&[instruction::activate_auto(&env.keys, &owner, auto, &auto_account)], &[]).await;
+    assert_stache_error(result, StacheError::MissingTrigger);
+
+    // schedules have to make sense
+    let result = env.send(&[instruction::set_auto_cron_trigger(&env.keys, &owner, auto, "every tuesday")], &[]).await;
+    assert_stache_error(result, StacheError::InvalidSchedule);
+    let result = env.send(&[instruction::set_auto_cron_trigger(&env.keys, &owner, auto, &"0 ".repeat(33))], &[]).await;
+    assert_stache_error(result, StacheError::InvalidSchedule);
+    let result = env.send(&[instruction::set_auto_interval_trigger(&env.keys, &owner, auto, 0, 0)], &[]).await;
+    assert_stache_error(result, StacheError::InvalidSchedule);
+    let result = env.send(&[instruction::set_auto_interval_trigger(&env.keys, &owner, auto, i64::MAX, 0)], &[]).await;
+    assert_stache_error(result, StacheError::InvalidSchedule);
+    let result = env.send(&[instruction::set_auto_interval_trigger(&env.keys, &owner, auto, 60, i64::MAX)], &[]).await;
+    assert_stache_error(result, StacheError::InvalidSchedule);
+    env.send_ok(&[instruction::set_auto_cron_trigger(&env.keys, &owner, auto, "0 0 12 1 * * *")], &[]).await;
+
+    // so do limits
+    let now = env.now().await;
+    for (max_execs, max_total_amount, expires_at) in [(Some(0), None, None), (None, Some(0), None), (None, None, Some(now - 1))] {
+        let result = env.send(&[instruction::set_auto_limits(&env.keys, &owner, auto, max_execs, max_total_amount, expires_at)], &[]).await;
+        assert_stache_error(result, StacheError::InvalidAutomationLimit);
+    }
+    env.send_ok(&[instruction::set_auto_limits(&env.keys, &owner, auto, Some(10), Some(1000), Some(now + 86400))], &[]).await;
+
+    // only keychain keys get to touch it
+    let outsider = copy_keypair(&env.outsider);
+    let result = env.send(&[instruction::clear_auto_actions(&env.keys, &outsider.pubkey(), auto)], &[&outsider]).await;
+    assert_anchor_error(result, ErrorCode::ConstraintRaw);
+
+    for name in ["two", "three", "four", "five"] {
+        env.create_auto(name).await;
+    }
+    let result = env.send(&[instruction::create_auto(&env.keys, &owner, 6, "six")], &[]).await;
+    assert_stache_error(result, StacheError::HitLimit);
+}
+
+#[tokio::test]
+async fn fires_on_a_balance_trigger() {
+    let (mut env, mint, payee, auto) = payday().await;
+    let owner = env.owner.pubkey();
+    let stache_ata = env.keys.ata(&mint, &spl_token::ID);
+    set_balance_trigger(&mut env, &mint, auto).await;
+    activate(&mut env, auto).await;
+
+    let thread = find_thread(&env.keys.auto(auto), "payday");
+    let auto_account = env.auto(auto).await;
+    assert!(auto_account.active);
+    assert_eq!(auto_account.thread, Some(thread));
+    assert_eq!(env.thread(auto).await.authority, env.keys.auto(auto));
+
+    // 700 -> 600 -> 500, and then it's below the trigger balance
+    for _ in 0..3 {
+        env.exec_thread(auto).await.unwrap();
+    }
+    assert_eq!(env.token_balance(&stache_ata).await, 500);
+    assert_eq!(env.token_balance(&payee).await, 200);
+    let auto_account = env.auto(auto).await;
+    assert_eq!(auto_account.num_triggers, 3);
+    assert_eq!(auto_account.num_execs, 2);
+    assert_eq!(auto_account.total_amount, 200);
+
+    // active automations are locked
+    let result = env.send(&[instruction::set_auto_limits(&env.keys, &owner, auto, Some(5), None, None)], &[]).await;
+    assert_stache_error(result, StacheError::AutomationLocked);
+    let result = env.send(&[instruction::clear_auto_actions(&env.keys, &owner, auto)], &[]).await;
+    assert_stache_error(result, StacheError::AutomationLocked);
+
+    // pausing pauses the thread too
+    env.send_ok(&[instruction::pause_auto(&env.keys, &owner, auto, "payday")], &[]).await;
+    assert!(env.auto(auto).await.paused);
+    assert!(env.thread(auto).await.paused);
+    assert!(env.exec_thread(auto).await.is_err());
+    let result = env.send(&[instruction::pause_auto(&env.keys, &owner, auto, "payday")], &[]).await;
+    assert_stache_error(result, StacheError::AutomationPaused);
+
+    env.send_ok(&[instruction::resume_auto(&env.keys, &owner, auto, "payday")], &[]).await;
+    assert!(!env.thread(auto).await.paused);
+    let result = env.send(&[instruction::resume_auto(&env.keys, &owner, auto, "payday")], &[]).await;
+    assert_stache_error(result, StacheError::AutomationNotPaused);
+
+    // another automation's thread doesn't cut it
+    let other = env.create_auto("other").await;
+    env.send_ok(&[instruction::add_auto_action(&env.keys, &owner, other, None, &payee, &mint, &spl_token::ID, 100, TransferMode::Fixed)], &[]).await;
+    set_balance_trigger(&mut env, &mint, other).await;
+    activate(&mut env, other).await;
+    let mut deactivate = instruction::deactivate_auto(&env.keys, &owner, other, "other");
+    deactivate.accounts[4] = AccountMeta::new(thread, false);
+    let result = env.send(&[deactivate], &[]).await;
+    assert_stache_error(result, StacheError::InvalidThread);
+
+    // deactivating deletes the thread, after which the automation can go w/out one
+    env.send_ok(&[instruction::deactivate_auto(&env.keys, &owner, auto, "payday")], &[]).await;
+    assert!(!env.exists(&thread).await);
+    let auto_account = env.auto(auto).await;
+    assert!(!auto_account.active);
+    assert_eq!(auto_account.thread, None);
+
+    env.send_ok(&[instruction::destroy_auto(&env.keys, &owner, auto, None)], &[]).await;
+    assert!(!env.exists(&env.keys.auto(auto)).await);
+    assert_eq!(env.stache().await.autos, vec![other]);
+}
+
+#[tokio::test]
+async fn reschedules_interval_automations() {
+    let (mut env, mint, payee, auto) = payday().await;
+    let owner = env.owner.pubkey();
+    let now = env.now().await;
+    env.send_ok(&[instruction::set_auto_interval_trigger(&env.keys, &owner, auto, 3600, now)], &[]).await;
+    activate(&mut env, auto).await;
+    assert!(matches!(env.thread(auto).await.trigger, Trigger::Timestamp { unix_ts } if unix_ts >= now + 3600));
+
+    // not due yet, for the thread or by hand
+    assert!(env.exec_thread(auto).await.is_err());
+    let auto_account = env.auto(auto).await;
+    let result = env.send(&[instruction::fire_auto(&env.keys, &owner, auto, &auto_account)], &[]).await;
+    assert_stache_error(result, StacheError::TriggerNotDue);
+
+    env.warp(3600).await;
+    env.exec_thread(auto).await.unwrap();
+    assert_eq!(env.token_balance(&payee).await, 100);
+    assert_eq!(env.token_balance(&env.keys.ata(&mint, &spl_token::ID)).await, 600);
+
+    // and the thread waits for the next one
+    let now = env.now().await;
+    assert!(matches!(env.thread(auto).await.trigger, Trigger::Timestamp { unix_ts } if unix_ts > now));
+    assert!(env.exec_thread(auto).await.is_err());
+}
+
+#[tokio::test]
+async fn pauses_and_resumes() {
+    let (mut env, mint, payee, auto) = payday().await;
+    let owner = env.owner.pubkey();
+    let outsider = copy_keypair(&env.outsider);
+    let now = env.now().await;
+    env.send_ok(&[instruction::set_auto_interval_trigger(&env.keys, &owner, auto, 3600, now)], &[]).await;
+
+    // only active automations can be paused
+    let result = env.send(&[instruction::pause_auto(&env.keys, &owner, auto, "payday")], &[]).await;
+    assert_stache_error(result, StacheError::AutomationInactive);
+    activate(&mut env, auto).await;
+    let result = env.send(&[instruction::pause_auto(&env.keys, &outsider.pubkey(), auto, "payday")], &[&outsider]).await;
+    assert_anchor_error(result, ErrorCode::ConstraintRaw);
+    env.send_ok(&[instruction::pause_auto(&env.keys, &owner, auto, "payday")], &[]).await;
+
+    // several intervals go by while it's paused: nothing fires, by thread or by hand
+    env.warp(3 * 3600 + 60).await;
+    assert!(env.exec_thread(auto).await.is_err());
+    let auto_account = env.auto(auto).await;
+    let result = env.send(&[instruction::fire_auto(&env.keys, &owner, auto, &auto_account)], &[]).await;
+    assert_stache_error(result, StacheError::AutomationPaused);
+    assert_eq!(env.token_balance(&payee).await, 0);
+    // and it stays locked while paused
+    let result = env.send(&[instruction::set_auto_limits(&env.keys, &owner, auto, Some(5), None, None)], &[]).await;
+    assert_stache_error(result, StacheError::AutomationLocked);
+
+    // the missed trigger fires once on resume, not once per missed interval
+    env.send_ok(&[instruction::resume_auto(&env.keys, &owner, auto, "payday")], &[]).await;
+    assert!(!env.auto(auto).await.paused);
+    env.exec_thread(auto).await.unwrap();
+    assert_eq!(env.token_balance(&payee).await, 100);
+    assert_eq!(env.token_balance(&env.keys.ata(&mint, &spl_token::ID)).await, 600);
+    let now = env.now().await;
+    assert!(matches!(env.thread(auto).await.trigger, Trigger::Timestamp { unix_ts } if unix_ts > now && unix_ts <= now + 3600));
+    assert!(env.exec_thread(auto).await.is_err());
+    assert_eq!(env.auto(auto).await.num_execs, 1);
+}
+
+#[tokio::test]
+async fn stops_at_its_limits() {
+    let (mut env, mint, payee, auto) = payday().await;
+    let owner = env.owner.pubkey();
+    set_balance_trigger(&mut env, &mint, auto).await;
+    env.send_ok(&[instruction::set_auto_limits(&env.keys, &owner, auto, Some(1), None, None)], &[]).await;
+    activate(&mut env, auto).await;
+
+    // stops right after the last execution, and parks the thread
+    env.exec_thread(auto).await.unwrap();
+    assert_eq!(env.token_balance(&payee).await, 100);
+    let auto_account = env.auto(auto).await;
+    assert!(!auto_account.active);
+    assert_eq!(auto_account.num_execs, 1);
+    assert!(matches!(env.thread(auto).await.trigger, Trigger::Timestamp { unix_ts: i64::MAX }));
+    assert!(env.exec_thread(auto).await.is_err());
+
+    let result = env.send(&[instruction::pause_auto(&env.keys, &owner, auto, "payday")], &[]).await;
+    assert_stache_error(result, StacheError::AutomationInactive);
+    let result = env.send(&[instruction::resume_auto(&env.keys, &owner, auto, "payday")], &[]).await;
+    assert_stache_error(result, StacheError::AutomationInactive);
+    // the parked thread is still around, so it can't be activated again until it's deactivated
+    let result = env.send(&[instruction::activate_auto(&env.keys, &owner, auto, &auto_account)], &[]).await;
+    assert_anchor_error(result, ErrorCode::AccountNotSystemOwned);
+
+    // the parked thread has to go along w/it
+    let result = env.send(&[instruction::destroy_auto(&env.keys, &owner, auto, None)], &[]).await;
+    assert_stache_error(result, StacheError::MissingAccount);
+    env.send_ok(&[instruction::destroy_auto(&env.keys, &owner, auto, Some("payday"))], &[]).await;
+    assert!(!env.exists(&env.keys.auto(auto)).await);
+    assert!(!env.exists(&find_thread(&env.keys.auto(auto), "payday")).await);
+}
+
+#[tokio::test]
+async fn keeps_its_counters_through_a_deactivation() {
+    let (mut env, mint, payee, auto) = payday().await;
+    let owner = env.owner.pubkey();
+    let stache_ata = env.keys.ata(&mint, &spl_token::ID);
+    let thread = find_thread(&env.keys.auto(auto), "payday");
+    set_balance_trigger(&mut env, &mint, auto).await;
+    env.send_ok(&[instruction::set_auto_limits(&env.keys, &owner, auto, Some(5), Some(250), None)], &[]).await;
+    activate(&mut env, auto).await;
+    env.exec_thread(auto).await.unwrap();
+
+    // deactivating drops the thread but not the history
+    env.send_ok(&[instruction::deactivate_auto(&env.keys, &owner, auto, "payday")], &[]).await;
+    assert!(!env.exists(&thread).await);
+    let auto_account = env.auto(auto).await;
+    assert!(!auto_account.active);
+    assert_eq!(auto_account.thread, None);
+    assert_eq!(auto_account.num_execs, 1);
+    assert_eq!(auto_account.total_amount, 100);
+
+    // unlocked for edits, then back on a fresh thread
+    env.send_ok(&[instruction::set_auto_limits(&env.keys, &owner, auto, Some(5), Some(250), None)], &[]).await;
+    env.stash(&mint, 200).await;
+    activate(&mut env, auto).await;
+    assert_eq!(env.auto(auto).await.thread, Some(thread));
+    env.exec_thread(auto).await.unwrap();
+    assert_eq!(env.token_balance(&payee).await, 200);
+    let auto_account = env.auto(auto).await;
+    assert_eq!(auto_account.num_execs, 2);
+    assert_eq!(auto_account.total_amount, 200);
+
+    // the budget counts what it spent before the deactivation: another 100 would make 300
+    env.exec_thread(auto).await.unwrap();
+    assert_eq!(env.token_balance(&payee).await, 200);
+    assert_eq!(env.token_balance(&stache_ata).await, 700);
+    let auto_account = env.auto(auto).await;
+    assert!(!auto_account.active);
+    assert_eq!(auto_account.num_execs, 2);
+    assert_eq!(auto_account.total_amount, 200);
+}
+
+#[tokio::test]
+async fn trims_variable_transfers_to_the_budget() {
+    let mut env = TestEnv::with_stache().await;
+    let owner = env.owner.pubkey();
+    let mint = env.funded_mint(1000).await;
+    env.stash(&mint, 600).await;
+    let payee = env.create_ata(&env.key2.pubkey(), &mint).await;
+    let stache_ata = env.keys.ata(&mint, &spl_token::ID);
+
+    // sweeps everything above 100 once the stache has more than 650, w/a budget of 250
+    let auto = env.create_auto("sweeper").await;
+    env.send_ok(&[instruction::add_auto_action(&env.keys, &owner, auto, None, &payee, &mint, &spl_token::ID, 100, TransferMode::SweepAbove)], &[]).await;
+    env.send_ok(&[instruction::set_auto_balance_trigger(&env.keys, &owner, auto, &stache_ata, 650, true)], &[]).await;
+    env.send_ok(&[instruction::set_auto_limits(&env.keys, &owner, auto, None, Some(250), None)], &[]).await;
+    activate(&mut env, auto).await;
+
+    // a sweep bigger than the budget doesn't stop it while the trigger isn't met
+    env.exec_thread(auto).await.unwrap();
+    assert!(env.auto(auto).await.active);
+    assert_eq!(env.token_balance(&payee).await, 0);
+
+    // once it is, the sweep gets trimmed to the budget, which stops it
+    env.stash(&mint, 100).await;
+    env.exec_thread(auto).await.unwrap();
+    assert_eq!(env.token_balance(&payee).await, 250);
+    assert_eq!(env.token_balance(&stache_ata).await, 450);
+    let auto_account = env.auto(auto).await;
+    assert_eq!(auto_account.total_amount, 250);
+    assert!(!auto_account.active);
+}
+
+#[tokio::test]
+async fn fires_by_hand() {
+    let (mut env, mint, payee, auto) = payday().await;
+    let owner = env.owner.pubkey();
+    let owner_ata = ata(&owner, &mint);
+    let outsider = copy_keypair(&env.outsider);
+    set_balance_trigger(&mut env, &mint, auto).await;
+    let auto_key = env.keys.auto(auto);
+    let auto_account = env.auto(auto).await;
+
+    // only a key on the keychain, and only once it's active
+    let result = env.send(&[instruction::fire_auto(&env.keys, &outsider.pubkey(), auto, &auto_account)], &[&outsider]).await;
+    assert_stache_error(result, StacheError::NotAuthorized);
+    let result = env.send(&[instruction::fire_auto(&env.keys, &owner, auto, &auto_account)], &[]).await;
+    assert_stache_error(result, StacheError::AutomationInactive);
+    activate(&mut env, auto).await;
+    env.send_ok(&[instruction::fire_auto(&env.keys, &owner, auto, &auto_account)], &[]).await;
+    assert_eq!(env.token_balance(&payee).await, 100);
+    assert_eq!(env.auto(auto).await.num_execs, 1);
+
+    // accounts: stache, auto, thread, keychain, authority, then the trigger account + each action's accounts
+    let fire = |env: &TestEnv, slot: usize, account: AccountMeta| {
+        let mut fire = instruction::fire_auto(&env.keys, &env.owner.pubkey(), auto, &auto_account);
+        fire.accounts[slot] = account;
+        fire
+    };
+    let result = env.send(&[fire(&env, 5, AccountMeta::new_readonly(owner_ata, false))], &[]).await;
+    assert_stache_error(result, StacheError::InvalidTrigger);
+    let result = env.send(&[fire(&env, 7, AccountMeta::new(owner_ata, false))], &[]).await;
+    assert_stache_error(result, StacheError::InvalidAction);
+    let result = env.send(&[fire(&env, 3, AccountMeta::new_readonly(stache::ID, false))], &[]).await;
+    assert_stache_error(result, StacheError::MissingAccount);
+
+    // counters can't overflow
+    let mut overflowing = env.auto(auto).await;
+    overflowing.num_triggers = u32::MAX;
+    env.set_account(&auto_key, &overflowing).await;
+    let result = env.send(&[instruction::fire_auto(&env.keys, &owner, auto, &auto_account)], &[]).await;
+    assert_stache_error(result, StacheError::TriggerLimit);
+
+    overflowing.num_triggers = 0;
+    overflowing.total_amount = u64::MAX - 50;
+    env.set_account(&auto_key, &overflowing).await;
+    let result = env.send(&[instruction::fire_auto(&env.keys, &owner, auto, &auto_account)], &[]).await;
+    assert_stache_error(result, StacheError::ExecLimit);
+
+    overflowing.total_amount = 100;
+    env.set_account(&auto_key, &overflowing).await;
+
+    // held to the stache's spending limits
+    env.send_ok(&[instruction::set_spending_limit(&env.keys, &owner, None, None, &mint, 50, 86400)], &[]).await;
+    let result = env.send(&[instruction::fire_auto(&env.keys, &owner, auto, &auto_account)], &[]).await;
+    assert_stache_error(result, StacheError::SpendingLimitExceeded);
+
+    env.send_ok(&[instruction::pause_auto(&env.keys, &owner, auto, "payday")], &[]).await;
+    let result = env.send(&[instruction::fire_auto(&env.keys, &owner, auto, &auto_account)], &[]).await;
+    assert_stache_error(result, StacheError::AutomationPaused);
+}
+
+#[tokio::test]
+async fn dry_runs_without_changing_anything() {
+    let (mut env, mint, payee, auto) = payday().await;
+    let owner = env.owner.pubkey();
+    set_balance_trigger(&mut env, &mint, auto).await;
+    let auto_account = env.auto(auto).await;
+
+    env.send_ok(&[instruction::dry_run_auto(&env.keys, &owner, auto, &auto_account)], &[]).await;
+    assert_eq!(env.token_balance(&payee).await, 0);
+    assert_eq!(env.token_balance(&env.keys.ata(&mint, &spl_token::ID)).await, 700);
+    let after = env.auto(auto).await;
+    assert_eq!(after.num_triggers, 0);
+    assert_eq!(after.num_execs, 0);
+
+    let outsider = copy_keypair(&env.outsider);
+    let result = env.send(&[instruction::dry_run_auto(&env.keys, &outsider.pubkey(), auto, &auto_account)], &[&outsider]).await;
+    assert_anchor_error(result, ErrorCode::ConstraintRaw);
+}
+
+#[tokio::test]
+async fn pulls_from_approved_vaults() {
+    let mut env = TestEnv::with_stache().await;
+    let owner = env.owner.pubkey();
+    let key2 = copy_keypair(&env.key2);
+    let mint = env.funded_mint(1000).await;
+    env.stash(&mint, 1000).await;
+    let payee = env.create_ata(&key2.pubkey(), &mint).await;
+
+    let savings = env.create_vault("savings", VaultType::TwoSig).await;
+    env.fund_vault(savings, &mint, 500).await;
+    let other = env.create_vault("other", VaultType::Easy).await;
+    env.fund_vault(other, &mint, 100).await;
+    let savings_ata = env.keys.vault_ata(savings, &mint, &spl_token::ID);
+
+    let auto = env.create_auto("sweep").await;
+    let auto_key = env.keys.auto(auto);
+    env.send_ok(&[instruction::add_auto_action(&env.keys, &owner, auto, Some(savings), &payee, &mint, &spl_token::ID, 100, TransferMode::Fixed)], &[]).await;
+    env.send_ok(&[instruction::set_auto_balance_trigger(&env.keys, &owner, auto, &savings_ata, 0, true)], &[]).await;
+    let auto_account = env.auto(auto).await;
+
+    // a 2-sig vault has to sign off on it first
+    let result = env.send(&[instruction::activate_auto(&env.keys, &owner, auto, &auto_account)], &[]).await;
+    assert_stache_error(result, StacheError::NotAuthorized);
+    let mut wrong_vault = instruction::activate_auto(&env.keys, &owner, auto, &auto_account);
+    let last = wrong_vault.accounts.len() - 1;
+    wrong_vault.accounts[last] = AccountMeta::new_readonly(env.keys.vault(other), false);
+    let result = env.send(&[wrong_vault], &[]).await;
+    assert_stache_error(result, StacheError::InvalidVault);
+
+    let result = env.send(&[instruction::authorize_vault_auto(&env.keys, &owner, savings, auto, 1, None)], &[]).await;
+    assert_stache_error(result, StacheError::MissingAction);
+    let result = env.send(&[instruction::authorize_vault_auto(&env.keys, &owner, other, auto, 0, None)], &[]).await;
+    assert_stache_error(result, StacheError::InvalidAction);
+
+    env.send_ok(&[instruction::authorize_vault_auto(&env.keys, &owner, savings, auto, 0, None)], &[]).await;
+    let action_index = env.vault(savings).await.actions[0].action_index;
+    env.send_ok(&[instruction::approve_action(&env.keys, &key2.pubkey(), savings, action_index, &spl_token::ID, None, vec![])], &[&key2]).await;
+    assert_eq!(env.vault(savings).await.authorized_autos[0].auto, auto_key);
+
+    activate(&mut env, auto).await;
+    env.exec_thread(auto).await.unwrap();
+    assert_eq!(env.token_balance(&savings_ata).await, 400);
+    assert_eq!(env.token_balance(&payee).await, 100);
+
+    // revoking takes effect on the next fire
+    env.send_ok(&[instruction::revoke_vault_auto(&env.keys, &owner, savings, &auto_key)], &[]).await;
+    let result = env.exec_thread(auto).await;
+    assert_stache_error(result, StacheError::NotAuthorized);
+    assert_eq!(env.token_balance(&payee).await, 100);
+}
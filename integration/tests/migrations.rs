@@ -0,0 +1,259 @@
+use anchor_lang::error::ErrorCode;
+use anchor_lang::{AnchorSerialize, Discriminator};
+use anchor_spl::token::spl_token;
+use solana_sdk::{pubkey::Pubkey, rent::Rent, signature::Signer};
+use stache::account::{ActionType, Auto, CurrentStache, TransferAction, TransferMode, Vault, VaultType};
+use stache::error::StacheError;
+use stache::migration::{clockwork_v1, AutoV0, StacheV0, VaultActionV0, VaultV0};
+use stache_client::{instruction, THREAD_PROGRAM_ID};
+use stache_client::pda::find_thread;
+use stache_integration::*;
+
+#[tokio::test]
+async fn migrates_the_stache() {
+    let mut env = TestEnv::with_stache().await;
+    let owner = env.owner.pubkey();
+    let outsider = copy_keypair(&env.outsider);
+    let stache_key = env.keys.stache;
+    let vault = env.create_vault("savings", VaultType::Easy).await;
+
+    // new staches start out on the current version
+    let stache = env.stache().await;
+    assert_eq!(stache.version, CurrentStache::CURRENT_VERSION);
+    let result = env.send(&[instruction::migrate_stache(&env.keys, &owner)], &[]).await;
+    assert_stache_error(result, StacheError::AlreadyMigrated);
+
+    let legacy = StacheV0 {
+        version: 0,
+        bump: stache.bump,
+        keychain: stache.keychain,
+        domain: stache.domain,
+        stacheid: stache.stacheid.clone(),
+        next_vault_index: stache.next_vault_index,
+        next_auto_index: stache.next_auto_index,
+        vaults: stache.vaults.clone(),
+        autos: stache.autos.clone(),
+    };
+    // versions it doesn't know about
+    let unknown = StacheV0 { version: 9, ..legacy.clone() };
+    env.set_legacy_account(&stache_key, CurrentStache::DISCRIMINATOR, &unknown, 8 + StacheV0::MAX_SIZE).await;
+    let result = env.send(&[instruction::migrate_stache(&env.keys, &owner)], &[]).await;
+    assert_stache_error(result, StacheError::InvalidVersion);
+
+    env.set_legacy_account(&stache_key, CurrentStache::DISCRIMINATOR, &legacy, 8 + StacheV0::MAX_SIZE).await;
+
+    let result = env.send(&[instruction::migrate_stache(&env.keys, &outsider.pubkey())], &[&outsider]).await;
+    assert_anchor_error(result, ErrorCode::ConstraintRaw);
+
+    env.send_ok(&[instruction::migrate_stache(&env.keys, &owner)], &[]).await;
+    let migrated = env.stache().await;
+    assert_eq!(migrated.version, CurrentStache::CURRENT_VERSION);
+    assert_eq!(migrated.stacheid, NAME);
+    assert_eq!(migrated.vaults, vec![vault]);
+    assert_eq!(migrated.next_vault_index, stache.next_vault_index);
+    assert!(migrated.spending_limits.is_empty());
+
+    // the owner paid for the extra space, which the spending limits need
+    assert_eq!(env.lamports(&stache_key).await, Rent::default().minimum_balance(8 + CurrentStache::MAX_SIZE));
+    for _ in 0..3 {
+        env.send_ok(&[instruction::set_spending_limit(&env.keys, &owner, None, None, &Pubkey::new_unique(), 100, 3600)], &[]).await;
+    }
+    assert_eq!(env.stache().await.spending_limits.len(), 3);
+}
+
+#[tokio::test]
+async fn migrates_vaults_w_pending_actions() {
+    let mut env = TestEnv::with_stache().await;
+    let owner = env.owner.pubkey();
+    let key2 = copy_keypair(&env.key2);
+    let mint = env.funded_mint(1000).await;
+    let owner_ata = ata(&owner, &mint);
+    env.stash(&mint, 1000).await;
+    let vault = env.create_vault("shared", VaultType::TwoSig).await;
+    env.fund_vault(vault, &mint, 500).await;
+    let vault_key = env.keys.vault(vault);
+    let vault_ata = env.keys.vault_ata(vault, &mint, &spl_token::ID);
+
+    env.send_ok(&[instruction::withdraw_from_vault(&env.keys, &owner, vault, &mint, &spl_token::ID, &owner_ata, None, 100)], &[]).await;
+    let current = env.vault(vault).await;
+    assert_eq!(current.version, Vault::CURRENT_VERSION);
+
+    let legacy = VaultV0 {
+        stache: current.stache,
+        index: current.index,
+        bump: current.bump,
+        vault_type: current.vault_type.clone(),
+        locked: current.locked,
+        name: current.name.clone(),
+        next_action_index: current.next_action_index,
+        actions: current.actions.iter().map(|action| VaultActionV0 {
+            action_index: action.action_index,
+            action_type: action.action_type.clone(),
+            approvers: action.approvers.clone(),
+            action: action.action.clone(),
+        }).collect(),
+    };
+    env.set_legacy_account(&vault_key, Vault::DISCRIMINATOR, &legacy, 8 + VaultV0::MAX_SIZE).await;
+
+    env.send_ok(&[instruction::migrate_vault(&env.keys, &owner, vault)], &[]).await;
+    let migrated = env.vault(vault).await;
+    assert_eq!(migrated.version, Vault::CURRENT_VERSION);
+    assert_eq!(migrated.name, "shared");
+    assert_eq!(migrated.actions.len(), 1);
+    assert_eq!(migrated.actions[0].approvers, vec![owner]);
+    assert_eq!(migrated.actions[0].executable_at, 0);
+    assert!(migrated.mints.is_empty());
+    assert_eq!(env.lamports(&vault_key).await, Rent::default().minimum_balance(8 + Vault::MAX_SIZE));
+
+    // the pending withdrawal carried over
+    let accounts = instruction::transfer_action_accounts(&vault_ata, &owner_ata, &mint);
+    env.send_ok(&[instruction::approve_action(&env.keys, &key2.pubkey(), vault, 1, &spl_token::ID, None, accounts)], &[&key2]).await;
+    assert_eq!(env.token_balance(&vault_ata).await, 400);
+
+    let result = env.send(&[instruction::migrate_vault(&env.keys, &owner, vault)], &[]).await;
+    assert_stache_error(result, StacheError::AlreadyMigrated);
+}
+
+#[tokio::test]
+async fn migrates_automations() {
+    let mut env = TestEnv::with_stache().await;
+    let owner = env.owner.pubkey();
+    let mint = env.funded_mint(1000).await;
+    env.stash(&mint, 700).await;
+    let payee = env.create_ata(&env.key2.pubkey(), &mint).await;
+    let stache_ata = env.keys.ata(&mint, &spl_token::ID);
+
+    let auto = env.create_auto("payday").await;
+    let auto_key = env.keys.auto(auto);
+    let thread = find_thread(&auto_key, "payday");
+    env.send_ok(&[instruction::add_auto_action(&env.keys, &owner, auto, None, &payee, &mint, &spl_token::ID, 100, TransferMode::Fixed)], &[]).await;
+    env.send_ok(&[instruction::set_auto_balance_trigger(&env.keys, &owner, auto, &stache_ata, 600, true)], &[]).await;
+    let current = env.auto(auto).await;
+    env.send_ok(&[instruction::activate_auto(&env.keys, &owner, auto, &current)], &[]).await;
+    assert!(env.exists(&thread).await);
+
+    // a v0 automation: a single transfer out of the stache ata, w/a thread that calls the old fire_auto
+    let legacy = AutoV0 {
+        stache: env.keys.stache,
+        index: auto,
+        bump: current.bump,
+        active: true,
+        paused: false,
+        num_triggers: 3,
+        num_execs: 2,
+        thread: Some(thread),
+        name: "payday".to_string(),
+        action_type: Some(ActionType::Transfer),
+        action: Some(TransferAction { from: stache_ata, to: payee, amount: 100 }.try_to_vec().unwrap()),
+        trigger_type: current.trigger_type.clone(),
+        trigger: current.trigger.clone(),
+    };
+    env.set_legacy_account(&auto_key, Auto::DISCRIMINATOR, &legacy, 8 + AutoV0::MAX_SIZE).await;
+
+    let old_thread = Some((thread, THREAD_PROGRAM_ID));
+    let migrate = |env: &TestEnv, from_token: Option<Pubkey>, thread: Option<(Pubkey, Pubkey)>| {
+        instruction::migrate_auto(&env.keys, &env.owner.pubkey(), auto, from_token, thread)
+    };
+
+    // the transfer's mint comes from its from account, and the old thread has to go (through the program that owns it)
+    let result = env.send(&[migrate(&env, None, old_thread)], &[]).await;
+    assert_stache_error(result, StacheError::MissingAccount);
+    let result = env.send(&[migrate(&env, Some(payee), old_thread)], &[]).await;
+    assert_stache_error(result, StacheError::TokenAccountsMismatch);
+    let result = env.send(&[migrate(&env, Some(stache_ata), None)], &[]).await;
+    assert_stache_error(result, StacheError::MissingAccount);
+    let result = env.send(&[migrate(&env, Some(stache_ata), Some((thread, clockwork_v1::ID)))], &[]).await;
+    assert_stache_error(result, StacheError::InvalidThread);
+
+    env.send_ok(&[migrate(&env, Some(stache_ata), old_thread)], &[]).await;
+    assert!(!env.exists(&thread).await);
+    let migrated = env.auto(auto).await;
+    assert_eq!(migrated.version, Auto::CURRENT_VERSION);
+    assert!(!migrated.active);
+    assert!(migrated.thread.is_none());
+    assert_eq!(migrated.num_execs, 2);
+    assert_eq!(migrated.actions.len(), 1);
+    let action = &migrated.actions[0];
+    assert_eq!((action.from, action.to, action.mint, action.token_program), (stache_ata, payee, mint, spl_token::ID));
+    assert_eq!(action.amount, 100);
+    assert_eq!(action.mode, TransferMode::Fixed);
+    assert!(action.vault.is_none());
+
+    let result = env.send(&[migrate(&env, Some(stache_ata), None)], &[]).await;
+    assert_stache_error(result, StacheError::AlreadyMigrated);
+
+    // and it runs again once it's reactivated
+    env.send_ok(&[instruction::activate_auto(&env.keys, &owner, auto, &migrated)], &[]).await;
+    env.exec_thread(auto).await.unwrap();
+    assert_eq!(env.token_balance(&payee).await, 100);
+}
+
+// automations from before the move to clockwork 2.0 have clockwork 1.x threads, which the 2.0 sdk can't load
+#[tokio::test]
+async fn migrates_automations_w_clockwork_v1_threads() {
+    let mut env = TestEnv::with_stache().await;
+    let owner = env.owner.pubkey();
+    let auto = env.create_auto("payday").await;
+    let auto_key = env.keys.auto(auto);
+    let current = env.auto(auto).await;
+    let thread = env.create_v1_thread(auto, "payday").await;
+
+    let legacy = AutoV0 {
+        stache: env.keys.stache,
+        index: auto,
+        bump: current.bump,
+        active: true,
+        paused: false,
+        num_triggers: 0,
+        num_execs: 0,
+        thread: Some(thread),
+        name: "payday".to_string(),
+        action_type: None,
+        action: None,
+        trigger_type: None,
+        trigger: None,
+    };
+    env.set_legacy_account(&auto_key, Auto::DISCRIMINATOR, &legacy, 8 + AutoV0::MAX_SIZE).await;
+
+    let result = env.send(&[instruction::migrate_auto(&env.keys, &owner, auto, None, Some((thread, THREAD_PROGRAM_ID)))], &[]).await;
+    assert_stache_error(result, StacheError::InvalidThread);
+
+    // the automation signs for the 1.x thread_delete
+    env.send_ok(&[instruction::migrate_auto(&env.keys, &owner, auto, None, Some((thread, clockwork_v1::ID)))], &[]).await;
+    assert!(!env.exists(&thread).await);
+    let migrated = env.auto(auto).await;
+    assert_eq!(migrated.version, Auto::CURRENT_VERSION);
+    assert!(migrated.thread.is_none());
+}
+
+#[tokio::test]
+async fn skips_threads_that_are_already_gone() {
+    let mut env = TestEnv::with_stache().await;
+    let owner = env.owner.pubkey();
+    let auto = env.create_auto("payday").await;
+    let auto_key = env.keys.auto(auto);
+    let current = env.auto(auto).await;
+    let thread = find_thread(&auto_key, "payday");
+
+    let legacy = AutoV0 {
+        stache: env.keys.stache,
+        index: auto,
+        bump: current.bump,
+        active: false,
+        paused: false,
+        num_triggers: 0,
+        num_execs: 0,
+        thread: Some(thread),
+        name: "payday".to_string(),
+        action_type: None,
+        action: None,
+        trigger_type: None,
+        trigger: None,
+    };
+    env.set_legacy_account(&auto_key, Auto::DISCRIMINATOR, &legacy, 8 + AutoV0::MAX_SIZE).await;
+
+    let result = env.send(&[instruction::migrate_auto(&env.keys, &owner, auto, None, Some((Pubkey::new_unique(), THREAD_PROGRAM_ID)))], &[]).await;
+    assert_stache_error(result, StacheError::InvalidThread);
+    env.send_ok(&[instruction::migrate_auto(&env.keys, &owner, auto, None, Some((thread, THREAD_PROGRAM_ID)))], &[]).await;
+    assert_eq!(env.auto(auto).await.version, Auto::CURRENT_VERSION);
+}
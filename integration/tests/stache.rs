@@ -0,0 +1,193 @@
+use anchor_lang::error::ErrorCode;
+use anchor_lang::solana_program::{native_token::LAMPORTS_PER_SOL, system_instruction};
+use anchor_spl::token::spl_token::{self, native_mint};
+use anchor_spl::token_2022::spl_token_2022;
+use solana_sdk::{pubkey::Pubkey, signature::Signer};
+use stache::account::VaultType;
+use stache::error::StacheError;
+use stache_client::instruction;
+use stache_integration::*;
+
+#[tokio::test]
+async fn creates_the_stache() {
+    let mut env = TestEnv::new().await;
+    let pending = copy_keypair(&env.pending);
+    let outsider = copy_keypair(&env.outsider);
+
+    // has to be a verified key on the keychain
+    let result = env.send(&[instruction::create_stache(&env.keys, &outsider.pubkey())], &[&outsider]).await;
+    assert_anchor_error(result, ErrorCode::ConstraintRaw);
+    let result = env.send(&[instruction::create_stache(&env.keys, &pending.pubkey())], &[&pending]).await;
+    assert_stache_error(result, StacheError::NotAuthorized);
+
+    env.send_ok(&[instruction::create_stache(&env.keys, &env.owner.pubkey())], &[]).await;
+    let stache = env.stache().await;
+    assert_eq!(stache.stacheid, NAME);
+    assert_eq!(stache.keychain, env.keys.keychain);
+    assert_eq!(stache.domain, env.keys.domain);
+    assert_eq!(stache.next_vault_index, 1);
+    assert_eq!(stache.next_auto_index, 1);
+}
+
+#[tokio::test]
+async fn stashes_and_unstashes_tokens() {
+    let mut env = TestEnv::with_stache().await;
+    let owner = env.owner.pubkey();
+    let mint = env.funded_mint(1000).await;
+    let owner_ata = ata(&owner, &mint);
+    let stache_ata = env.keys.ata(&mint, &spl_token::ID);
+
+    env.stash(&mint, 600).await;
+    assert_eq!(env.token_balance(&stache_ata).await, 600);
+    assert_eq!(env.token_balance(&owner_ata).await, 400);
+
+    env.send_ok(&[instruction::unstash(&env.keys, &owner, &mint, &spl_token::ID, Some(owner_ata), false, 100)], &[]).await;
+    assert_eq!(env.token_balance(&stache_ata).await, 500);
+    assert_eq!(env.token_balance(&owner_ata).await, 500);
+
+    // only wsol can be unstashed w/out a token account to send it to
+    let result = env.send(&[instruction::unstash(&env.keys, &owner, &mint, &spl_token::ID, None, false, 100)], &[]).await;
+    assert_stache_error(result, StacheError::MissingAccount);
+
+    // outsiders can't touch it
+    let outsider = copy_keypair(&env.outsider);
+    let result = env.send(&[instruction::unstash(&env.keys, &outsider.pubkey(), &mint, &spl_token::ID, Some(owner_ata), false, 100)], &[&outsider]).await;
+    assert_anchor_error(result, ErrorCode::ConstraintRaw);
+}
+
+#[tokio::test]
+async fn rejects_mints_with_a_transfer_hook() {
+    let mut env = TestEnv::with_stache().await;
+    let owner = env.owner.pubkey();
+    let mint = env.create_hook_mint(6, &Pubkey::new_unique()).await;
+    let owner_ata = env.create_ata(&owner, &mint).await;
+    env.mint_to(&mint, &owner_ata, 1000).await;
+
+    let result = env.send(&[instruction::stash(&env.keys, &owner, &mint, &spl_token_2022::ID, Some(owner_ata), 100)], &[]).await;
+    assert_stache_error(result, StacheError::TransferHookNotSupported);
+    assert_eq!(env.token_balance(&owner_ata).await, 1000);
+}
+
+#[tokio::test]
+async fn wraps_and_unwraps_sol() {
+    let mut env = TestEnv::with_stache().await;
+    let owner = env.owner.pubkey();
+    let stache_ata = env.keys.ata(&native_mint::ID, &spl_token::ID);
+
+    // no from token = wrap the owner's sol
+    env.send_ok(&[instruction::stash(&env.keys, &owner, &native_mint::ID, &spl_token::ID, None, LAMPORTS_PER_SOL)], &[]).await;
+    assert_eq!(env.token_balance(&stache_ata).await, LAMPORTS_PER_SOL);
+
+    // partial unwraps go through the unwrap account, which gets closed again
+    env.send_ok(&[instruction::unstash(&env.keys, &owner, &native_mint::ID, &spl_token::ID, None, true, LAMPORTS_PER_SOL / 4)], &[]).await;
+    assert_eq!(env.token_balance(&stache_ata).await, LAMPORTS_PER_SOL * 3 / 4);
+    assert!(!env.exists(&env.keys.unwrap()).await);
+
+    let result = env.send(&[instruction::unstash(&env.keys, &owner, &native_mint::ID, &spl_token::ID, None, true, LAMPORTS_PER_SOL)], &[]).await;
+    assert_stache_error(result, StacheError::InsufficientFunds);
+
+    // unwrapping w/out the unwrap account has to take everything (it closes the stache ata)
+    let result = env.send(&[instruction::unstash(&env.keys, &owner, &native_mint::ID, &spl_token::ID, None, false, LAMPORTS_PER_SOL / 4)], &[]).await;
+    assert_stache_error(result, StacheError::MissingAccount);
+
+    env.send_ok(&[instruction::unstash(&env.keys, &owner, &native_mint::ID, &spl_token::ID, None, false, LAMPORTS_PER_SOL * 3 / 4)], &[]).await;
+    assert!(!env.exists(&stache_ata).await);
+}
+
+#[tokio::test]
+async fn unstashes_sol_held_by_the_stache() {
+    let mut env = TestEnv::with_stache().await;
+    let owner = env.owner.pubkey();
+    let stache = env.keys.stache;
+
+    env.send_ok(&[system_instruction::transfer(&owner, &stache, LAMPORTS_PER_SOL)], &[]).await;
+    let before = env.lamports(&stache).await;
+
+    env.send_ok(&[instruction::unstash_sol(&env.keys, &owner, LAMPORTS_PER_SOL / 2)], &[]).await;
+    assert_eq!(env.lamports(&stache).await, before - LAMPORTS_PER_SOL / 2);
+
+    // the stache keeps its rent
+    let result = env.send(&[instruction::unstash_sol(&env.keys, &owner, LAMPORTS_PER_SOL)], &[]).await;
+    assert_stache_error(result, StacheError::InsufficientFunds);
+}
+
+#[tokio::test]
+async fn enforces_stache_spending_limits() {
+    let mut env = TestEnv::with_stache().await;
+    let owner = env.owner.pubkey();
+    let key2 = copy_keypair(&env.key2);
+    let mint = env.funded_mint(1000).await;
+    let owner_ata = ata(&owner, &mint);
+    env.stash(&mint, 1000).await;
+
+    let result = env.send(&[instruction::set_spending_limit(&env.keys, &owner, None, None, &mint, 100, 0)], &[]).await;
+    assert_stache_error(result, StacheError::InvalidSpendingLimit);
+
+    // a new limit doesn't need a cosigner
+    env.send_ok(&[instruction::set_spending_limit(&env.keys, &owner, None, None, &mint, 100, 3600)], &[]).await;
+
+    env.send_ok(&[instruction::unstash(&env.keys, &owner, &mint, &spl_token::ID, Some(owner_ata), false, 60)], &[]).await;
+    let result = env.send(&[instruction::unstash(&env.keys, &owner, &mint, &spl_token::ID, Some(owner_ata), false, 50)], &[]).await;
+    assert_stache_error(result, StacheError::SpendingLimitExceeded);
+
+    // next window
+    env.warp(3600).await;
+    env.send_ok(&[instruction::unstash(&env.keys, &owner, &mint, &spl_token::ID, Some(owner_ata), false, 50)], &[]).await;
+
+    // loosening needs a 2nd verified key, tightening doesn't
+    let result = env.send(&[instruction::set_spending_limit(&env.keys, &owner, None, None, &mint, 200, 3600)], &[]).await;
+    assert_stache_error(result, StacheError::InvalidCosigner);
+    let result = env.send(&[instruction::set_spending_limit(&env.keys, &owner, None, None, &mint, 100, 60)], &[]).await;
+    assert_stache_error(result, StacheError::InvalidCosigner);
+    env.send_ok(&[instruction::set_spending_limit(&env.keys, &owner, None, Some(key2.pubkey()), &mint, 200, 3600)], &[&key2]).await;
+    env.send_ok(&[instruction::set_spending_limit(&env.keys, &owner, None, None, &mint, 150, 3600)], &[]).await;
+    assert_eq!(env.stache().await.spending_limits[0].amount, 150);
+
+    let result = env.send(&[instruction::remove_spending_limit(&env.keys, &owner, None, &owner, &mint)], &[]).await;
+    assert_stache_error(result, StacheError::InvalidCosigner);
+    env.send_ok(&[instruction::remove_spending_limit(&env.keys, &owner, None, &key2.pubkey(), &mint)], &[&key2]).await;
+    assert!(env.stache().await.spending_limits.is_empty());
+
+    // limits per mint are capped
+    for _ in 0..3 {
+        env.send_ok(&[instruction::set_spending_limit(&env.keys, &owner, None, None, &Pubkey::new_unique(), 100, 3600)], &[]).await;
+    }
+    let result = env.send(&[instruction::set_spending_limit(&env.keys, &owner, None, None, &mint, 100, 3600)], &[]).await;
+    assert_stache_error(result, StacheError::HitLimit);
+}
+
+#[tokio::test]
+async fn destroys_the_stache() {
+    let mut env = TestEnv::with_stache().await;
+    let owner = env.owner.pubkey();
+    let key2 = copy_keypair(&env.key2);
+    let pending = copy_keypair(&env.pending);
+    let mint = env.funded_mint(1000).await;
+    let owner_ata = ata(&owner, &mint);
+    let stache_ata = env.keys.ata(&mint, &spl_token::ID);
+    env.stash(&mint, 500).await;
+    let vault = env.create_vault("savings", VaultType::Easy).await;
+
+    let destroy = |env: &TestEnv, cosigner: &Pubkey, sweep: &[(Pubkey, Pubkey)]| {
+        instruction::destroy_stache(&env.keys, &env.owner.pubkey(), cosigner, &env.owner.pubkey(), &spl_token::ID, sweep)
+    };
+
+    let result = env.send(&[destroy(&env, &key2.pubkey(), &[(stache_ata, mint)])], &[&key2]).await;
+    assert_stache_error(result, StacheError::StacheNotEmpty);
+    env.send_ok(&[instruction::destroy_vault(&env.keys, &owner, vault)], &[]).await;
+
+    // needs a 2nd verified key
+    let result = env.send(&[destroy(&env, &owner, &[(stache_ata, mint)])], &[]).await;
+    assert_stache_error(result, StacheError::InvalidCosigner);
+    let result = env.send(&[destroy(&env, &pending.pubkey(), &[(stache_ata, mint)])], &[&pending]).await;
+    assert_stache_error(result, StacheError::InvalidCosigner);
+
+    // sweeps only empty the stache's own token accounts
+    let result = env.send(&[destroy(&env, &key2.pubkey(), &[(owner_ata, mint)])], &[&key2]).await;
+    assert_stache_error(result, StacheError::TokenAccountsMismatch);
+
+    env.send_ok(&[destroy(&env, &key2.pubkey(), &[(stache_ata, mint)])], &[&key2]).await;
+    assert!(!env.exists(&env.keys.stache).await);
+    assert!(!env.exists(&stache_ata).await);
+    assert_eq!(env.token_balance(&owner_ata).await, 1000);
+}
@@ -0,0 +1,450 @@
+use anchor_lang::error::ErrorCode;
+use anchor_lang::solana_program::{native_token::LAMPORTS_PER_SOL, system_program};
+use anchor_spl::token::spl_token;
+use solana_sdk::{instruction::AccountMeta, pubkey::Pubkey, signature::Signer};
+use stache::account::{ActionType, Approver, Submitter, VaultType};
+use stache::constant::MAX_WITHDRAW_DELAY;
+use stache::error::StacheError;
+use stache::nft::CnftArgs;
+use stache_client::instruction::{self, CnftAccounts, NftAccounts};
+use stache_client::pda::find_squads_transaction;
+use stache_integration::*;
+
+#[tokio::test]
+async fn approves_two_sig_withdrawals() {
+    let (mut env, mint, vault) = TestEnv::funded_vault(VaultType::TwoSig).await;
+    let owner = env.owner.pubkey();
+    let owner_ata = ata(&owner, &mint);
+    let key2 = copy_keypair(&env.key2);
+    let outsider = copy_keypair(&env.outsider);
+    let vault_ata = env.keys.vault_ata(vault, &mint, &spl_token::ID);
+    let stache_ata = env.keys.ata(&mint, &spl_token::ID);
+
+    // proposing counts as the first approval
+    env.send_ok(&[instruction::withdraw_from_vault(&env.keys, &owner, vault, &mint, &spl_token::ID, &owner_ata, None, 100)], &[]).await;
+    let action = env.vault(vault).await.actions[0].clone();
+    assert_eq!(action.action_type, ActionType::Transfer);
+    assert_eq!(action.approvers, vec![owner]);
+    assert_eq!(env.token_balance(&vault_ata).await, 500);
+
+    let approve = |env: &TestEnv, authority: &Pubkey, action_index: u8, remaining: Vec<AccountMeta>| {
+        instruction::approve_action(&env.keys, authority, vault, action_index, &spl_token::ID, None, remaining)
+    };
+    let accounts = instruction::transfer_action_accounts(&vault_ata, &owner_ata, &mint);
+
+    let result = env.send(&[approve(&env, &owner, action.action_index, accounts.clone())], &[]).await;
+    assert_stache_error(result, StacheError::AlreadyApproved);
+    let result = env.send(&[approve(&env, &key2.pubkey(), 9, accounts.clone())], &[&key2]).await;
+    assert_stache_error(result, StacheError::InvalidAction);
+    let result = env.send(&[approve(&env, &outsider.pubkey(), action.action_index, accounts.clone())], &[&outsider]).await;
+    assert_stache_error(result, StacheError::NotAuthorized);
+
+    // the remaining accounts have to match the action
+    let wrong_to = instruction::transfer_action_accounts(&vault_ata, &stache_ata, &mint);
+    let result = env.send(&[approve(&env, &key2.pubkey(), action.action_index, wrong_to)], &[&key2]).await;
+    assert_stache_error(result, StacheError::InvalidAction);
+    let wrong_mint = instruction::transfer_action_accounts(&vault_ata, &owner_ata, &Pubkey::new_unique());
+    let result = env.send(&[approve(&env, &key2.pubkey(), action.action_index, wrong_mint)], &[&key2]).await;
+    assert_stache_error(result, StacheError::TokenAccountsMismatch);
+
+    // 2nd sig executes it
+    env.send_ok(&[approve(&env, &key2.pubkey(), action.action_index, accounts)], &[&key2]).await;
+    assert_eq!(env.token_balance(&vault_ata).await, 400);
+    assert_eq!(env.token_balance(&owner_ata).await, 100);
+    assert!(env.vault(vault).await.actions.is_empty());
+}
+
+#[tokio::test]
+async fn executes_and_denies_actions() {
+    let (mut env, mint, vault) = TestEnv::funded_vault(VaultType::TwoSig).await;
+    let owner = env.owner.pubkey();
+    let owner_ata = ata(&owner, &mint);
+    let key2 = copy_keypair(&env.key2);
+    let vault_ata = env.keys.vault_ata(vault, &mint, &spl_token::ID);
+    let accounts = instruction::transfer_action_accounts(&vault_ata, &owner_ata, &mint);
+    let withdraw = |env: &TestEnv, amount: u64| {
+        instruction::withdraw_from_vault(&env.keys, &env.owner.pubkey(), vault, &mint, &spl_token::ID, &owner_ata, None, amount)
+    };
+
+    env.send_ok(&[withdraw(&env, 300)], &[]).await;
+    env.send_ok(&[withdraw(&env, 300)], &[]).await;
+    assert_eq!(env.vault(vault).await.actions.len(), 2);
+
+    let result = env.send(&[instruction::execute_action(&env.keys, &owner, vault, 1, &spl_token::ID, None, accounts.clone())], &[]).await;
+    assert_stache_error(result, StacheError::ActionNotExecutable);
+
+    env.send_ok(&[instruction::approve_action(&env.keys, &key2.pubkey(), vault, 1, &spl_token::ID, None, accounts.clone())], &[&key2]).await;
+    assert_eq!(env.token_balance(&vault_ata).await, 200);
+
+    // both got proposed while there was enough in the vault
+    let result = env.send(&[instruction::approve_action(&env.keys, &key2.pubkey(), vault, 2, &spl_token::ID, None, accounts)], &[&key2]).await;
+    assert_stache_error(result, StacheError::InsufficientFunds);
+
+    env.send_ok(&[instruction::deny_action(&env.keys, &key2.pubkey(), vault, 2, None)], &[&key2]).await;
+    assert!(env.vault(vault).await.actions.is_empty());
+    let result = env.send(&[instruction::deny_action(&env.keys, &owner, vault, 2, None)], &[]).await;
+    assert_stache_error(result, StacheError::InvalidAction);
+
+    // pending actions are capped
+    for _ in 0..5 {
+        env.send_ok(&[withdraw(&env, 10)], &[]).await;
+    }
+    let result = env.send(&[withdraw(&env, 10)], &[]).await;
+    assert_stache_error(result, StacheError::HitLimit);
+}
+
+#[tokio::test]
+async fn waits_out_the_withdraw_delay() {
+    let (mut env, mint, vault) = TestEnv::funded_vault(VaultType::Easy).await;
+    let owner = env.owner.pubkey();
+    let owner_ata = ata(&owner, &mint);
+    let vault_ata = env.keys.vault_ata(vault, &mint, &spl_token::ID);
+    let accounts = instruction::transfer_action_accounts(&vault_ata, &owner_ata, &mint);
+
+    // raising the delay is immediate, up to a point
+    let result = env.send(&[instruction::set_vault_delay(&env.keys, &owner, vault, MAX_WITHDRAW_DELAY + 1)], &[]).await;
+    assert_stache_error(result, StacheError::InvalidWithdrawDelay);
+    env.send_ok(&[instruction::set_vault_delay(&env.keys, &owner, vault, 3600)], &[]).await;
+    assert_eq!(env.vault(vault).await.withdraw_delay, 3600);
+
+    // even easy vaults have to wait it out
+    env.send_ok(&[instruction::withdraw_from_vault(&env.keys, &owner, vault, &mint, &spl_token::ID, &owner_ata, None, 100)], &[]).await;
+    let action = env.vault(vault).await.actions[0].clone();
+    assert!(action.executable_at > 0);
+
+    let result = env.send(&[instruction::execute_action(&env.keys, &owner, vault, action.action_index, &spl_token::ID, None, accounts.clone())], &[]).await;
+    assert_stache_error(result, StacheError::ActionNotExecutable);
+    env.warp(3600).await;
+    env.send_ok(&[instruction::execute_action(&env.keys, &owner, vault, action.action_index, &spl_token::ID, None, accounts)], &[]).await;
+    assert_eq!(env.token_balance(&owner_ata).await, 100);
+
+    // lowering it has to wait out the current delay
+    env.send_ok(&[instruction::set_vault_delay(&env.keys, &owner, vault, 0)], &[]).await;
+    let vault_account = env.vault(vault).await;
+    assert_eq!(vault_account.withdraw_delay, 3600);
+    let action = vault_account.actions[0].clone();
+    assert_eq!(action.action_type, ActionType::SetDelay);
+
+    let result = env.send(&[instruction::execute_action(&env.keys, &owner, vault, action.action_index, &spl_token::ID, None, vec![])], &[]).await;
+    assert_stache_error(result, StacheError::ActionNotExecutable);
+    env.warp(3600).await;
+    env.send_ok(&[instruction::execute_action(&env.keys, &owner, vault, action.action_index, &spl_token::ID, None, vec![])], &[]).await;
+    assert_eq!(env.vault(vault).await.withdraw_delay, 0);
+}
+
+#[tokio::test]
+async fn approves_sol_transfers() {
+    let mut env = TestEnv::with_stache().await;
+    let owner = env.owner.pubkey();
+    let key2 = copy_keypair(&env.key2);
+    let vault = env.create_vault("shared", VaultType::TwoSig).await;
+    env.send_ok(&[instruction::deposit_sol_to_vault(&env.keys, &owner, vault, LAMPORTS_PER_SOL)], &[]).await;
+
+    env.send_ok(&[instruction::withdraw_sol_from_vault(&env.keys, &owner, vault, &key2.pubkey(), None, LAMPORTS_PER_SOL / 2)], &[]).await;
+    let action = env.vault(vault).await.actions[0].clone();
+    assert_eq!(action.action_type, ActionType::SolTransfer);
+
+    let approve = |env: &TestEnv, to: &Pubkey| {
+        instruction::approve_action(&env.keys, &env.key2.pubkey(), vault, action.action_index, &spl_token::ID, None,
+                                    instruction::sol_transfer_action_accounts(to))
+    };
+    let result = env.send(&[approve(&env, &owner)], &[&key2]).await;
+    assert_stache_error(result, StacheError::InvalidAction);
+
+    let before = env.lamports(&key2.pubkey()).await;
+    env.send_ok(&[approve(&env, &key2.pubkey())], &[&key2]).await;
+    assert_eq!(env.lamports(&key2.pubkey()).await, before + LAMPORTS_PER_SOL / 2);
+}
+
+#[tokio::test]
+async fn approves_threshold_vault_actions() {
+    let mut env = TestEnv::with_stache().await;
+    let owner = env.owner.pubkey();
+    let key2 = copy_keypair(&env.key2);
+    let pending = copy_keypair(&env.pending);
+    let outsider = copy_keypair(&env.outsider);
+
+    // approvers don't have to be on the keychain
+    let (mint, vault) = {
+        let mint = env.funded_mint(1000).await;
+        env.stash(&mint, 1000).await;
+        let vault = env.create_vault("shared", VaultType::Threshold {
+            approvers: vec![
+                Approver { key: owner, enabled: true },
+                Approver { key: key2.pubkey(), enabled: true },
+                Approver { key: outsider.pubkey(), enabled: true },
+            ],
+            submitters: vec![Submitter { key: owner, enabled: true }],
+            threshold: 2,
+        }).await;
+        env.fund_vault(vault, &mint, 500).await;
+        (mint, vault)
+    };
+    let owner_ata = ata(&owner, &mint);
+    let accounts = instruction::transfer_action_accounts(&env.keys.vault_ata(vault, &mint, &spl_token::ID), &owner_ata, &mint);
+
+    // only submitters propose
+    let result = env.send(&[instruction::withdraw_from_vault(&env.keys, &key2.pubkey(), vault, &mint, &spl_token::ID, &owner_ata, None, 100)], &[&key2]).await;
+    assert_stache_error(result, StacheError::NotAuthorized);
+    env.send_ok(&[instruction::withdraw_from_vault(&env.keys, &owner, vault, &mint, &spl_token::ID, &owner_ata, None, 100)], &[]).await;
+
+    // and only approvers approve (keychain or not)
+    let result = env.send(&[instruction::approve_action(&env.keys, &pending.pubkey(), vault, 1, &spl_token::ID, None, accounts.clone())], &[&pending]).await;
+    assert_stache_error(result, StacheError::NotAuthorized);
+    env.send_ok(&[instruction::approve_action(&env.keys, &outsider.pubkey(), vault, 1, &spl_token::ID, None, accounts)], &[&outsider]).await;
+    assert_eq!(env.token_balance(&owner_ata).await, 100);
+}
+
+#[tokio::test]
+async fn checks_who_denies_and_sets_delays() {
+    let mut env = TestEnv::with_stache().await;
+    let owner = env.owner.pubkey();
+    let key2 = copy_keypair(&env.key2);
+    let outsider = copy_keypair(&env.outsider);
+    let vault = env.create_vault("shared", VaultType::Threshold {
+        approvers: vec![
+            Approver { key: owner, enabled: true },
+            Approver { key: outsider.pubkey(), enabled: true },
+        ],
+        submitters: vec![Submitter { key: owner, enabled: true }],
+        threshold: 2,
+    }).await;
+
+    // keychain keys that aren't submitters can't touch the delay
+    let result = env.send(&[instruction::set_vault_delay(&env.keys, &key2.pubkey(), vault, 3600)], &[&key2]).await;
+    assert_stache_error(result, StacheError::NotAuthorized);
+    env.send_ok(&[instruction::set_vault_delay(&env.keys, &owner, vault, 3600)], &[]).await;
+    env.send_ok(&[instruction::set_vault_delay(&env.keys, &owner, vault, 0)], &[]).await;
+    let action = env.vault(vault).await.actions[0].clone();
+
+    // approvers deny, whether they're on the keychain or not
+    let result = env.send(&[instruction::deny_action(&env.keys, &key2.pubkey(), vault, action.action_index, None)], &[&key2]).await;
+    assert_stache_error(result, StacheError::NotAuthorized);
+    env.send_ok(&[instruction::deny_action(&env.keys, &outsider.pubkey(), vault, action.action_index, None)], &[&outsider]).await;
+    let vault_account = env.vault(vault).await;
+    assert!(vault_account.actions.is_empty());
+    assert_eq!(vault_account.withdraw_delay, 3600);
+}
+
+#[tokio::test]
+async fn approves_squads_vault_actions() {
+    let mut env = TestEnv::with_stache().await;
+    let owner = env.owner.pubkey();
+    let key2 = copy_keypair(&env.key2);
+    let outsider = copy_keypair(&env.outsider);
+    let multisig = env.create_multisig(2, vec![owner, outsider.pubkey()]).await;
+
+    let mint = env.funded_mint(1000).await;
+    env.stash(&mint, 1000).await;
+    let vault = env.create_vault("squad", VaultType::Squads { multisig, sigs: 2 }).await;
+    env.fund_vault(vault, &mint, 500).await;
+    let owner_ata = ata(&owner, &mint);
+    let accounts = instruction::transfer_action_accounts(&env.keys.vault_ata(vault, &mint, &spl_token::ID), &owner_ata, &mint);
+
+    let result = env.send(&[instruction::withdraw_from_vault(&env.keys, &owner, vault, &mint, &spl_token::ID, &owner_ata, None, 100)], &[]).await;
+    assert_stache_error(result, StacheError::MissingAccount);
+    env.send_ok(&[instruction::withdraw_from_vault(&env.keys, &owner, vault, &mint, &spl_token::ID, &owner_ata, Some(multisig), 100)], &[]).await;
+
+    // multisig members approve, not keychain keys
+    let result = env.send(&[instruction::approve_action(&env.keys, &key2.pubkey(), vault, 1, &spl_token::ID, Some(multisig), accounts.clone())], &[&key2]).await;
+    assert_stache_error(result, StacheError::NotAuthorized);
+    env.send_ok(&[instruction::approve_action(&env.keys, &outsider.pubkey(), vault, 1, &spl_token::ID, Some(multisig), accounts)], &[&outsider]).await;
+    assert_eq!(env.token_balance(&owner_ata).await, 100);
+}
+
+// the same, but approved + executed in squads: squads signs into stache w/its authority, and the vault's rules still apply
+#[tokio::test]
+async fn runs_squads_vault_actions_through_squads() {
+    let mut env = TestEnv::with_stache().await;
+    let owner = env.owner.pubkey();
+    let key2 = copy_keypair(&env.key2);
+    let outsider = copy_keypair(&env.outsider);
+    let multisig = env.create_multisig(2, vec![owner, outsider.pubkey()]).await;
+
+    let mint = env.funded_mint(1000).await;
+    env.stash(&mint, 1000).await;
+    let vault = env.create_vault("squad", VaultType::Squads { multisig, sigs: 2 }).await;
+    env.fund_vault(vault, &mint, 500).await;
+    env.send_ok(&[instruction::set_vault_delay(&env.keys, &owner, vault, 3600)], &[]).await;
+    let owner_ata = ata(&owner, &mint);
+    let accounts = instruction::transfer_action_accounts(&env.keys.vault_ata(vault, &mint, &spl_token::ID), &owner_ata, &mint);
+
+    env.send_ok(&[instruction::withdraw_from_vault(&env.keys, &owner, vault, &mint, &spl_token::ID, &owner_ata, Some(multisig), 100)], &[]).await;
+    let action_index = env.vault(vault).await.actions[0].action_index;
+
+    // only members can put it in a squads transaction
+    let propose = instruction::propose_to_squads(&env.keys, &key2.pubkey(), vault, action_index, &multisig, 1, &spl_token::ID, accounts.clone());
+    let result = env.send(&[propose], &[&key2]).await;
+    assert_stache_error(result, StacheError::NotAuthorized);
+    let propose = instruction::propose_to_squads(&env.keys, &owner, vault, action_index, &multisig, 1, &spl_token::ID, accounts.clone());
+    env.send_ok(&[propose], &[]).await;
+
+    let transaction = find_squads_transaction(&multisig, 1);
+    let execute = instruction::execute_squads_action(&env.keys, vault, action_index, &multisig, &transaction, &spl_token::ID, accounts);
+    env.send_ok(&[instruction::approve_squads_transaction(&multisig, &transaction, &owner)], &[]).await;
+    env.send_ok(&[instruction::approve_squads_transaction(&multisig, &transaction, &outsider.pubkey())], &[&outsider]).await;
+
+    // squads is happy, but the withdraw delay isn't up yet
+    let result = env.send(&[instruction::execute_squads_transaction(&multisig, &transaction, &owner, &execute)], &[]).await;
+    assert_stache_error(result, StacheError::ActionNotExecutable);
+
+    env.warp(3600).await;
+    env.send_ok(&[instruction::execute_squads_transaction(&multisig, &transaction, &owner, &execute)], &[]).await;
+    assert_eq!(env.token_balance(&owner_ata).await, 100);
+    assert!(env.vault(vault).await.actions.is_empty());
+}
+
+#[tokio::test]
+async fn unlocks_two_sig_vaults_through_approvals() {
+    let mut env = TestEnv::with_stache().await;
+    let owner = env.owner.pubkey();
+    let key2 = copy_keypair(&env.key2);
+    let vault = env.create_vault("shared", VaultType::TwoSig).await;
+
+    // unlocking clears the time-lock too
+    let now = env.now().await;
+    env.send_ok(&[instruction::lock_vault(&env.keys, &owner, vault)], &[]).await;
+    env.send_ok(&[instruction::lock_vault_until(&env.keys, &owner, vault, now + 3600)], &[]).await;
+    env.send_ok(&[instruction::unlock_vault(&env.keys, &owner, vault, None)], &[]).await;
+    let vault_account = env.vault(vault).await;
+    assert!(vault_account.locked);
+    assert_eq!(vault_account.actions[0].action_type, ActionType::Unlock);
+
+    let approve = instruction::approve_action(&env.keys, &key2.pubkey(), vault, vault_account.actions[0].action_index, &spl_token::ID, None, vec![]);
+    env.send_ok(&[approve], &[&key2]).await;
+    let vault_account = env.vault(vault).await;
+    assert!(!vault_account.locked);
+    assert_eq!(vault_account.locked_until, 0);
+
+    // and a vault that's only time-locked can be unlocked the same way
+    env.send_ok(&[instruction::lock_vault_until(&env.keys, &owner, vault, now + 3600)], &[]).await;
+    env.send_ok(&[instruction::unlock_vault(&env.keys, &owner, vault, None)], &[]).await;
+    let action_index = env.vault(vault).await.actions[0].action_index;
+    let approve = instruction::approve_action(&env.keys, &key2.pubkey(), vault, action_index, &spl_token::ID, None, vec![]);
+    env.send_ok(&[approve], &[&key2]).await;
+    assert_eq!(env.vault(vault).await.locked_until, 0);
+}
+
+// metaplex isn't loaded by default, so this stops at the cpi (see withdraws_nfts_through_metaplex for the happy path)
+#[tokio::test]
+async fn checks_nft_action_accounts() {
+    let mut env = TestEnv::with_stache().await;
+    let owner = env.owner.pubkey();
+    let key2 = copy_keypair(&env.key2);
+    let vault = env.create_vault("nfts", VaultType::TwoSig).await;
+
+    let mint = env.create_mint(0).await;
+    let owner_ata = env.create_ata(&owner, &mint).await;
+    env.mint_to(&mint, &owner_ata, 1).await;
+    env.send_ok(&[instruction::deposit_to_vault(&env.keys, &owner, vault, &mint, &spl_token::ID, &owner_ata, 1)], &[]).await;
+
+    env.send_ok(&[instruction::withdraw_nft_from_vault(&env.keys, &owner, vault, &owner, &mint, &spl_token::ID, false, None)], &[]).await;
+    let action = env.vault(vault).await.actions[0].clone();
+    assert_eq!(action.action_type, ActionType::NftTransfer);
+
+    let vault_token = env.keys.vault_ata(vault, &mint, &spl_token::ID);
+    let accounts = NftAccounts::new(&mint, &vault_token, &owner_ata, false).action_accounts(&vault_token, &owner_ata, &owner);
+    let approve = |env: &TestEnv, remaining: Vec<AccountMeta>| {
+        instruction::approve_action(&env.keys, &env.key2.pubkey(), vault, action.action_index, &spl_token::ID, None, remaining)
+    };
+
+    let result = env.send(&[approve(&env, accounts[..8].to_vec())], &[&key2]).await;
+    assert_stache_error(result, StacheError::MissingAccount);
+
+    let mut wrong_program = accounts.clone();
+    wrong_program[8] = AccountMeta::new_readonly(system_program::ID, false);
+    let result = env.send(&[approve(&env, wrong_program)], &[&key2]).await;
+    assert_stache_error(result, StacheError::InvalidNft);
+
+    // vaults restricted to other mints don't take it either
+    let art = env.create_vault("art", VaultType::Easy).await;
+    env.send_ok(&[instruction::set_vault_mints(&env.keys, &owner, art, vec![Pubkey::new_unique()])], &[]).await;
+    let result = env.send(&[instruction::stash_nft(&env.keys, &owner, Some(art), &spl_token::ID, &mint, false)], &[]).await;
+    assert_stache_error(result, StacheError::MintNotAllowed);
+
+    let outsider = copy_keypair(&env.outsider);
+    env.create_ata(&env.keys.stache, &mint).await;
+    let result = env.send(&[instruction::unstash_nft(&env.keys, &outsider.pubkey(), &spl_token::ID, &mint, false)], &[&outsider]).await;
+    assert_anchor_error(result, ErrorCode::ConstraintRaw);
+}
+
+#[tokio::test]
+#[ignore = "needs metaplex dumped into tests/fixtures, see has_metaplex"]
+async fn withdraws_nfts_through_metaplex() {
+    assert!(has_metaplex(), "dump mpl_token_metadata.so into tests/fixtures first");
+    let mut env = TestEnv::with_stache().await;
+    let owner = env.owner.pubkey();
+    let key2 = copy_keypair(&env.key2);
+    let vault = env.create_vault("nfts", VaultType::TwoSig).await;
+    let mint = env.create_nft().await;
+    let owner_ata = ata(&owner, &mint);
+    let vault_token = env.keys.vault_ata(vault, &mint, &spl_token::ID);
+
+    env.send_ok(&[instruction::stash_nft(&env.keys, &owner, Some(vault), &spl_token::ID, &mint, false)], &[]).await;
+    assert_eq!(env.token_balance(&vault_token).await, 1);
+    assert_eq!(env.token_balance(&owner_ata).await, 0);
+
+    env.send_ok(&[instruction::withdraw_nft_from_vault(&env.keys, &owner, vault, &owner, &mint, &spl_token::ID, false, None)], &[]).await;
+    let action_index = env.vault(vault).await.actions[0].action_index;
+    assert_eq!(env.token_balance(&vault_token).await, 1);
+
+    let accounts = NftAccounts::new(&mint, &vault_token, &owner_ata, false).action_accounts(&vault_token, &owner_ata, &owner);
+    let approve = instruction::approve_action(&env.keys, &key2.pubkey(), vault, action_index, &spl_token::ID, None, accounts);
+    env.send_ok(&[approve], &[&key2]).await;
+    assert_eq!(env.token_balance(&owner_ata).await, 1);
+    assert!(env.vault(vault).await.actions.is_empty());
+}
+
+// same for compressed nfts: bubblegum isn't loaded, so this stops at the cpi
+#[tokio::test]
+async fn checks_cnft_action_accounts() {
+    let mut env = TestEnv::with_stache().await;
+    let owner = env.owner.pubkey();
+    let key2 = copy_keypair(&env.key2);
+    let vault = env.create_vault("cnfts", VaultType::TwoSig).await;
+
+    let merkle_tree = Pubkey::new_unique();
+    let args = CnftArgs {
+        root: [1; 32],
+        data_hash: [2; 32],
+        creator_hash: [3; 32],
+        nonce: 7,
+        index: 7,
+    };
+    let cnft = CnftAccounts::new(&merkle_tree, &env.keys.vault(vault), vec![Pubkey::new_unique(), Pubkey::new_unique()]);
+
+    // a two-sig vault just proposes it, which keeps the leaf but not the root
+    env.send_ok(&[instruction::withdraw_cnft_from_vault(&env.keys, &owner, vault, &owner, &cnft, args.clone(), None)], &[]).await;
+    let mut action = env.vault(vault).await.actions[0].clone();
+    assert_eq!(action.action_type, ActionType::CnftTransfer);
+    let transfer = action.cnft_transfer_action().unwrap();
+    assert_eq!(transfer.asset_id, args.asset_id(&merkle_tree));
+    assert_eq!(transfer.to, owner);
+    assert_eq!(transfer.args([1; 32]), args);
+
+    let accounts = cnft.action_accounts(&owner);
+    assert_eq!(accounts.len(), 10);
+    let approve = |env: &TestEnv, remaining: Vec<AccountMeta>| {
+        instruction::approve_action(&env.keys, &env.key2.pubkey(), vault, action.action_index, &spl_token::ID, None, remaining)
+    };
+
+    let result = env.send(&[approve(&env, accounts[..5].to_vec())], &[&key2]).await;
+    assert_stache_error(result, StacheError::MissingAccount);
+
+    let result = env.send(&[approve(&env, cnft.action_accounts(&key2.pubkey()))], &[&key2]).await;
+    assert_stache_error(result, StacheError::InvalidAction);
+
+    // the tree isn't an account compression tree, so there's no current root to transfer against
+    let result = env.send(&[approve(&env, accounts)], &[&key2]).await;
+    assert_stache_error(result, StacheError::InvalidNft);
+
+    // vaults restricted to other mints don't take it
+    let art = env.create_vault("art", VaultType::Easy).await;
+    env.send_ok(&[instruction::set_vault_mints(&env.keys, &owner, art, vec![Pubkey::new_unique()])], &[]).await;
+    let result = env.send(&[instruction::stash_cnft(&env.keys, &owner, Some(art), &cnft, args.clone())], &[]).await;
+    assert_stache_error(result, StacheError::MintNotAllowed);
+
+    let outsider = copy_keypair(&env.outsider);
+    let result = env.send(&[instruction::unstash_cnft(&env.keys, &outsider.pubkey(), &cnft, args)], &[&outsider]).await;
+    assert_anchor_error(result, ErrorCode::ConstraintRaw);
+}
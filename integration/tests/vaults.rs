@@ -0,0 +1,393 @@
+use anchor_lang::solana_program::native_token::LAMPORTS_PER_SOL;
+use anchor_spl::token::spl_token;
+use anchor_spl::token_2022::spl_token_2022;
+use solana_sdk::{pubkey::Pubkey, signature::Signer};
+use stache::account::{ActionType, Approver, Submitter, VaultType};
+use stache::constant::MAX_LOCK_SECONDS;
+use stache::error::StacheError;
+use stache_client::instruction;
+use stache_integration::*;
+
+fn approver(key: Pubkey) -> Approver {
+    Approver { key, enabled: true }
+}
+
+fn submitter(key: Pubkey) -> Submitter {
+    Submitter { key, enabled: true }
+}
+
+#[tokio::test]
+async fn creates_vaults() {
+    let mut env = TestEnv::with_stache().await;
+    let owner = env.owner.pubkey();
+
+    let result = env.send(&[instruction::create_vault(&env.keys, &owner, 1, "not valid", VaultType::Easy)], &[]).await;
+    assert_stache_error(result, StacheError::InvalidName);
+
+    let vault = env.create_vault("savings", VaultType::Easy).await;
+    assert_eq!(vault, 1);
+    let created = env.vault(vault).await;
+    assert_eq!(created.name, "savings");
+    assert_eq!(created.stache, env.keys.stache);
+    assert_eq!(created.vault_type, VaultType::Easy);
+    assert_eq!(env.stache().await.vaults, vec![1]);
+
+    for name in ["two", "three", "four", "five"] {
+        env.create_vault(name, VaultType::TwoSig).await;
+    }
+    let result = env.send(&[instruction::create_vault(&env.keys, &owner, 6, "six", VaultType::Easy)], &[]).await;
+    assert_stache_error(result, StacheError::HitLimit);
+
+    // destroying one frees up a slot, but indexes keep going
+    env.send_ok(&[instruction::destroy_vault(&env.keys, &owner, 2)], &[]).await;
+    assert_eq!(env.create_vault("six", VaultType::Easy).await, 6);
+    assert_eq!(env.stache().await.vaults, vec![1, 5, 3, 4, 6]);
+}
+
+#[tokio::test]
+async fn validates_threshold_vaults() {
+    let mut env = TestEnv::with_stache().await;
+    let owner = env.owner.pubkey();
+    let key2 = env.key2.pubkey();
+
+    let threshold = |approvers: Vec<Approver>, submitters: Vec<Submitter>, threshold: u8| {
+        VaultType::Threshold { approvers, submitters, threshold }
+    };
+    let cases = [
+        (threshold(vec![approver(owner), approver(key2)], vec![submitter(owner)], 0), StacheError::InvalidThreshold),
+        (threshold(vec![approver(owner), approver(key2)], vec![submitter(owner)], 3), StacheError::InvalidThreshold),
+        (threshold(vec![approver(owner), Approver { key: key2, enabled: false }], vec![submitter(owner)], 2), StacheError::InvalidThreshold),
+        (threshold(vec![approver(owner), approver(key2)], vec![Submitter { key: owner, enabled: false }], 1), StacheError::InvalidThreshold),
+        (threshold(vec![approver(owner), approver(owner)], vec![submitter(owner)], 1), StacheError::DupeAccount),
+        (threshold(vec![approver(owner)], vec![submitter(owner), submitter(owner)], 1), StacheError::DupeAccount),
+        (threshold((0..6).map(|_| approver(Pubkey::new_unique())).collect(), vec![submitter(owner)], 2), StacheError::HitLimit),
+    ];
+    for (vault_type, error) in cases {
+        let result = env.send(&[instruction::create_vault(&env.keys, &owner, 1, "shared", vault_type)], &[]).await;
+        assert_stache_error(result, error);
+    }
+
+    let vault = env.create_vault("shared", threshold(vec![approver(owner), approver(key2)], vec![submitter(owner)], 2)).await;
+    assert_eq!(env.vault(vault).await.required_approvals(), 2);
+}
+
+#[tokio::test]
+async fn validates_squads_vaults() {
+    let mut env = TestEnv::with_stache().await;
+    let owner = env.owner.pubkey();
+    let multisig = env.create_multisig(2, vec![owner, env.key2.pubkey()]).await;
+
+    let squads = |multisig: Pubkey, sigs: u8| VaultType::Squads { multisig, sigs };
+
+    // more sigs than members
+    let result = env.send(&[instruction::create_vault(&env.keys, &owner, 1, "squad", squads(multisig, 3))], &[]).await;
+    assert_stache_error(result, StacheError::InvalidMultisig);
+
+    // fewer sigs than the multisig's own threshold
+    let result = env.send(&[instruction::create_vault(&env.keys, &owner, 1, "squad", squads(multisig, 1))], &[]).await;
+    assert_stache_error(result, StacheError::InvalidMultisig);
+
+    // not a squads multisig
+    let result = env.send(&[instruction::create_vault(&env.keys, &owner, 1, "squad", squads(env.keys.keychain, 2))], &[]).await;
+    assert_stache_error(result, StacheError::InvalidMultisig);
+
+    let vault = env.create_vault("squad", squads(multisig, 2)).await;
+    assert_eq!(env.vault(vault).await.vault_type, squads(multisig, 2));
+}
+
+#[tokio::test]
+async fn withdraws_from_easy_vaults() {
+    let (mut env, mint, vault) = TestEnv::funded_vault(VaultType::Easy).await;
+    let owner = env.owner.pubkey();
+    let owner_ata = ata(&owner, &mint);
+    let vault_ata = env.keys.vault_ata(vault, &mint, &spl_token::ID);
+
+    env.send_ok(&[instruction::withdraw_from_vault(&env.keys, &owner, vault, &mint, &spl_token::ID, &owner_ata, None, 100)], &[]).await;
+    assert_eq!(env.token_balance(&vault_ata).await, 400);
+    assert_eq!(env.token_balance(&owner_ata).await, 100);
+    assert!(env.vault(vault).await.actions.is_empty());
+
+    let result = env.send(&[instruction::withdraw_from_vault(&env.keys, &owner, vault, &mint, &spl_token::ID, &owner_ata, None, 401)], &[]).await;
+    assert_stache_error(result, StacheError::InsufficientFunds);
+
+    let outsider = copy_keypair(&env.outsider);
+    let result = env.send(&[instruction::withdraw_from_vault(&env.keys, &outsider.pubkey(), vault, &mint, &spl_token::ID, &owner_ata, None, 100)], &[&outsider]).await;
+    assert_stache_error(result, StacheError::NotAuthorized);
+
+    // deposits from a wallet have to come from the authority's own token account
+    let result = env.send(&[instruction::deposit_to_vault(&env.keys, &env.key2.pubkey(), vault, &mint, &spl_token::ID, &owner_ata, 10)], &[&copy_keypair(&env.key2)]).await;
+    assert_stache_error(result, StacheError::NotAuthorized);
+    env.send_ok(&[instruction::deposit_to_vault(&env.keys, &owner, vault, &mint, &spl_token::ID, &owner_ata, 100)], &[]).await;
+    assert_eq!(env.token_balance(&vault_ata).await, 500);
+
+    let result = env.send(&[instruction::deposit_to_vault(&env.keys, &owner, vault, &mint, &spl_token::ID, &owner_ata, 1)], &[]).await;
+    assert_stache_error(result, StacheError::InsufficientFunds);
+}
+
+#[tokio::test]
+async fn withdraws_token_2022_mints_net_of_the_transfer_fee() {
+    let mut env = TestEnv::with_stache().await;
+    let owner = env.owner.pubkey();
+    // 1% fee, rounded up
+    let mint = env.create_fee_mint(6, 100, 1_000_000).await;
+    let owner_ata = env.create_ata(&owner, &mint).await;
+    env.mint_to(&mint, &owner_ata, 10_000).await;
+
+    // every hop pays the fee: 10_000 -> 9_900 in the stache -> 4_950 of 5_000 in the vault
+    env.stash(&mint, 10_000).await;
+    assert_eq!(env.token_balance(&env.keys.ata(&mint, &spl_token_2022::ID)).await, 9_900);
+    let vault = env.create_vault("savings", VaultType::Easy).await;
+    env.fund_vault(vault, &mint, 5_000).await;
+    let vault_ata = env.keys.vault_ata(vault, &mint, &spl_token_2022::ID);
+    assert_eq!(env.token_balance(&vault_ata).await, 4_950);
+
+    // the vault covers the amount, the recipient eats the fee
+    env.send_ok(&[instruction::withdraw_from_vault(&env.keys, &owner, vault, &mint, &spl_token_2022::ID, &owner_ata, None, 1_000)], &[]).await;
+    assert_eq!(env.token_balance(&vault_ata).await, 3_950);
+    assert_eq!(env.token_balance(&owner_ata).await, 990);
+
+    let result = env.send(&[instruction::withdraw_from_vault(&env.keys, &owner, vault, &mint, &spl_token_2022::ID, &owner_ata, None, 3_951)], &[]).await;
+    assert_stache_error(result, StacheError::InsufficientFunds);
+
+    // the fee on a single token is the whole token, so nothing would actually move
+    let result = env.send(&[instruction::withdraw_from_vault(&env.keys, &owner, vault, &mint, &spl_token_2022::ID, &owner_ata, None, 1)], &[]).await;
+    assert_stache_error(result, StacheError::InsufficientFunds);
+    assert_eq!(env.token_balance(&vault_ata).await, 3_950);
+}
+
+#[tokio::test]
+async fn moves_tokens_between_the_stache_and_vaults() {
+    let (mut env, mint, vault) = TestEnv::funded_vault(VaultType::Easy).await;
+    let owner = env.owner.pubkey();
+    let other = env.create_vault("other", VaultType::Easy).await;
+    env.create_ata(&env.keys.vault(other), &mint).await;
+    let stache_ata = env.keys.ata(&mint, &spl_token::ID);
+
+    // entering a vault is always fine, leaving one follows the vault's rules (straight through for easy vaults)
+    env.send_ok(&[instruction::internal_transfer(&env.keys, &owner, None, Some(other), &mint, &spl_token::ID, None, 200)], &[]).await;
+    env.send_ok(&[instruction::internal_transfer(&env.keys, &owner, Some(vault), Some(other), &mint, &spl_token::ID, None, 100)], &[]).await;
+    env.send_ok(&[instruction::internal_transfer(&env.keys, &owner, Some(other), None, &mint, &spl_token::ID, None, 50)], &[]).await;
+    assert_eq!(env.token_balance(&stache_ata).await, 350);
+    assert_eq!(env.token_balance(&env.keys.vault_ata(vault, &mint, &spl_token::ID)).await, 400);
+    assert_eq!(env.token_balance(&env.keys.vault_ata(other, &mint, &spl_token::ID)).await, 250);
+
+    let result = env.send(&[instruction::internal_transfer(&env.keys, &owner, None, None, &mint, &spl_token::ID, None, 1)], &[]).await;
+    assert_stache_error(result, StacheError::DupeAccount);
+
+    let result = env.send(&[instruction::internal_transfer(&env.keys, &owner, Some(vault), None, &mint, &spl_token::ID, None, 401)], &[]).await;
+    assert_stache_error(result, StacheError::InsufficientFunds);
+}
+
+#[tokio::test]
+async fn locks_and_unlocks_vaults() {
+    let (mut env, mint, vault) = TestEnv::funded_vault(VaultType::Easy).await;
+    let owner = env.owner.pubkey();
+    let owner_ata = ata(&owner, &mint);
+    let key2 = copy_keypair(&env.key2);
+    let pending = copy_keypair(&env.pending);
+    let withdraw = |env: &TestEnv| instruction::withdraw_from_vault(&env.keys, &env.owner.pubkey(), vault, &mint, &spl_token::ID, &owner_ata, None, 100);
+
+    let result = env.send(&[instruction::unlock_vault(&env.keys, &owner, vault, None)], &[]).await;
+    assert_stache_error(result, StacheError::VaultNotLocked);
+
+    env.send_ok(&[instruction::lock_vault(&env.keys, &owner, vault)], &[]).await;
+    let result = env.send(&[withdraw(&env)], &[]).await;
+    assert_stache_error(result, StacheError::VaultLocked);
+
+    // easy vaults get unlocked by a different verified key than the one that locked it
+    let result = env.send(&[instruction::unlock_vault(&env.keys, &owner, vault, None)], &[]).await;
+    assert_stache_error(result, StacheError::NotAuthorized);
+    let result = env.send(&[instruction::unlock_vault(&env.keys, &pending.pubkey(), vault, None)], &[&pending]).await;
+    assert_stache_error(result, StacheError::NotAuthorized);
+    env.send_ok(&[instruction::unlock_vault(&env.keys, &key2.pubkey(), vault, None)], &[&key2]).await;
+    env.send_ok(&[withdraw(&env)], &[]).await;
+
+    // time-locks
+    let now = env.now().await;
+    let result = env.send(&[instruction::lock_vault_until(&env.keys, &owner, vault, now - 1)], &[]).await;
+    assert_stache_error(result, StacheError::InvalidLockTime);
+    let result = env.send(&[instruction::lock_vault_until(&env.keys, &owner, vault, now + MAX_LOCK_SECONDS + 1)], &[]).await;
+    assert_stache_error(result, StacheError::InvalidLockTime);
+    env.send_ok(&[instruction::lock_vault_until(&env.keys, &owner, vault, now + 7200)], &[]).await;
+
+    // extending is immediate, shortening needs a 2nd approval (even on an easy vault)
+    env.send_ok(&[instruction::lock_vault_until(&env.keys, &owner, vault, now + 3600)], &[]).await;
+    let vault_account = env.vault(vault).await;
+    assert_eq!(vault_account.locked_until, now + 7200);
+    let action = vault_account.actions[0].clone();
+    assert_eq!(action.action_type, ActionType::LockUntil);
+
+    let result = env.send(&[instruction::execute_action(&env.keys, &owner, vault, action.action_index, &spl_token::ID, None, vec![])], &[]).await;
+    assert_stache_error(result, StacheError::ActionNotExecutable);
+    env.send_ok(&[instruction::approve_action(&env.keys, &key2.pubkey(), vault, action.action_index, &spl_token::ID, None, vec![])], &[&key2]).await;
+    assert_eq!(env.vault(vault).await.locked_until, now + 3600);
+
+    let result = env.send(&[withdraw(&env)], &[]).await;
+    assert_stache_error(result, StacheError::VaultLocked);
+    env.warp(3600).await;
+    env.send_ok(&[withdraw(&env)], &[]).await;
+    assert_eq!(env.token_balance(&owner_ata).await, 200);
+}
+
+#[tokio::test]
+async fn moves_sol_in_and_out_of_vaults() {
+    let mut env = TestEnv::with_stache().await;
+    let owner = env.owner.pubkey();
+    let key2 = env.key2.pubkey();
+    let vault = env.create_vault("sol", VaultType::Easy).await;
+    let vault_key = env.keys.vault(vault);
+
+    env.send_ok(&[instruction::deposit_sol_to_vault(&env.keys, &owner, vault, LAMPORTS_PER_SOL)], &[]).await;
+    let key2_before = env.lamports(&key2).await;
+
+    env.send_ok(&[instruction::withdraw_sol_from_vault(&env.keys, &owner, vault, &key2, None, LAMPORTS_PER_SOL / 4)], &[]).await;
+    assert_eq!(env.lamports(&key2).await, key2_before + LAMPORTS_PER_SOL / 4);
+
+    // the vault's rent isn't up for grabs
+    let result = env.send(&[instruction::withdraw_sol_from_vault(&env.keys, &owner, vault, &key2, None, LAMPORTS_PER_SOL)], &[]).await;
+    assert_stache_error(result, StacheError::InsufficientFunds);
+
+    let result = env.send(&[instruction::destroy_vault(&env.keys, &owner, vault)], &[]).await;
+    assert_stache_error(result, StacheError::VaultNotEmpty);
+
+    env.send_ok(&[instruction::withdraw_sol_from_vault(&env.keys, &owner, vault, &key2, None, LAMPORTS_PER_SOL * 3 / 4)], &[]).await;
+    env.send_ok(&[instruction::destroy_vault(&env.keys, &owner, vault)], &[]).await;
+    assert!(!env.exists(&vault_key).await);
+    assert!(env.stache().await.vaults.is_empty());
+}
+
+#[tokio::test]
+async fn restricts_vault_mints() {
+    let (mut env, mint, vault) = TestEnv::funded_vault(VaultType::Easy).await;
+    let owner = env.owner.pubkey();
+    let other_mint = env.funded_mint(100).await;
+    let other_ata = ata(&owner, &other_mint);
+
+    let too_many = (0..4).map(|_| Pubkey::new_unique()).collect();
+    let result = env.send(&[instruction::set_vault_mints(&env.keys, &owner, vault, too_many)], &[]).await;
+    assert_stache_error(result, StacheError::HitLimit);
+
+    env.send_ok(&[instruction::set_vault_mints(&env.keys, &owner, vault, vec![mint])], &[]).await;
+
+    let result = env.send(&[instruction::deposit_to_vault(&env.keys, &owner, vault, &other_mint, &spl_token::ID, &other_ata, 10)], &[]).await;
+    assert_stache_error(result, StacheError::MintNotAllowed);
+    let result = env.send(&[instruction::deposit_sol_to_vault(&env.keys, &owner, vault, LAMPORTS_PER_SOL)], &[]).await;
+    assert_stache_error(result, StacheError::MintNotAllowed);
+    env.fund_vault(vault, &mint, 100).await;
+
+    // empty = anything goes again
+    env.send_ok(&[instruction::set_vault_mints(&env.keys, &owner, vault, vec![])], &[]).await;
+    env.send_ok(&[instruction::deposit_to_vault(&env.keys, &owner, vault, &other_mint, &spl_token::ID, &other_ata, 10)], &[]).await;
+}
+
+#[tokio::test]
+async fn holds_vaults_to_their_spending_limits() {
+    let (mut env, mint, vault) = TestEnv::funded_vault(VaultType::Easy).await;
+    let owner = env.owner.pubkey();
+    let owner_ata = ata(&owner, &mint);
+    let key2 = copy_keypair(&env.key2);
+    let vault_ata = env.keys.vault_ata(vault, &mint, &spl_token::ID);
+
+    env.send_ok(&[instruction::set_spending_limit(&env.keys, &owner, Some(vault), None, &mint, 100, 86400)], &[]).await;
+    env.send_ok(&[instruction::withdraw_from_vault(&env.keys, &owner, vault, &mint, &spl_token::ID, &owner_ata, None, 60)], &[]).await;
+
+    // going over the limit turns the withdrawal into an action that needs a 2nd approval, even in an easy vault
+    env.send_ok(&[instruction::withdraw_from_vault(&env.keys, &owner, vault, &mint, &spl_token::ID, &owner_ata, None, 60)], &[]).await;
+    let action = env.vault(vault).await.actions[0].clone();
+    assert!(action.over_limit);
+    assert_eq!(env.token_balance(&owner_ata).await, 60);
+
+    let approve = instruction::approve_action(&env.keys, &key2.pubkey(), vault, action.action_index, &spl_token::ID, None,
+                                              instruction::transfer_action_accounts(&vault_ata, &owner_ata, &mint));
+    env.send_ok(&[approve], &[&key2]).await;
+    assert_eq!(env.token_balance(&owner_ata).await, 120);
+    assert!(env.vault(vault).await.actions.is_empty());
+
+    let result = env.send(&[instruction::set_spending_limit(&env.keys, &owner, Some(vault), None, &mint, 1000, 86400)], &[]).await;
+    assert_stache_error(result, StacheError::InvalidCosigner);
+}
+
+#[tokio::test]
+async fn needs_one_more_approval_over_the_limit() {
+    let mut env = TestEnv::with_stache().await;
+    let owner = env.owner.pubkey();
+    let key2 = copy_keypair(&env.key2);
+    let outsider = copy_keypair(&env.outsider);
+    let mint = env.funded_mint(1000).await;
+    env.stash(&mint, 1000).await;
+    let vault = env.create_vault("shared", VaultType::Threshold {
+        approvers: vec![approver(owner), approver(key2.pubkey()), approver(outsider.pubkey())],
+        submitters: vec![submitter(owner)],
+        threshold: 2,
+    }).await;
+    env.fund_vault(vault, &mint, 500).await;
+    let owner_ata = ata(&owner, &mint);
+    let accounts = instruction::transfer_action_accounts(&env.keys.vault_ata(vault, &mint, &spl_token::ID), &owner_ata, &mint);
+    let approve = |env: &TestEnv, approver: &Pubkey, action_index: u8| {
+        instruction::approve_action(&env.keys, approver, vault, action_index, &spl_token::ID, None, accounts.clone())
+    };
+
+    env.send_ok(&[instruction::set_spending_limit(&env.keys, &owner, Some(vault), None, &mint, 100, 86400)], &[]).await;
+
+    // under the limit the threshold does it
+    env.send_ok(&[instruction::withdraw_from_vault(&env.keys, &owner, vault, &mint, &spl_token::ID, &owner_ata, None, 50)], &[]).await;
+    let action = env.vault(vault).await.actions[0].clone();
+    env.send_ok(&[approve(&env, &key2.pubkey(), action.action_index)], &[&key2]).await;
+    assert_eq!(env.token_balance(&owner_ata).await, 50);
+
+    // over it, a 2 of 3 vault needs all 3
+    env.send_ok(&[instruction::withdraw_from_vault(&env.keys, &owner, vault, &mint, &spl_token::ID, &owner_ata, None, 100)], &[]).await;
+    let action = env.vault(vault).await.actions[0].clone();
+    assert!(action.over_limit);
+    env.send_ok(&[approve(&env, &key2.pubkey(), action.action_index)], &[&key2]).await;
+    assert_eq!(env.token_balance(&owner_ata).await, 50);
+    assert_eq!(env.vault(vault).await.actions.len(), 1);
+
+    env.send_ok(&[approve(&env, &outsider.pubkey(), action.action_index)], &[&outsider]).await;
+    assert_eq!(env.token_balance(&owner_ata).await, 150);
+    assert!(env.vault(vault).await.actions.is_empty());
+}
+
+#[tokio::test]
+async fn charges_deposits_from_the_stache_to_its_spending_limit() {
+    let (mut env, mint, vault) = TestEnv::funded_vault(VaultType::Easy).await;
+    let owner = env.owner.pubkey();
+    let owner_ata = ata(&owner, &mint);
+    let stache_ata = env.keys.ata(&mint, &spl_token::ID);
+    env.send_ok(&[instruction::set_spending_limit(&env.keys, &owner, None, None, &mint, 100, 3600)], &[]).await;
+
+    // an easy vault w/out a limit isn't a way around the stache's
+    env.send_ok(&[instruction::deposit_to_vault(&env.keys, &owner, vault, &mint, &spl_token::ID, &stache_ata, 100)], &[]).await;
+    let result = env.send(&[instruction::deposit_to_vault(&env.keys, &owner, vault, &mint, &spl_token::ID, &stache_ata, 1)], &[]).await;
+    assert_stache_error(result, StacheError::SpendingLimitExceeded);
+    let result = env.send(&[instruction::unstash(&env.keys, &owner, &mint, &spl_token::ID, Some(owner_ata), false, 1)], &[]).await;
+    assert_stache_error(result, StacheError::SpendingLimitExceeded);
+    assert_eq!(env.token_balance(&stache_ata).await, 400);
+
+    env.warp(3600).await;
+    env.send_ok(&[instruction::deposit_to_vault(&env.keys, &owner, vault, &mint, &spl_token::ID, &stache_ata, 1)], &[]).await;
+}
+
+#[tokio::test]
+async fn charges_internal_transfers_from_the_stache_to_its_spending_limit() {
+    let (mut env, mint, vault) = TestEnv::funded_vault(VaultType::Easy).await;
+    let owner = env.owner.pubkey();
+    let owner_ata = ata(&owner, &mint);
+    let stache_ata = env.keys.ata(&mint, &spl_token::ID);
+    env.send_ok(&[instruction::set_spending_limit(&env.keys, &owner, None, None, &mint, 100, 3600)], &[]).await;
+
+    // moving into an easy vault and straight back out would otherwise get around the stache's limit
+    let into_vault = |env: &TestEnv, amount: u64| {
+        instruction::internal_transfer(&env.keys, &env.owner.pubkey(), None, Some(vault), &mint, &spl_token::ID, None, amount)
+    };
+    let result = env.send(&[into_vault(&env, 101)], &[]).await;
+    assert_stache_error(result, StacheError::SpendingLimitExceeded);
+    env.send_ok(&[into_vault(&env, 100)], &[]).await;
+    let result = env.send(&[into_vault(&env, 1)], &[]).await;
+    assert_stache_error(result, StacheError::SpendingLimitExceeded);
+
+    // what's already in the vault follows the vault's rules (no limit here)
+    env.send_ok(&[instruction::withdraw_from_vault(&env.keys, &owner, vault, &mint, &spl_token::ID, &owner_ata, None, 600)], &[]).await;
+    assert_eq!(env.token_balance(&stache_ata).await, 400);
+    assert_eq!(env.token_balance(&owner_ata).await, 600);
+}
@@ -0,0 +1,181 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{
+    instruction::Instruction,
+    program::{get_return_data, invoke_signed},
+};
+use anchor_lang::system_program;
+use clockwork_sdk::state::{ClockData, SerializableInstruction, Thread, ThreadResponse, Trigger};
+
+// local stand-in for the clockwork thread program: same program id, thread seeds + thread account layout, and the
+// same create/delete/pause/resume instructions stache cpis into. there's no worker network, so thread_exec stands in
+// for one: it runs the thread's instruction signed by the thread (like a worker would), and applies the trigger the
+// instruction returns. only meant for running the automations in-process (solana-program-test), so it doesn't check
+// account/cron triggers, or charge fees
+declare_id!("CLoCKyJ6DXBJqqu2VWx9RLbgnwwR6BMHHuyasVmfMzBh");
+
+pub const SEED_THREAD: &str = "thread";
+
+// room for the thread + a fire_auto instruction w/3 actions
+pub const THREAD_SPACE: usize = 8 + 2048;
+
+#[program]
+pub mod clockwork_mock {
+    use super::*;
+
+    pub fn thread_create(ctx: Context<ThreadCreate>, amount: u64, id: Vec<u8>, instructions: Vec<SerializableInstruction>, trigger: Trigger) -> Result<()> {
+        require!(!instructions.is_empty(), ThreadError::MissingInstruction);
+
+        // the thread gets funded on top of its rent
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: ctx.accounts.thread.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let clock = Clock::get()?;
+        let thread = &mut ctx.accounts.thread;
+        thread.authority = ctx.accounts.authority.key();
+        thread.bump = *ctx.bumps.get("thread").unwrap();
+        thread.created_at = ClockData {
+            slot: clock.slot,
+            epoch: clock.epoch,
+            unix_timestamp: clock.unix_timestamp,
+        };
+        thread.exec_context = None;
+        thread.fee = 0;
+        thread.id = id;
+        thread.instructions = instructions;
+        thread.name = String::new();
+        thread.next_instruction = None;
+        thread.paused = false;
+        thread.rate_limit = u64::MAX;
+        thread.trigger = trigger;
+        Ok(())
+    }
+
+    pub fn thread_delete(_ctx: Context<ThreadDelete>) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn thread_pause(ctx: Context<ThreadPause>) -> Result<()> {
+        ctx.accounts.thread.paused = true;
+        Ok(())
+    }
+
+    pub fn thread_resume(ctx: Context<ThreadResume>) -> Result<()> {
+        ctx.accounts.thread.paused = false;
+        Ok(())
+    }
+
+    // what a worker does when the thread's trigger fires. remaining accounts: the accounts the thread's instruction
+    // needs, including the program it calls (the thread itself is passed as the thread account)
+    pub fn thread_exec<'a, 'b, 'c, 'info>(ctx: Context<'a, 'b, 'c, 'info, ThreadExec<'info>>) -> Result<()> {
+        let thread = &mut ctx.accounts.thread;
+        require!(!thread.paused, ThreadError::ThreadPaused);
+
+        // timestamp triggers are the only ones the mock holds the thread to (that's how stache parks threads)
+        if let Trigger::Timestamp { unix_ts } = thread.trigger {
+            require!(Clock::get()?.unix_timestamp >= unix_ts, ThreadError::TriggerNotActive);
+        }
+
+        let instruction: Instruction = thread.instructions[0].clone().into();
+        let mut account_infos = ctx.remaining_accounts.to_vec();
+        account_infos.push(thread.to_account_info());
+
+        invoke_signed(
+            &instruction,
+            &account_infos,
+            &[&[SEED_THREAD.as_bytes(), thread.authority.as_ref(), thread.id.as_slice(), &[thread.bump]]],
+        )?;
+
+        if let Some((program_id, data)) = get_return_data() {
+            if program_id == instruction.program_id {
+                let response = ThreadResponse::try_from_slice(&data)?;
+                if let Some(trigger) = response.trigger {
+                    thread.trigger = trigger;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// same accounts (+ order) as clockwork's thread instructions, so the clockwork_sdk cpi calls line up
+
+#[derive(Accounts)]
+#[instruction(amount: u64, id: Vec<u8>)]
+pub struct ThreadCreate<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    #[account(
+    init,
+    payer = payer,
+    space = THREAD_SPACE,
+    seeds = [SEED_THREAD.as_bytes(), authority.key().as_ref(), id.as_slice()],
+    bump
+    )]
+    pub thread: Account<'info, Thread>,
+}
+
+#[derive(Accounts)]
+pub struct ThreadDelete<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub close_to: SystemAccount<'info>,
+
+    #[account(
+    mut,
+    close = close_to,
+    has_one = authority,
+    )]
+    pub thread: Account<'info, Thread>,
+}
+
+#[derive(Accounts)]
+pub struct ThreadPause<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+    mut,
+    has_one = authority,
+    )]
+    pub thread: Account<'info, Thread>,
+}
+
+#[derive(Accounts)]
+pub struct ThreadResume<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+    mut,
+    has_one = authority,
+    )]
+    pub thread: Account<'info, Thread>,
+}
+
+#[derive(Accounts)]
+pub struct ThreadExec<'info> {
+    #[account(mut)]
+    pub thread: Account<'info, Thread>,
+}
+
+#[error_code]
+pub enum ThreadError {
+    #[msg("Thread has no instructions")]
+    MissingInstruction,
+    #[msg("Thread is paused")]
+    ThreadPaused,
+    #[msg("Thread's trigger isn't active yet")]
+    TriggerNotActive,
+}
@@ -0,0 +1,389 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program::invoke_signed;
+
+// local stand-in for the squads v3 multisig program: same program id, same account layouts/seeds and the same
+// instructions for the transaction flow stache uses (create, create_transaction, add_instruction, activate_transaction,
+// approve_transaction, execute_instruction), minus everything else (rejecting, cancelling, multisig changes,
+// execute_transaction), so stache's squads vaults can be tested on a local validator.
+// load it at the squads address, ie: solana-test-validator --bpf-program SMPLecH534NA9acpos4G6x7uf3LWbCAwZQE9e8ZekMu target/deploy/squads_mock.so
+declare_id!("SMPLecH534NA9acpos4G6x7uf3LWbCAwZQE9e8ZekMu");
+
+#[program]
+pub mod squads_mock {
+    use super::*;
+
+    pub fn create(ctx: Context<Create>, threshold: u16, create_key: Pubkey, members: Vec<Pubkey>) -> Result<()> {
+        require!(threshold > 0 && usize::from(threshold) <= members.len(), MsError::InvalidThreshold);
+
+        let multisig = &mut ctx.accounts.multisig;
+        multisig.threshold = threshold;
+        multisig.authority_index = 1;
+        multisig.transaction_index = 0;
+        multisig.ms_change_index = 0;
+        multisig.bump = *ctx.bumps.get("multisig").unwrap();
+        multisig.create_key = create_key;
+        multisig.allow_external_execute = false;
+        multisig.keys = members;
+        Ok(())
+    }
+
+    pub fn create_transaction(ctx: Context<CreateTransaction>, authority_index: u32) -> Result<()> {
+        let multisig = &mut ctx.accounts.multisig;
+        multisig.transaction_index += 1;
+
+        let (_, authority_bump) = Pubkey::find_program_address(
+            &[b"squad", multisig.key().as_ref(), &authority_index.to_le_bytes(), b"authority"], &ID);
+
+        let transaction = &mut ctx.accounts.transaction;
+        transaction.creator = ctx.accounts.creator.key();
+        transaction.ms = multisig.key();
+        transaction.transaction_index = multisig.transaction_index;
+        transaction.authority_index = authority_index;
+        transaction.authority_bump = authority_bump;
+        transaction.status = MsTransactionStatus::Draft;
+        transaction.instruction_index = 0;
+        transaction.bump = *ctx.bumps.get("transaction").unwrap();
+        transaction.approved = Vec::new();
+        transaction.rejected = Vec::new();
+        transaction.cancelled = Vec::new();
+        transaction.executed_index = 0;
+        Ok(())
+    }
+
+    pub fn add_instruction(ctx: Context<AddInstruction>, incoming_instruction: IncomingInstruction) -> Result<()> {
+        let transaction = &mut ctx.accounts.transaction;
+        transaction.instruction_index += 1;
+
+        let instruction = &mut ctx.accounts.instruction;
+        instruction.program_id = incoming_instruction.program_id;
+        instruction.keys = incoming_instruction.keys;
+        instruction.data = incoming_instruction.data;
+        instruction.instruction_index = transaction.instruction_index;
+        instruction.bump = *ctx.bumps.get("instruction").unwrap();
+        instruction.executed = false;
+        Ok(())
+    }
+
+    pub fn activate_transaction(ctx: Context<ActivateTransaction>) -> Result<()> {
+        let transaction = &mut ctx.accounts.transaction;
+        require!(transaction.instruction_index > 0, MsError::InvalidTransactionState);
+        transaction.status = MsTransactionStatus::Active;
+        Ok(())
+    }
+
+    pub fn approve_transaction(ctx: Context<ApproveTransaction>) -> Result<()> {
+        let member = ctx.accounts.member.key();
+        let transaction = &mut ctx.accounts.transaction;
+        require!(!transaction.approved.contains(&member), MsError::AlreadyApproved);
+        transaction.approved.push(member);
+
+        if transaction.approved.len() >= usize::from(ctx.accounts.multisig.threshold) {
+            transaction.status = MsTransactionStatus::ExecuteReady;
+        }
+        Ok(())
+    }
+
+    // executes the transaction's next instruction. remaining accounts: the instruction's program, then its keys in order
+    pub fn execute_instruction(ctx: Context<ExecuteInstruction>) -> Result<()> {
+        let multisig = ctx.accounts.multisig.key();
+        let transaction = &mut ctx.accounts.transaction;
+        let ms_instruction = &mut ctx.accounts.instruction;
+        require!(ms_instruction.instruction_index == transaction.executed_index + 1, MsError::InvalidInstructionAccount);
+
+        let (program, keys) = ctx.remaining_accounts.split_first().ok_or(MsError::InvalidInstructionAccount)?;
+        require!(program.key() == ms_instruction.program_id, MsError::InvalidInstructionAccount);
+        require!(keys.len() == ms_instruction.keys.len(), MsError::InvalidInstructionAccount);
+        for (account, meta) in keys.iter().zip(ms_instruction.keys.iter()) {
+            require!(account.key() == meta.pubkey, MsError::InvalidInstructionAccount);
+        }
+
+        let instruction = Instruction {
+            program_id: ms_instruction.program_id,
+            accounts: ms_instruction.keys.iter().map(|meta| AccountMeta {
+                pubkey: meta.pubkey,
+                is_signer: meta.is_signer,
+                is_writable: meta.is_writable,
+            }).collect(),
+            data: ms_instruction.data.clone(),
+        };
+
+        let authority_index = transaction.authority_index.to_le_bytes();
+        let seeds = &[
+            b"squad".as_ref(),
+            multisig.as_ref(),
+            authority_index.as_ref(),
+            b"authority".as_ref(),
+            &[transaction.authority_bump],
+        ];
+        invoke_signed(&instruction, ctx.remaining_accounts, &[&seeds[..]])?;
+
+        ms_instruction.executed = true;
+        transaction.executed_index = ms_instruction.instruction_index;
+        if transaction.executed_index == transaction.instruction_index {
+            transaction.status = MsTransactionStatus::Executed;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(threshold: u16, create_key: Pubkey, members: Vec<Pubkey>)]
+pub struct Create<'info> {
+    #[account(
+    init,
+    payer = creator,
+    space = Ms::SIZE_WITHOUT_MEMBERS + (members.len() * 32),
+    seeds = [b"squad", create_key.as_ref(), b"multisig"],
+    bump
+    )]
+    pub multisig: Account<'info, Ms>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateTransaction<'info> {
+    #[account(
+    mut,
+    seeds = [b"squad", multisig.create_key.as_ref(), b"multisig"],
+    bump = multisig.bump,
+    )]
+    pub multisig: Account<'info, Ms>,
+
+    #[account(
+    init,
+    payer = creator,
+    space = MsTransaction::SIZE_WITHOUT_MEMBERS + (multisig.keys.len() * 32 * 3),
+    seeds = [b"squad", multisig.key().as_ref(), &(multisig.transaction_index + 1).to_le_bytes(), b"transaction"],
+    bump
+    )]
+    pub transaction: Account<'info, MsTransaction>,
+
+    #[account(mut, constraint = multisig.keys.contains(&creator.key()) @MsError::KeyNotInMultisig)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(incoming_instruction: IncomingInstruction)]
+pub struct AddInstruction<'info> {
+    #[account(
+    seeds = [b"squad", multisig.create_key.as_ref(), b"multisig"],
+    bump = multisig.bump,
+    )]
+    pub multisig: Account<'info, Ms>,
+
+    #[account(
+    mut,
+    has_one = creator @MsError::KeyNotInMultisig,
+    constraint = transaction.ms == multisig.key() @MsError::InvalidInstructionAccount,
+    constraint = transaction.status == MsTransactionStatus::Draft @MsError::InvalidTransactionState,
+    )]
+    pub transaction: Account<'info, MsTransaction>,
+
+    #[account(
+    init,
+    payer = creator,
+    space = incoming_instruction.size(),
+    seeds = [b"squad", transaction.key().as_ref(), &(transaction.instruction_index + 1).to_le_bytes(), b"instruction"],
+    bump
+    )]
+    pub instruction: Account<'info, MsInstruction>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ActivateTransaction<'info> {
+    #[account(
+    seeds = [b"squad", multisig.create_key.as_ref(), b"multisig"],
+    bump = multisig.bump,
+    )]
+    pub multisig: Account<'info, Ms>,
+
+    #[account(
+    mut,
+    has_one = creator @MsError::KeyNotInMultisig,
+    constraint = transaction.ms == multisig.key() @MsError::InvalidInstructionAccount,
+    constraint = transaction.status == MsTransactionStatus::Draft @MsError::InvalidTransactionState,
+    )]
+    pub transaction: Account<'info, MsTransaction>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveTransaction<'info> {
+    #[account(
+    mut,
+    seeds = [b"squad", multisig.create_key.as_ref(), b"multisig"],
+    bump = multisig.bump,
+    )]
+    pub multisig: Account<'info, Ms>,
+
+    #[account(
+    mut,
+    constraint = transaction.ms == multisig.key() @MsError::InvalidInstructionAccount,
+    constraint = transaction.status == MsTransactionStatus::Active @MsError::InvalidTransactionState,
+    )]
+    pub transaction: Account<'info, MsTransaction>,
+
+    #[account(mut, constraint = multisig.keys.contains(&member.key()) @MsError::KeyNotInMultisig)]
+    pub member: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteInstruction<'info> {
+    #[account(
+    mut,
+    seeds = [b"squad", multisig.create_key.as_ref(), b"multisig"],
+    bump = multisig.bump,
+    )]
+    pub multisig: Account<'info, Ms>,
+
+    #[account(
+    mut,
+    constraint = transaction.ms == multisig.key() @MsError::InvalidInstructionAccount,
+    constraint = transaction.status == MsTransactionStatus::ExecuteReady @MsError::InvalidTransactionState,
+    )]
+    pub transaction: Account<'info, MsTransaction>,
+
+    #[account(
+    mut,
+    seeds = [b"squad", transaction.key().as_ref(), &(transaction.executed_index + 1).to_le_bytes(), b"instruction"],
+    bump = instruction.bump,
+    )]
+    pub instruction: Account<'info, MsInstruction>,
+
+    #[account(mut, constraint = multisig.keys.contains(&member.key()) @MsError::KeyNotInMultisig)]
+    pub member: Signer<'info>,
+}
+
+// same layout as the squads v3 Ms account
+#[account]
+pub struct Ms {
+    pub threshold: u16,
+    pub authority_index: u16,
+    pub transaction_index: u32,
+    pub ms_change_index: u32,
+    pub bump: u8,
+    pub create_key: Pubkey,
+    pub allow_external_execute: bool,
+    pub keys: Vec<Pubkey>,
+}
+
+impl Ms {
+    pub const SIZE_WITHOUT_MEMBERS: usize = 8 + // anchor account discriminator
+        2 +         // threshold
+        2 +         // authority index
+        4 +         // transaction index
+        4 +         // ms change index
+        1 +         // bump
+        32 +        // create key
+        1 +         // allow external execute
+        4;          // keys vec length
+}
+
+// same layout as the squads v3 MsTransaction account
+#[account]
+pub struct MsTransaction {
+    pub creator: Pubkey,
+    pub ms: Pubkey,
+    pub transaction_index: u32,
+    pub authority_index: u32,
+    pub authority_bump: u8,
+    pub status: MsTransactionStatus,
+    pub instruction_index: u8,
+    pub bump: u8,
+    pub approved: Vec<Pubkey>,
+    pub rejected: Vec<Pubkey>,
+    pub cancelled: Vec<Pubkey>,
+    pub executed_index: u8,
+}
+
+impl MsTransaction {
+    pub const SIZE_WITHOUT_MEMBERS: usize = 8 + // anchor account discriminator
+        32 +        // creator
+        32 +        // ms
+        4 +         // transaction index
+        4 +         // authority index
+        1 +         // authority bump
+        1 +         // status
+        1 +         // instruction index
+        1 +         // bump
+        4 +         // approved vec length
+        4 +         // rejected vec length
+        4 +         // cancelled vec length
+        1;          // executed index
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub enum MsTransactionStatus {
+    Draft,
+    Active,
+    ExecuteReady,
+    Executed,
+    Rejected,
+    Cancelled,
+}
+
+// same layout as the squads v3 MsInstruction account
+#[account]
+pub struct MsInstruction {
+    pub program_id: Pubkey,
+    pub keys: Vec<MsAccountMeta>,
+    pub data: Vec<u8>,
+    pub instruction_index: u8,
+    pub bump: u8,
+    pub executed: bool,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct MsAccountMeta {
+    pub pubkey: Pubkey,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct IncomingInstruction {
+    pub program_id: Pubkey,
+    pub keys: Vec<MsAccountMeta>,
+    pub data: Vec<u8>,
+}
+
+impl IncomingInstruction {
+    // the MsInstruction account holding it
+    pub fn size(&self) -> usize {
+        8 +                             // anchor account discriminator
+        32 +                            // program id
+        4 + (self.keys.len() * 34) +    // keys
+        4 + self.data.len() +           // data
+        1 +                             // instruction index
+        1 +                             // bump
+        1                               // executed
+    }
+}
+
+#[error_code]
+pub enum MsError {
+    #[msg("Invalid threshold")]
+    InvalidThreshold,
+    #[msg("Key isn't a member of the multisig")]
+    KeyNotInMultisig,
+    #[msg("Transaction isn't in the right state")]
+    InvalidTransactionState,
+    #[msg("Invalid instruction account")]
+    InvalidInstructionAccount,
+    #[msg("Already approved")]
+    AlreadyApproved,
+}
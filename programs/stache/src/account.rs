@@ -1,10 +1,15 @@
 use anchor_lang::prelude::*;
+use anchor_spl::token::spl_token::native_mint;
 
-use crate::constant::{MAX_SUBMITTERS, MAX_APPROVERS, MAX_VAULTS, MAX_VAULT_ACTIONS, MAX_AUTOS};
+use crate::constant::{MAX_SUBMITTERS, MAX_APPROVERS, MAX_VAULTS, MAX_VAULT_ACTIONS, MAX_AUTOS, MAX_SPENDING_LIMITS, MAX_VAULT_MINTS, MAX_VAULT_AUTOS, MAX_AUTO_ACTIONS, MAX_CRON_SCHEDULE_LEN};
 use crate::error::StacheError;
+use crate::nft::CnftArgs;
+use crate::squads::Ms;
+use crate::util::next_cron_timestamp;
+use clockwork_sdk::state::{ThreadResponse, Trigger};
 
 
-// "current" cause later we'll use the versioning system that keychain
+// "current" as in the current layout: older versions get converted by the migrate_* instructions (see migration.rs)
 
 #[account]
 pub struct CurrentStache {
@@ -24,10 +29,13 @@ pub struct CurrentStache {
     // automation ids that are currently active
     pub autos: Vec<u8>,
 
+    // caps on how much can leave the stache per period (per mint)
+    pub spending_limits: Vec<SpendingLimit>,
 }
 
 impl CurrentStache {
     pub const MAX_SIZE: usize = 1 + 1 + 32 + 32 + 32 + 1 + 1 + (4 + (MAX_VAULTS)) + (4 + (MAX_AUTOS)) +
+        (4 + (MAX_SPENDING_LIMITS * SpendingLimit::MAX_SIZE)) +
         128; // extra space for now;
     pub const CURRENT_VERSION: u8 = 1;
 
@@ -91,33 +99,147 @@ impl CurrentStache {
         return Self::add_index(&mut self.autos, MAX_AUTOS, &mut self.next_auto_index);
     }
 
+    pub fn within_limit(&mut self, mint: &Pubkey, amount: u64, now: i64) -> bool {
+        SpendingLimit::within_limit(&mut self.spending_limits, mint, amount, now)
+    }
+
+    pub fn record_spend(&mut self, mint: &Pubkey, amount: u64, now: i64) {
+        SpendingLimit::record_spend(&mut self.spending_limits, mint, amount, now);
+    }
+
+}
+
+// caps how much of a mint can leave the stache/vault within a window of period_seconds
+#[derive(Debug, Clone, AnchorSerialize, AnchorDeserialize, PartialEq, Eq)]
+pub struct SpendingLimit {
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub period_seconds: u32,
+    pub window_start: i64,
+    pub spent: u64,        // spent so far in the current window
+}
+
+impl SpendingLimit {
+
+    pub const MAX_SIZE: usize =
+        32 +        // mint
+        8 +         // amount
+        4 +         // period seconds
+        8 +         // window start
+        8;          // spent
+
+    // starts a new window once the current one's expired
+    fn roll(&mut self, now: i64) {
+        if now >= self.window_start + i64::from(self.period_seconds) {
+            self.window_start = now;
+            self.spent = 0;
+        }
+    }
+
+    pub fn remaining(&mut self, now: i64) -> u64 {
+        self.roll(now);
+        self.amount.saturating_sub(self.spent)
+    }
+
+    // a new limit is looser if it allows more, or resets more often
+    pub fn is_looser(&self, amount: u64, period_seconds: u32) -> bool {
+        amount > self.amount || period_seconds < self.period_seconds
+    }
+
+    // no limit for the mint = always within limit
+    pub fn within_limit(limits: &mut Vec<SpendingLimit>, mint: &Pubkey, amount: u64, now: i64) -> bool {
+        match limits.iter_mut().find(|limit| limit.mint == *mint) {
+            Some(limit) => limit.remaining(now) >= amount,
+            None => true,
+        }
+    }
+
+    pub fn record_spend(limits: &mut Vec<SpendingLimit>, mint: &Pubkey, amount: u64, now: i64) {
+        if let Some(limit) = limits.iter_mut().find(|limit| limit.mint == *mint) {
+            limit.roll(now);
+            limit.spent = limit.spent.saturating_add(amount);
+        }
+    }
+
+    // adds or updates the limit for a mint
+    pub fn set(limits: &mut Vec<SpendingLimit>, mint: &Pubkey, amount: u64, period_seconds: u32, now: i64) -> Result<()> {
+        match limits.iter_mut().find(|limit| limit.mint == *mint) {
+            Some(limit) => {
+                limit.amount = amount;
+                limit.period_seconds = period_seconds;
+            }
+            None => {
+                require!(limits.len() < MAX_SPENDING_LIMITS, StacheError::HitLimit);
+                limits.push(SpendingLimit {
+                    mint: *mint,
+                    amount,
+                    period_seconds,
+                    window_start: now,
+                    spent: 0,
+                });
+            }
+        }
+        Ok(())
+    }
 }
 
-#[derive(Debug, Clone, AnchorSerialize, AnchorDeserialize)]
+#[derive(Debug, Clone, AnchorSerialize, AnchorDeserialize, PartialEq, Eq)]
 pub struct Submitter {
     pub key: Pubkey,
     pub enabled: bool,
 }
 
-#[derive(Debug, Clone, AnchorSerialize, AnchorDeserialize)]
+#[derive(Debug, Clone, AnchorSerialize, AnchorDeserialize, PartialEq, Eq)]
 pub struct Approver {
     pub key: Pubkey,
     pub enabled: bool,
 }
 
-// "sub" stache accounts always start w/the stache so we can filter using getProgramAccounts
+// "sub" stache accounts always start w/the stache so we can filter using getProgramAccounts, then the layout version
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
 pub enum VaultType {
     Easy,   // not requiring sigs (like the stache)
     TwoSig,   // just 2 sigs
     Squads { multisig: Pubkey, sigs: u8}, //  squads; sigs = threshold
+    Threshold { approvers: Vec<Approver>, submitters: Vec<Submitter>, threshold: u8 },  // native m of n
+}
+
+impl VaultType {
+
+    // checks the vault type is set up sanely before creating a vault with it
+    pub fn validate(&self) -> Result<()> {
+        if let VaultType::Threshold { approvers, submitters, threshold } = self {
+            require!(approvers.len() <= MAX_APPROVERS && submitters.len() <= MAX_SUBMITTERS, StacheError::HitLimit);
+            let enabled_approvers = approvers.iter().filter(|approver| approver.enabled).count();
+            require!(*threshold > 0 && usize::from(*threshold) <= enabled_approvers, StacheError::InvalidThreshold);
+            require!(submitters.iter().any(|submitter| submitter.enabled), StacheError::InvalidThreshold);
+
+            // no dupes
+            for (i, approver) in approvers.iter().enumerate() {
+                require!(!approvers[i + 1..].iter().any(|a| a.key == approver.key), StacheError::DupeAccount);
+            }
+            for (i, submitter) in submitters.iter().enumerate() {
+                require!(!submitters[i + 1..].iter().any(|s| s.key == submitter.key), StacheError::DupeAccount);
+            }
+        }
+        Ok(())
+    }
+
+    fn is_approver(approvers: &Vec<Approver>, key: &Pubkey) -> bool {
+        approvers.iter().any(|approver| approver.enabled && approver.key == *key)
+    }
+
+    fn is_submitter(submitters: &Vec<Submitter>, key: &Pubkey) -> bool {
+        submitters.iter().any(|submitter| submitter.enabled && submitter.key == *key)
+    }
 }
 
 
 #[account]
 pub struct Vault {
     pub stache: Pubkey,
+    pub version: u8,
     pub index: u8,
     pub bump: u8,
     pub vault_type: VaultType,
@@ -125,19 +247,33 @@ pub struct Vault {
     pub name: String,
     pub next_action_index: u8,      // todo: deal with wrapping
     pub actions: Vec<VaultAction>,
+    pub locked_until: i64,      // unix timestamp the vault is time-locked until (0 = not time-locked)
+    pub withdraw_delay: u32,    // seconds an action has to wait before it can be executed (0 = no cooldown)
+    pub locked_by: Pubkey,      // the key that locked the vault (an easy vault has to be unlocked by a different key)
+    pub spending_limits: Vec<SpendingLimit>,
+    pub mints: Vec<Pubkey>,     // mints the vault is allowed to hold (empty = any)
+    pub authorized_autos: Vec<AuthorizedAuto>,  // automations the approvers have signed off on
 }
 
 impl Vault {
 
     pub const MAX_SIZE: usize =
         32 +        // stache
+        1 +         // version
         1 +        // index
         1 +         // bump
-        1 + 1 + 32 +         // vault type
+        1 + (4 + MAX_APPROVERS * (32 + 1)) + (4 + MAX_SUBMITTERS * (32 + 1)) + 1 +         // vault type (threshold is the biggest)
         1 +         // locked
         32 +        // name
         (4 + (MAX_VAULT_ACTIONS * VaultAction::MAX_SIZE)) + // actions
+        8 +         // locked until
+        4 +         // withdraw delay
+        32 +        // locked by
+        (4 + (MAX_SPENDING_LIMITS * SpendingLimit::MAX_SIZE)) + // spending limits
+        (4 + (MAX_VAULT_MINTS * 32)) +      // mints
+        (4 + (MAX_VAULT_AUTOS * AuthorizedAuto::MAX_SIZE)) +    // authorized autos
         128;        // extra space for now during dev
+    pub const CURRENT_VERSION: u8 = 1;
 
     pub fn get_action(&mut self, action_index: u8) -> Option<&mut VaultAction> {
         match self.actions.iter().position(|x| x.action_index == action_index) {
@@ -162,44 +298,205 @@ impl Vault {
         }
     }
 
-    // return whether to proceed with withdrawal or not
-    pub fn withdraw(&mut self, initiator: &Pubkey, from: &Pubkey, to: &Pubkey, amount: u64) -> Result<bool> {
-        if self.locked {
-            return Err(StacheError::VaultLocked.into());
+    // hands out the next action index
+    fn take_action_index(&mut self) -> u8 {
+        // todo: deal with expiring previous actions correctly to make sure we don't have duplicate indexes
+        let index = self.next_action_index;
+        self.next_action_index = match index + 1 {
+            u8::MAX => 1,
+            next => next,
+        };
+        index
+    }
+
+    // number of approvals needed before an action can be executed
+    pub fn required_approvals(&self) -> usize {
+        match &self.vault_type {
+            VaultType::Easy => 1,
+            VaultType::TwoSig => 2,
+            VaultType::Squads { sigs, .. } => *sigs as usize,
+            VaultType::Threshold { threshold, .. } => *threshold as usize,
         }
-        match self.vault_type {
-            VaultType::Easy => {
-                // do the withdraw
-                return Ok(true);
-            }
-            VaultType::TwoSig => {
-                // create the action
-
-                // todo: deal with expiring previous actions correctly to make sure we don't have duplicate indexes
-                self.next_action_index = match self.next_action_index + 1 {
-                    u8::MAX => 1,
-                    _ => self.next_action_index + 1,
-                };
-
-                let mut action = VaultAction {
-                    action_index: self.next_action_index - 1,
-                    action_type: ActionType::Transfer,
-                    approvers: vec![initiator.clone()],
-                    action: TransferAction {
-                        from: from.clone(),
-                        to: to.clone(),
-                        amount,
-                    }.try_to_vec().unwrap(),
-                };
-                self.actions.push(action);
-                return Ok(false);
+    }
+
+    // for squads vaults, only approvals from members of the multisig count, and for threshold vaults only enabled approvers
+    pub fn count_approvals(&self, action: &VaultAction, multisig: Option<&Ms>) -> usize {
+        match &self.vault_type {
+            VaultType::Squads { .. } => match multisig {
+                Some(ms) => action.approvers.iter().filter(|approver| ms.is_member(approver)).count(),
+                None => 0,
+            },
+            VaultType::Threshold { approvers, .. } => {
+                action.approvers.iter().filter(|approver| VaultType::is_approver(approvers, approver)).count()
             }
-            _ => {
-                // don't support multisig for now (todo)
-                msg!("withdraw for vault type not supported: {:?}", self.vault_type);
-                return Ok(false);
+            _ => action.count_approvers(),
+        }
+    }
+
+    // how many approvers there are to draw from, when the vault knows (keychain keys can come and go)
+    fn approver_count(&self, multisig: Option<&Ms>) -> Option<usize> {
+        match &self.vault_type {
+            VaultType::Squads { .. } => multisig.map(|ms| ms.keys.len()),
+            VaultType::Threshold { approvers, .. } => Some(approvers.iter().filter(|approver| approver.enabled).count()),
+            _ => None,
+        }
+    }
+
+    pub fn is_approved(&self, action: &VaultAction, multisig: Option<&Ms>) -> bool {
+        let mut required_approvals = self.required_approvals();
+        // squads thresholds can be raised after the vault is created, so never go under the multisig's current one
+        if let Some(ms) = multisig {
+            required_approvals = required_approvals.max(usize::from(ms.threshold));
+        }
+        // going over the spending limit needs one more approval than usual, as long as there's someone left to give it
+        if action.over_limit {
+            required_approvals += 1;
+            if let Some(approver_count) = self.approver_count(multisig) {
+                required_approvals = required_approvals.min(approver_count);
             }
         }
+        // shortening a time-lock needs at least a 2nd approval, so the key that proposed it can't undo the lock on its own
+        if action.action_type == ActionType::LockUntil {
+            required_approvals = required_approvals.max(2);
+        }
+        self.count_approvals(action, multisig) >= required_approvals
+    }
+
+    // whether the given key can approve actions on this vault
+    pub fn can_approve(&self, approver: &Pubkey, is_keychain_key: bool, multisig: Option<&Ms>) -> bool {
+        match &self.vault_type {
+            VaultType::Squads { .. } => multisig.map_or(false, |ms| ms.is_member(approver)),
+            VaultType::Threshold { approvers, .. } => VaultType::is_approver(approvers, approver),
+            _ => is_keychain_key,
+        }
+    }
+
+    // whether the given key can propose actions (withdrawals) on this vault
+    pub fn can_submit(&self, submitter: &Pubkey, is_keychain_key: bool) -> bool {
+        match &self.vault_type {
+            VaultType::Threshold { submitters, .. } => VaultType::is_submitter(submitters, submitter),
+            _ => is_keychain_key,
+        }
+    }
+
+    // locked either indefinitely or until a certain time
+    pub fn is_locked(&self, now: i64) -> bool {
+        self.locked || now < self.locked_until
+    }
+
+    // approved, past the cooldown, and (for transfers) not locked
+    pub fn is_executable(&self, action: &VaultAction, now: i64, multisig: Option<&Ms>) -> bool {
+        if action.action_type.is_transfer() && self.is_locked(now) {
+            return false;
+        }
+        self.is_approved(action, multisig) && now >= action.executable_at
+    }
+
+    // creates an action, and returns whether it can be executed right away (in which case it isn't stored)
+    pub fn propose(&mut self, initiator: &Pubkey, action_type: ActionType, action_data: Vec<u8>, over_limit: bool, now: i64, multisig: Option<&Ms>) -> Result<bool> {
+        let mut action = VaultAction {
+            action_index: 0,
+            action_type,
+            approvers: vec![initiator.clone()],
+            action: action_data,
+            executable_at: if self.withdraw_delay > 0 { now + i64::from(self.withdraw_delay) } else { 0 },
+            over_limit,
+        };
+
+        // ie: a 1-sig squads vault proposed by a member doesn't need to wait around
+        if self.is_executable(&action, now, multisig) {
+            return Ok(true);
+        }
+
+        require!(self.actions.len() < MAX_VAULT_ACTIONS, StacheError::HitLimit);
+        action.action_index = self.take_action_index();
+        self.actions.push(action);
+        Ok(false)
+    }
+
+    // return whether to proceed with withdrawal or not
+    pub fn withdraw(&mut self, initiator: &Pubkey, from: &Pubkey, to: &Pubkey, mint: &Pubkey, amount: u64, now: i64, multisig: Option<&Ms>) -> Result<bool> {
+        let transfer = TransferAction {
+            from: from.clone(),
+            to: to.clone(),
+            amount,
+        }.try_to_vec().unwrap();
+        self.release(initiator, ActionType::Transfer, transfer, mint, amount, now, multisig)
+    }
+
+    // same as withdraw, but for lamports held by the vault account itself (limits are tracked against the native mint)
+    pub fn withdraw_sol(&mut self, initiator: &Pubkey, to: &Pubkey, lamports: u64, now: i64, multisig: Option<&Ms>) -> Result<bool> {
+        let transfer = SolTransferAction {
+            to: to.clone(),
+            lamports,
+        }.try_to_vec().unwrap();
+        self.release(initiator, ActionType::SolTransfer, transfer, &native_mint::ID, lamports, now, multisig)
+    }
+
+    // nfts (incl. programmable nfts) go through metaplex, so the action just needs the mint + who gets it
+    pub fn withdraw_nft(&mut self, initiator: &Pubkey, mint: &Pubkey, to: &Pubkey, now: i64, multisig: Option<&Ms>) -> Result<bool> {
+        let transfer = NftTransferAction {
+            mint: mint.clone(),
+            to: to.clone(),
+        }.try_to_vec().unwrap();
+        self.release(initiator, ActionType::NftTransfer, transfer, mint, 1, now, multisig)
+    }
+
+    // compressed nfts go through bubblegum. limits are tracked against the asset id, same as an nft's mint
+    pub fn withdraw_cnft(&mut self, initiator: &Pubkey, transfer: &CnftTransferAction, now: i64, multisig: Option<&Ms>) -> Result<bool> {
+        let asset_id = transfer.asset_id;
+        self.release(initiator, ActionType::CnftTransfer, transfer.try_to_vec().unwrap(), &asset_id, 1, now, multisig)
+    }
+
+    fn release(&mut self, initiator: &Pubkey, action_type: ActionType, action_data: Vec<u8>, mint: &Pubkey, amount: u64, now: i64, multisig: Option<&Ms>) -> Result<bool> {
+        if self.is_locked(now) {
+            return Err(StacheError::VaultLocked.into());
+        }
+
+        let over_limit = !self.within_limit(mint, amount, now);
+
+        // easy vaults w/out a cooldown just do the withdraw, as long as it's under the spending limit
+        let execute = if self.vault_type == VaultType::Easy && self.withdraw_delay == 0 && !over_limit {
+            true
+        } else {
+            self.propose(initiator, action_type, action_data, over_limit, now, multisig)?
+        };
+
+        if execute {
+            self.record_spend(mint, amount, now);
+        }
+        Ok(execute)
+    }
+
+    pub fn within_limit(&mut self, mint: &Pubkey, amount: u64, now: i64) -> bool {
+        SpendingLimit::within_limit(&mut self.spending_limits, mint, amount, now)
+    }
+
+    pub fn record_spend(&mut self, mint: &Pubkey, amount: u64, now: i64) {
+        SpendingLimit::record_spend(&mut self.spending_limits, mint, amount, now);
+    }
+
+    pub fn allows_mint(&self, mint: &Pubkey) -> bool {
+        self.mints.is_empty() || self.mints.contains(mint)
+    }
+
+    // automations can pull from easy vaults (w/out a cooldown) freely, everything else needs the approvers to have
+    // authorized that automation doing exactly this
+    pub fn allows_auto(&self, auto: &Pubkey, action: &AutoTransferAction) -> bool {
+        (self.vault_type == VaultType::Easy && self.withdraw_delay == 0) ||
+            self.authorized_autos.iter().any(|authorized| authorized.auto == *auto && authorized.action == *action)
+    }
+
+    // replaces any previous authorization for the same automation
+    pub fn authorize_auto(&mut self, authorized: AuthorizedAuto) -> Result<()> {
+        self.revoke_auto(&authorized.auto);
+        require!(self.authorized_autos.len() < MAX_VAULT_AUTOS, StacheError::HitLimit);
+        self.authorized_autos.push(authorized);
+        Ok(())
+    }
+
+    pub fn revoke_auto(&mut self, auto: &Pubkey) {
+        self.authorized_autos.retain(|authorized| authorized.auto != *auto);
     }
 
     pub fn is_type(&self, vault_type: VaultType) -> bool {
@@ -210,6 +507,21 @@ impl Vault {
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
 pub enum ActionType {
     Transfer,
+    SetDelay,
+    Unlock,
+    SolTransfer,
+    NftTransfer,
+    AuthorizeAuto,
+    LockUntil,
+    CnftTransfer,
+}
+
+impl ActionType {
+
+    // actions that move funds out of the vault (blocked while it's locked)
+    pub fn is_transfer(&self) -> bool {
+        matches!(self, ActionType::Transfer | ActionType::SolTransfer | ActionType::NftTransfer | ActionType::CnftTransfer)
+    }
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
@@ -226,12 +538,89 @@ impl TransferAction {
             32;         // to
 }
 
+// lamports out of the vault account itself
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct SolTransferAction {
+    pub to: Pubkey,
+    pub lamports: u64,
+}
+
+// an nft out of the vault; to = the wallet that ends up owning it
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct NftTransferAction {
+    pub mint: Pubkey,
+    pub to: Pubkey,
+}
+
+// a compressed nft out of the vault. the leaf is kept as of when the action was proposed, the tree's root + the proof
+// are whatever they are once it's executed (see nft::current_root)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct CnftTransferAction {
+    pub asset_id: Pubkey,
+    pub to: Pubkey,
+    pub data_hash: [u8; 32],
+    pub creator_hash: [u8; 32],
+    pub nonce: u64,
+    pub index: u32,
+}
+
+impl CnftTransferAction {
+    pub fn new(args: &CnftArgs, merkle_tree: &Pubkey, to: &Pubkey) -> Self {
+        CnftTransferAction {
+            asset_id: args.asset_id(merkle_tree),
+            to: to.clone(),
+            data_hash: args.data_hash,
+            creator_hash: args.creator_hash,
+            nonce: args.nonce,
+            index: args.index,
+        }
+    }
+
+    pub fn args(&self, root: [u8; 32]) -> CnftArgs {
+        CnftArgs {
+            root,
+            data_hash: self.data_hash,
+            creator_hash: self.creator_hash,
+            nonce: self.nonce,
+            index: self.index,
+        }
+    }
+}
+
+// lets an automation move funds out of the vault: the approvers sign off on the automation's exact action, so
+// changing the action afterwards needs a new authorization
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct AuthorizedAuto {
+    pub auto: Pubkey,
+    pub action: AutoTransferAction,
+}
+
+impl AuthorizedAuto {
+    pub const MAX_SIZE: usize =
+        32 +        // auto
+        AutoTransferAction::MAX_SIZE;   // action
+}
+
+// lowering a vault's withdraw delay has to wait out the current delay
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct SetDelayAction {
+    pub withdraw_delay: u32,
+}
+
+// moves a time-lock up to an earlier time
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct LockUntilAction {
+    pub locked_until: i64,
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
 pub struct VaultAction {
     pub action_index: u8,
     pub action_type: ActionType,
     pub approvers: Vec<Pubkey>,
     pub action: Vec<u8>,      // depends on the ActionType
+    pub executable_at: i64,   // unix timestamp after which the action can be executed (0 = as soon as it's approved)
+    pub over_limit: bool,     // over the vault's spending limit, so needs an extra approval
 }
 
 impl VaultAction {
@@ -239,8 +628,10 @@ impl VaultAction {
     pub const MAX_SIZE: usize =
         1 +         // action index
         1 +         // action type
-        4 + (32 * MAX_VAULTS)  +        // approvers
-        128;        // should be good enough for whatever action for now
+        4 + (32 * MAX_APPROVERS)  +        // approvers
+        8 +         // executable at
+        1 +         // over limit
+        4 + AuthorizedAuto::MAX_SIZE;   // action data (authorizing an automation is the biggest)
 
     pub fn transfer_action(&mut self) -> Result<TransferAction> {
         if self.action_type != ActionType::Transfer {
@@ -251,6 +642,54 @@ impl VaultAction {
         Ok(withdraw_data)
     }
 
+    pub fn sol_transfer_action(&mut self) -> Result<SolTransferAction> {
+        if self.action_type != ActionType::SolTransfer {
+            return err!(StacheError::InvalidAction);
+        }
+        let sol_transfer_data = AnchorDeserialize::deserialize(&mut self.action.as_slice())?;
+        Ok(sol_transfer_data)
+    }
+
+    pub fn nft_transfer_action(&mut self) -> Result<NftTransferAction> {
+        if self.action_type != ActionType::NftTransfer {
+            return err!(StacheError::InvalidAction);
+        }
+        let nft_transfer_data = AnchorDeserialize::deserialize(&mut self.action.as_slice())?;
+        Ok(nft_transfer_data)
+    }
+
+    pub fn cnft_transfer_action(&mut self) -> Result<CnftTransferAction> {
+        if self.action_type != ActionType::CnftTransfer {
+            return err!(StacheError::InvalidAction);
+        }
+        let cnft_transfer_data = AnchorDeserialize::deserialize(&mut self.action.as_slice())?;
+        Ok(cnft_transfer_data)
+    }
+
+    pub fn authorize_auto_action(&mut self) -> Result<AuthorizedAuto> {
+        if self.action_type != ActionType::AuthorizeAuto {
+            return err!(StacheError::InvalidAction);
+        }
+        let authorize_data = AnchorDeserialize::deserialize(&mut self.action.as_slice())?;
+        Ok(authorize_data)
+    }
+
+    pub fn set_delay_action(&mut self) -> Result<SetDelayAction> {
+        if self.action_type != ActionType::SetDelay {
+            return err!(StacheError::InvalidAction);
+        }
+        let set_delay_data = AnchorDeserialize::deserialize(&mut self.action.as_slice())?;
+        Ok(set_delay_data)
+    }
+
+    pub fn lock_until_action(&mut self) -> Result<LockUntilAction> {
+        if self.action_type != ActionType::LockUntil {
+            return err!(StacheError::InvalidAction);
+        }
+        let lock_until_data = AnchorDeserialize::deserialize(&mut self.action.as_slice())?;
+        Ok(lock_until_data)
+    }
+
     pub fn approve(&mut self, approver: &Pubkey) -> Result<()> {
         if self.approvers.contains(approver) {
             return err!(StacheError::AlreadyApproved);
@@ -269,6 +708,8 @@ impl VaultAction {
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
 pub enum TriggerType {
     Balance,
+    Cron,
+    Interval,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
@@ -278,10 +719,73 @@ pub struct BalanceTrigger {
     pub above: bool,            // above = true, then trigger fires if balance is above trigger_balance, else when below
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct CronTrigger {
+    pub schedule: String,       // clockwork cron schedule (sec min hour day month weekday year), ie: "0 0 0 1 * * *"
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct IntervalTrigger {
+    pub seconds: i64,           // fires every this many seconds..
+    pub start: i64,             // ..starting at this unix timestamp
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub enum TransferMode {
+    Fixed,          // transfer amount
+    Percent,        // transfer amount (basis points) of the from balance
+    SweepAbove,     // transfer everything in the from account above amount
+    TopUp,          // transfer enough to bring the to account up to amount
+}
+
+// an automation's transfer: unlike vault transfers, the amount can depend on balances at the time it fires
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct AutoTransferAction {
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub mint: Pubkey,
+    pub token_program: Pubkey,
+    pub amount: u64,
+    pub mode: TransferMode,
+    pub vault: Option<Pubkey>,  // the vault the from account belongs to (None = the stache)
+}
+
+impl AutoTransferAction {
+
+    pub const MAX_SIZE: usize =
+        32 +        // from
+        32 +        // to
+        32 +        // mint
+        32 +        // token program
+        8 +         // amount
+        1 +         // mode
+        1 + 32;     // vault
+
+    // what to transfer given the current from/to balances (0 = nothing to do)
+    pub fn amount_for(&self, from_balance: u64, to_balance: u64) -> u64 {
+        match self.mode {
+            TransferMode::Fixed => self.amount,
+            TransferMode::Percent => (from_balance as u128 * self.amount as u128 / 10000) as u64,
+            TransferMode::SweepAbove => from_balance.saturating_sub(self.amount),
+            // partial top ups are fine if the from account can't cover all of it
+            TransferMode::TopUp => self.amount.saturating_sub(to_balance).min(from_balance),
+        }
+    }
+
+    // the least the next execution could move (for checking caps): only fixed transfers know ahead of time, the
+    // others can move nothing (and get trimmed to what's left of the budget anyway)
+    pub fn min_amount(&self) -> u64 {
+        match self.mode {
+            TransferMode::Fixed => self.amount,
+            _ => 0,
+        }
+    }
+}
 
 #[account]
 pub struct Auto {
     pub stache: Pubkey,
+    pub version: u8,
     pub index: u8,
     pub bump: u8,
     pub active: bool,
@@ -290,15 +794,20 @@ pub struct Auto {
     pub num_execs: u32,         // number of times this automation was executed (action taken)
     pub thread: Option<Pubkey>,         // clockwork thread
     pub name: String,
-    pub action_type: Option<ActionType>,
-    pub action: Option<Vec<u8>>,       //  depends on the action type
+    pub actions: Vec<AutoTransferAction>,   // run in order, all or nothing
     pub trigger_type: Option<TriggerType>,
     pub trigger: Option<Vec<u8>>,
+    pub last_fired_at: i64,     // last time the automation fired (set on activation so time triggers count from then)
+    pub max_execs: Option<u32>,         // stop after this many executions
+    pub max_total_amount: Option<u64>,  // stop once this much has been moved in total
+    pub expires_at: Option<i64>,        // stop after this time
+    pub total_amount: u64,      // total amount moved by this automation
 }
 
 impl Auto {
     pub const MAX_SIZE: usize =
         32 +        // stache
+        1 +         // version
         1 +        // index
         1 +         // bump
         1 +         // active
@@ -307,40 +816,116 @@ impl Auto {
         4 +         // num_execs
         1 + 32 +         // thread
         32 +        // name
-        1 + 1 +         // action type
+        4 + (MAX_AUTO_ACTIONS * AutoTransferAction::MAX_SIZE) +    // actions
         1 + 1 +         // trigger type
-        128;        // should be good enough for whatever action for now
+        8 +         // last fired at
+        1 + 4 +     // max execs
+        1 + 8 +     // max total amount
+        1 + 8 +     // expires at
+        8 +         // total amount
+        128 +       // should be good enough for whatever trigger for now
+        MAX_CRON_SCHEDULE_LEN;  // room for a cron schedule in the trigger
+    pub const CURRENT_VERSION: u8 = 1;
 
 
     // todo: pull into a trait / remove dupe code
 
-    pub fn transfer_action(&mut self) -> Result<TransferAction> {
-        if let Some(action_type) = &self.action_type {
-            if *action_type != ActionType::Transfer {
-                return err!(StacheError::InvalidAction);
+    pub fn balance_trigger(&mut self) -> Result<BalanceTrigger> {
+        if let Some(trigger_type) = &self.trigger_type {
+            if *trigger_type != TriggerType::Balance {
+                return err!(StacheError::InvalidTrigger);
             }
         } else {
-            return err!(StacheError::MissingAction);
+            return err!(StacheError::MissingTrigger);
         }
+        // deserialize the data into the TransferAction
+        let trigger_slice = &mut self.trigger.as_mut().ok_or(StacheError::MissingTrigger)?.as_slice();
+        let balance_trigger = AnchorDeserialize::deserialize(trigger_slice)?;
+        Ok(balance_trigger)
+    }
 
-        // Deserialize the data into the TransferAction.
-        let action_slice = &mut self.action.as_mut().ok_or(StacheError::MissingAction)?.as_slice();
-        let transfer_action = AnchorDeserialize::deserialize(action_slice)?;
-        Ok(transfer_action)
+    pub fn cron_trigger(&mut self) -> Result<CronTrigger> {
+        if let Some(trigger_type) = &self.trigger_type {
+            if *trigger_type != TriggerType::Cron {
+                return err!(StacheError::InvalidTrigger);
+            }
+        } else {
+            return err!(StacheError::MissingTrigger);
+        }
+        let trigger_slice = &mut self.trigger.as_mut().ok_or(StacheError::MissingTrigger)?.as_slice();
+        let cron_trigger = AnchorDeserialize::deserialize(trigger_slice)?;
+        Ok(cron_trigger)
     }
 
-    pub fn balance_trigger(&mut self) -> Result<BalanceTrigger> {
+    pub fn interval_trigger(&mut self) -> Result<IntervalTrigger> {
         if let Some(trigger_type) = &self.trigger_type {
-            if *trigger_type != TriggerType::Balance {
+            if *trigger_type != TriggerType::Interval {
                 return err!(StacheError::InvalidTrigger);
             }
         } else {
             return err!(StacheError::MissingTrigger);
         }
-        // deserialize the data into the TransferAction
         let trigger_slice = &mut self.trigger.as_mut().ok_or(StacheError::MissingTrigger)?.as_slice();
-        let balance_trigger = AnchorDeserialize::deserialize(trigger_slice)?;
-        Ok(balance_trigger)
+        let interval_trigger = AnchorDeserialize::deserialize(trigger_slice)?;
+        Ok(interval_trigger)
+    }
+
+    // whether the automation has expired, spent its budget, or executing the given amount would go over one of its caps
+    pub fn is_exhausted(&self, amount: u64, now: i64) -> bool {
+        let expired = self.expires_at.map_or(false, |expires_at| now >= expires_at);
+        let execs_reached = self.max_execs.map_or(false, |max| self.num_execs >= max);
+        let over_budget = self.remaining_budget().map_or(false, |remaining| remaining == 0 || amount > remaining);
+        expired || execs_reached || over_budget
+    }
+
+    // what's left of max_total_amount (None = no budget)
+    pub fn remaining_budget(&self) -> Option<u64> {
+        self.max_total_amount.map(|max| max.saturating_sub(self.total_amount))
+    }
+
+    // marks the automation as no longer running; it can't stop its own thread from inside fire_auto (clockwork
+    // invoking us means we can't cpi back into clockwork), so the thread gets parked by stopped_response instead
+    pub fn stop(&mut self) {
+        self.active = false;
+        self.paused = true;
+    }
+
+    // thread response that parks the thread: a trigger that never comes
+    pub fn stopped_response(&self) -> ThreadResponse {
+        ThreadResponse {
+            trigger: Some(Trigger::Timestamp { unix_ts: i64::MAX }),
+            ..ThreadResponse::default()
+        }
+    }
+
+    // when a time-based trigger is next due (after the last fire)
+    pub fn next_fire_at(&mut self) -> Result<i64> {
+        match self.trigger_type {
+            Some(TriggerType::Cron) => {
+                let trigger = self.cron_trigger()?;
+                Ok(next_cron_timestamp(&trigger.schedule, self.last_fired_at).ok_or(StacheError::InvalidSchedule)?)
+            }
+            Some(TriggerType::Interval) => {
+                let trigger = self.interval_trigger()?;
+                trigger.next_after(self.last_fired_at)
+            }
+            Some(TriggerType::Balance) => err!(StacheError::InvalidTrigger),
+            None => err!(StacheError::MissingTrigger),
+        }
+    }
+}
+
+impl IntervalTrigger {
+    // intervals stay anchored to the start, so a late fire doesn't push back the ones after it
+    pub fn next_after(&self, after: i64) -> Result<i64> {
+        if after < self.start {
+            return Ok(self.start);
+        }
+        let next = (after - self.start).checked_div(self.seconds)
+            .and_then(|intervals| intervals.checked_add(1))
+            .and_then(|intervals| intervals.checked_mul(self.seconds))
+            .and_then(|offset| offset.checked_add(self.start));
+        Ok(next.ok_or(StacheError::InvalidSchedule)?)
     }
 }
 
@@ -0,0 +1,209 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TransferChecked};
+use std::slice::Iter;
+
+use crate::account::*;
+use crate::constant::*;
+use crate::error::StacheError;
+use crate::util::{reject_transfer_hook, transfer_fee};
+
+// whether the automation's trigger condition holds right now. balance triggers read the token account to check from
+// the (first of the) remaining accounts
+pub fn trigger_met<'info>(auto: &mut Auto, accs: &mut Iter<AccountInfo<'info>>, now: i64) -> Result<bool> {
+    match auto.trigger_type.clone().ok_or(StacheError::MissingTrigger)? {
+        TriggerType::Balance => {
+            let trigger = auto.balance_trigger()?;
+
+            // check that the trigger account is the same as the account we're checking
+            let balance_check_account_info = next_account_info(accs)?;
+            require!(balance_check_account_info.key() == trigger.account, StacheError::InvalidTrigger);
+            let balance = InterfaceAccount::<TokenAccount>::try_from(balance_check_account_info)?.amount;
+
+            if balance >= trigger.trigger_balance && trigger.above {
+                msg!("balance above trigger condition met. account balance: {}, trigger balance: {}, ", balance, trigger.trigger_balance);
+                Ok(true)
+            } else if balance < trigger.trigger_balance && !trigger.above {
+                msg!("balance below trigger condition met. account balance: {}, trigger balance: {}", balance, trigger.trigger_balance);
+                Ok(true)
+            } else {
+                Ok(false)
+            }
+        }
+        TriggerType::Cron | TriggerType::Interval => {
+            let due_at = auto.next_fire_at()?;
+            msg!("schedule due at: {}, now: {}", due_at, now);
+            Ok(now >= due_at)
+        }
+    }
+}
+
+// what firing the automation's steps would come to
+pub enum Plan {
+    Ready(Vec<u64>),        // the amount for each step
+    InsufficientFunds,
+    VaultLocked,
+}
+
+// one of an automation's actions along w/the accounts it needs when the automation fires
+pub struct AutoStep<'info> {
+    pub action: AutoTransferAction,
+    pub from: AccountInfo<'info>,
+    pub to: AccountInfo<'info>,
+    pub mint: AccountInfo<'info>,
+    pub token_program: AccountInfo<'info>,
+    pub vault: Option<AccountInfo<'info>>,
+}
+
+impl<'info> AutoStep<'info> {
+
+    // for each action, in order: from, to, mint, token program, + the vault if the action pulls from one
+    pub fn from_remaining_accounts(actions: &[AutoTransferAction],
+                                   accs: &mut Iter<AccountInfo<'info>>,
+                                   stache: &Pubkey,
+                                   auto: &Pubkey) -> Result<Vec<Self>> {
+        let mut steps = Vec::with_capacity(actions.len());
+        for action in actions {
+            let from = next_account_info(accs)?.clone();
+            let to = next_account_info(accs)?.clone();
+            let mint = next_account_info(accs)?.clone();
+            let token_program = next_account_info(accs)?.clone();
+
+            require!(from.key() == action.from && to.key() == action.to, StacheError::InvalidAction);
+            require!(mint.key() == action.mint && token_program.key() == action.token_program, StacheError::InvalidAction);
+            reject_transfer_hook(&mint)?;
+
+            // the vault has to (still) allow this automation
+            let vault = match action.vault {
+                Some(vault_key) => {
+                    let vault_info = next_account_info(accs)?.clone();
+                    require!(vault_info.key() == vault_key, StacheError::InvalidVault);
+                    let vault = Account::<Vault>::try_from(&vault_info)?;
+                    require!(vault.stache == *stache, StacheError::InvalidVault);
+                    require!(vault.allows_auto(auto, action), StacheError::NotAuthorized);
+                    Some(vault_info)
+                }
+                None => None,
+            };
+
+            steps.push(AutoStep {
+                action: action.clone(),
+                from,
+                to,
+                mint,
+                token_program,
+                vault,
+            });
+        }
+        Ok(steps)
+    }
+
+    // the amount each step would move, given what the steps before it leave behind - unless any of them can't go
+    // through (short balance, transfer fee eats the amount, vault locked). variable amounts get trimmed to what's left
+    // of the automation's budget
+    pub fn plan(steps: &[Self], now: i64, mut budget: Option<u64>) -> Result<Plan> {
+        let mut balances: Vec<(Pubkey, u64)> = Vec::new();
+        let mut amounts = Vec::with_capacity(steps.len());
+
+        for step in steps {
+            let from_balance = Self::balance(&mut balances, &step.from)?;
+            let to_balance = Self::balance(&mut balances, &step.to)?;
+
+            let mut amount = step.action.amount_for(from_balance, to_balance);
+            if let Some(remaining) = budget {
+                if step.action.mode != TransferMode::Fixed {
+                    amount = amount.min(remaining);
+                }
+                budget = Some(remaining.saturating_sub(amount));
+            }
+            if amount == 0 {
+                amounts.push(0);
+                continue;
+            }
+
+            // token-2022 transfer fees come out of the amount, so the from account only needs to cover the amount
+            // itself - but if the fee would eat the whole amount, nothing actually gets transferred
+            let fee = transfer_fee(&step.mint, amount)?;
+            if from_balance < amount || fee >= amount {
+                msg!("insufficient funds to execute action. from token amount: {}, action amount: {}, fee: {}", from_balance, amount, fee);
+                return Ok(Plan::InsufficientFunds);
+            }
+
+            if let Some(vault) = &step.vault {
+                if Account::<Vault>::try_from(vault)?.is_locked(now) {
+                    msg!("vault {} is locked", vault.key());
+                    return Ok(Plan::VaultLocked);
+                }
+            }
+
+            Self::set_balance(&mut balances, &step.from.key(), from_balance - amount);
+            let to_balance = Self::balance(&mut balances, &step.to)?;
+            Self::set_balance(&mut balances, &step.to.key(), to_balance.saturating_add(amount - fee));
+            amounts.push(amount);
+        }
+        Ok(Plan::Ready(amounts))
+    }
+
+    // signed by whichever of the stache/vault owns the from account
+    pub fn transfer(&self, stache: &Account<'info, CurrentStache>, amount: u64) -> Result<()> {
+        let decimals = InterfaceAccount::<Mint>::try_from(&self.mint)?.decimals;
+        let fee = transfer_fee(&self.mint, amount)?;
+
+        match &self.vault {
+            Some(vault_info) => {
+                let vault = Account::<Vault>::try_from(vault_info)?;
+                let binding = vault.index.to_le_bytes();
+                let seeds = &[
+                    binding.as_ref(),
+                    VAULT_SPACE.as_bytes().as_ref(),
+                    stache.stacheid.as_bytes().as_ref(),
+                    BEARD_SPACE.as_bytes().as_ref(),
+                    stache.domain.as_ref(),
+                    STACHE.as_bytes().as_ref(),
+                    &[vault.bump],
+                ];
+                self.transfer_checked(vault_info.clone(), &[&seeds[..]], amount, decimals)?;
+            }
+            None => {
+                let seeds = &[
+                    stache.stacheid.as_bytes().as_ref(),
+                    BEARD_SPACE.as_bytes().as_ref(),
+                    stache.domain.as_ref(),
+                    STACHE.as_bytes().as_ref(),
+                    &[stache.bump],
+                ];
+                self.transfer_checked(stache.to_account_info(), &[&seeds[..]], amount, decimals)?;
+            }
+        }
+
+        msg!("transferred {} tokens (fee: {}) from {} to {}", amount, fee, self.from.key(), self.to.key());
+        Ok(())
+    }
+
+    fn transfer_checked(&self, authority: AccountInfo<'info>, signer: &[&[&[u8]]], amount: u64, decimals: u8) -> Result<()> {
+        let cpi_accounts = TransferChecked {
+            from: self.from.clone(),
+            mint: self.mint.clone(),
+            to: self.to.clone(),
+            authority,
+        };
+        let cpi_ctx = CpiContext::new_with_signer(self.token_program.clone(), cpi_accounts, signer);
+        token_interface::transfer_checked(cpi_ctx, amount, decimals)
+    }
+
+    fn balance(balances: &mut Vec<(Pubkey, u64)>, token_account: &AccountInfo<'info>) -> Result<u64> {
+        match balances.iter().find(|(key, _)| *key == token_account.key()) {
+            Some((_, balance)) => Ok(*balance),
+            None => {
+                let balance = InterfaceAccount::<TokenAccount>::try_from(token_account)?.amount;
+                balances.push((token_account.key(), balance));
+                Ok(balance)
+            }
+        }
+    }
+
+    fn set_balance(balances: &mut Vec<(Pubkey, u64)>, key: &Pubkey, balance: u64) {
+        if let Some(entry) = balances.iter_mut().find(|(k, _)| k == key) {
+            entry.1 = balance;
+        }
+    }
+}
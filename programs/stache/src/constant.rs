@@ -1,12 +1,25 @@
 // root seed
 pub const STACHE: &str = "stache";
 
-// allow up to 3 submitters/approvers for now
-pub const MAX_SUBMITTERS: usize = 3;
-pub const MAX_APPROVERS: usize = 3;
+// allow up to 5 submitters/approvers for now (enough for a 3 of 5)
+pub const MAX_SUBMITTERS: usize = 5;
+pub const MAX_APPROVERS: usize = 5;
 pub const MAX_VAULTS: usize = 5;
 pub const MAX_VAULT_ACTIONS: usize = 5;
 pub const MAX_AUTOS: usize = 5;
+pub const MAX_AUTO_ACTIONS: usize = 3;
+pub const MAX_SPENDING_LIMITS: usize = 3;
+pub const MAX_VAULT_MINTS: usize = 3;
+pub const MAX_VAULT_AUTOS: usize = 3;
+pub const MAX_CRON_SCHEDULE_LEN: usize = 64;
+
+// bounds on interval triggers, so working out when one is next due can't overflow
+pub const MAX_INTERVAL_SECONDS: i64 = 10 * 365 * 24 * 60 * 60;    // 10 years
+pub const MAX_INTERVAL_START: i64 = 32_503_680_000;                // the year 3000
+
+// how far out a vault can be time-locked, and the longest withdraw delay it can have, so neither can freeze it for good
+pub const MAX_LOCK_SECONDS: i64 = 365 * 24 * 60 * 60;           // 1 year
+pub const MAX_WITHDRAW_DELAY: u32 = 30 * 24 * 60 * 60;          // 30 days
 
 // the space for beards (staches)
 pub const BEARD_SPACE: &str = "beards";
@@ -17,4 +30,6 @@ pub const VAULT_SPACE: &str = "vaults";
 // the space for stache automations
 pub const AUTO_SPACE: &str = "automations";
 
+// temporary wsol account used to partially unwrap sol out of the stache
+pub const UNWRAP: &str = "unwrap";
 
@@ -1,13 +1,17 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar;
 use crate::account::*;
 use crate::constant::*;
 use crate::error::*;
+use crate::squads;
+use crate::nft::{spl_account_compression, spl_noop, CnftTransfer, NftTransfer, BUBBLEGUM_ID, TOKEN_METADATA_ID};
 
 use keychain::program::Keychain;
 use keychain::account::CurrentKeyChain;
 
 use anchor_spl::associated_token::AssociatedToken;
-use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use anchor_spl::token::spl_token::native_mint;
 
 use clockwork_sdk::{
     self,
@@ -57,8 +61,18 @@ pub struct DestroyStache<'info> {
     #[account(mut)]
     pub authority: Signer<'info>,
 
+    // a 2nd verified key on the keychain has to sign off on destroying the stache
+    pub cosigner: Signer<'info>,
+
+    // only needed when sweeping: owner of the destination token accounts, gets the rent from the closed stache atas
+    #[account(mut)]
+    pub destination: Option<SystemAccount<'info>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+
     pub system_program: Program <'info, System>,
     pub keychain_program: Program<'info, Keychain>,
+
+    /* if sweeping, remaining accounts are triples of: stache ata, destination token account, mint */
 }
 
 
@@ -76,21 +90,24 @@ pub struct Stash<'info> {
     pub keychain: Account<'info, CurrentKeyChain>,
 
     #[account(
-        mut,
+        init_if_needed,
+        payer = owner,
         associated_token::mint = mint,
-        associated_token::authority = stache
+        associated_token::authority = stache,
+        associated_token::token_program = token_program,
     )]
-    pub stache_ata: Account<'info, TokenAccount>,
-    pub mint: Account<'info, Mint>,
+    pub stache_ata: InterfaceAccount<'info, TokenAccount>,
+    pub mint: InterfaceAccount<'info, Mint>,
 
     #[account(mut)]
     pub owner: Signer<'info>,
 
+    // not needed when stashing native sol (mint = native mint), which gets wrapped straight into the stache ata
     #[account(mut, has_one = owner)]
-    pub from_token: Account<'info, TokenAccount>,
+    pub from_token: Option<InterfaceAccount<'info, TokenAccount>>,
 
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
 }
 
@@ -129,18 +146,33 @@ pub struct Unstash<'info> {
     #[account(
         mut,
         associated_token::mint = mint,
-        associated_token::authority = stache
+        associated_token::authority = stache,
+        associated_token::token_program = token_program,
     )]
-    pub stache_ata: Account<'info, TokenAccount>,
-    pub mint: Account<'info, Mint>,
+    pub stache_ata: InterfaceAccount<'info, TokenAccount>,
+    pub mint: InterfaceAccount<'info, Mint>,
 
     #[account(mut)]
     pub owner: Signer<'info>,
 
+    // not needed when unstashing native sol (mint = native mint), which gets unwrapped and sent to the owner
     #[account(mut, has_one = owner)]
-    pub to_token: Account<'info, TokenAccount>,
+    pub to_token: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    // only for partially unwrapping sol: the wsol gets moved here, then this gets closed to the owner
+    #[account(
+        init,
+        payer = owner,
+        seeds = [UNWRAP.as_bytes().as_ref(), stache.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = stache,
+        token::token_program = token_program,
+    )]
+    pub unwrap_token: Option<InterfaceAccount<'info, TokenAccount>>,
 
-    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
 }
 
@@ -177,6 +209,9 @@ pub struct CreateVault<'info> {
     #[account(mut)]
     pub authority: Signer<'info>,
 
+    /// CHECK: only needed when creating a squads vault; verified in the instruction
+    pub multisig: Option<UncheckedAccount<'info>>,
+
     pub system_program: Program<'info, System>,
 }
 
@@ -205,7 +240,7 @@ pub struct LockVault<'info> {
 
 
 #[derive(Accounts)]
-pub struct DestroyVault<'info> {
+pub struct UnlockVault<'info> {
 
     #[account(
     mut,
@@ -214,22 +249,24 @@ pub struct DestroyVault<'info> {
     )]
     pub stache: Account<'info, CurrentStache>,
 
-    #[account(constraint = keychain.has_key(&authority.key()))]
+    // threshold vault submitters aren't necessarily on the keychain, so this gets checked in the instruction
     pub keychain: Account<'info, CurrentKeyChain>,
 
     #[account(
     mut,
     has_one = stache,
-    close = authority,
     )]
     pub vault: Account<'info, Vault>,
 
     #[account(mut)]
     pub authority: Signer<'info>,
+
+    /// CHECK: the multisig backing the vault; only needed for squads vaults, verified in the instruction
+    pub multisig: Option<UncheckedAccount<'info>>,
 }
 
 #[derive(Accounts)]
-pub struct WithdrawFromVault<'info> {
+pub struct DestroyVault<'info> {
 
     #[account(
     mut,
@@ -244,35 +281,38 @@ pub struct WithdrawFromVault<'info> {
     #[account(
     mut,
     has_one = stache,
+    close = authority,
     )]
     pub vault: Account<'info, Vault>,
 
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetVaultMints<'info> {
+
     #[account(
-    mut,
-    associated_token::mint = mint,
-    associated_token::authority = vault,
+    constraint = stache.is_vault(vault.index).is_some() @StacheError::InvalidVault,
+    has_one = keychain,
     )]
-    pub vault_ata: Account<'info, TokenAccount>,
+    pub stache: Account<'info, CurrentStache>,
+
+    #[account(constraint = keychain.has_key(&authority.key()))]
+    pub keychain: Account<'info, CurrentKeyChain>,
 
-    // where to send the tokens when emptying
     #[account(
     mut,
-    token::mint = mint,
+    has_one = stache,
     )]
-    pub to_token: Account<'info, TokenAccount>,
-
-    pub mint: Account<'info, Mint>,
+    pub vault: Account<'info, Vault>,
 
     #[account(mut)]
     pub authority: Signer<'info>,
-
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
 }
 
 #[derive(Accounts)]
-#[instruction(action_index: u8)]
-pub struct ApproveVaultAction<'info> {
+pub struct DepositToVault<'info> {
 
     #[account(
     mut,
@@ -285,30 +325,42 @@ pub struct ApproveVaultAction<'info> {
     pub keychain: Account<'info, CurrentKeyChain>,
 
     #[account(
-    mut,
     has_one = stache,
-    constraint = vault.is_action(action_index).is_some() @StacheError::InvalidAction,
+    constraint = vault.allows_mint(&mint.key()) @StacheError::MintNotAllowed,
     )]
     pub vault: Account<'info, Vault>,
 
-    #[account(mut)]
-    pub authority: Signer<'info>,
+    #[account(
+    init_if_needed,
+    payer = authority,
+    associated_token::mint = mint,
+    associated_token::authority = vault,
+    associated_token::token_program = token_program,
+    )]
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
 
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub mint: InterfaceAccount<'info, Mint>,
 
+    #[account(mut)]
+    pub authority: Signer<'info>,
 
-    /* if the action is a withdraw, then the remaining accounts will be:
-    from vault ata, to token account
-     */
+    // either the authority's own token account, or the stache ata
+    #[account(
+    mut,
+    token::mint = mint,
+    constraint = from_token.owner == authority.key() || from_token.owner == stache.key() @StacheError::NotAuthorized,
+    )]
+    pub from_token: InterfaceAccount<'info, TokenAccount>,
 
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
 }
 
 #[derive(Accounts)]
-pub struct DenyVaultAction<'info> {
+pub struct DepositSolToVault<'info> {
 
     #[account(
-    mut,
     constraint = stache.is_vault(vault.index).is_some() @StacheError::InvalidVault,
     has_one = keychain,
     )]
@@ -320,208 +372,1186 @@ pub struct DenyVaultAction<'info> {
     #[account(
     mut,
     has_one = stache,
+    constraint = vault.allows_mint(&native_mint::ID) @StacheError::MintNotAllowed,
     )]
     pub vault: Account<'info, Vault>,
 
     #[account(mut)]
     pub authority: Signer<'info>,
-}
 
-//////// AUTOMATIONS ////////
+    pub system_program: Program<'info, System>,
+}
 
 #[derive(Accounts)]
-pub struct CreateAutomation<'info> {
+pub struct InternalTransfer<'info> {
 
     #[account(
-        mut,
-        has_one = keychain,
+    mut,
+    has_one = keychain,
     )]
     pub stache: Account<'info, CurrentStache>,
 
     #[account(constraint = keychain.has_key(&authority.key()))]
     pub keychain: Account<'info, CurrentKeyChain>,
 
+    // the vault the tokens are coming out of; if not passed in, they're coming out of the stache ata
     #[account(
-        init,
-        payer = authority,
-        seeds = [&stache.next_auto_index.to_le_bytes(),
-        AUTO_SPACE.as_bytes().as_ref(),
-        stache.stacheid.as_bytes().as_ref(),
-        BEARD_SPACE.as_bytes().as_ref(),
-        stache.domain.as_ref(),
-        STACHE.as_bytes().as_ref()],
-        bump,
-        space = 8 + Auto::MAX_SIZE,
+    mut,
+    has_one = stache,
+    constraint = stache.is_vault(from_vault.index).is_some() @StacheError::InvalidVault,
     )]
-    pub auto: Account<'info, Auto>,
+    pub from_vault: Option<Account<'info, Vault>>,
+
+    // the vault the tokens are going into; if not passed in, they're going into the stache ata
+    #[account(
+    has_one = stache,
+    constraint = stache.is_vault(to_vault.index).is_some() @StacheError::InvalidVault,
+    constraint = to_vault.allows_mint(&mint.key()) @StacheError::MintNotAllowed,
+    )]
+    pub to_vault: Option<Account<'info, Vault>>,
+
+    #[account(
+    mut,
+    token::mint = mint,
+    )]
+    pub from_token: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+    mut,
+    token::mint = mint,
+    )]
+    pub to_token: InterfaceAccount<'info, TokenAccount>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
 
     #[account(mut)]
     pub authority: Signer<'info>,
 
-    pub system_program: Program<'info, System>,
+    /// CHECK: the multisig backing the from vault; only needed for squads vaults, verified in the instruction
+    pub multisig: Option<UncheckedAccount<'info>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 #[derive(Accounts)]
-pub struct DestroyAutomation<'info> {
+pub struct WithdrawFromVault<'info> {
 
     #[account(
     mut,
+    constraint = stache.is_vault(vault.index).is_some() @StacheError::InvalidVault,
     has_one = keychain,
     )]
     pub stache: Account<'info, CurrentStache>,
 
-    #[account(constraint = keychain.has_key(&authority.key()))]
+    // submitters aren't necessarily on the keychain (threshold vaults), so this gets checked in the instruction
     pub keychain: Account<'info, CurrentKeyChain>,
 
     #[account(
     mut,
     has_one = stache,
-    close = authority,
     )]
-    pub auto: Account<'info, Auto>,
+    pub vault: Account<'info, Vault>,
+
+    #[account(
+    mut,
+    associated_token::mint = mint,
+    associated_token::authority = vault,
+    associated_token::token_program = token_program,
+    )]
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
+
+    // where to send the tokens when emptying
+    #[account(
+    mut,
+    token::mint = mint,
+    )]
+    pub to_token: InterfaceAccount<'info, TokenAccount>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
 
     #[account(mut)]
     pub authority: Signer<'info>,
 
-    // if a thread was attached, we'll need these 2 to destroy the thread
-    #[account(mut,
-    address = Thread::pubkey(auto.key(), auto.name.clone().into()))
-    ]
-    pub thread: Option<Account<'info, Thread>>,
-    pub clockwork_program: Option<Program<'info, ThreadProgram>>,
+    /// CHECK: the multisig backing the vault; only needed for squads vaults, verified in the instruction
+    pub multisig: Option<UncheckedAccount<'info>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
 }
 
 #[derive(Accounts)]
-pub struct SetAutomationTrigger<'info> {
+pub struct WithdrawSolFromVault<'info> {
 
     #[account(
     mut,
+    constraint = stache.is_vault(vault.index).is_some() @StacheError::InvalidVault,
     has_one = keychain,
     )]
     pub stache: Account<'info, CurrentStache>,
 
-    #[account(constraint = keychain.has_key(&authority.key()))]
+    // submitters aren't necessarily on the keychain (threshold vaults), so this gets checked in the instruction
     pub keychain: Account<'info, CurrentKeyChain>,
 
     #[account(
     mut,
     has_one = stache,
     )]
-    pub auto: Account<'info, Auto>,
+    pub vault: Account<'info, Vault>,
+
+    /// CHECK: just receives the lamports
+    #[account(mut)]
+    pub to: UncheckedAccount<'info>,
 
     #[account(mut)]
     pub authority: Signer<'info>,
 
-    // depending on the trigger being set, this token account might not be needed
-    #[account(
-    mut,
-    )]
-    pub token: Option<Account<'info, TokenAccount>>,
+    /// CHECK: the multisig backing the vault; only needed for squads vaults, verified in the instruction
+    pub multisig: Option<UncheckedAccount<'info>>,
 }
 
 #[derive(Accounts)]
-pub struct SetAutomationAction<'info> {
+#[instruction(action_index: u8)]
+pub struct ApproveVaultAction<'info> {
 
     #[account(
     mut,
+    constraint = stache.is_vault(vault.index).is_some() @StacheError::InvalidVault,
     has_one = keychain,
     )]
     pub stache: Account<'info, CurrentStache>,
 
-    #[account(constraint = keychain.has_key(&authority.key()))]
+    // approvers aren't necessarily on the keychain (squads members), so this gets checked in the instruction
     pub keychain: Account<'info, CurrentKeyChain>,
 
     #[account(
     mut,
     has_one = stache,
+    constraint = vault.is_action(action_index).is_some() @StacheError::InvalidAction,
     )]
-    pub auto: Account<'info, Auto>,
+    pub vault: Account<'info, Vault>,
 
     #[account(mut)]
     pub authority: Signer<'info>,
 
-    // depending on the trigger being set, these accounts might not be needed but for now we'll just make them req'd to make my life easier
-    // todo: we can make these remaining accounts later since they're tied to a specific action
+    /// CHECK: the multisig backing the vault; only needed for squads vaults, verified in the instruction
+    pub multisig: Option<UncheckedAccount<'info>>,
 
-    // for now, needs to be a stache ata
-    #[account(
-    associated_token::mint = mint,
-    associated_token::authority = stache
-    )]
-    pub from_token: Option<Account<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
 
-    #[account(
-    token::mint = mint,
-    )]
-    pub to_token: Option<Account<'info, TokenAccount>>,
 
-    pub mint: Account<'info, Mint>,
-    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+    /* if the action is a withdraw, then the remaining accounts will be:
+    from vault ata, to token account
+     */
+
 }
 
 #[derive(Accounts)]
-pub struct ActivateAutomation<'info> {
+#[instruction(action_index: u8)]
+pub struct ExecuteVaultAction<'info> {
 
     #[account(
     mut,
+    constraint = stache.is_vault(vault.index).is_some() @StacheError::InvalidVault,
     has_one = keychain,
     )]
     pub stache: Account<'info, CurrentStache>,
 
-    #[account(constraint = keychain.has_key(&authority.key()))]
+    // checked in the instruction (same as approvals)
     pub keychain: Account<'info, CurrentKeyChain>,
 
     #[account(
     mut,
     has_one = stache,
+    constraint = vault.is_action(action_index).is_some() @StacheError::InvalidAction,
     )]
-    pub auto: Account<'info, Auto>,
+    pub vault: Account<'info, Vault>,
 
     #[account(mut)]
     pub authority: Signer<'info>,
 
-    // the clockwork thread account
-    #[account(mut,
-        address = Thread::pubkey(auto.key(), auto.name.clone().into()))
-    ]
-    pub thread: SystemAccount<'info>,
+    /// CHECK: the multisig backing the vault; only needed for squads vaults, verified in the instruction
+    pub multisig: Option<UncheckedAccount<'info>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    /* same remaining accounts as approving */
+}
+
+// puts an action on a squads vault into a squads transaction (see squads.rs)
+#[derive(Accounts)]
+#[instruction(action_index: u8)]
+pub struct ProposeToSquads<'info> {
+
+    #[account(
+    constraint = stache.is_vault(vault.index).is_some() @StacheError::InvalidVault,
+    )]
+    pub stache: Account<'info, CurrentStache>,
+
+    #[account(
+    has_one = stache,
+    constraint = vault.is_action(action_index).is_some() @StacheError::InvalidAction,
+    )]
+    pub vault: Account<'info, Vault>,
+
+    /// CHECK: the multisig backing the vault, verified in the instruction (squads bumps its transaction index)
+    #[account(mut)]
+    pub multisig: UncheckedAccount<'info>,
+
+    /// CHECK: the squads transaction, created by squads
+    #[account(mut)]
+    pub transaction: UncheckedAccount<'info>,
+
+    /// CHECK: the transaction's instruction, created by squads
+    #[account(mut)]
+    pub instruction: UncheckedAccount<'info>,
+
+    // has to be a member of the multisig, and pays for the squads accounts
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    /// CHECK: the squads program
+    #[account(address = squads::ID)]
+    pub squads_program: UncheckedAccount<'info>,
 
-    pub clockwork_program: Program<'info, ThreadProgram>,
     pub system_program: Program<'info, System>,
+
+    /* remaining accounts: the ones the action needs to execute (same as approving) */
 }
 
+// squads executing a transaction proposed by propose_to_squads
 #[derive(Accounts)]
-pub struct FireAutomation<'info> {
+#[instruction(action_index: u8)]
+pub struct ExecuteSquadsAction<'info> {
 
     #[account(
-    mut,
+    constraint = stache.is_vault(vault.index).is_some() @StacheError::InvalidVault,
     )]
     pub stache: Account<'info, CurrentStache>,
 
     #[account(
     mut,
     has_one = stache,
+    constraint = vault.is_action(action_index).is_some() @StacheError::InvalidAction,
     )]
-    pub auto: Account<'info, Auto>,
+    pub vault: Account<'info, Vault>,
+
+    /// CHECK: the multisig backing the vault, verified in the instruction
+    pub multisig: UncheckedAccount<'info>,
+
+    /// CHECK: the squads transaction being executed, verified in the instruction
+    pub transaction: UncheckedAccount<'info>,
+
+    // the multisig's default authority, which only squads can sign for. pays for any accounts the action needs created
+    #[account(mut)]
+    pub squads_authority: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    /// CHECK: instructions sysvar
+    #[account(address = sysvar::instructions::ID)]
+    pub sysvar_instructions: UncheckedAccount<'info>,
+
+    /* same remaining accounts as approving */
+}
+
+#[derive(Accounts)]
+pub struct AuthorizeVaultAuto<'info> {
+
+    #[account(
+    constraint = stache.is_vault(vault.index).is_some() @StacheError::InvalidVault,
+    has_one = keychain,
+    )]
+    pub stache: Account<'info, CurrentStache>,
+
+    #[account(constraint = keychain.has_key(&authority.key()))]
+    pub keychain: Account<'info, CurrentKeyChain>,
 
-    // the clockwork thread account - optional in case the user wants to test the automation outside of clockwork
     #[account(
     mut,
-    signer,
-    constraint = thread.authority.eq(&auto.key()) @StacheError::InvalidThread,
-    address = Thread::pubkey(auto.key(), auto.name.clone().into()))
-    ]
-    pub thread: Account<'info, Thread>,
+    has_one = stache,
+    )]
+    pub vault: Account<'info, Vault>,
 
-    // for doing transfers we'll need the appropriate token accounts - but normally would be optional cause depends what the automation needs but for now req'd since
-    // our only action = transfer and i don't have time to solve "instruction tries to borrow reference for an account which is already borrowed" error
+    #[account(has_one = stache)]
+    pub auto: Account<'info, Auto>,
 
     #[account(mut)]
-    pub from_token: Account<'info, TokenAccount>,
+    pub authority: Signer<'info>,
 
-    #[account(mut)]
-    pub to_token: Account<'info, TokenAccount>,
+    /// CHECK: the multisig backing the vault; only needed for squads vaults, verified in the instruction
+    pub multisig: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeVaultAuto<'info> {
 
-    pub token_program: Program<'info, Token>,
+    #[account(
+    constraint = stache.is_vault(vault.index).is_some() @StacheError::InvalidVault,
+    has_one = keychain,
+    )]
+    pub stache: Account<'info, CurrentStache>,
 
+    #[account(constraint = keychain.has_key(&authority.key()))]
+    pub keychain: Account<'info, CurrentKeyChain>,
+
+    #[account(
+    mut,
+    has_one = stache,
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetVaultDelay<'info> {
+
+    #[account(
+    constraint = stache.is_vault(vault.index).is_some() @StacheError::InvalidVault,
+    has_one = keychain,
+    )]
+    pub stache: Account<'info, CurrentStache>,
+
+    // submitters aren't necessarily on the keychain (threshold vaults), so this gets checked in the instruction
+    pub keychain: Account<'info, CurrentKeyChain>,
+
+    #[account(
+    mut,
+    has_one = stache,
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetSpendingLimit<'info> {
+
+    #[account(
+    mut,
+    has_one = keychain,
+    )]
+    pub stache: Account<'info, CurrentStache>,
+
+    #[account(constraint = keychain.has_key(&authority.key()))]
+    pub keychain: Account<'info, CurrentKeyChain>,
+
+    // if passed in, the limit goes on the vault instead of the stache
+    #[account(
+    mut,
+    has_one = stache,
+    constraint = stache.is_vault(vault.index).is_some() @StacheError::InvalidVault,
+    )]
+    pub vault: Option<Account<'info, Vault>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    // a 2nd verified key on the keychain; only needed when loosening a limit
+    pub cosigner: Option<Signer<'info>>,
+}
+
+impl<'info> SetSpendingLimit<'info> {
+    pub fn is_cosigned(&self) -> bool {
+        match &self.cosigner {
+            Some(cosigner) => cosigner.key() != self.authority.key() && self.keychain.has_verified_key(&cosigner.key()),
+            None => false,
+        }
+    }
+}
+
+#[derive(Accounts)]
+pub struct DenyVaultAction<'info> {
+
+    #[account(
+    mut,
+    constraint = stache.is_vault(vault.index).is_some() @StacheError::InvalidVault,
+    has_one = keychain,
+    )]
+    pub stache: Account<'info, CurrentStache>,
+
+    // anyone who can approve can deny (squads members, threshold approvers), so this gets checked in the instruction
+    pub keychain: Account<'info, CurrentKeyChain>,
+
+    #[account(
+    mut,
+    has_one = stache,
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: the multisig backing the vault; only needed for squads vaults, verified in the instruction
+    pub multisig: Option<UncheckedAccount<'info>>,
+}
+
+//////// NFTS ////////
+
+// nft transfers go through metaplex (TransferV1) so programmable nfts work as well. compressed nfts go through
+// bubblegum (Transfer), w/the stache or vault pda as the leaf owner
+
+// the metaplex accounts every nft transfer needs, shared by the nft instructions
+#[derive(Accounts)]
+pub struct MetaplexAccounts<'info> {
+
+    /// CHECK: checked by metaplex
+    #[account(mut)]
+    pub metadata: UncheckedAccount<'info>,
+
+    /// CHECK: checked by metaplex
+    pub edition: Option<UncheckedAccount<'info>>,
+
+    // programmable nfts only
+    /// CHECK: checked by metaplex
+    #[account(mut)]
+    pub token_record: Option<UncheckedAccount<'info>>,
+    /// CHECK: checked by metaplex
+    #[account(mut)]
+    pub destination_token_record: Option<UncheckedAccount<'info>>,
+    /// CHECK: checked by metaplex
+    pub authorization_rules: Option<UncheckedAccount<'info>>,
+    /// CHECK: checked by metaplex
+    pub authorization_rules_program: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: the token metadata program
+    #[account(address = TOKEN_METADATA_ID)]
+    pub token_metadata_program: UncheckedAccount<'info>,
+
+    /// CHECK: instructions sysvar
+    #[account(address = sysvar::instructions::ID)]
+    pub sysvar_instructions: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+impl<'info> MetaplexAccounts<'info> {
+    // the transfer of the nft in token (owned by token_owner, which signs for it) to destination_owner
+    pub fn nft_transfer<'a>(&'a self,
+                            token: &'a AccountInfo<'info>,
+                            token_owner: &'a AccountInfo<'info>,
+                            destination_token: &'a AccountInfo<'info>,
+                            destination_owner: &'a AccountInfo<'info>,
+                            mint: &'a AccountInfo<'info>,
+                            payer: &'a AccountInfo<'info>,
+                            token_program: &'a AccountInfo<'info>) -> NftTransfer<'a, 'info> {
+        NftTransfer {
+            token,
+            token_owner,
+            destination_token,
+            destination_owner,
+            mint,
+            metadata: self.metadata.as_ref(),
+            edition: self.edition.as_ref().map(|account| account.as_ref()),
+            token_record: self.token_record.as_ref().map(|account| account.as_ref()),
+            destination_token_record: self.destination_token_record.as_ref().map(|account| account.as_ref()),
+            authority: token_owner,
+            payer,
+            token_metadata_program: self.token_metadata_program.as_ref(),
+            system_program: self.system_program.as_ref(),
+            sysvar_instructions: self.sysvar_instructions.as_ref(),
+            token_program,
+            associated_token_program: self.associated_token_program.as_ref(),
+            authorization_rules_program: self.authorization_rules_program.as_ref().map(|account| account.as_ref()),
+            authorization_rules: self.authorization_rules.as_ref().map(|account| account.as_ref()),
+        }
+    }
+}
+
+#[derive(Accounts)]
+pub struct StashNft<'info> {
+
+    #[account(
+    has_one = keychain,
+    )]
+    pub stache: Account<'info, CurrentStache>,
+
+    #[account(constraint = keychain.has_key(&owner.key()))]
+    pub keychain: Account<'info, CurrentKeyChain>,
+
+    // stash straight into a vault, otherwise into the stache
+    #[account(
+    has_one = stache,
+    constraint = stache.is_vault(vault.index).is_some() @StacheError::InvalidVault,
+    constraint = vault.allows_mint(&mint.key()) @StacheError::MintNotAllowed,
+    )]
+    pub vault: Option<Account<'info, Vault>>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+    mut,
+    token::mint = mint,
+    token::authority = owner,
+    )]
+    pub from_token: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: the stache/vault ata for the nft, created by metaplex if needed
+    #[account(mut)]
+    pub destination_token: UncheckedAccount<'info>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub metaplex: MetaplexAccounts<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> StashNft<'info> {
+    pub fn nft_transfer(&self) -> NftTransfer<'_, 'info> {
+        self.metaplex.nft_transfer(self.from_token.as_ref(),
+                                   self.owner.as_ref(),
+                                   self.destination_token.as_ref(),
+                                   match &self.vault {
+                                       Some(vault) => vault.as_ref(),
+                                       None => self.stache.as_ref(),
+                                   },
+                                   self.mint.as_ref(),
+                                   self.owner.as_ref(),
+                                   self.token_program.as_ref())
+    }
+}
+
+#[derive(Accounts)]
+pub struct UnstashNft<'info> {
+
+    #[account(
+    has_one = keychain,
+    )]
+    pub stache: Account<'info, CurrentStache>,
+
+    #[account(constraint = keychain.has_key(&owner.key()))]
+    pub keychain: Account<'info, CurrentKeyChain>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+    mut,
+    token::mint = mint,
+    token::authority = stache,
+    )]
+    pub stache_token: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: the owner's ata for the nft, created by metaplex if needed
+    #[account(mut)]
+    pub destination_token: UncheckedAccount<'info>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub metaplex: MetaplexAccounts<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> UnstashNft<'info> {
+    pub fn nft_transfer(&self) -> NftTransfer<'_, 'info> {
+        self.metaplex.nft_transfer(self.stache_token.as_ref(),
+                                   self.stache.as_ref(),
+                                   self.destination_token.as_ref(),
+                                   self.owner.as_ref(),
+                                   self.mint.as_ref(),
+                                   self.owner.as_ref(),
+                                   self.token_program.as_ref())
+    }
+}
+
+#[derive(Accounts)]
+pub struct WithdrawNftFromVault<'info> {
+
+    #[account(
+    mut,
+    constraint = stache.is_vault(vault.index).is_some() @StacheError::InvalidVault,
+    has_one = keychain,
+    )]
+    pub stache: Account<'info, CurrentStache>,
+
+    // submitters aren't necessarily on the keychain (threshold vaults), so this gets checked in the instruction
+    pub keychain: Account<'info, CurrentKeyChain>,
+
+    #[account(
+    mut,
+    has_one = stache,
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: the wallet that ends up with the nft
+    pub to: UncheckedAccount<'info>,
+
+    #[account(
+    mut,
+    token::mint = mint,
+    token::authority = vault,
+    )]
+    pub vault_token: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: the to wallet's ata for the nft, created by metaplex if needed
+    #[account(mut)]
+    pub destination_token: UncheckedAccount<'info>,
+
+    /// CHECK: the multisig backing the vault; only needed for squads vaults, verified in the instruction
+    pub multisig: Option<UncheckedAccount<'info>>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub metaplex: MetaplexAccounts<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> WithdrawNftFromVault<'info> {
+    pub fn nft_transfer(&self) -> NftTransfer<'_, 'info> {
+        self.metaplex.nft_transfer(self.vault_token.as_ref(),
+                                   self.vault.as_ref(),
+                                   self.destination_token.as_ref(),
+                                   self.to.as_ref(),
+                                   self.mint.as_ref(),
+                                   self.authority.as_ref(),
+                                   self.token_program.as_ref())
+    }
+}
+
+// the bubblegum accounts every cnft transfer needs, shared by the cnft instructions. the proof goes in remaining accounts
+#[derive(Accounts)]
+pub struct BubblegumAccounts<'info> {
+
+    /// CHECK: checked by bubblegum
+    pub tree_config: UncheckedAccount<'info>,
+
+    /// CHECK: checked by bubblegum
+    #[account(mut)]
+    pub merkle_tree: UncheckedAccount<'info>,
+
+    /// CHECK: checked by bubblegum
+    pub leaf_delegate: UncheckedAccount<'info>,
+
+    /// CHECK: the bubblegum program
+    #[account(address = BUBBLEGUM_ID)]
+    pub bubblegum_program: UncheckedAccount<'info>,
+
+    /// CHECK: the noop program
+    #[account(address = spl_noop::ID)]
+    pub log_wrapper: UncheckedAccount<'info>,
+
+    /// CHECK: the account compression program
+    #[account(address = spl_account_compression::ID)]
+    pub compression_program: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> BubblegumAccounts<'info> {
+    // the transfer of the cnft owned by leaf_owner (which signs for it) to new_leaf_owner
+    pub fn cnft_transfer<'a>(&'a self,
+                             leaf_owner: &'a AccountInfo<'info>,
+                             new_leaf_owner: &'a AccountInfo<'info>,
+                             proof: &'a [AccountInfo<'info>]) -> CnftTransfer<'a, 'info> {
+        CnftTransfer {
+            leaf_owner,
+            leaf_delegate: self.leaf_delegate.as_ref(),
+            new_leaf_owner,
+            tree_config: self.tree_config.as_ref(),
+            merkle_tree: self.merkle_tree.as_ref(),
+            log_wrapper: self.log_wrapper.as_ref(),
+            compression_program: self.compression_program.as_ref(),
+            system_program: self.system_program.as_ref(),
+            bubblegum_program: self.bubblegum_program.as_ref(),
+            proof,
+        }
+    }
+}
+
+#[derive(Accounts)]
+pub struct StashCnft<'info> {
+
+    #[account(
+    has_one = keychain,
+    )]
+    pub stache: Account<'info, CurrentStache>,
+
+    #[account(constraint = keychain.has_key(&owner.key()))]
+    pub keychain: Account<'info, CurrentKeyChain>,
+
+    // stash straight into a vault, otherwise into the stache (the vault's mints get checked in the instruction)
+    #[account(
+    has_one = stache,
+    constraint = stache.is_vault(vault.index).is_some() @StacheError::InvalidVault,
+    )]
+    pub vault: Option<Account<'info, Vault>>,
+
+    pub owner: Signer<'info>,
+
+    pub bubblegum: BubblegumAccounts<'info>,
+}
+
+impl<'info> StashCnft<'info> {
+    pub fn cnft_transfer<'a>(&'a self, proof: &'a [AccountInfo<'info>]) -> CnftTransfer<'a, 'info> {
+        self.bubblegum.cnft_transfer(self.owner.as_ref(),
+                                     match &self.vault {
+                                         Some(vault) => vault.as_ref(),
+                                         None => self.stache.as_ref(),
+                                     },
+                                     proof)
+    }
+}
+
+#[derive(Accounts)]
+pub struct UnstashCnft<'info> {
+
+    #[account(
+    has_one = keychain,
+    )]
+    pub stache: Account<'info, CurrentStache>,
+
+    #[account(constraint = keychain.has_key(&owner.key()))]
+    pub keychain: Account<'info, CurrentKeyChain>,
+
+    pub owner: Signer<'info>,
+
+    pub bubblegum: BubblegumAccounts<'info>,
+}
+
+impl<'info> UnstashCnft<'info> {
+    pub fn cnft_transfer<'a>(&'a self, proof: &'a [AccountInfo<'info>]) -> CnftTransfer<'a, 'info> {
+        self.bubblegum.cnft_transfer(self.stache.as_ref(), self.owner.as_ref(), proof)
+    }
+}
+
+#[derive(Accounts)]
+pub struct WithdrawCnftFromVault<'info> {
+
+    #[account(
+    constraint = stache.is_vault(vault.index).is_some() @StacheError::InvalidVault,
+    has_one = keychain,
+    )]
+    pub stache: Account<'info, CurrentStache>,
+
+    // submitters aren't necessarily on the keychain (threshold vaults), so this gets checked in the instruction
+    pub keychain: Account<'info, CurrentKeyChain>,
+
+    #[account(
+    mut,
+    has_one = stache,
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub authority: Signer<'info>,
+
+    /// CHECK: the wallet that ends up with the cnft
+    pub to: UncheckedAccount<'info>,
+
+    /// CHECK: the multisig backing the vault; only needed for squads vaults, verified in the instruction
+    pub multisig: Option<UncheckedAccount<'info>>,
+
+    pub bubblegum: BubblegumAccounts<'info>,
+}
+
+impl<'info> WithdrawCnftFromVault<'info> {
+    pub fn cnft_transfer<'a>(&'a self, proof: &'a [AccountInfo<'info>]) -> CnftTransfer<'a, 'info> {
+        self.bubblegum.cnft_transfer(self.vault.as_ref(), self.to.as_ref(), proof)
+    }
+}
+
+//////// AUTOMATIONS ////////
+
+#[derive(Accounts)]
+pub struct CreateAutomation<'info> {
+
+    #[account(
+        mut,
+        has_one = keychain,
+    )]
+    pub stache: Account<'info, CurrentStache>,
+
+    #[account(constraint = keychain.has_key(&authority.key()))]
+    pub keychain: Account<'info, CurrentKeyChain>,
+
+    #[account(
+        init,
+        payer = authority,
+        seeds = [&stache.next_auto_index.to_le_bytes(),
+        AUTO_SPACE.as_bytes().as_ref(),
+        stache.stacheid.as_bytes().as_ref(),
+        BEARD_SPACE.as_bytes().as_ref(),
+        stache.domain.as_ref(),
+        STACHE.as_bytes().as_ref()],
+        bump,
+        space = 8 + Auto::MAX_SIZE,
+    )]
+    pub auto: Account<'info, Auto>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DestroyAutomation<'info> {
+
+    #[account(
+    mut,
+    has_one = keychain,
+    )]
+    pub stache: Account<'info, CurrentStache>,
+
+    #[account(constraint = keychain.has_key(&authority.key()))]
+    pub keychain: Account<'info, CurrentKeyChain>,
+
+    #[account(
+    mut,
+    has_one = stache,
+    close = authority,
+    )]
+    pub auto: Account<'info, Auto>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    // if a thread was attached, we'll need these 2 to destroy the thread
+    #[account(mut,
+    address = Thread::pubkey(auto.key(), auto.name.clone().into()))
+    ]
+    pub thread: Option<Account<'info, Thread>>,
+    pub clockwork_program: Option<Program<'info, ThreadProgram>>,
+}
+
+#[derive(Accounts)]
+pub struct SetAutomationTrigger<'info> {
+
+    #[account(
+    mut,
+    has_one = keychain,
+    )]
+    pub stache: Account<'info, CurrentStache>,
+
+    #[account(constraint = keychain.has_key(&authority.key()))]
+    pub keychain: Account<'info, CurrentKeyChain>,
+
+    #[account(
+    mut,
+    has_one = stache,
+    )]
+    pub auto: Account<'info, Auto>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    // depending on the trigger being set, this token account might not be needed
+    #[account(
+    mut,
+    )]
+    pub token: Option<InterfaceAccount<'info, TokenAccount>>,
+}
+
+#[derive(Accounts)]
+pub struct ClearAutomationActions<'info> {
+
+    #[account(
+    has_one = keychain,
+    )]
+    pub stache: Account<'info, CurrentStache>,
+
+    #[account(constraint = keychain.has_key(&authority.key()))]
+    pub keychain: Account<'info, CurrentKeyChain>,
+
+    #[account(
+    mut,
+    has_one = stache,
+    )]
+    pub auto: Account<'info, Auto>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetAutomationLimits<'info> {
+
+    #[account(
+    has_one = keychain,
+    )]
+    pub stache: Account<'info, CurrentStache>,
+
+    #[account(constraint = keychain.has_key(&authority.key()))]
+    pub keychain: Account<'info, CurrentKeyChain>,
+
+    #[account(
+    mut,
+    has_one = stache,
+    )]
+    pub auto: Account<'info, Auto>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetAutomationAction<'info> {
+
+    #[account(
+    mut,
+    has_one = keychain,
+    )]
+    pub stache: Account<'info, CurrentStache>,
+
+    #[account(constraint = keychain.has_key(&authority.key()))]
+    pub keychain: Account<'info, CurrentKeyChain>,
+
+    #[account(
+    mut,
+    has_one = stache,
+    )]
+    pub auto: Account<'info, Auto>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    // depending on the trigger being set, these accounts might not be needed but for now we'll just make them req'd to make my life easier
+    // todo: we can make these remaining accounts later since they're tied to a specific action
+
+    // owned by the stache, or by the vault if the automation pulls from a vault
+    #[account(
+    token::mint = mint,
+    token::token_program = token_program,
+    constraint = from_token.owner == vault.as_ref().map_or(stache.key(), |vault| vault.key()) @StacheError::InvalidAction,
+    )]
+    pub from_token: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+    token::mint = mint,
+    )]
+    pub to_token: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+
+    // the vault to pull from (none = the stache)
+    #[account(
+    has_one = stache,
+    constraint = stache.is_vault(vault.index).is_some() @StacheError::InvalidVault,
+    )]
+    pub vault: Option<Account<'info, Vault>>,
+}
+
+#[derive(Accounts)]
+pub struct ActivateAutomation<'info> {
+
+    #[account(
+    mut,
+    has_one = keychain,
+    )]
+    pub stache: Account<'info, CurrentStache>,
+
+    #[account(constraint = keychain.has_key(&authority.key()))]
+    pub keychain: Account<'info, CurrentKeyChain>,
+
+    #[account(
+    mut,
+    has_one = stache,
+    )]
+    pub auto: Account<'info, Auto>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    // the clockwork thread account
+    #[account(mut,
+        address = Thread::pubkey(auto.key(), auto.name.clone().into()))
+    ]
+    pub thread: SystemAccount<'info>,
+
+    pub clockwork_program: Program<'info, ThreadProgram>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DeactivateAutomation<'info> {
+
+    #[account(
+    has_one = keychain,
+    )]
+    pub stache: Account<'info, CurrentStache>,
+
+    #[account(constraint = keychain.has_key(&authority.key()))]
+    pub keychain: Account<'info, CurrentKeyChain>,
+
+    #[account(
+    mut,
+    has_one = stache,
+    constraint = auto.thread == Some(thread.key()) @StacheError::InvalidThread,
+    )]
+    pub auto: Account<'info, Auto>,
+
+    // gets the thread's rent back
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(mut,
+    address = Thread::pubkey(auto.key(), auto.name.clone().into()))
+    ]
+    pub thread: Account<'info, Thread>,
+    pub clockwork_program: Program<'info, ThreadProgram>,
+}
+
+// pausing + resuming an automation (and its thread)
+#[derive(Accounts)]
+pub struct ToggleAutomation<'info> {
+
+    #[account(
+    has_one = keychain,
+    )]
+    pub stache: Account<'info, CurrentStache>,
+
+    #[account(constraint = keychain.has_key(&authority.key()))]
+    pub keychain: Account<'info, CurrentKeyChain>,
+
+    #[account(
+    mut,
+    has_one = stache,
+    constraint = auto.thread == Some(thread.key()) @StacheError::InvalidThread,
+    )]
+    pub auto: Account<'info, Auto>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut,
+    address = Thread::pubkey(auto.key(), auto.name.clone().into()))
+    ]
+    pub thread: Account<'info, Thread>,
+    pub clockwork_program: Program<'info, ThreadProgram>,
+}
+
+#[derive(Accounts)]
+pub struct FireAutomation<'info> {
+
+    #[account(
+    mut,
+    )]
+    pub stache: Account<'info, CurrentStache>,
+
+    #[account(
+    mut,
+    has_one = stache,
+    )]
+    pub auto: Account<'info, Auto>,
+
+    // the clockwork thread account - optional in case the user wants to test the automation outside of clockwork
+    #[account(
+    mut,
+    signer,
+    constraint = thread.authority.eq(&auto.key()) @StacheError::InvalidThread,
+    address = Thread::pubkey(auto.key(), auto.name.clone().into()))
+    ]
+    pub thread: Option<Account<'info, Thread>>,
+
+    // w/out a thread, a key on the stache's keychain has to fire it
+    #[account(constraint = keychain.key() == stache.keychain @StacheError::NotAuthorized)]
+    pub keychain: Option<Account<'info, CurrentKeyChain>>,
+    pub authority: Option<Signer<'info>>,
+
+    // the token accounts etc. each action needs come in as remaining accounts
+}
+
+#[derive(Accounts)]
+pub struct DryRunAutomation<'info> {
+
+    #[account(
+    has_one = keychain,
+    )]
+    pub stache: Account<'info, CurrentStache>,
+
+    #[account(constraint = keychain.has_key(&authority.key()))]
+    pub keychain: Account<'info, CurrentKeyChain>,
+
+    #[account(
+    has_one = stache,
+    )]
+    pub auto: Account<'info, Auto>,
+
+    pub authority: Signer<'info>,
+
+    // same remaining accounts as fire_auto
+}
+
+// the account being migrated is unchecked since it's still in an older layout - it gets checked as it's converted
+
+#[derive(Accounts)]
+pub struct MigrateStache<'info> {
+
+    /// CHECK: an older version of the stache
+    #[account(
+    mut,
+    seeds = [keychain.name.as_bytes().as_ref(), BEARD_SPACE.as_bytes().as_ref(), keychain.domain.as_ref(), STACHE.as_bytes().as_ref()],
+    bump,
+    )]
+    pub stache: UncheckedAccount<'info>,
+
+    #[account(constraint = keychain.has_key(&authority.key()))]
+    pub keychain: Account<'info, CurrentKeyChain>,
+
+    // pays for the extra space
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateVault<'info> {
+
+    #[account(
+    has_one = keychain,
+    )]
+    pub stache: Account<'info, CurrentStache>,
+
+    #[account(constraint = keychain.has_key(&authority.key()))]
+    pub keychain: Account<'info, CurrentKeyChain>,
+
+    /// CHECK: an older version of the vault
+    #[account(mut)]
+    pub vault: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateAutomation<'info> {
+
+    #[account(
+    has_one = keychain,
+    )]
+    pub stache: Account<'info, CurrentStache>,
+
+    #[account(constraint = keychain.has_key(&authority.key()))]
+    pub keychain: Account<'info, CurrentKeyChain>,
+
+    /// CHECK: an older version of the automation
+    #[account(mut)]
+    pub auto: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    // the from account of a v0 transfer, for its mint + token program
+    pub from_token: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    // if a thread was attached, it gets deleted (it was created for the old layout). v0 threads can be clockwork 1.x
+    // threads, which don't load as a (2.0) Thread, so the thread + the program that owns it get checked in the instruction
+    /// CHECK: checked in the instruction
+    #[account(mut)]
+    pub thread: Option<UncheckedAccount<'info>>,
+    /// CHECK: checked in the instruction
+    pub clockwork_program: Option<UncheckedAccount<'info>>,
 }
@@ -16,6 +16,8 @@ pub enum StacheError {
     InsufficientFunds,
     #[msg("Vault locked")]
     VaultLocked,
+    #[msg("Vault not locked")]
+    VaultNotLocked,
     #[msg("Invalid action")]
     InvalidAction,
     #[msg("Missing action")]
@@ -48,5 +50,47 @@ pub enum StacheError {
     TriggerLimit,
     #[msg("Automation execution limit hit")]
     ExecLimit,
+    #[msg("Invalid squads multisig")]
+    InvalidMultisig,
+    #[msg("Invalid threshold")]
+    InvalidThreshold,
+    #[msg("Invalid lock time")]
+    InvalidLockTime,
+    #[msg("Action not executable yet")]
+    ActionNotExecutable,
+    #[msg("Spending limit exceeded")]
+    SpendingLimitExceeded,
+    #[msg("Invalid spending limit")]
+    InvalidSpendingLimit,
+    #[msg("Missing or invalid cosigner")]
+    InvalidCosigner,
+    #[msg("Stache still has vaults or automations")]
+    StacheNotEmpty,
+    #[msg("Mint not allowed in this vault")]
+    MintNotAllowed,
+    #[msg("Vault still holds SOL")]
+    VaultNotEmpty,
+    #[msg("Invalid nft accounts")]
+    InvalidNft,
+    #[msg("Invalid automation schedule")]
+    InvalidSchedule,
+    #[msg("Automation isn't due yet")]
+    TriggerNotDue,
+    #[msg("Invalid automation limit")]
+    InvalidAutomationLimit,
+    #[msg("Automation isn't active")]
+    AutomationInactive,
+    #[msg("Automation is paused")]
+    AutomationPaused,
+    #[msg("Automation isn't paused")]
+    AutomationNotPaused,
+    #[msg("Account is already on the current version")]
+    AlreadyMigrated,
+    #[msg("Unknown account version")]
+    InvalidVersion,
+    #[msg("Invalid withdraw delay")]
+    InvalidWithdrawDelay,
+    #[msg("Mints with a transfer hook aren't supported")]
+    TransferHookNotSupported,
 
 }
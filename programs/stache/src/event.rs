@@ -0,0 +1,206 @@
+use anchor_lang::prelude::*;
+
+use crate::account::ActionType;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub enum DryRunOutcome {
+    WouldExecute,
+    WouldNotExecute,    // trigger not met/due, or nothing to transfer
+    InsufficientFunds,
+    VaultLocked,        // an action pulls from a vault that's locked
+    LimitReached,       // the automation's caps or a spending limit
+}
+
+// what firing an automation would do right now; amounts = what each of its actions would transfer
+#[event]
+pub struct AutoDryRun {
+    pub stache: Pubkey,
+    pub auto: Pubkey,
+    pub outcome: DryRunOutcome,
+    pub amounts: Vec<u64>,
+}
+
+// everything below is emitted as the stache's state changes, for indexers/notifications. stache/vault/auto are always
+// the pdas involved
+
+/////// STACHE ///////
+
+#[event]
+pub struct StacheCreated {
+    pub stache: Pubkey,
+    pub keychain: Pubkey,
+    pub stacheid: String,
+}
+
+#[event]
+pub struct StacheDestroyed {
+    pub stache: Pubkey,
+    pub swept: bool,
+}
+
+// tokens/sol/nfts coming into the stache, or straight into one of its vaults
+#[event]
+pub struct Stashed {
+    pub stache: Pubkey,
+    pub vault: Option<Pubkey>,
+    pub mint: Pubkey,       // native mint for sol
+    pub amount: u64,
+}
+
+#[event]
+pub struct Unstashed {
+    pub stache: Pubkey,
+    pub mint: Pubkey,       // native mint for sol
+    pub amount: u64,
+    pub to: Pubkey,
+}
+
+/////// VAULTS ///////
+
+#[event]
+pub struct VaultCreated {
+    pub stache: Pubkey,
+    pub vault: Pubkey,
+    pub index: u8,
+    pub name: String,
+}
+
+#[event]
+pub struct VaultLocked {
+    pub stache: Pubkey,
+    pub vault: Pubkey,
+    pub locked_until: i64,  // 0 = locked until unlocked
+}
+
+#[event]
+pub struct VaultUnlocked {
+    pub stache: Pubkey,
+    pub vault: Pubkey,
+}
+
+#[event]
+pub struct VaultDestroyed {
+    pub stache: Pubkey,
+    pub vault: Pubkey,
+}
+
+#[event]
+pub struct ActionProposed {
+    pub stache: Pubkey,
+    pub vault: Pubkey,
+    pub action_index: u8,
+    pub action_type: ActionType,
+    pub initiator: Pubkey,
+    pub executable_at: i64,
+}
+
+#[event]
+pub struct ActionApproved {
+    pub stache: Pubkey,
+    pub vault: Pubkey,
+    pub action_index: u8,
+    pub approver: Pubkey,
+    pub approvals: u8,
+}
+
+#[event]
+pub struct ActionDenied {
+    pub stache: Pubkey,
+    pub vault: Pubkey,
+    pub action_index: u8,
+    pub denied_by: Pubkey,
+}
+
+// the action is up for approval in squads as well (see squads.rs)
+#[event]
+pub struct ActionProposedToSquads {
+    pub stache: Pubkey,
+    pub vault: Pubkey,
+    pub action_index: u8,
+    pub transaction: Pubkey,
+}
+
+// action_index 0 = didn't need approvals, so it executed right away w/out being proposed
+#[event]
+pub struct ActionExecuted {
+    pub stache: Pubkey,
+    pub vault: Pubkey,
+    pub action_index: u8,
+    pub action_type: ActionType,
+}
+
+/////// AUTOMATIONS ///////
+
+#[event]
+pub struct AutoCreated {
+    pub stache: Pubkey,
+    pub auto: Pubkey,
+    pub index: u8,
+    pub name: String,
+}
+
+#[event]
+pub struct AutoActivated {
+    pub stache: Pubkey,
+    pub auto: Pubkey,
+    pub thread: Pubkey,
+}
+
+#[event]
+pub struct AutoPaused {
+    pub stache: Pubkey,
+    pub auto: Pubkey,
+}
+
+#[event]
+pub struct AutoResumed {
+    pub stache: Pubkey,
+    pub auto: Pubkey,
+}
+
+// deactivated (thread deleted, editable again) or destroyed
+#[event]
+pub struct AutoDeactivated {
+    pub stache: Pubkey,
+    pub auto: Pubkey,
+    pub destroyed: bool,
+}
+
+// every time the automation fires, whether or not it executes
+#[event]
+pub struct AutoFired {
+    pub stache: Pubkey,
+    pub auto: Pubkey,
+    pub manual: bool,
+    pub trigger_met: bool,
+}
+
+// amounts = what each of its actions transferred
+#[event]
+pub struct AutoExecuted {
+    pub stache: Pubkey,
+    pub auto: Pubkey,
+    pub amounts: Vec<u64>,
+    pub num_execs: u32,
+    pub total_amount: u64,
+}
+
+// hit its limits, so the thread is parked until it's deactivated
+#[event]
+pub struct AutoStopped {
+    pub stache: Pubkey,
+    pub auto: Pubkey,
+    pub num_execs: u32,
+    pub total_amount: u64,
+}
+
+/////// MIGRATIONS ///////
+
+// account = the stache, or the vault/auto that was migrated
+#[event]
+pub struct AccountMigrated {
+    pub stache: Pubkey,
+    pub account: Pubkey,
+    pub from_version: u8,
+    pub to_version: u8,
+}
@@ -2,7 +2,8 @@ use anchor_lang::prelude::*;
 use crate::program::Stache;
 
 use anchor_spl::associated_token::AssociatedToken;
-use anchor_spl::token::{self, CloseAccount, Mint, Token, TokenAccount, Transfer};
+use anchor_spl::token_interface::{self, CloseAccount, Mint, SyncNative, TokenAccount, TokenInterface, TransferChecked};
+use anchor_spl::token::spl_token::native_mint;
 
 // declare_id!("71KtSAv6Qtpa2AZAwDhipKeoiCoyUXKbLpNheTJUGW8B");
 declare_id!("staWbEoarryYLMGxptDQKLvVMD8HqhzmBfWsAWGJQrz");
@@ -12,12 +13,22 @@ pub mod error;
 pub mod account;
 pub mod constant;
 pub mod context;
+pub mod squads;
+pub mod nft;
+pub mod automation;
+pub mod event;
+pub mod migration;
 mod util;
 
 use error::*;
 use account::*;
 use constant::*;
 use context::*;
+use squads::*;
+use nft::*;
+use automation::*;
+use event::*;
+use migration::*;
 use util::*;
 
 use {
@@ -41,6 +52,7 @@ pub mod stache {
     use anchor_lang::solana_program::{
         program::{invoke},
         system_instruction,
+        sysvar,
     };
 
     // creates the stache (beard) account
@@ -55,6 +67,7 @@ pub mod stache {
 
         let stache = &mut ctx.accounts.stache;
 
+        stache.version = CurrentStache::CURRENT_VERSION;
         // use the same name as the keychain
         stache.stacheid = keychain.name.clone();
         stache.domain = keychain.domain.clone();
@@ -64,42 +77,142 @@ pub mod stache {
         stache.next_auto_index = 1;
         stache.vaults = Vec::with_capacity(MAX_VAULTS);
         stache.autos = Vec::with_capacity(MAX_AUTOS);
+        stache.spending_limits = Vec::new();
 
+        emit!(StacheCreated {
+            stache: stache.key(),
+            keychain: stache.keychain,
+            stacheid: stache.stacheid.clone(),
+        });
         Ok(())
     }
 
-    pub fn destroy_stache(ctx: Context<DestroyStache>) -> Result<()> {
-        let keychain = &mut ctx.accounts.keychain;
+    // needs a 2nd verified key to cosign, and the vaults + automations need to be destroyed first. if sweeping,
+    // remaining accounts come in triples of (stache ata, destination token account, mint): each stache ata gets emptied into
+    // the destination token account and closed
+    pub fn destroy_stache<'a, 'b, 'c, 'info>(ctx: Context<'a, 'b, 'c, 'info, DestroyStache<'info>>, sweep: bool) -> Result<()> {
+        let keychain = &ctx.accounts.keychain;
+        let authority = ctx.accounts.authority.key();
+        let cosigner = ctx.accounts.cosigner.key();
+
         // check that the creator is on the keychain
-        require!(keychain.has_verified_key(&ctx.accounts.authority.key()), StacheError::NotAuthorized);
+        require!(keychain.has_verified_key(&authority), StacheError::NotAuthorized);
+        require!(cosigner != authority && keychain.has_verified_key(&cosigner), StacheError::InvalidCosigner);
+
+        // otherwise the vault + automation pdas (and threads) get orphaned w/nothing that can sign for them
+        let stache = &ctx.accounts.stache;
+        require!(stache.vaults.is_empty(), StacheError::StacheNotEmpty);
+        require!(stache.autos.is_empty(), StacheError::StacheNotEmpty);
+
+        if sweep {
+            let token_program = ctx.accounts.token_program.as_ref().ok_or(StacheError::MissingAccount)?;
+            let destination = ctx.accounts.destination.as_ref().ok_or(StacheError::MissingAccount)?;
+            require!(ctx.remaining_accounts.len() % 3 == 0, StacheError::MissingAccount);
+
+            let seeds = &[
+                stache.stacheid.as_bytes().as_ref(),
+                BEARD_SPACE.as_bytes().as_ref(),
+                stache.domain.as_ref(),
+                STACHE.as_bytes().as_ref(),
+                &[stache.bump],
+            ];
+            let signer = &[&seeds[..]];
+
+            for accounts in ctx.remaining_accounts.chunks(3) {
+                let stache_ata = &accounts[0];
+                let to_token = &accounts[1];
+                let mint_info = &accounts[2];
+
+                let stache_token = InterfaceAccount::<'_, TokenAccount>::try_from(stache_ata)?;
+                let destination_token = InterfaceAccount::<'_, TokenAccount>::try_from(to_token)?;
+                let mint = InterfaceAccount::<'_, Mint>::try_from(mint_info)?;
+                require!(stache_token.owner == stache.key(), StacheError::TokenAccountsMismatch);
+                require!(destination_token.owner == destination.key(), StacheError::TokenAccountsMismatch);
+                require!(destination_token.mint == stache_token.mint, StacheError::TokenAccountsMismatch);
+                require!(mint.key() == stache_token.mint, StacheError::TokenAccountsMismatch);
+
+                if stache_token.amount > 0 {
+                    reject_transfer_hook(mint_info)?;
+                    let cpi_accounts = TransferChecked {
+                        from: stache_ata.clone(),
+                        mint: mint_info.clone(),
+                        to: to_token.clone(),
+                        authority: stache.to_account_info(),
+                    };
+                    token_interface::transfer_checked(CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer),
+                                                      stache_token.amount,
+                                                      mint.decimals)?;
+                }
 
-        // todo: needs to be a 2-sig thing for security
-        // todo: check for any stashes (token accounts)
-        // todo: check for vaults
+                let cpi_close_accounts = CloseAccount {
+                    account: stache_ata.clone(),
+                    destination: destination.to_account_info(),
+                    authority: stache.to_account_info(),
+                };
+                token_interface::close_account(CpiContext::new_with_signer(token_program.to_account_info(), cpi_close_accounts, signer))?;
+                msg!("swept {} tokens and closed stache ata: {}", stache_token.amount, stache_ata.key());
+            }
+        }
 
+        emit!(StacheDestroyed {
+            stache: stache.key(),
+            swept: sweep,
+        });
         Ok(())
     }
 
     // right now this is just a wrapper around the token transfer instruction, but possibly will
-    // do some additional checks/stuff here depending on settings
+    // do some additional checks/stuff here depending on settings.
+    // stashing the native mint w/out a from token account wraps the owner's sol into the stache's wsol ata
     pub fn stash(ctx: Context<Stash>, amount: u64) -> Result<()> {
         // todo: proper checks
 
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.from_token.to_account_info(),
-            to: ctx.accounts.stache_ata.to_account_info(),
-            authority: ctx.accounts.owner.to_account_info(),
-        };
-
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-        token::transfer(cpi_ctx, amount)?;
+        match &ctx.accounts.from_token {
+            Some(from_token) => {
+                reject_transfer_hook(&ctx.accounts.mint.to_account_info())?;
+                let cpi_accounts = TransferChecked {
+                    from: from_token.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.stache_ata.to_account_info(),
+                    authority: ctx.accounts.owner.to_account_info(),
+                };
+
+                let cpi_program = ctx.accounts.token_program.to_account_info();
+                let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+                token_interface::transfer_checked(cpi_ctx, amount, ctx.accounts.mint.decimals)?;
+            }
+            None => {
+                require!(ctx.accounts.mint.key() == native_mint::ID, StacheError::MissingAccount);
+
+                let ix = system_instruction::transfer(&ctx.accounts.owner.key(), &ctx.accounts.stache_ata.key(), amount);
+                invoke(&ix, &[
+                    ctx.accounts.owner.to_account_info(),
+                    ctx.accounts.stache_ata.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ])?;
+
+                let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(),
+                                              SyncNative { account: ctx.accounts.stache_ata.to_account_info() });
+                token_interface::sync_native(cpi_ctx)?;
+                msg!("wrapped {} lamports into stache ata: {}", amount, ctx.accounts.stache_ata.key());
+            }
+        }
 
+        emit!(Stashed {
+            stache: ctx.accounts.stache.key(),
+            vault: None,
+            mint: ctx.accounts.mint.key(),
+            amount,
+        });
         Ok(())
     }
 
     pub fn unstash_sol(ctx: Context<UnstashSol>, lamports: u64) -> Result<()> {
 
+        let now = Clock::get()?.unix_timestamp;
+        require!(ctx.accounts.stache.within_limit(&native_mint::ID, lamports, now), StacheError::SpendingLimitExceeded);
+        ctx.accounts.stache.record_spend(&native_mint::ID, lamports, now);
+
         let rent = &ctx.accounts.rent;
         let min_rent = rent.minimum_balance(8 + CurrentStache::MAX_SIZE);
 
@@ -114,13 +227,29 @@ pub mod stache {
         // Debit from_account and credit to_account
         **from_account.try_borrow_mut_lamports()? -= lamports;
         **to_account.try_borrow_mut_lamports()? += lamports;
+
+        emit!(Unstashed {
+            stache: from_account.key(),
+            mint: native_mint::ID,
+            amount: lamports,
+            to: to_account.key(),
+        });
         Ok(())
     }
 
+    // unstashing the native mint w/out a to token account unwraps the wsol and sends the sol to the owner: a partial
+    // unwrap goes through the (temporary) unwrap token account, otherwise unstashing everything closes the stache ata
     pub fn unstash(ctx: Context<Unstash>, amount: u64) -> Result<()> {
 
         // todo: proper checks
 
+        reject_transfer_hook(&ctx.accounts.mint.to_account_info())?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let mint = ctx.accounts.mint.key();
+        require!(ctx.accounts.stache.within_limit(&mint, amount, now), StacheError::SpendingLimitExceeded);
+        ctx.accounts.stache.record_spend(&mint, amount, now);
+
         let stache = &ctx.accounts.stache;
         //        seeds = [keychain.name.as_bytes().as_ref(), BEARD_SPACE.as_bytes().as_ref(), keychain.domain.as_ref(), STACHE.as_bytes().as_ref()]
 
@@ -134,17 +263,142 @@ pub mod stache {
 
         let signer = &[&seeds[..]];
 
-        let cpi_accouts = Transfer {
-            from: ctx.accounts.stache_ata.to_account_info(),
-            to: ctx.accounts.to_token.to_account_info(),
+        if let Some(to_token) = &ctx.accounts.to_token {
+            let cpi_accouts = TransferChecked {
+                from: ctx.accounts.stache_ata.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                to: to_token.to_account_info(),
+                authority: ctx.accounts.stache.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                cpi_accouts,
+                signer);
+            token_interface::transfer_checked(cpi_ctx, amount, ctx.accounts.mint.decimals)?;
+
+            emit!(Unstashed {
+                stache: stache.key(),
+                mint,
+                amount,
+                to: to_token.key(),
+            });
+            return Ok(());
+        }
+
+        require!(mint == native_mint::ID, StacheError::MissingAccount);
+        require!(amount <= ctx.accounts.stache_ata.amount, StacheError::InsufficientFunds);
+
+        let wsol_account = match &ctx.accounts.unwrap_token {
+            Some(unwrap_token) => {
+                let cpi_accouts = TransferChecked {
+                    from: ctx.accounts.stache_ata.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: unwrap_token.to_account_info(),
+                    authority: ctx.accounts.stache.to_account_info(),
+                };
+                let cpi_ctx = CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    cpi_accouts,
+                    signer);
+                token_interface::transfer_checked(cpi_ctx, amount, ctx.accounts.mint.decimals)?;
+                unwrap_token.to_account_info()
+            }
+            None => {
+                // unwrapping everything, so we can just close the stache's wsol ata
+                require!(amount == ctx.accounts.stache_ata.amount, StacheError::MissingAccount);
+                ctx.accounts.stache_ata.to_account_info()
+            }
+        };
+
+        // closing a wsol account sends its lamports (the unwrapped sol + rent) to the destination
+        let cpi_close_accounts = CloseAccount {
+            account: wsol_account.clone(),
+            destination: ctx.accounts.owner.to_account_info(),
             authority: ctx.accounts.stache.to_account_info(),
         };
-        let cpi_ctx = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            cpi_accouts,
-            signer);
-        token::transfer(cpi_ctx, amount)?;
+        let cpi_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(),
+                                                  cpi_close_accounts, signer);
+        token_interface::close_account(cpi_ctx)?;
 
+        msg!("unwrapped {} lamports from stache ata: {}", amount, ctx.accounts.stache_ata.key());
+        emit!(Unstashed {
+            stache: stache.key(),
+            mint,
+            amount,
+            to: ctx.accounts.owner.key(),
+        });
+        Ok(())
+    }
+
+    // stash an nft (regular or programmable) into the stache, or straight into one of its vaults
+    pub fn stash_nft(ctx: Context<StashNft>) -> Result<()> {
+        ctx.accounts.nft_transfer().transfer(&[])?;
+
+        emit!(Stashed {
+            stache: ctx.accounts.stache.key(),
+            vault: ctx.accounts.vault.as_ref().map(|vault| vault.key()),
+            mint: ctx.accounts.mint.key(),
+            amount: 1,
+        });
+        Ok(())
+    }
+
+    pub fn unstash_nft(ctx: Context<UnstashNft>) -> Result<()> {
+        let stache = &ctx.accounts.stache;
+        let seeds = &[
+            stache.stacheid.as_bytes().as_ref(),
+            BEARD_SPACE.as_bytes().as_ref(),
+            stache.domain.as_ref(),
+            STACHE.as_bytes().as_ref(),
+            &[stache.bump],
+        ];
+        ctx.accounts.nft_transfer().transfer(&[&seeds[..]])?;
+
+        emit!(Unstashed {
+            stache: stache.key(),
+            mint: ctx.accounts.mint.key(),
+            amount: 1,
+            to: ctx.accounts.owner.key(),
+        });
+        Ok(())
+    }
+
+    // stash a compressed nft into the stache (or one of its vaults): the stache/vault pda becomes the leaf owner.
+    // remaining accounts are the proof. the asset id stands in for the mint in events + vault mints
+    pub fn stash_cnft<'a, 'b, 'c, 'info>(ctx: Context<'a, 'b, 'c, 'info, StashCnft<'info>>, args: CnftArgs) -> Result<()> {
+        let asset_id = args.asset_id(&ctx.accounts.bubblegum.merkle_tree.key());
+        if let Some(vault) = &ctx.accounts.vault {
+            require!(vault.allows_mint(&asset_id), StacheError::MintNotAllowed);
+        }
+
+        ctx.accounts.cnft_transfer(ctx.remaining_accounts).transfer(&args, &[])?;
+
+        emit!(Stashed {
+            stache: ctx.accounts.stache.key(),
+            vault: ctx.accounts.vault.as_ref().map(|vault| vault.key()),
+            mint: asset_id,
+            amount: 1,
+        });
+        Ok(())
+    }
+
+    pub fn unstash_cnft<'a, 'b, 'c, 'info>(ctx: Context<'a, 'b, 'c, 'info, UnstashCnft<'info>>, args: CnftArgs) -> Result<()> {
+        let stache = &ctx.accounts.stache;
+        let seeds = &[
+            stache.stacheid.as_bytes().as_ref(),
+            BEARD_SPACE.as_bytes().as_ref(),
+            stache.domain.as_ref(),
+            STACHE.as_bytes().as_ref(),
+            &[stache.bump],
+        ];
+        ctx.accounts.cnft_transfer(ctx.remaining_accounts).transfer(&args, &[&seeds[..]])?;
+
+        emit!(Unstashed {
+            stache: stache.key(),
+            mint: args.asset_id(&ctx.accounts.bubblegum.merkle_tree.key()),
+            amount: 1,
+            to: ctx.accounts.owner.key(),
+        });
         Ok(())
     }
 
@@ -153,35 +407,344 @@ pub mod stache {
         let is_valid_name = is_valid_name(&name, false);
         require!(is_valid_name, StacheError::InvalidName);
 
+        vault_type.validate()?;
+
         let stache = &mut ctx.accounts.stache;
 
         // add the vault to the stache
         let vault_index = stache.add_vault()?;
 
-        // todo: if squads vault, verify squads multisig seed
-        // seeds = [b"squad", create_key.as_ref(), b"multisig"], bump
+        // squads vaults need to point at a real squads multisig: seeds = [b"squad", create_key.as_ref(), b"multisig"], bump
+        if let VaultType::Squads { multisig, sigs } = &vault_type {
+            let multisig_account = ctx.accounts.multisig.as_ref().ok_or(StacheError::MissingAccount)?;
+            require!(multisig_account.key() == *multisig, StacheError::InvalidMultisig);
+            let ms = Ms::load(&multisig_account.to_account_info())?;
+            // stache counts the member approvals itself (there's no cpi into squads), so it can't ask for fewer than the multisig does
+            require!(*sigs > 0 && u16::from(*sigs) >= ms.threshold && usize::from(*sigs) <= ms.keys.len(), StacheError::InvalidMultisig);
+        }
 
         // setup the vault
         let vault = &mut ctx.accounts.vault;
 
         vault.stache = ctx.accounts.stache.key();
+        vault.version = Vault::CURRENT_VERSION;
         vault.index = vault_index;
         vault.name = name;
         vault.vault_type = vault_type;
         vault.bump = *ctx.bumps.get("vault").unwrap();
         vault.next_action_index = 1;
         vault.locked = false;
-
+        vault.locked_until = 0;
+        vault.withdraw_delay = 0;
+        vault.locked_by = Pubkey::default();
+        vault.spending_limits = Vec::new();
+        vault.mints = Vec::new();
+        vault.authorized_autos = Vec::new();
+
+        emit!(VaultCreated {
+            stache: vault.stache,
+            vault: vault.key(),
+            index: vault.index,
+            name: vault.name.clone(),
+        });
         Ok(())
     }
 
     pub fn lock_vault(ctx: Context<LockVault>) -> Result<()> {
         let vault = &mut ctx.accounts.vault;
         vault.locked = true;
+        vault.locked_by = ctx.accounts.authority.key();
+
+        emit!(VaultLocked {
+            stache: vault.stache,
+            vault: vault.key(),
+            locked_until: 0,
+        });
+        Ok(())
+    }
+
+    // easy vaults get unlocked by a 2nd verified key; everything else needs the same approvals as a withdrawal.
+    // unlocking clears a time-lock as well
+    pub fn unlock_vault(ctx: Context<UnlockVault>) -> Result<()> {
+        let authority = ctx.accounts.authority.key();
+        let keychain = &ctx.accounts.keychain;
+        let vault = &mut ctx.accounts.vault;
+
+        let now = Clock::get()?.unix_timestamp;
+        require!(vault.is_locked(now), StacheError::VaultNotLocked);
+
+        if vault.vault_type == VaultType::Easy {
+            require!(keychain.has_verified_key(&authority), StacheError::NotAuthorized);
+            require!(authority != vault.locked_by, StacheError::NotAuthorized);
+            vault.locked = false;
+            vault.locked_until = 0;
+            msg!("unlocked vault {}", vault.name);
+            emit!(VaultUnlocked {
+                stache: vault.stache,
+                vault: vault.key(),
+            });
+        } else {
+            require!(vault.can_submit(&authority, keychain.has_key(&authority)), StacheError::NotAuthorized);
+
+            let multisig = load_vault_multisig(vault, &ctx.accounts.multisig)?;
+            if vault.propose(&authority, ActionType::Unlock, Vec::new(), false, now, multisig.as_ref())? {
+                vault.locked = false;
+                vault.locked_until = 0;
+                msg!("unlocked vault {}", vault.name);
+                emit_executed(vault, ActionType::Unlock);
+                emit!(VaultUnlocked {
+                    stache: vault.stache,
+                    vault: vault.key(),
+                });
+            } else {
+                emit_proposed(vault);
+            }
+        }
+        Ok(())
+    }
+
+    // time-lock the vault until the given unix timestamp (at most MAX_LOCK_SECONDS out). extending a time-lock takes
+    // effect right away, but shortening one needs the vault's approvals (and at least a 2nd one)
+    pub fn lock_vault_until(ctx: Context<LockVault>, unlock_at: i64) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(unlock_at > now && unlock_at <= now + MAX_LOCK_SECONDS, StacheError::InvalidLockTime);
+
+        let authority = ctx.accounts.authority.key();
+        let vault = &mut ctx.accounts.vault;
+
+        if unlock_at >= vault.locked_until {
+            vault.locked_until = unlock_at;
+            vault.locked_by = authority;
+            msg!("vault locked until {}", vault.locked_until);
+            emit!(VaultLocked {
+                stache: vault.stache,
+                vault: vault.key(),
+                locked_until: vault.locked_until,
+            });
+        } else {
+            let lock_until = LockUntilAction { locked_until: unlock_at }.try_to_vec().unwrap();
+            // squads multisig isn't needed: shortening always needs a 2nd approval, approvals load it
+            if vault.propose(&authority, ActionType::LockUntil, lock_until, false, now, None)? {
+                vault.locked_until = unlock_at;
+                emit_executed(vault, ActionType::LockUntil);
+            } else {
+                emit_proposed(vault);
+            }
+        }
+        Ok(())
+    }
+
+    // raising the withdraw delay (up to MAX_WITHDRAW_DELAY) takes effect right away, but lowering it has to wait out
+    // the current delay
+    pub fn set_vault_delay(ctx: Context<SetVaultDelay>, withdraw_delay: u32) -> Result<()> {
+        require!(withdraw_delay <= MAX_WITHDRAW_DELAY, StacheError::InvalidWithdrawDelay);
+
+        let authority = ctx.accounts.authority.key();
+        let vault = &mut ctx.accounts.vault;
+        require!(vault.can_submit(&authority, ctx.accounts.keychain.has_key(&authority)), StacheError::NotAuthorized);
+
+        if withdraw_delay >= vault.withdraw_delay {
+            vault.withdraw_delay = withdraw_delay;
+        } else {
+            let now = Clock::get()?.unix_timestamp;
+            let set_delay = SetDelayAction { withdraw_delay }.try_to_vec().unwrap();
+            // squads multisig isn't needed: the proposal can't execute right away anyway, approvals load it
+            if vault.propose(&authority, ActionType::SetDelay, set_delay, false, now, None)? {
+                vault.withdraw_delay = withdraw_delay;
+                emit_executed(vault, ActionType::SetDelay);
+            } else {
+                emit_proposed(vault);
+            }
+        }
         Ok(())
     }
 
 
+    // restricts which mints can be deposited into the vault; empty = anything goes
+    pub fn set_vault_mints(ctx: Context<SetVaultMints>, mints: Vec<Pubkey>) -> Result<()> {
+        require!(mints.len() <= MAX_VAULT_MINTS, StacheError::HitLimit);
+        let vault = &mut ctx.accounts.vault;
+        vault.mints = mints;
+        Ok(())
+    }
+
+    // lets an automation pull from the vault, ie: to sweep a savings vault into a hot wallet on a schedule. needs the
+    // same approvals as a withdrawal, and covers that one action of the automation (as it is now) only
+    pub fn authorize_vault_auto(ctx: Context<AuthorizeVaultAuto>, action_index: u8) -> Result<()> {
+        let auto_key = ctx.accounts.auto.key();
+        let action = ctx.accounts.auto.actions.get(usize::from(action_index)).ok_or(StacheError::MissingAction)?.clone();
+        require!(action.vault == Some(ctx.accounts.vault.key()), StacheError::InvalidAction);
+
+        let authority = ctx.accounts.authority.key();
+        let vault = &mut ctx.accounts.vault;
+        require!(vault.can_submit(&authority, ctx.accounts.keychain.has_key(&authority)), StacheError::NotAuthorized);
+
+        let multisig = load_vault_multisig(vault, &ctx.accounts.multisig)?;
+        let now = Clock::get()?.unix_timestamp;
+
+        let authorized = AuthorizedAuto {
+            auto: auto_key,
+            action,
+        };
+        if vault.propose(&authority, ActionType::AuthorizeAuto, authorized.try_to_vec().unwrap(), false, now, multisig.as_ref())? {
+            vault.authorize_auto(authorized)?;
+            msg!("authorized automation {} on vault {}", auto_key, vault.name);
+            emit_executed(vault, ActionType::AuthorizeAuto);
+        } else {
+            emit_proposed(vault);
+        }
+        Ok(())
+    }
+
+    // taking away an automation's access doesn't need approvals
+    pub fn revoke_vault_auto(ctx: Context<RevokeVaultAuto>, auto: Pubkey) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        vault.revoke_auto(&auto);
+        Ok(())
+    }
+
+    // deposit from a token account owned by the authority (a key on the keychain) or from the stache ata.
+    // creates the vault ata if needed. coming out of the stache ata counts toward the stache's spending limit, same
+    // as unstashing (otherwise an easy vault would be a way around it)
+    pub fn deposit_to_vault(ctx: Context<DepositToVault>, amount: u64) -> Result<()> {
+        let from_token = &ctx.accounts.from_token;
+        require!(from_token.amount >= amount, StacheError::InsufficientFunds);
+        reject_transfer_hook(&ctx.accounts.mint.to_account_info())?;
+
+        if from_token.owner == ctx.accounts.stache.key() {
+            let now = Clock::get()?.unix_timestamp;
+            let mint = ctx.accounts.mint.key();
+            require!(ctx.accounts.stache.within_limit(&mint, amount, now), StacheError::SpendingLimitExceeded);
+            ctx.accounts.stache.record_spend(&mint, amount, now);
+        }
+
+        let stache = &ctx.accounts.stache;
+
+        let cpi_accounts = TransferChecked {
+            from: from_token.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            to: ctx.accounts.vault_ata.to_account_info(),
+            authority: if from_token.owner == stache.key() {
+                stache.to_account_info()
+            } else {
+                ctx.accounts.authority.to_account_info()
+            },
+        };
+
+        let seeds = &[
+            stache.stacheid.as_bytes().as_ref(),
+            BEARD_SPACE.as_bytes().as_ref(),
+            stache.domain.as_ref(),
+            STACHE.as_bytes().as_ref(),
+            &[stache.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        // the stache only needs to sign if it's coming out of the stache ata
+        let cpi_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_accounts, signer);
+        token_interface::transfer_checked(cpi_ctx, amount, ctx.accounts.mint.decimals)?;
+
+        msg!("deposited {} tokens into vault ata: {}", amount, ctx.accounts.vault_ata.key());
+        emit!(Stashed {
+            stache: stache.key(),
+            vault: Some(ctx.accounts.vault.key()),
+            mint: ctx.accounts.mint.key(),
+            amount,
+        });
+        Ok(())
+    }
+
+    // deposit lamports straight into the vault account
+    pub fn deposit_sol_to_vault(ctx: Context<DepositSolToVault>, lamports: u64) -> Result<()> {
+        let ix = system_instruction::transfer(&ctx.accounts.authority.key(), &ctx.accounts.vault.key(), lamports);
+        invoke(&ix, &[
+            ctx.accounts.authority.to_account_info(),
+            ctx.accounts.vault.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+        ])?;
+
+        msg!("deposited {} lamports into vault: {}", lamports, ctx.accounts.vault.key());
+        emit!(Stashed {
+            stache: ctx.accounts.vault.stache,
+            vault: Some(ctx.accounts.vault.key()),
+            mint: native_mint::ID,
+            amount: lamports,
+        });
+        Ok(())
+    }
+
+    // moves tokens between the stache ata and vault atas (or between vaults). entering a vault is always allowed,
+    // leaving one follows the vault's rules (ie: leaving a 2-sig vault becomes a vault action). leaving the stache ata
+    // counts toward the stache's spending limit, same as deposit_to_vault
+    pub fn internal_transfer(ctx: Context<InternalTransfer>, amount: u64) -> Result<()> {
+        reject_transfer_hook(&ctx.accounts.mint.to_account_info())?;
+
+        if ctx.accounts.from_vault.is_none() {
+            let now = Clock::get()?.unix_timestamp;
+            let mint = ctx.accounts.mint.key();
+            require!(ctx.accounts.stache.within_limit(&mint, amount, now), StacheError::SpendingLimitExceeded);
+            ctx.accounts.stache.record_spend(&mint, amount, now);
+        }
+
+        let stache = &ctx.accounts.stache;
+        let from_token = &ctx.accounts.from_token;
+        let to_token = &ctx.accounts.to_token;
+
+        // no vault = the stache ata
+        let from_owner = ctx.accounts.from_vault.as_ref().map_or(stache.key(), |vault| vault.key());
+        let to_owner = ctx.accounts.to_vault.as_ref().map_or(stache.key(), |vault| vault.key());
+        require!(from_owner != to_owner, StacheError::DupeAccount);
+        require!(from_token.owner == from_owner, StacheError::TokenAccountsMismatch);
+        require!(to_token.owner == to_owner, StacheError::TokenAccountsMismatch);
+        require!(from_token.amount >= amount, StacheError::InsufficientFunds);
+
+        match ctx.accounts.from_vault.as_mut() {
+            None => {
+                let seeds = &[
+                    stache.stacheid.as_bytes().as_ref(),
+                    BEARD_SPACE.as_bytes().as_ref(),
+                    stache.domain.as_ref(),
+                    STACHE.as_bytes().as_ref(),
+                    &[stache.bump],
+                ];
+                let signer = &[&seeds[..]];
+
+                let cpi_accounts = TransferChecked {
+                    from: from_token.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: to_token.to_account_info(),
+                    authority: stache.to_account_info(),
+                };
+                let cpi_ctx = CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_accounts, signer);
+                token_interface::transfer_checked(cpi_ctx, amount, ctx.accounts.mint.decimals)?;
+                msg!("transferred {} tokens from stache ata: {}, to: {}", amount, from_token.key(), to_token.key());
+            }
+            Some(from_vault) => {
+                let authority = ctx.accounts.authority.key();
+                let vault_authority = from_vault.to_account_info();
+
+                let multisig = load_vault_multisig(from_vault, &ctx.accounts.multisig)?;
+                require!(from_vault.can_submit(&authority, true), StacheError::NotAuthorized);
+
+                let now = Clock::get()?.unix_timestamp;
+                if from_vault.withdraw(&authority, &from_token.key(), &to_token.key(), &from_token.mint, amount, now, multisig.as_ref())? {
+                    transfer_from_vault(stache,
+                                        from_vault,
+                                        vault_authority,
+                                        from_token.to_account_info(),
+                                        to_token.to_account_info(),
+                                        ctx.accounts.mint.to_account_info(),
+                                        amount,
+                                        ctx.accounts.token_program.to_account_info())?;
+                    emit_executed(from_vault, ActionType::Transfer);
+                } else {
+                    emit_proposed(from_vault);
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub fn withdraw_from_vault(ctx: Context<WithdrawFromVault>, amount: u64) -> Result<()> {
         let stache = &mut ctx.accounts.stache;
         let tokens_left = ctx.accounts.vault_ata.amount;
@@ -191,17 +754,129 @@ pub mod stache {
 
         require!(amount <= tokens_left, StacheError::InsufficientFunds);
 
-        if vault.withdraw(&ctx.accounts.authority.key(), &ctx.accounts.vault_ata.key(), &ctx.accounts.to_token.key(), amount).unwrap() {
+        // threshold vaults have their own list of submitters, everything else goes through the keychain
+        let is_keychain_key = ctx.accounts.keychain.has_key(&ctx.accounts.authority.key());
+        require!(vault.can_submit(&ctx.accounts.authority.key(), is_keychain_key), StacheError::NotAuthorized);
+
+        let multisig = load_vault_multisig(vault, &ctx.accounts.multisig)?;
+
+        let now = Clock::get()?.unix_timestamp;
+
+        if vault.withdraw(&ctx.accounts.authority.key(),
+                          &ctx.accounts.vault_ata.key(),
+                          &ctx.accounts.to_token.key(),
+                          &ctx.accounts.mint.key(),
+                          amount,
+                          now,
+                          multisig.as_ref())? {
             // withdraw
             transfer_from_vault(&stache,
                                 &mut vault,
                                 vault_authority,
                                 ctx.accounts.vault_ata.clone().to_account_info(),
                                 ctx.accounts.to_token.clone().to_account_info(),
+                                ctx.accounts.mint.clone().to_account_info(),
                                 amount,
                                 ctx.accounts.token_program.clone().to_account_info())?;
+            emit_executed(vault, ActionType::Transfer);
+        } else {
+            emit_proposed(vault);
+        }
+
+        Ok(())
+    }
+
+    // same rules as withdraw_from_vault, for the lamports held by the vault account (above its rent-exempt minimum)
+    pub fn withdraw_sol_from_vault(ctx: Context<WithdrawSolFromVault>, lamports: u64) -> Result<()> {
+        let vault_info = ctx.accounts.vault.to_account_info();
+        let to_info = ctx.accounts.to.to_account_info();
+        let vault = &mut ctx.accounts.vault;
+
+        require!(lamports <= available_vault_lamports(&vault_info)?, StacheError::InsufficientFunds);
+
+        let is_keychain_key = ctx.accounts.keychain.has_key(&ctx.accounts.authority.key());
+        require!(vault.can_submit(&ctx.accounts.authority.key(), is_keychain_key), StacheError::NotAuthorized);
+
+        let multisig = load_vault_multisig(vault, &ctx.accounts.multisig)?;
+
+        let now = Clock::get()?.unix_timestamp;
+
+        if vault.withdraw_sol(&ctx.accounts.authority.key(), &to_info.key(), lamports, now, multisig.as_ref())? {
+            transfer_sol_from_vault(&vault_info, &to_info, lamports)?;
+            emit_executed(vault, ActionType::SolTransfer);
+        } else {
+            emit_proposed(vault);
+        }
+
+        Ok(())
+    }
+
+    // same rules as withdraw_from_vault: nfts leave easy vaults right away, otherwise it becomes an nft transfer action
+    pub fn withdraw_nft_from_vault(ctx: Context<WithdrawNftFromVault>) -> Result<()> {
+        let authority = ctx.accounts.authority.key();
+        let is_keychain_key = ctx.accounts.keychain.has_key(&authority);
+        let mint = ctx.accounts.mint.key();
+        let to = ctx.accounts.to.key();
+
+        require!(ctx.accounts.vault_token.amount == 1, StacheError::InsufficientFunds);
+
+        let vault = &mut ctx.accounts.vault;
+        require!(vault.can_submit(&authority, is_keychain_key), StacheError::NotAuthorized);
+
+        let multisig = load_vault_multisig(vault, &ctx.accounts.multisig)?;
+
+        let now = Clock::get()?.unix_timestamp;
+
+        if vault.withdraw_nft(&authority, &mint, &to, now, multisig.as_ref())? {
+            let stache = &ctx.accounts.stache;
+            let binding = ctx.accounts.vault.index.to_le_bytes();
+            let seeds = &[
+                binding.as_ref(),
+                VAULT_SPACE.as_bytes().as_ref(),
+                stache.stacheid.as_bytes().as_ref(),
+                BEARD_SPACE.as_bytes().as_ref(),
+                stache.domain.as_ref(),
+                STACHE.as_bytes().as_ref(),
+                &[ctx.accounts.vault.bump],
+            ];
+            ctx.accounts.nft_transfer().transfer(&[&seeds[..]])?;
+            emit_executed(&ctx.accounts.vault, ActionType::NftTransfer);
+        } else {
+            emit_proposed(&ctx.accounts.vault);
+        }
 
+        Ok(())
+    }
 
+    // same rules as withdraw_nft_from_vault. the proposed action keeps the leaf, the proof is passed in again on execution
+    pub fn withdraw_cnft_from_vault<'a, 'b, 'c, 'info>(ctx: Context<'a, 'b, 'c, 'info, WithdrawCnftFromVault<'info>>, args: CnftArgs) -> Result<()> {
+        let authority = ctx.accounts.authority.key();
+        let is_keychain_key = ctx.accounts.keychain.has_key(&authority);
+        let transfer = CnftTransferAction::new(&args, &ctx.accounts.bubblegum.merkle_tree.key(), &ctx.accounts.to.key());
+
+        let vault = &mut ctx.accounts.vault;
+        require!(vault.can_submit(&authority, is_keychain_key), StacheError::NotAuthorized);
+
+        let multisig = load_vault_multisig(vault, &ctx.accounts.multisig)?;
+
+        let now = Clock::get()?.unix_timestamp;
+
+        if vault.withdraw_cnft(&authority, &transfer, now, multisig.as_ref())? {
+            let stache = &ctx.accounts.stache;
+            let binding = ctx.accounts.vault.index.to_le_bytes();
+            let seeds = &[
+                binding.as_ref(),
+                VAULT_SPACE.as_bytes().as_ref(),
+                stache.stacheid.as_bytes().as_ref(),
+                BEARD_SPACE.as_bytes().as_ref(),
+                stache.domain.as_ref(),
+                STACHE.as_bytes().as_ref(),
+                &[ctx.accounts.vault.bump],
+            ];
+            ctx.accounts.cnft_transfer(ctx.remaining_accounts).transfer(&args, &[&seeds[..]])?;
+            emit_executed(&ctx.accounts.vault, ActionType::CnftTransfer);
+        } else {
+            emit_proposed(&ctx.accounts.vault);
         }
 
         Ok(())
@@ -211,62 +886,228 @@ pub mod stache {
 
         msg!("approving vault action");
         let stache = &mut ctx.accounts.stache;
+        let approver = ctx.accounts.authority.key();
+        let is_keychain_key = ctx.accounts.keychain.has_key(&approver);
 
         let vault_authority = ctx.accounts.vault.clone().to_account_info();
         let vault = &mut ctx.accounts.vault;
-        let vault_type = vault.vault_type.clone();
-        let vault_action = vault.get_action(action_index).unwrap();
 
-        match vault_action.action_type {
-            ActionType::Transfer => {
+        // squads vaults are approved by the members of the multisig, threshold vaults by their approvers, everything else by the keychain
+        let multisig = load_vault_multisig(vault, &ctx.accounts.multisig)?;
+        require!(vault.can_approve(&approver, is_keychain_key, multisig.as_ref()), StacheError::NotAuthorized);
+
+        let vault_action = vault.get_action(action_index).ok_or(StacheError::InvalidAction)?;
+        vault_action.approve(&approver)?;
+        let mut vault_action = vault_action.clone();
+
+        emit!(ActionApproved {
+            stache: vault.stache,
+            vault: vault.key(),
+            action_index,
+            approver,
+            approvals: vault_action.count_approvers() as u8,
+        });
+
+        // approved actions still have to wait out the vault's withdraw delay (see execute_action)
+        let now = Clock::get()?.unix_timestamp;
+        if vault.is_executable(&vault_action, now, multisig.as_ref()) {
+            execute_vault_action(&stache,
+                                 vault,
+                                 vault_authority,
+                                 &mut vault_action,
+                                 ctx.remaining_accounts,
+                                 ctx.accounts.token_program.clone().to_account_info(),
+                                 ctx.accounts.authority.to_account_info(),
+                                 now)?;
+
+            // action has been executed
+            vault.remove_action(action_index);
+        }
+        Ok(())
+    }
 
-                vault_action.approve(&ctx.accounts.authority.key())?;
+    // executes an approved action once the vault's withdraw delay is up (same remaining accounts as approve_action)
+    pub fn execute_action<'a, 'b, 'c, 'info>(ctx: Context<'a, 'b, 'c, 'info, ExecuteVaultAction<'info>>, action_index: u8) -> Result<()> {
+        let stache = &ctx.accounts.stache;
+        let authority = ctx.accounts.authority.key();
+        let is_keychain_key = ctx.accounts.keychain.has_key(&authority);
 
-                if vault_action.count_approvers() == 2 && vault_type == VaultType::TwoSig {
+        let vault_authority = ctx.accounts.vault.clone().to_account_info();
+        let vault = &mut ctx.accounts.vault;
 
-                    let withdraw_vault_action_data = vault_action.transfer_action()?;
+        let multisig = load_vault_multisig(vault, &ctx.accounts.multisig)?;
+        require!(vault.can_submit(&authority, is_keychain_key) || vault.can_approve(&authority, is_keychain_key, multisig.as_ref()),
+            StacheError::NotAuthorized);
 
-                    // check that the remaining accounts passed in match
-                    let accs = &mut ctx.remaining_accounts.iter();
-                    let from = next_account_info(accs)?.to_account_info();
-                    let to = next_account_info(accs)?.to_account_info();
+        let mut vault_action = vault.get_action(action_index).ok_or(StacheError::InvalidAction)?.clone();
 
-                    require!(from.key() == withdraw_vault_action_data.from, StacheError::InvalidAction);
-                    require!(to.key() == withdraw_vault_action_data.to, StacheError::InvalidAction);
+        let now = Clock::get()?.unix_timestamp;
+        require!(vault.is_executable(&vault_action, now, multisig.as_ref()), StacheError::ActionNotExecutable);
 
-                    let from_token = Account::<'_, TokenAccount>::try_from(&from).unwrap();
+        execute_vault_action(&stache,
+                             vault,
+                             vault_authority,
+                             &mut vault_action,
+                             ctx.remaining_accounts,
+                             ctx.accounts.token_program.clone().to_account_info(),
+                             ctx.accounts.authority.to_account_info(),
+                             now)?;
 
-                    require!(withdraw_vault_action_data.amount <= from_token.amount, StacheError::InsufficientFunds);
+        vault.remove_action(action_index);
+        Ok(())
+    }
 
-                    // withdraw
-                    transfer_from_vault(&stache,
-                                         vault,
-                                        vault_authority,
-                                        from,
-                                        to,
-                                        withdraw_vault_action_data.amount,
-                                        ctx.accounts.token_program.clone().to_account_info())?;
+    // a member of a squads vault's multisig puts one of its actions into a squads transaction, so it can be approved +
+    // executed in squads instead (see squads.rs). remaining accounts = what the action needs to execute
+    pub fn propose_to_squads<'a, 'b, 'c, 'info>(ctx: Context<'a, 'b, 'c, 'info, ProposeToSquads<'info>>, action_index: u8) -> Result<()> {
+        let authority = ctx.accounts.authority.key();
+        let multisig = ctx.accounts.multisig.key();
+
+        let ms = load_vault_multisig(&ctx.accounts.vault, &Some(ctx.accounts.multisig.clone()))?
+            .ok_or(StacheError::InvalidVault)?;
+        require!(ms.is_member(&authority), StacheError::NotAuthorized);
+
+        // what squads ends up calling, w/its authority signing
+        let mut accounts = crate::accounts::ExecuteSquadsAction {
+            stache: ctx.accounts.stache.key(),
+            vault: ctx.accounts.vault.key(),
+            multisig,
+            transaction: ctx.accounts.transaction.key(),
+            squads_authority: find_authority(&multisig).0,
+            token_program: ctx.accounts.token_program.key(),
+            sysvar_instructions: sysvar::instructions::ID,
+        }.to_account_metas(None);
+        accounts.extend(ctx.remaining_accounts.iter().map(|account| match account.is_writable {
+            true => AccountMeta::new(account.key(), false),
+            false => AccountMeta::new_readonly(account.key(), false),
+        }));
+        let execute = Instruction {
+            program_id: crate::ID,
+            accounts,
+            data: crate::instruction::ExecuteSquadsAction { action_index }.data(),
+        };
 
-                    // action has been executed
-                    vault.remove_action(action_index);
-                }
+        SquadsTransaction {
+            multisig: ctx.accounts.multisig.as_ref(),
+            transaction: ctx.accounts.transaction.as_ref(),
+            instruction: ctx.accounts.instruction.as_ref(),
+            creator: ctx.accounts.authority.as_ref(),
+            system_program: ctx.accounts.system_program.as_ref(),
+            squads_program: ctx.accounts.squads_program.as_ref(),
+        }.propose(execute.into())?;
+
+        emit!(ActionProposedToSquads {
+            stache: ctx.accounts.stache.key(),
+            vault: ctx.accounts.vault.key(),
+            action_index,
+            transaction: ctx.accounts.transaction.key(),
+        });
+        Ok(())
+    }
+
+    // squads executing a transaction from propose_to_squads. the approvals on the squads transaction stand in for the
+    // ones gathered in stache, so the vault's usual rules (sigs, spending limits, withdraw delay, locks) still apply
+    pub fn execute_squads_action<'a, 'b, 'c, 'info>(ctx: Context<'a, 'b, 'c, 'info, ExecuteSquadsAction<'info>>, action_index: u8) -> Result<()> {
+        let multisig = ctx.accounts.multisig.key();
+        require!(ctx.accounts.squads_authority.key() == find_authority(&multisig).0, StacheError::NotAuthorized);
+
+        let ms = load_vault_multisig(&ctx.accounts.vault, &Some(ctx.accounts.multisig.clone()))?
+            .ok_or(StacheError::InvalidVault)?;
+
+        let transaction = MsTransaction::load(&ctx.accounts.transaction, &multisig)?;
+        require!(transaction.status == MsTransactionStatus::ExecuteReady, StacheError::ActionNotExecutable);
+        MsTransaction::check_executing(&ctx.accounts.transaction, &ctx.accounts.sysvar_instructions)?;
+
+        let stache = &ctx.accounts.stache;
+        let vault_authority = ctx.accounts.vault.clone().to_account_info();
+        let vault = &mut ctx.accounts.vault;
+
+        let mut vault_action = vault.get_action(action_index).ok_or(StacheError::InvalidAction)?.clone();
+        vault_action.approvers = transaction.approved;
+
+        let now = Clock::get()?.unix_timestamp;
+        require!(vault.is_executable(&vault_action, now, Some(&ms)), StacheError::ActionNotExecutable);
+
+        execute_vault_action(&stache,
+                             vault,
+                             vault_authority,
+                             &mut vault_action,
+                             ctx.remaining_accounts,
+                             ctx.accounts.token_program.clone().to_account_info(),
+                             ctx.accounts.squads_authority.to_account_info(),
+                             now)?;
+
+        vault.remove_action(action_index);
+        Ok(())
+    }
+
+    // anyone who could approve the action can deny it instead
+    pub fn deny_action(ctx: Context<DenyVaultAction>, action_index: u8) -> Result<()> {
+        let authority = ctx.accounts.authority.key();
+        let is_keychain_key = ctx.accounts.keychain.has_key(&authority);
+        let vault = &mut ctx.accounts.vault;
+
+        let multisig = load_vault_multisig(vault, &ctx.accounts.multisig)?;
+        require!(vault.can_approve(&authority, is_keychain_key, multisig.as_ref()), StacheError::NotAuthorized);
+
+        require!(vault.get_action(action_index).is_some(), StacheError::InvalidAction);
+        vault.remove_action(action_index);
+
+        emit!(ActionDenied {
+            stache: vault.stache,
+            vault: vault.key(),
+            action_index,
+            denied_by: authority,
+        });
+        Ok(())
+    }
+
+    // sets a spending limit on the vault (if passed in) or the stache. loosening a limit needs a 2nd verified key to cosign
+    pub fn set_spending_limit(ctx: Context<SetSpendingLimit>, mint: Pubkey, amount: u64, period_seconds: u32) -> Result<()> {
+        require!(period_seconds > 0, StacheError::InvalidSpendingLimit);
+
+        let now = Clock::get()?.unix_timestamp;
+        let cosigned = ctx.accounts.is_cosigned();
+
+        let limits = match ctx.accounts.vault.as_mut() {
+            Some(vault) => &mut vault.spending_limits,
+            None => &mut ctx.accounts.stache.spending_limits,
+        };
+
+        if let Some(limit) = limits.iter().find(|limit| limit.mint == mint) {
+            if limit.is_looser(amount, period_seconds) {
+                require!(cosigned, StacheError::InvalidCosigner);
             }
         }
-        Ok(())
+
+        SpendingLimit::set(limits, &mint, amount, period_seconds, now)
     }
 
-    pub fn deny_action(ctx: Context<DenyVaultAction>, action_index: u8) -> Result<()> {
-        let mut vault = &mut ctx.accounts.vault;
-        vault.remove_action(action_index);
+    // removing a limit is always loosening it, so always needs a cosigner
+    pub fn remove_spending_limit(ctx: Context<SetSpendingLimit>, mint: Pubkey) -> Result<()> {
+        require!(ctx.accounts.is_cosigned(), StacheError::InvalidCosigner);
+
+        let limits = match ctx.accounts.vault.as_mut() {
+            Some(vault) => &mut vault.spending_limits,
+            None => &mut ctx.accounts.stache.spending_limits,
+        };
+        limits.retain(|limit| limit.mint != mint);
         Ok(())
     }
 
     pub fn destroy_vault(ctx: Context<DestroyVault>) -> Result<()> {
+        // closing the vault would hand its sol to the authority without going through the vault's rules
+        require!(available_vault_lamports(&ctx.accounts.vault.to_account_info())? == 0, StacheError::VaultNotEmpty);
+
         let stache = &mut ctx.accounts.stache;
 
         // get rid of the vault from stache
         stache.remove_vault(ctx.accounts.vault.index);
 
+        emit!(VaultDestroyed {
+            stache: stache.key(),
+            vault: ctx.accounts.vault.key(),
+        });
         Ok(())
     }
 
@@ -290,6 +1131,7 @@ pub mod stache {
         let auto = &mut ctx.accounts.auto;
 
         auto.stache = ctx.accounts.stache.key();
+        auto.version = Auto::CURRENT_VERSION;
         auto.index = auto_index;
         auto.bump = *ctx.bumps.get("auto").unwrap();
         auto.active = false;
@@ -299,10 +1141,20 @@ pub mod stache {
         auto.num_triggers = 0;
         auto.trigger = None;
         auto.trigger_type = None;
-        auto.action = None;
-        auto.action_type = None;
+        auto.actions = Vec::new();
         auto.thread = None;
-
+        auto.last_fired_at = 0;
+        auto.max_execs = None;
+        auto.max_total_amount = None;
+        auto.expires_at = None;
+        auto.total_amount = 0;
+
+        emit!(AutoCreated {
+            stache: auto.stache,
+            auto: auto.key(),
+            index: auto.index,
+            name: auto.name.clone(),
+        });
         Ok(())
     }
 
@@ -315,37 +1167,44 @@ pub mod stache {
             require!(ctx.accounts.thread.is_some(), StacheError::MissingAccount);
             require!(ctx.accounts.clockwork_program.is_some(), StacheError::MissingAccount);
 
-            let thread = &mut ctx.accounts.thread.as_mut().unwrap();
-
-            let binding = auto.index.to_le_bytes();
-            let seeds = &[
-                binding.as_ref(),
-                AUTO_SPACE.as_bytes().as_ref(),
-                stache.stacheid.as_bytes().as_ref(),
-                BEARD_SPACE.as_bytes().as_ref(),
-                stache.domain.as_ref(),
-                STACHE.as_bytes().as_ref(),
-                &[auto.bump]
-            ];
-
-            // delete the thread
-            clockwork_sdk::cpi::thread_delete(
-                CpiContext::new_with_signer(
-                    ctx.accounts.clockwork_program.as_ref().unwrap().to_account_info(),
-                    clockwork_sdk::cpi::ThreadDelete {
-                        authority: auto.to_account_info(),
-                        close_to: ctx.accounts.authority.to_account_info(),
-                        thread: thread.to_account_info(),
-                    },
-                    &[seeds],
-                )
-            )?;
-            msg!("deleted clockwork thread {}", thread.key());
+            delete_auto_thread(stache,
+                               auto,
+                               ctx.accounts.thread.as_ref().unwrap().to_account_info(),
+                               ctx.accounts.clockwork_program.as_ref().unwrap().to_account_info(),
+                               ctx.accounts.authority.to_account_info())?;
         }
 
         // get rid of the automation from stache
         stache.remove_auto(auto.index);
 
+        emit!(AutoDeactivated {
+            stache: stache.key(),
+            auto: auto.key(),
+            destroyed: true,
+        });
+        Ok(())
+    }
+
+    // deletes the automation's thread and makes it editable again, keeping its index + counters
+    pub fn deactivate_auto(ctx: Context<DeactivateAutomation>) -> Result<()> {
+        let stache = &ctx.accounts.stache;
+        let auto = &mut ctx.accounts.auto;
+
+        delete_auto_thread(stache,
+                           auto,
+                           ctx.accounts.thread.to_account_info(),
+                           ctx.accounts.clockwork_program.to_account_info(),
+                           ctx.accounts.authority.to_account_info())?;
+
+        auto.active = false;
+        auto.paused = true;
+        auto.thread = None;
+
+        emit!(AutoDeactivated {
+            stache: stache.key(),
+            auto: auto.key(),
+            destroyed: false,
+        });
         Ok(())
     }
 
@@ -373,278 +1232,782 @@ pub mod stache {
         Ok(())
     }
 
-    // from = stache ata; for now just works on stache, but later can easily set up to work on vaults as well
-    pub fn set_auto_action(ctx: Context<SetAutomationAction>, amount: u64) -> Result<()> {
+    // pauses an active automation + its thread; keeps its config/counters (unlike destroy_auto)
+    pub fn pause_auto(ctx: Context<ToggleAutomation>) -> Result<()> {
+        let stache = &ctx.accounts.stache;
+        let auto = &mut ctx.accounts.auto;
+
+        require!(auto.active, StacheError::AutomationInactive);
+        require!(!auto.paused, StacheError::AutomationPaused);
+
+        with_auto_signer(stache, auto, |signer| {
+            clockwork_sdk::cpi::thread_pause(
+                CpiContext::new_with_signer(
+                    ctx.accounts.clockwork_program.to_account_info(),
+                    clockwork_sdk::cpi::ThreadPause {
+                        authority: auto.to_account_info(),
+                        thread: ctx.accounts.thread.to_account_info(),
+                    },
+                    signer,
+                )
+            )
+        })?;
+
+        auto.paused = true;
+        msg!("paused clockwork thread {}", ctx.accounts.thread.key());
+        emit!(AutoPaused {
+            stache: stache.key(),
+            auto: auto.key(),
+        });
+
+        Ok(())
+    }
+
+    pub fn resume_auto(ctx: Context<ToggleAutomation>) -> Result<()> {
+        let stache = &ctx.accounts.stache;
+        let auto = &mut ctx.accounts.auto;
+
+        require!(auto.active, StacheError::AutomationInactive);
+        require!(auto.paused, StacheError::AutomationNotPaused);
+
+        with_auto_signer(stache, auto, |signer| {
+            clockwork_sdk::cpi::thread_resume(
+                CpiContext::new_with_signer(
+                    ctx.accounts.clockwork_program.to_account_info(),
+                    clockwork_sdk::cpi::ThreadResume {
+                        authority: auto.to_account_info(),
+                        thread: ctx.accounts.thread.to_account_info(),
+                    },
+                    signer,
+                )
+            )
+        })?;
+
+        auto.paused = false;
+        msg!("resumed clockwork thread {}", ctx.accounts.thread.key());
+        emit!(AutoResumed {
+            stache: stache.key(),
+            auto: auto.key(),
+        });
+
+        Ok(())
+    }
+
+    // schedule is a clockwork cron schedule (sec min hour day month weekday year), ie: "0 0 12 1 * * *" = noon on the 1st of every month
+    pub fn set_auto_cron_trigger(ctx: Context<SetAutomationTrigger>, schedule: String) -> Result<()> {
+        let auto = &mut ctx.accounts.auto;
+
+        require!(!auto.active, StacheError::AutomationLocked);
+        require!(schedule.len() <= MAX_CRON_SCHEDULE_LEN, StacheError::InvalidSchedule);
+        // make sure it parses (and fires at some point)
+        require!(next_cron_timestamp(&schedule, Clock::get()?.unix_timestamp).is_some(), StacheError::InvalidSchedule);
+
+        auto.trigger_type = Some(TriggerType::Cron);
+        auto.trigger = Some(CronTrigger {
+            schedule,
+        }.try_to_vec().unwrap());
+
+        Ok(())
+    }
+
+    // fires every `seconds` seconds, starting at `start`
+    pub fn set_auto_interval_trigger(ctx: Context<SetAutomationTrigger>, seconds: i64, start: i64) -> Result<()> {
+        let auto = &mut ctx.accounts.auto;
+
+        require!(!auto.active, StacheError::AutomationLocked);
+        require!(seconds > 0 && seconds <= MAX_INTERVAL_SECONDS, StacheError::InvalidSchedule);
+        require!(start >= 0 && start <= MAX_INTERVAL_START, StacheError::InvalidSchedule);
+
+        auto.trigger_type = Some(TriggerType::Interval);
+        auto.trigger = Some(IntervalTrigger {
+            seconds,
+            start,
+        }.try_to_vec().unwrap());
+
+        Ok(())
+    }
+
+    // optional caps on an automation: once it's been executed max_execs times, has moved max_total_amount, or
+    // expires_at has passed, fire_auto stops it
+    pub fn set_auto_limits(ctx: Context<SetAutomationLimits>, max_execs: Option<u32>, max_total_amount: Option<u64>, expires_at: Option<i64>) -> Result<()> {
+        let auto = &mut ctx.accounts.auto;
+
+        require!(!auto.active, StacheError::AutomationLocked);
+
+        let now = Clock::get()?.unix_timestamp;
+        require!(max_execs.map_or(true, |max| max > 0), StacheError::InvalidAutomationLimit);
+        require!(max_total_amount.map_or(true, |max| max > 0), StacheError::InvalidAutomationLimit);
+        require!(expires_at.map_or(true, |expires_at| expires_at > now), StacheError::InvalidAutomationLimit);
+
+        auto.max_execs = max_execs;
+        auto.max_total_amount = max_total_amount;
+        auto.expires_at = expires_at;
+
+        Ok(())
+    }
+
+    // adds a step to the automation; steps run in order, all or nothing, each time the automation fires.
+    // from = stache ata, or a vault ata if a vault is passed in
+    // amount depends on the mode: fixed = amount to transfer, percent = basis points of the from balance,
+    // sweep above = balance to leave behind, top up = balance to bring the to account up to
+    pub fn add_auto_action(ctx: Context<SetAutomationAction>, amount: u64, mode: TransferMode) -> Result<()> {
         let auto = &mut ctx.accounts.auto;
 
         require!(!auto.active, StacheError::AutomationLocked);
+        require!(auto.actions.len() < MAX_AUTO_ACTIONS, StacheError::HitLimit);
+        require!(mode != TransferMode::Percent || (amount > 0 && amount <= 10000), StacheError::InvalidAction);
         if ctx.accounts.from_token.is_none() || ctx.accounts.to_token.is_none() || ctx.accounts.associated_token_program.is_none() {
             return Err(StacheError::MissingAccount.into());
         }
+        reject_transfer_hook(&ctx.accounts.mint.to_account_info())?;
 
-        // this check will need to go in after we remove the constraints
-        // require!(ctx.accounts.from_token.unwrap().mint == ctx.accounts.to_token.unwrap().mint, StacheError::TokenAccountsMismatch);
-
-        // set the action
-        auto.action_type = Some(ActionType::Transfer);
-        auto.action = Some(TransferAction {
+        // pulling from a vault also needs the vault to allow it (checked when activating + firing)
+        auto.actions.push(AutoTransferAction {
             from: ctx.accounts.from_token.as_ref().unwrap().key(),
             to: ctx.accounts.to_token.as_ref().unwrap().key(),
+            mint: ctx.accounts.mint.key(),
+            token_program: ctx.accounts.token_program.key(),
             amount,
-        }.try_to_vec().unwrap());
+            mode,
+            vault: ctx.accounts.vault.as_ref().map(|vault| vault.key()),
+        });
 
         Ok(())
     }
 
-    // ok this is a bit inelegant but we don't wanna pass in the same token account twice or we'll get
-    // 'instruction tries to borrow reference for an account which is already borrowed'
-    // so use_ref needs to be true and if use_from = true, then that account will be checked for the trigger balance
-    // otherwise it'll be the to account
-
-    // fire an automation, called by clockwork thread: needs 1 remaining account (token account to check balance)
-    pub fn fire_auto(ctx: Context<FireAutomation>, use_ref: bool, use_from: bool) -> Result<()> {
-
+    pub fn clear_auto_actions(ctx: Context<ClearAutomationActions>) -> Result<()> {
         let auto = &mut ctx.accounts.auto;
 
-        auto.num_triggers = auto.num_triggers.checked_add(1).ok_or(StacheError::TriggerLimit)?;
+        require!(!auto.active, StacheError::AutomationLocked);
+        auto.actions.clear();
 
-        // only 1 type of action/trigger now so we can just do this
-        let action = auto.transfer_action()?;
-        let trigger = auto.balance_trigger()?;
+        Ok(())
+    }
 
-        // since there's only 1 action/trigger, we'll always need these programs
-        // require!(ctx.accounts.token_program.is_some(), StacheError::MissingAccount);
+    // fire an automation, called by clockwork thread - or by hand by a keychain key (ie: to test it outside of
+    // clockwork). remaining accounts: the token account to check the balance of (balance triggers only), then for each
+    // action: from, to, mint, token program, + the vault if it pulls from one. accounts can repeat (ie: the trigger
+    // account is usually also an action's from account)
+    pub fn fire_auto<'a, 'b, 'c, 'info>(ctx: Context<'a, 'b, 'c, 'info, FireAutomation<'info>>) -> Result<ThreadResponse> {
+
+        let manual = ctx.accounts.thread.is_none();
+        if manual {
+            require!(ctx.accounts.keychain.is_some() && ctx.accounts.authority.is_some(), StacheError::MissingAccount);
+            let authority = ctx.accounts.authority.as_ref().unwrap().key();
+            require!(ctx.accounts.keychain.as_ref().unwrap().has_key(&authority), StacheError::NotAuthorized);
+            msg!("manual fire by {}", authority);
+        }
 
-        // either thread (automation) or authority (manual) has to be present
+        let auto = &mut ctx.accounts.auto;
 
-        // todo: if this is a manual fire, then need to check keychain too
-        // require!(ctx.accounts.authority.is_some() || ctx.accounts.thread.is_some(), StacheError::MissingAccount);
+        // stopped/paused automations don't run. the thread just gets parked (an error would only make it retry),
+        // a manual fire gets told why - dry_run_auto is there for trying one out
+        if !auto.active {
+            require!(!manual, StacheError::AutomationInactive);
+            msg!("automation isn't active, not executing");
+            return Ok(auto.stopped_response());
+        }
 
-        let mut should_execute = false;
+        // paused automations don't do anything (the thread should be paused too, but just in case)
+        if auto.paused {
+            require!(!manual, StacheError::AutomationPaused);
+            msg!("automation is paused, not executing");
+            return Ok(ThreadResponse::default());
+        }
 
-        // figure out the balance of the account we need to check
-        let balance_trigger_account_balance = if use_ref {
-            if use_from {
-                ctx.accounts.from_token.amount
-            } else {
-                ctx.accounts.to_token.amount
+        auto.num_triggers = auto.num_triggers.checked_add(1).ok_or(StacheError::TriggerLimit)?;
+        require!(!auto.actions.is_empty(), StacheError::MissingAction);
+
+        let now = Clock::get()?.unix_timestamp;
+        // interval threads get rescheduled to the next interval after every fire
+        let mut next_trigger = None;
+        let accs = &mut ctx.remaining_accounts.iter();
+
+        let should_execute = trigger_met(auto, accs, now)?;
+        emit!(AutoFired {
+            stache: ctx.accounts.stache.key(),
+            auto: auto.key(),
+            manual,
+            trigger_met: should_execute,
+        });
+        match auto.trigger_type {
+            Some(TriggerType::Cron) | Some(TriggerType::Interval) => {
+                // the thread only fires on schedule, but anyone firing it by hand shouldn't be able to get ahead of it
+                require!(should_execute, StacheError::TriggerNotDue);
+
+                if auto.trigger_type == Some(TriggerType::Interval) {
+                    let interval = auto.interval_trigger()?;
+                    next_trigger = Some(Trigger::Timestamp { unix_ts: interval.next_after(now)? });
+                }
             }
-        } else {
-            // then pull from the remaining accounts
-            let accs = &mut ctx.remaining_accounts.iter();
-            let balance_check_account_info = next_account_info(accs)?.to_account_info();
-
-            let balance_account_key = balance_check_account_info.key();
-            // check that the trigger account is the same as the account we're checking
-            require!(balance_account_key == trigger.account, StacheError::InvalidTrigger);
-
-            // and make sure it's not one of the to/from accounts
-            require!(balance_account_key != ctx.accounts.to_token.key(), StacheError::DupeAccount);
-            require!(balance_account_key != ctx.accounts.from_token.key(), StacheError::DupeAccount);
+            _ => {}
+        }
+        auto.last_fired_at = now;
 
-            let account_data = &mut &**balance_check_account_info.try_borrow_mut_data()?;
-            let balance_token_account = TokenAccount::try_deserialize(account_data)?;
-            balance_token_account.amount
+        let response = ThreadResponse {
+            trigger: next_trigger,
+            ..ThreadResponse::default()
         };
 
-        // validate conditions of the trigger - the remaining account passed in needs to be the one specified in the trigger
-        if balance_trigger_account_balance >= trigger.trigger_balance && trigger.above {
-            msg!("balance above trigger condition met. account balance: {}, trigger balance: {}, ", balance_trigger_account_balance, trigger.trigger_balance);
-            should_execute = true;
-        } else if balance_trigger_account_balance < trigger.trigger_balance && !trigger.above {
-            msg!("balance below trigger condition met. account balance: {}, trigger balance: {}", balance_trigger_account_balance, trigger.trigger_balance);
-            should_execute = true;
+        if !should_execute {
+            msg!("automation conditions not met, not executing");
+            return Ok(response);
         }
 
-        let from_token = &ctx.accounts.from_token;
-        let to_token = &ctx.accounts.to_token;
+        let steps = AutoStep::from_remaining_accounts(&auto.actions, accs, &ctx.accounts.stache.key(), &auto.key())?;
 
-        if from_token.amount < action.amount {
-            msg!("insufficient funds to execute action. from token amount: {}, action amount: {}", from_token.amount, action.amount);
-            should_execute = false;
+        // work out every step's amount up front (against the balances the earlier steps leave behind), so either
+        // everything goes through or nothing does
+        let amounts = match AutoStep::plan(&steps, now, auto.remaining_budget())? {
+            Plan::Ready(amounts) => amounts,
+            Plan::InsufficientFunds | Plan::VaultLocked => {
+                msg!("automation can't execute all of its actions, not executing");
+                return Ok(response);
+            }
+        };
+        let total = amounts.iter().try_fold(0u64, |total, amount| total.checked_add(*amount)).ok_or(StacheError::ExecLimit)?;
+
+        // expired or this execution would go over a cap (only fixed amounts can, the rest are trimmed to the budget):
+        // stop instead of executing
+        if auto.is_exhausted(total, now) {
+            msg!("automation hit its limits (execs: {}, total amount: {}), stopping", auto.num_execs, auto.total_amount);
+            auto.stop();
+            emit_stopped(auto);
+            return Ok(auto.stopped_response());
         }
 
-        require!(from_token.amount >= action.amount, StacheError::InsufficientFunds);
+        if total == 0 {
+            msg!("nothing to transfer, not executing");
+            return Ok(response);
+        }
 
-        // if should_trigger {
-        if should_execute {
-            let stache = &ctx.accounts.stache;
-            auto.num_execs = auto.num_execs.checked_add(1).ok_or(StacheError::ExecLimit)?;
+        let stache = &mut ctx.accounts.stache;
+        for (step, amount) in steps.iter().zip(amounts.iter()) {
+            if *amount == 0 {
+                continue;
+            }
 
-            // todo: remove dupe code
+            // automations are held to the stache's (or the vault's) spending limits too
+            match &step.vault {
+                Some(vault) => {
+                    let mut vault = Account::<Vault>::try_from(vault)?;
+                    require!(vault.within_limit(&step.action.mint, *amount, now), StacheError::SpendingLimitExceeded);
+                    vault.record_spend(&step.action.mint, *amount, now);
+                    vault.exit(&ID)?;
+                }
+                None => {
+                    require!(stache.within_limit(&step.action.mint, *amount, now), StacheError::SpendingLimitExceeded);
+                    stache.record_spend(&step.action.mint, *amount, now);
+                }
+            }
 
-            // execute the action
-            let seeds = &[
-                stache.stacheid.as_bytes().as_ref(),
-                BEARD_SPACE.as_bytes().as_ref(),
-                stache.domain.as_ref(),
-                STACHE.as_bytes().as_ref(),
-                &[stache.bump],
-            ];
+            step.transfer(stache, *amount)?;
+        }
 
-            let signer = &[&seeds[..]];
+        auto.num_execs = auto.num_execs.checked_add(1).ok_or(StacheError::ExecLimit)?;
+        auto.total_amount = auto.total_amount.checked_add(total).ok_or(StacheError::ExecLimit)?;
+        msg!("executed automation! {} actions, {} tokens total", steps.len(), total);
+        emit!(AutoExecuted {
+            stache: auto.stache,
+            auto: auto.key(),
+            amounts,
+            num_execs: auto.num_execs,
+            total_amount: auto.total_amount,
+        });
+
+        // don't wait for the next fire to find out this was the last one
+        let next_min_total: u64 = auto.actions.iter().map(|action| action.min_amount()).sum();
+        if auto.is_exhausted(next_min_total, now) {
+            msg!("automation reached its limits (execs: {}, total amount: {}), stopping", auto.num_execs, auto.total_amount);
+            auto.stop();
+            emit_stopped(auto);
+            return Ok(auto.stopped_response());
+        }
 
-            let cpi_accounts = Transfer {
-                from: from_token.to_account_info(),
-                to: to_token.to_account_info(),
-                authority: stache.to_account_info(),
-            };
+        Ok(response)
+    }
 
-            let cpi_ctx = CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                cpi_accounts,
-                signer);
-            token::transfer(cpi_ctx, action.amount)?;
+    // evaluates the automation against the current balances (same remaining accounts as fire_auto) and emits what
+    // firing it would do, without changing anything
+    pub fn dry_run_auto<'a, 'b, 'c, 'info>(ctx: Context<'a, 'b, 'c, 'info, DryRunAutomation<'info>>) -> Result<()> {
+        let stache = &mut ctx.accounts.stache;
+        let auto = &mut ctx.accounts.auto;
+        require!(!auto.actions.is_empty(), StacheError::MissingAction);
 
-            msg!("executed automation! transferred {} tokens to {}", action.amount, to_token.key());
+        let now = Clock::get()?.unix_timestamp;
+        let accs = &mut ctx.remaining_accounts.iter();
+
+        let (outcome, amounts) = if !trigger_met(auto, accs, now)? {
+            (DryRunOutcome::WouldNotExecute, Vec::new())
         } else {
-            msg!("automation conditions not met or insufficient funds, not executing");
-        }
+            let steps = AutoStep::from_remaining_accounts(&auto.actions, accs, &stache.key(), &auto.key())?;
+            match AutoStep::plan(&steps, now, auto.remaining_budget())? {
+                Plan::InsufficientFunds => (DryRunOutcome::InsufficientFunds, Vec::new()),
+                Plan::VaultLocked => (DryRunOutcome::VaultLocked, Vec::new()),
+                Plan::Ready(amounts) => {
+                    let total = amounts.iter().try_fold(0u64, |total, amount| total.checked_add(*amount)).ok_or(StacheError::ExecLimit)?;
+
+                    // the automation's own caps, and the stache/vault spending limits
+                    let mut within_limits = !auto.is_exhausted(total, now);
+                    for (step, amount) in steps.iter().zip(amounts.iter()) {
+                        within_limits = within_limits && match &step.vault {
+                            Some(vault) => Account::<Vault>::try_from(vault)?.within_limit(&step.action.mint, *amount, now),
+                            None => stache.within_limit(&step.action.mint, *amount, now),
+                        };
+                    }
+
+                    let outcome = if !within_limits {
+                        DryRunOutcome::LimitReached
+                    } else if total == 0 {
+                        DryRunOutcome::WouldNotExecute
+                    } else {
+                        DryRunOutcome::WouldExecute
+                    };
+                    (outcome, amounts)
+                }
+            }
+        };
 
+        msg!("dry run: {:?} {:?}", outcome, amounts);
+        emit!(AutoDryRun {
+            stache: stache.key(),
+            auto: auto.key(),
+            outcome,
+            amounts,
+        });
         Ok(())
     }
 
-    // if automated = true, will use the trigger to configure a clockwork thread
-    pub fn activate_auto(ctx: Context<ActivateAutomation>) -> Result<()> {
+    // creates the clockwork thread that fires the automation off the trigger. remaining accounts: the vault for each
+    // action that pulls from one (in action order)
+    pub fn activate_auto<'a, 'b, 'c, 'info>(ctx: Context<'a, 'b, 'c, 'info, ActivateAutomation<'info>>) -> Result<()> {
 
         let auto = &mut ctx.accounts.auto;
+        // a stopped automation still has its (parked) thread, which needs to be deactivated first
+        require!(!auto.active && auto.thread.is_none(), StacheError::AutomationLocked);
+        require!(!auto.actions.is_empty(), StacheError::MissingAction);
         auto.active = true;
+        auto.paused = false;
         auto.thread = Some(ctx.accounts.thread.key());
 
-        let stache = &ctx.accounts.stache;
+        // time triggers count from activation
+        auto.last_fired_at = Clock::get()?.unix_timestamp;
 
-        let trigger = auto.balance_trigger()?;
-        let action = auto.transfer_action()?;
+        let stache = &ctx.accounts.stache;
 
-        // since i'm not sure automations accept remaining accounts, just require that the trigger account is one of the to/from accounts
-        let mut use_from = true;
-        if trigger.account == action.from {
-            use_from = true;
-        } else if trigger.account == action.to {
-            use_from = false;
-        } else {
-            return Err(StacheError::AutomationTriggerAccountMismatch.into());
+        // automations pulling from a vault need the vault to allow it
+        let vaults = &mut ctx.remaining_accounts.iter();
+        for action in auto.actions.iter() {
+            if let Some(vault_key) = action.vault {
+                let vault = Account::<Vault>::try_from(next_account_info(vaults)?)?;
+                require!(vault.key() == vault_key && vault.stache == stache.key(), StacheError::InvalidVault);
+                require!(vault.allows_auto(&auto.key(), action), StacheError::NotAuthorized);
+            }
         }
 
-           // not sure if remaining accounts would be supported here ..?
+        let mut fire_auto_accounts = vec![
+            AccountMeta::new(ctx.accounts.stache.key(), false),
+            AccountMeta::new(auto.key(), false),
+            AccountMeta::new(ctx.accounts.thread.key(), true),
+            // keychain + authority are for manual fires only: the program id stands in for none
+            AccountMeta::new_readonly(ID, false),
+            AccountMeta::new_readonly(ID, false),
+        ];
+
+        let trigger = match auto.trigger_type.clone().ok_or(StacheError::MissingTrigger)? {
+            TriggerType::Balance => {
+                let trigger = auto.balance_trigger()?;
+                fire_auto_accounts.push(AccountMeta::new_readonly(trigger.account, false));
+
+                // watch the amount of a token account
+                Trigger::Account {
+                    address: trigger.account,
+                    offset: 64,
+                    size: 8,
+                }
+            }
+            TriggerType::Cron => {
+                // skippable: if the thread falls behind, don't fire a burst of catch-up payments
+                Trigger::Cron {
+                    schedule: auto.cron_trigger()?.schedule,
+                    skippable: true,
+                }
+            }
+            TriggerType::Interval => {
+                // clockwork doesn't do intervals, so fire at the next one and fire_auto reschedules the thread from there
+                Trigger::Timestamp {
+                    unix_ts: auto.next_fire_at()?,
+                }
+            }
+        };
+
+        for action in auto.actions.iter() {
+            fire_auto_accounts.push(AccountMeta::new(action.from, false));
+            fire_auto_accounts.push(AccountMeta::new(action.to, false));
+            fire_auto_accounts.push(AccountMeta::new_readonly(action.mint, false));
+            fire_auto_accounts.push(AccountMeta::new_readonly(action.token_program, false));
+            if let Some(vault) = action.vault {
+                fire_auto_accounts.push(AccountMeta::new(vault, false));
+            }
+        }
 
         let fire_auto_ix = Instruction {
             program_id: ID,
-            accounts: vec![
-                AccountMeta::new(ctx.accounts.stache.key(), false),
-                AccountMeta::new(auto.key(), false),
-                AccountMeta::new(ctx.accounts.thread.key(), true),
-                AccountMeta::new(action.from, false),
-                AccountMeta::new(action.to, false),
-                AccountMeta::new_readonly(anchor_spl::token::ID, false),
-            ],
-            data: stache::instruction::FireAuto {
-                use_ref: true,
-                use_from,
-            }.data()
+            accounts: fire_auto_accounts,
+            data: stache::instruction::FireAuto {}.data()
         };
 
         let clockwork = &ctx.accounts.clockwork_program;
         let system_program = &ctx.accounts.system_program;
 
-        let binding = auto.index.to_le_bytes();
-        let seeds = &[
-            binding.as_ref(),
-            AUTO_SPACE.as_bytes().as_ref(),
-            stache.stacheid.as_bytes().as_ref(),
-            BEARD_SPACE.as_bytes().as_ref(),
-            stache.domain.as_ref(),
-            STACHE.as_bytes().as_ref(),
-            &[auto.bump]
-        ];
-
-        // todo: change to account trigger
-
         // v2 = now
         // let trigger = Trigger::Now;
 
         // v1.4.2  = immediate
         // let trigger = Trigger::Immediate;
 
-        // watch the amount of a token account
-        let trigger =
-            Trigger::Account {
-                address: trigger.account,
-                offset: 64,
-                size: 8,
-            };
+        // clockwork v2 - thread funding built in this call
+        with_auto_signer(stache, auto, |signer| {
+            clockwork_sdk::cpi::thread_create(
+                CpiContext::new_with_signer(
+                    clockwork.to_account_info(),
+                    clockwork_sdk::cpi::ThreadCreate {
+                        authority: auto.to_account_info(),
+                        payer: ctx.accounts.authority.to_account_info(),
+                        system_program: system_program.to_account_info(),
+                        thread: ctx.accounts.thread.to_account_info(),
+                    },
+                    signer,
+                ),
+                20000000 as u64,
+                // thread id
+                auto.name.clone().into(),
+                // instruction
+                vec![fire_auto_ix.into()],
+                // trigger
+                trigger
+            )
+        })?;
+
+        emit!(AutoActivated {
+            stache: stache.key(),
+            auto: auto.key(),
+            thread: ctx.accounts.thread.key(),
+        });
+        Ok(())
+    }
 
+    /////// MIGRATIONS ///////
 
-        // clockwork v2 - thread funding built in this call
-        /*
-        clockwork_sdk::cpi::thread_create(
-            CpiContext::new_with_signer(
-                clockwork.to_account_info(),
-                clockwork_sdk::cpi::ThreadCreate {
-                    authority: auto.to_account_info(),
-                    payer: ctx.accounts.authority.to_account_info(),
-                    system_program: system_program.to_account_info(),
-                    thread: ctx.accounts.thread.as_ref().unwrap().to_account_info(),
-                },
-                &[seeds],
-                // &[&[SEED_AUTHORITY, &[bump]]],
-            ),
-            10000000 as u64,
-            // thread id
-            auto.name.clone().into(),
-            // instruction
-            vec![fire_auto_ix.into()],
-            // trigger
-            trigger
-        )?;
-         */
-
-        // clockwork v1.4.2
-        clockwork_sdk::cpi::thread_create(
-            CpiContext::new_with_signer(
-                clockwork.to_account_info(),
-                clockwork_sdk::cpi::ThreadCreate {
-                    authority: auto.to_account_info(),
-                    payer: ctx.accounts.authority.to_account_info(),
-                    system_program: system_program.to_account_info(),
-                    thread: ctx.accounts.thread.to_account_info(),
-                },
-                &[seeds],
-                // &[&[SEED_AUTHORITY, &[bump]]],
-            ),
-            // thread id
-            auto.name.clone().into(),
-            // instruction
-            fire_auto_ix.into(),
-            // trigger
-            trigger
-        )?;
-
-        // fund the thread a bit
-        invoke(
-            &system_instruction::transfer(
-                &ctx.accounts.authority.key(),
-                &ctx.accounts.thread.key(),
-                20000000 as u64
-            ),
-            &[
-                ctx.accounts.authority.to_account_info(),
-                ctx.accounts.thread.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
-            ],
-        )?;
+    // converts a stache in an older layout to the current one, in place. the authority pays for any extra space
+    pub fn migrate_stache(ctx: Context<MigrateStache>) -> Result<()> {
+        let info = ctx.accounts.stache.to_account_info();
+        check_account::<CurrentStache>(&info)?;
+
+        let version = info.try_borrow_data()?[8];
+        let stache = match version {
+            CurrentStache::CURRENT_VERSION => return err!(StacheError::AlreadyMigrated),
+            0 => read_account::<StacheV0>(&info)?.migrate(),
+            _ => return err!(StacheError::InvalidVersion),
+        };
+        require_keys_eq!(stache.keychain, ctx.accounts.keychain.key(), StacheError::NotAuthorized);
+
+        write_migrated(&stache,
+                       &info,
+                       8 + CurrentStache::MAX_SIZE,
+                       &ctx.accounts.authority.to_account_info(),
+                       &ctx.accounts.system_program.to_account_info())?;
+
+        emit!(AccountMigrated {
+            stache: info.key(),
+            account: info.key(),
+            from_version: version,
+            to_version: stache.version,
+        });
+        Ok(())
+    }
+
+    // same for a vault; its pending actions carry over
+    pub fn migrate_vault(ctx: Context<MigrateVault>) -> Result<()> {
+        let info = ctx.accounts.vault.to_account_info();
+        check_account::<Vault>(&info)?;
+
+        let version = sub_account_version(&info, VaultV0::MAX_SIZE)?;
+        let vault = match version {
+            Vault::CURRENT_VERSION => return err!(StacheError::AlreadyMigrated),
+            0 => read_account::<VaultV0>(&info)?.migrate(),
+            _ => return err!(StacheError::InvalidVersion),
+        };
+        require_keys_eq!(vault.stache, ctx.accounts.stache.key(), StacheError::InvalidVault);
+
+        write_migrated(&vault,
+                       &info,
+                       8 + Vault::MAX_SIZE,
+                       &ctx.accounts.authority.to_account_info(),
+                       &ctx.accounts.system_program.to_account_info())?;
+
+        emit!(AccountMigrated {
+            stache: vault.stache,
+            account: info.key(),
+            from_version: version,
+            to_version: vault.version,
+        });
+        Ok(())
+    }
+
+    // same for an automation. a v0 transfer needs its from token account passed in, and a v0 thread gets deleted
+    // (it calls the old fire_auto) so the automation has to be activated again
+    pub fn migrate_auto(ctx: Context<MigrateAutomation>) -> Result<()> {
+        let info = ctx.accounts.auto.to_account_info();
+        check_account::<Auto>(&info)?;
+
+        let version = sub_account_version(&info, AutoV0::MAX_SIZE)?;
+        let (auto, old_thread) = match version {
+            Auto::CURRENT_VERSION => return err!(StacheError::AlreadyMigrated),
+            0 => {
+                let legacy = read_account::<AutoV0>(&info)?;
+                let action = match legacy.transfer_action()? {
+                    Some(transfer) => {
+                        let from_token = ctx.accounts.from_token.as_ref().ok_or(StacheError::MissingAccount)?;
+                        require_keys_eq!(from_token.key(), transfer.from, StacheError::TokenAccountsMismatch);
+                        Some(AutoTransferAction {
+                            from: transfer.from,
+                            to: transfer.to,
+                            mint: from_token.mint,
+                            token_program: *from_token.to_account_info().owner,
+                            amount: transfer.amount,
+                            mode: TransferMode::Fixed,
+                            vault: None,
+                        })
+                    }
+                    None => None,
+                };
+                let old_thread = legacy.thread;
+                (legacy.migrate(action), old_thread)
+            }
+            _ => return err!(StacheError::InvalidVersion),
+        };
+        require_keys_eq!(auto.stache, ctx.accounts.stache.key(), StacheError::NotAuthorized);
+
+        // the thread can be from clockwork 1.x (see migration.rs), so it goes through whichever thread program owns it.
+        // a thread that's already gone (deleted, or closed by clockwork) gets skipped
+        let delete_thread = match old_thread {
+            Some(old_thread) => {
+                let thread = ctx.accounts.thread.as_ref().ok_or(StacheError::MissingAccount)?;
+                require_keys_eq!(thread.key(), old_thread, StacheError::InvalidThread);
+                if thread.data_is_empty() {
+                    false
+                } else {
+                    let clockwork_program = ctx.accounts.clockwork_program.as_ref().ok_or(StacheError::MissingAccount)?;
+                    require!(thread.owner == &thread_program_ID || thread.owner == &clockwork_v1::ID, StacheError::InvalidThread);
+                    require_keys_eq!(clockwork_program.key(), *thread.owner, StacheError::InvalidThread);
+                    true
+                }
+            }
+            None => false,
+        };
+
+        write_migrated(&auto,
+                       &info,
+                       8 + Auto::MAX_SIZE,
+                       &ctx.accounts.authority.to_account_info(),
+                       &ctx.accounts.system_program.to_account_info())?;
+
+        // the thread signs w/the automation's pda, so it can only go once the automation is readable again
+        if delete_thread {
+            let migrated = Account::<Auto>::try_from(&info)?;
+            let thread = ctx.accounts.thread.as_ref().unwrap().to_account_info();
+            let clockwork_program = ctx.accounts.clockwork_program.as_ref().unwrap().to_account_info();
+            if thread.owner == &clockwork_v1::ID {
+                with_auto_signer(&ctx.accounts.stache, &migrated, |signer| {
+                    delete_v1_thread(info.clone(), thread, clockwork_program, ctx.accounts.authority.to_account_info(), signer)
+                })?;
+            } else {
+                delete_auto_thread(&ctx.accounts.stache, &migrated, thread, clockwork_program, ctx.accounts.authority.to_account_info())?;
+            }
+        }
 
+        emit!(AccountMigrated {
+            stache: auto.stache,
+            account: info.key(),
+            from_version: version,
+            to_version: auto.version,
+        });
         Ok(())
     }
 
 }
 
-// transfer some tokens out of a vault ata
+// executes an approved vault action. transfers need 3 remaining accounts: from vault ata, to token account, mint.
+// sol transfers need 1: the account receiving the lamports. nft transfers need the accounts listed in NftTransfer,
+// and the payer (the approver/executor) covers the destination ata/token record if they need to be created. cnft
+// transfers need the accounts listed in CnftTransfer followed by the proof against the tree's current root
+pub fn execute_vault_action<'info>(current_stache: &CurrentStache,
+                                   vault: &mut Vault,
+                                   vault_authority: AccountInfo<'info>,
+                                   vault_action: &mut VaultAction,
+                                   remaining_accounts: &[AccountInfo<'info>],
+                                   token_program: AccountInfo<'info>,
+                                   payer: AccountInfo<'info>,
+                                   now: i64) -> Result<()> {
+    match vault_action.action_type {
+        ActionType::Transfer => {
+            let withdraw_vault_action_data = vault_action.transfer_action()?;
+
+            // check that the remaining accounts passed in match
+            let accs = &mut remaining_accounts.iter();
+            let from = next_account_info(accs)?.to_account_info();
+            let to = next_account_info(accs)?.to_account_info();
+            let mint = next_account_info(accs)?.to_account_info();
+
+            require!(from.key() == withdraw_vault_action_data.from, StacheError::InvalidAction);
+            require!(to.key() == withdraw_vault_action_data.to, StacheError::InvalidAction);
+
+            let from_token = InterfaceAccount::<'_, TokenAccount>::try_from(&from)?;
+            require!(from_token.mint == mint.key(), StacheError::TokenAccountsMismatch);
+
+            require!(withdraw_vault_action_data.amount <= from_token.amount, StacheError::InsufficientFunds);
+
+            // approved, so this counts toward the spending limit even if it goes over
+            vault.record_spend(&from_token.mint, withdraw_vault_action_data.amount, now);
+
+            // withdraw
+            transfer_from_vault(current_stache,
+                                vault,
+                                vault_authority,
+                                from,
+                                to,
+                                mint,
+                                withdraw_vault_action_data.amount,
+                                token_program)?;
+        }
+        ActionType::SolTransfer => {
+            let sol_transfer = vault_action.sol_transfer_action()?;
+
+            let accs = &mut remaining_accounts.iter();
+            let to = next_account_info(accs)?.to_account_info();
+            require!(to.key() == sol_transfer.to, StacheError::InvalidAction);
+
+            vault.record_spend(&native_mint::ID, sol_transfer.lamports, now);
+
+            transfer_sol_from_vault(&vault_authority, &to, sol_transfer.lamports)?;
+        }
+        ActionType::NftTransfer => {
+            let nft_transfer = vault_action.nft_transfer_action()?;
+
+            let nft = NftTransfer::from_remaining_accounts(remaining_accounts, &vault_authority, &payer, &token_program)?;
+            require!(nft.mint.key() == nft_transfer.mint, StacheError::InvalidAction);
+            require!(nft.destination_owner.key() == nft_transfer.to, StacheError::InvalidAction);
+
+            vault.record_spend(&nft_transfer.mint, 1, now);
+
+            let binding = vault.index.to_le_bytes();
+            let seeds = &[
+                binding.as_ref(),
+                VAULT_SPACE.as_bytes().as_ref(),
+                current_stache.stacheid.as_bytes().as_ref(),
+                BEARD_SPACE.as_bytes().as_ref(),
+                current_stache.domain.as_ref(),
+                STACHE.as_bytes().as_ref(),
+                &[vault.bump],
+            ];
+            nft.transfer(&[&seeds[..]])?;
+        }
+        ActionType::CnftTransfer => {
+            let cnft_transfer = vault_action.cnft_transfer_action()?;
+
+            let cnft = CnftTransfer::from_remaining_accounts(remaining_accounts, &vault_authority)?;
+            require!(cnft.new_leaf_owner.key() == cnft_transfer.to, StacheError::InvalidAction);
+
+            let args = cnft_transfer.args(current_root(cnft.merkle_tree)?);
+            require!(args.asset_id(&cnft.merkle_tree.key()) == cnft_transfer.asset_id, StacheError::InvalidAction);
+
+            vault.record_spend(&cnft_transfer.asset_id, 1, now);
+
+            let binding = vault.index.to_le_bytes();
+            let seeds = &[
+                binding.as_ref(),
+                VAULT_SPACE.as_bytes().as_ref(),
+                current_stache.stacheid.as_bytes().as_ref(),
+                BEARD_SPACE.as_bytes().as_ref(),
+                current_stache.domain.as_ref(),
+                STACHE.as_bytes().as_ref(),
+                &[vault.bump],
+            ];
+            cnft.transfer(&args, &[&seeds[..]])?;
+        }
+        ActionType::AuthorizeAuto => {
+            let authorized = vault_action.authorize_auto_action()?;
+            msg!("authorized automation {} on vault {}", authorized.auto, vault.name);
+            vault.authorize_auto(authorized)?;
+        }
+        ActionType::SetDelay => {
+            let set_delay = vault_action.set_delay_action()?;
+            vault.withdraw_delay = set_delay.withdraw_delay;
+            msg!("vault withdraw delay set to {} seconds", vault.withdraw_delay);
+        }
+        ActionType::LockUntil => {
+            let lock_until = vault_action.lock_until_action()?;
+            vault.locked_until = lock_until.locked_until;
+            msg!("vault locked until {}", vault.locked_until);
+            emit!(VaultLocked {
+                stache: vault.stache,
+                vault: vault_authority.key(),
+                locked_until: vault.locked_until,
+            });
+        }
+        ActionType::Unlock => {
+            vault.locked = false;
+            vault.locked_until = 0;
+            msg!("unlocked vault {}", vault.name);
+            emit!(VaultUnlocked {
+                stache: vault.stache,
+                vault: vault_authority.key(),
+            });
+        }
+    }
+
+    emit!(ActionExecuted {
+        stache: vault.stache,
+        vault: vault_authority.key(),
+        action_index: vault_action.action_index,
+        action_type: vault_action.action_type.clone(),
+    });
+    Ok(())
+}
+
+// the action the vault just queued up for approvals (see Vault::propose)
+pub fn emit_proposed(vault: &Account<Vault>) {
+    if let Some(action) = vault.actions.last() {
+        emit!(ActionProposed {
+            stache: vault.stache,
+            vault: vault.key(),
+            action_index: action.action_index,
+            action_type: action.action_type.clone(),
+            initiator: action.approvers[0],
+            executable_at: action.executable_at,
+        });
+    }
+}
+
+// an action that didn't need approvals, so never got an index
+pub fn emit_executed(vault: &Account<Vault>, action_type: ActionType) {
+    emit!(ActionExecuted {
+        stache: vault.stache,
+        vault: vault.key(),
+        action_index: 0,
+        action_type,
+    });
+}
+
+// transfer some tokens out of a vault ata. for token-2022 mints w/a transfer fee, the fee comes out of the amount
+// (so the recipient gets less), and the fees withheld in the vault ata keep it open until they're harvested
 pub fn transfer_from_vault<'a, 'b>(current_stache: &CurrentStache,
                                    vault: &Vault,
                                    vault_authority: AccountInfo<'a>,
                                    from_vault_ata: AccountInfo<'b>,
                                    to_token: AccountInfo<'b>,
+                                   mint: AccountInfo<'b>,
                                    amount: u64,
                                    token_program: AccountInfo<'a>) -> Result<()>
         where 'a: 'b, 'b: 'a {
@@ -662,8 +2025,9 @@ pub fn transfer_from_vault<'a, 'b>(current_stache: &CurrentStache,
 
     let signer = &[&seeds[..]];
 
-    let cpi_transfer_accounts = Transfer {
+    let cpi_transfer_accounts = TransferChecked {
         from: from_vault_ata.clone(),
+        mint: mint.clone(),
         to: to_token.clone(),
         authority: vault_authority.clone(),
     };
@@ -671,18 +2035,24 @@ pub fn transfer_from_vault<'a, 'b>(current_stache: &CurrentStache,
         token_program.clone(),
         cpi_transfer_accounts, signer);
 
-    let from_token_account = Account::<'_, TokenAccount>::try_from(&from_vault_ata).unwrap();
+    let from_token_account = InterfaceAccount::<'_, TokenAccount>::try_from(&from_vault_ata)?;
+    let mint_account = InterfaceAccount::<'_, Mint>::try_from(&mint)?;
+    require!(from_token_account.mint == mint.key(), StacheError::TokenAccountsMismatch);
 
     let tokens_available = from_token_account.amount;
     if tokens_available < amount {
         return err!(StacheError::InsufficientFunds);
     }
-    token::transfer(cpi_ctx, amount)?;
+    reject_transfer_hook(&mint)?;
+    // same as automations: a transfer fee that eats the whole amount would move nothing
+    let fee = transfer_fee(&mint, amount)?;
+    require!(amount == 0 || fee < amount, StacheError::InsufficientFunds);
+    token_interface::transfer_checked(cpi_ctx, amount, mint_account.decimals)?;
 
-    msg!("transfered {} tokens from vault ata: {}, to account: {}", amount, from_vault_ata.key(), to_token.key());
+    msg!("transfered {} tokens (fee: {}) from vault ata: {}, to account: {}", amount, fee, from_vault_ata.key(), to_token.key());
 
     // now see if the vault is empty and close if it is
-    if tokens_available == amount {
+    if tokens_available == amount && withheld_fees(&from_vault_ata)? == 0 {
         msg!("closing vault ata: {}", from_vault_ata.key());
 
         let cpi_close_accounts = CloseAccount {
@@ -692,12 +2062,74 @@ pub fn transfer_from_vault<'a, 'b>(current_stache: &CurrentStache,
         };
         let cpi_ctx = CpiContext::new_with_signer(token_program.clone(),
                                                   cpi_close_accounts, signer);
-        token::close_account(cpi_ctx)?;
+        token_interface::close_account(cpi_ctx)?;
     }
 
     Ok(())
 }
 
+pub fn emit_stopped(auto: &Account<Auto>) {
+    emit!(AutoStopped {
+        stache: auto.stache,
+        auto: auto.key(),
+        num_execs: auto.num_execs,
+        total_amount: auto.total_amount,
+    });
+}
+
+// deletes an automation's clockwork thread, sending its rent to close_to
+pub fn delete_auto_thread<'info>(stache: &CurrentStache,
+                                 auto: &Account<'info, Auto>,
+                                 thread: AccountInfo<'info>,
+                                 clockwork_program: AccountInfo<'info>,
+                                 close_to: AccountInfo<'info>) -> Result<()> {
+    with_auto_signer(stache, auto, |signer| {
+        clockwork_sdk::cpi::thread_delete(
+            CpiContext::new_with_signer(
+                clockwork_program,
+                clockwork_sdk::cpi::ThreadDelete {
+                    authority: auto.to_account_info(),
+                    close_to,
+                    thread: thread.clone(),
+                },
+                signer,
+            )
+        )
+    })?;
+    msg!("deleted clockwork thread {}", thread.key());
+    Ok(())
+}
+
+// runs f w/the automation's signer seeds; the automation pda is the authority on its clockwork thread
+pub fn with_auto_signer<T>(stache: &CurrentStache, auto: &Auto, f: impl FnOnce(&[&[&[u8]]]) -> Result<T>) -> Result<T> {
+    let binding = auto.index.to_le_bytes();
+    let seeds = &[
+        binding.as_ref(),
+        AUTO_SPACE.as_bytes().as_ref(),
+        stache.stacheid.as_bytes().as_ref(),
+        BEARD_SPACE.as_bytes().as_ref(),
+        stache.domain.as_ref(),
+        STACHE.as_bytes().as_ref(),
+        &[auto.bump]
+    ];
+    f(&[&seeds[..]])
+}
+
+// lamports the vault holds above its rent-exempt minimum
+pub fn available_vault_lamports(vault: &AccountInfo) -> Result<u64> {
+    let min_rent = Rent::get()?.minimum_balance(8 + Vault::MAX_SIZE);
+    Ok(vault.lamports().saturating_sub(min_rent))
+}
 
+// move lamports out of a vault account - the vault is owned by us so we can just debit it, but never below rent
+pub fn transfer_sol_from_vault(vault: &AccountInfo, to: &AccountInfo, lamports: u64) -> Result<()> {
+    if available_vault_lamports(vault)? < lamports {
+        return err!(StacheError::InsufficientFunds);
+    }
 
+    **vault.try_borrow_mut_lamports()? -= lamports;
+    **to.try_borrow_mut_lamports()? += lamports;
 
+    msg!("transfered {} lamports from vault: {}, to account: {}", lamports, vault.key(), to.key());
+    Ok(())
+}
@@ -0,0 +1,253 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{hash::hash, instruction::Instruction, program::invoke_signed};
+use anchor_lang::system_program::{transfer, Transfer};
+use anchor_lang::Discriminator;
+use anchor_lang::error::ErrorCode;
+use std::io::Cursor;
+
+use crate::account::*;
+use crate::error::StacheError;
+
+// accounts are migrated in place: the old layout gets read, converted to the current one, and the account is resized
+// to fit before it's written back. the layouts below are the ones each older version was written with, and their sizes
+// are fixed (they don't follow the constants, which can change)
+
+/////// V0 - the original layouts ///////
+
+// v0 staches already had a version field (it just never got set)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct StacheV0 {
+    pub version: u8,
+    pub bump: u8,
+    pub keychain: Pubkey,
+    pub domain: Pubkey,
+    pub stacheid: String,
+    pub next_vault_index: u8,
+    pub next_auto_index: u8,
+    pub vaults: Vec<u8>,
+    pub autos: Vec<u8>,
+}
+
+impl StacheV0 {
+    // 1 + 1 + 32 + 32 + 32 + 1 + 1 + (4 + 5 vaults) + (4 + 5 autos) + 128
+    pub const MAX_SIZE: usize = 246;
+
+    pub fn migrate(self) -> CurrentStache {
+        CurrentStache {
+            version: CurrentStache::CURRENT_VERSION,
+            bump: self.bump,
+            keychain: self.keychain,
+            domain: self.domain,
+            stacheid: self.stacheid,
+            next_vault_index: self.next_vault_index,
+            next_auto_index: self.next_auto_index,
+            vaults: self.vaults,
+            autos: self.autos,
+            spending_limits: Vec::new(),
+        }
+    }
+}
+
+// v0 actions could only be transfers, so the action data is a TransferAction
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct VaultActionV0 {
+    pub action_index: u8,
+    pub action_type: ActionType,
+    pub approvers: Vec<Pubkey>,
+    pub action: Vec<u8>,
+}
+
+impl VaultActionV0 {
+    // 1 + 1 + 4 + (32 * 5 approvers) + 128
+    pub const MAX_SIZE: usize = 294;
+
+    pub fn migrate(self) -> VaultAction {
+        VaultAction {
+            action_index: self.action_index,
+            action_type: self.action_type,
+            approvers: self.approvers,
+            action: self.action,
+            executable_at: 0,
+            over_limit: false,
+        }
+    }
+}
+
+// v0 vault types were easy, 2 sig, and squads (same encoding as now)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct VaultV0 {
+    pub stache: Pubkey,
+    pub index: u8,
+    pub bump: u8,
+    pub vault_type: VaultType,
+    pub locked: bool,
+    pub name: String,
+    pub next_action_index: u8,
+    pub actions: Vec<VaultActionV0>,
+}
+
+impl VaultV0 {
+    // 32 + 1 + 1 + (1 + 1 + 32) + 1 + 32 + (4 + 5 actions * 294) + 128
+    pub const MAX_SIZE: usize = 1703;
+
+    pub fn migrate(self) -> Vault {
+        Vault {
+            stache: self.stache,
+            version: Vault::CURRENT_VERSION,
+            index: self.index,
+            bump: self.bump,
+            vault_type: self.vault_type,
+            locked: self.locked,
+            name: self.name,
+            next_action_index: self.next_action_index,
+            actions: self.actions.into_iter().map(|action| action.migrate()).collect(),
+            locked_until: 0,
+            withdraw_delay: 0,
+            locked_by: Pubkey::default(),   // any key can unlock an easy vault locked before v1
+            spending_limits: Vec::new(),
+            mints: Vec::new(),
+            authorized_autos: Vec::new(),
+        }
+    }
+}
+
+// v0 automations had (at most) a single transfer out of the stache ata, and only balance triggers
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct AutoV0 {
+    pub stache: Pubkey,
+    pub index: u8,
+    pub bump: u8,
+    pub active: bool,
+    pub paused: bool,
+    pub num_triggers: u32,
+    pub num_execs: u32,
+    pub thread: Option<Pubkey>,
+    pub name: String,
+    pub action_type: Option<ActionType>,
+    pub action: Option<Vec<u8>>,
+    pub trigger_type: Option<TriggerType>,
+    pub trigger: Option<Vec<u8>>,
+}
+
+impl AutoV0 {
+    // 32 + 1 + 1 + 1 + 1 + 4 + 4 + (1 + 32) + 32 + (1 + 1) + (1 + 1) + 128
+    pub const MAX_SIZE: usize = 241;
+
+    pub fn transfer_action(&self) -> Result<Option<TransferAction>> {
+        match &self.action {
+            Some(action) => Ok(Some(AnchorDeserialize::deserialize(&mut action.as_slice())?)),
+            None => Ok(None),
+        }
+    }
+
+    // v0 transfers didn't store the mint + token program, so they come from the from token account. the thread (if
+    // any) was created to call the v0 fire_auto, so it gets deleted and the automation has to be activated again
+    pub fn migrate(self, action: Option<AutoTransferAction>) -> Auto {
+        Auto {
+            stache: self.stache,
+            version: Auto::CURRENT_VERSION,
+            index: self.index,
+            bump: self.bump,
+            active: false,
+            paused: true,
+            num_triggers: self.num_triggers,
+            num_execs: self.num_execs,
+            thread: None,
+            name: self.name,
+            actions: action.into_iter().collect(),
+            trigger_type: self.trigger_type,
+            trigger: self.trigger,
+            last_fired_at: 0,
+            max_execs: None,
+            max_total_amount: None,
+            expires_at: None,
+            total_amount: 0,
+        }
+    }
+}
+
+/////// CLOCKWORK 1.X ///////
+
+// v0 automations had their threads created by the clockwork 1.x thread program (stache moved to clockwork 2.0 along
+// w/anchor 0.28). 1.x lives at a different address and its threads have a different layout, so the 2.0 sdk can't load
+// or delete them - but its thread_delete takes the same accounts (authority, close_to, thread) under the same name, so
+// migrate_auto calls it directly
+pub mod clockwork_v1 {
+    use anchor_lang::prelude::*;
+    declare_id!("3XXuUFfweXBwFgFfYaejLvZE4cGZiHgKiGfMtdxNzYmv");
+}
+
+pub fn delete_v1_thread<'info>(auto: AccountInfo<'info>,
+                               thread: AccountInfo<'info>,
+                               clockwork_program: AccountInfo<'info>,
+                               close_to: AccountInfo<'info>,
+                               signer: &[&[&[u8]]]) -> Result<()> {
+    let ix = Instruction {
+        program_id: clockwork_v1::ID,
+        accounts: vec![
+            AccountMeta::new_readonly(auto.key(), true),
+            AccountMeta::new(close_to.key(), false),
+            AccountMeta::new(thread.key(), false),
+        ],
+        data: hash(b"global:thread_delete").to_bytes()[..8].to_vec(),
+    };
+    invoke_signed(&ix, &[auto, close_to, thread.clone(), clockwork_program], signer)?;
+    msg!("deleted clockwork 1.x thread {}", thread.key());
+    Ok(())
+}
+
+/////// HELPERS ///////
+
+// checks the account is one of ours, of the given type (the discriminator doesn't change between versions)
+pub fn check_account<T: Discriminator>(info: &AccountInfo) -> Result<()> {
+    require_keys_eq!(*info.owner, crate::ID, ErrorCode::AccountOwnedByWrongProgram);
+    let data = info.try_borrow_data()?;
+    require!(data.len() >= 8 && data[..8] == T::DISCRIMINATOR, ErrorCode::AccountDiscriminatorMismatch);
+    Ok(())
+}
+
+// vaults + automations only got a version (right after the stache) in v1, but every v0 one was created at its v0 size
+pub fn sub_account_version(info: &AccountInfo, v0_size: usize) -> Result<u8> {
+    let data = info.try_borrow_data()?;
+    if data.len() == 8 + v0_size {
+        return Ok(0);
+    }
+    require!(data.len() > 8 + 32, StacheError::InvalidVersion);
+    Ok(data[8 + 32])
+}
+
+// reads an older layout (after the discriminator)
+pub fn read_account<T: AnchorDeserialize>(info: &AccountInfo) -> Result<T> {
+    let data = info.try_borrow_data()?;
+    let account = AnchorDeserialize::deserialize(&mut &data[8..])?;
+    Ok(account)
+}
+
+// resizes the account for the current layout + writes it, the payer covers any extra rent
+pub fn write_migrated<'info, T: AccountSerialize>(account: &T,
+                                                  info: &AccountInfo<'info>,
+                                                  size: usize,
+                                                  payer: &AccountInfo<'info>,
+                                                  system_program: &AccountInfo<'info>) -> Result<()> {
+    let min_rent = Rent::get()?.minimum_balance(size);
+    let top_up = min_rent.saturating_sub(info.lamports());
+    if top_up > 0 {
+        transfer(
+            CpiContext::new(
+                system_program.clone(),
+                Transfer {
+                    from: payer.clone(),
+                    to: info.clone(),
+                },
+            ),
+            top_up,
+        )?;
+    }
+    info.realloc(size, true)?;
+
+    let mut data = info.try_borrow_mut_data()?;
+    let dst: &mut [u8] = &mut data;
+    let mut writer = Cursor::new(dst);
+    account.try_serialize(&mut writer)?;
+    Ok(())
+}
@@ -0,0 +1,237 @@
+use anchor_lang::prelude::*;
+use mpl_token_metadata::instructions::TransferV1CpiBuilder;
+use mpl_bubblegum::instructions::TransferCpiBuilder;
+use mpl_bubblegum::utils::get_asset_id;
+
+use crate::error::StacheError;
+
+// token metadata program
+pub use mpl_token_metadata::ID as TOKEN_METADATA_ID;
+
+// bubblegum program (compressed nfts)
+pub use mpl_bubblegum::ID as BUBBLEGUM_ID;
+
+// everything a metaplex TransferV1 needs. works for regular + programmable nfts: for regular nfts the token records
+// and auth rules aren't needed. compressed nfts aren't token accounts, they go through bubblegum instead (CnftTransfer)
+pub struct NftTransfer<'a, 'info> {
+    pub token: &'a AccountInfo<'info>,
+    pub token_owner: &'a AccountInfo<'info>,
+    pub destination_token: &'a AccountInfo<'info>,
+    pub destination_owner: &'a AccountInfo<'info>,
+    pub mint: &'a AccountInfo<'info>,
+    pub metadata: &'a AccountInfo<'info>,
+    pub edition: Option<&'a AccountInfo<'info>>,
+    pub token_record: Option<&'a AccountInfo<'info>>,
+    pub destination_token_record: Option<&'a AccountInfo<'info>>,
+    pub authority: &'a AccountInfo<'info>,
+    pub payer: &'a AccountInfo<'info>,
+    pub token_metadata_program: &'a AccountInfo<'info>,
+    pub system_program: &'a AccountInfo<'info>,
+    pub sysvar_instructions: &'a AccountInfo<'info>,
+    pub token_program: &'a AccountInfo<'info>,
+    pub associated_token_program: &'a AccountInfo<'info>,
+    pub authorization_rules_program: Option<&'a AccountInfo<'info>>,
+    pub authorization_rules: Option<&'a AccountInfo<'info>>,
+}
+
+impl<'a, 'info> NftTransfer<'a, 'info> {
+
+    // the accounts for an nft vault action, in order: vault token account, destination token account, destination owner,
+    // mint, metadata, edition, vault token record, destination token record, token metadata program, system program,
+    // sysvar instructions, associated token program, auth rules program, auth rules
+    pub const NUM_ACCOUNTS: usize = 14;
+
+    pub fn from_remaining_accounts(remaining_accounts: &'a [AccountInfo<'info>],
+                                   token_owner: &'a AccountInfo<'info>,
+                                   payer: &'a AccountInfo<'info>,
+                                   token_program: &'a AccountInfo<'info>) -> Result<Self> {
+        require!(remaining_accounts.len() >= Self::NUM_ACCOUNTS, StacheError::MissingAccount);
+        let accs = &remaining_accounts[..Self::NUM_ACCOUNTS];
+        Ok(NftTransfer {
+            token: &accs[0],
+            token_owner,
+            destination_token: &accs[1],
+            destination_owner: &accs[2],
+            mint: &accs[3],
+            metadata: &accs[4],
+            edition: Self::optional(&accs[5]),
+            token_record: Self::optional(&accs[6]),
+            destination_token_record: Self::optional(&accs[7]),
+            authority: token_owner,
+            payer,
+            token_metadata_program: &accs[8],
+            system_program: &accs[9],
+            sysvar_instructions: &accs[10],
+            token_program,
+            associated_token_program: &accs[11],
+            authorization_rules_program: Self::optional(&accs[12]),
+            authorization_rules: Self::optional(&accs[13]),
+        })
+    }
+
+    // like metaplex, remaining accounts use the token metadata program id in place of any account that isn't used
+    fn optional(account: &'a AccountInfo<'info>) -> Option<&'a AccountInfo<'info>> {
+        if account.key() == TOKEN_METADATA_ID {
+            None
+        } else {
+            Some(account)
+        }
+    }
+
+    // transfers the nft; signer_seeds are needed when the token owner is the stache or a vault
+    pub fn transfer(&self, signer_seeds: &[&[&[u8]]]) -> Result<()> {
+        require!(self.token_metadata_program.key() == TOKEN_METADATA_ID, StacheError::InvalidNft);
+
+        TransferV1CpiBuilder::new(self.token_metadata_program)
+            .token(self.token)
+            .token_owner(self.token_owner)
+            .destination_token(self.destination_token)
+            .destination_owner(self.destination_owner)
+            .mint(self.mint)
+            .metadata(self.metadata)
+            .edition(self.edition)
+            .token_record(self.token_record)
+            .destination_token_record(self.destination_token_record)
+            .authority(self.authority)
+            .payer(self.payer)
+            .system_program(self.system_program)
+            .sysvar_instructions(self.sysvar_instructions)
+            .spl_token_program(self.token_program)
+            .spl_ata_program(self.associated_token_program)
+            .authorization_rules_program(self.authorization_rules_program)
+            .authorization_rules(self.authorization_rules)
+            .amount(1)
+            .invoke_signed(signer_seeds)?;
+
+        msg!("transferred nft {} to {}", self.mint.key(), self.destination_owner.key());
+        Ok(())
+    }
+}
+
+/////// COMPRESSED NFTS ///////
+
+// the programs bubblegum logs through + keeps its trees in
+pub mod spl_noop {
+    use anchor_lang::prelude::*;
+    declare_id!("noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV");
+}
+
+pub mod spl_account_compression {
+    use anchor_lang::prelude::*;
+    declare_id!("cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK");
+}
+
+// a compressed nft's leaf, as handed out by the das api (getAsset + getAssetProof). the proof nodes themselves are
+// passed in as remaining accounts, and root is the tree root they were made against
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct CnftArgs {
+    pub root: [u8; 32],
+    pub data_hash: [u8; 32],
+    pub creator_hash: [u8; 32],
+    pub nonce: u64,
+    pub index: u32,
+}
+
+impl CnftArgs {
+    pub fn asset_id(&self, merkle_tree: &Pubkey) -> Pubkey {
+        get_asset_id(merkle_tree, self.nonce)
+    }
+}
+
+// everything a bubblegum Transfer needs. the leaf owner (the stache or a vault when taking a cnft out) is a pda, which
+// bubblegum is fine with as long as it signs
+pub struct CnftTransfer<'a, 'info> {
+    pub leaf_owner: &'a AccountInfo<'info>,
+    pub leaf_delegate: &'a AccountInfo<'info>,
+    pub new_leaf_owner: &'a AccountInfo<'info>,
+    pub tree_config: &'a AccountInfo<'info>,
+    pub merkle_tree: &'a AccountInfo<'info>,
+    pub log_wrapper: &'a AccountInfo<'info>,
+    pub compression_program: &'a AccountInfo<'info>,
+    pub system_program: &'a AccountInfo<'info>,
+    pub bubblegum_program: &'a AccountInfo<'info>,
+    pub proof: &'a [AccountInfo<'info>],
+}
+
+impl<'a, 'info> CnftTransfer<'a, 'info> {
+
+    // the accounts for a cnft vault action, in order: new leaf owner, tree config, merkle tree, leaf delegate,
+    // log wrapper, compression program, system program, bubblegum program. everything after that is the proof
+    pub const NUM_ACCOUNTS: usize = 8;
+
+    pub fn from_remaining_accounts(remaining_accounts: &'a [AccountInfo<'info>],
+                                   leaf_owner: &'a AccountInfo<'info>) -> Result<Self> {
+        require!(remaining_accounts.len() >= Self::NUM_ACCOUNTS, StacheError::MissingAccount);
+        let accs = &remaining_accounts[..Self::NUM_ACCOUNTS];
+        Ok(CnftTransfer {
+            leaf_owner,
+            new_leaf_owner: &accs[0],
+            tree_config: &accs[1],
+            merkle_tree: &accs[2],
+            leaf_delegate: &accs[3],
+            log_wrapper: &accs[4],
+            compression_program: &accs[5],
+            system_program: &accs[6],
+            bubblegum_program: &accs[7],
+            proof: &remaining_accounts[Self::NUM_ACCOUNTS..],
+        })
+    }
+
+    // transfers the cnft; signer_seeds are needed when the leaf owner is the stache or a vault
+    pub fn transfer(&self, args: &CnftArgs, signer_seeds: &[&[&[u8]]]) -> Result<()> {
+        require!(self.bubblegum_program.key() == BUBBLEGUM_ID, StacheError::InvalidNft);
+
+        let proof: Vec<(&AccountInfo<'info>, bool, bool)> = self.proof.iter()
+            .map(|node| (node, false, false))
+            .collect();
+
+        TransferCpiBuilder::new(self.bubblegum_program)
+            .tree_config(self.tree_config)
+            .leaf_owner(self.leaf_owner, true)
+            .leaf_delegate(self.leaf_delegate, false)
+            .new_leaf_owner(self.new_leaf_owner)
+            .merkle_tree(self.merkle_tree)
+            .log_wrapper(self.log_wrapper)
+            .compression_program(self.compression_program)
+            .system_program(self.system_program)
+            .root(args.root)
+            .data_hash(args.data_hash)
+            .creator_hash(args.creator_hash)
+            .nonce(args.nonce)
+            .index(args.index)
+            .add_remaining_accounts(&proof)
+            .invoke_signed(signer_seeds)?;
+
+        msg!("transferred cnft {} to {}", args.asset_id(&self.merkle_tree.key()), self.new_leaf_owner.key());
+        Ok(())
+    }
+}
+
+// concurrent merkle tree layout (spl-account-compression): a 56 byte header (account type, header version, max buffer
+// size, max depth, authority, creation slot, padding), then the sequence number, active index + buffer size (u64s),
+// then the changelog buffer. each changelog entry is the root, the path (max depth nodes), and the leaf index + padding
+const TREE_ACCOUNT_TYPE: u8 = 1;
+const TREE_HEADER_SIZE: usize = 56;
+const TREE_MAX_DEPTH_OFFSET: usize = 6;
+const TREE_ACTIVE_INDEX_OFFSET: usize = TREE_HEADER_SIZE + 8;
+const TREE_CHANGELOG_OFFSET: usize = TREE_HEADER_SIZE + 24;
+
+// the tree's current root. vault actions can sit around waiting for approvals while the tree moves on (and old roots
+// fall out of its changelog), so executing a cnft action goes by whatever the root is at that point
+pub fn current_root(merkle_tree: &AccountInfo) -> Result<[u8; 32]> {
+    require!(merkle_tree.owner == &spl_account_compression::ID, StacheError::InvalidNft);
+
+    let data = merkle_tree.try_borrow_data()?;
+    require!(data.len() >= TREE_CHANGELOG_OFFSET && data[0] == TREE_ACCOUNT_TYPE, StacheError::InvalidNft);
+
+    let max_depth = u32::from_le_bytes(data[TREE_MAX_DEPTH_OFFSET..TREE_MAX_DEPTH_OFFSET + 4].try_into().unwrap()) as usize;
+    let active_index = u64::from_le_bytes(data[TREE_ACTIVE_INDEX_OFFSET..TREE_ACTIVE_INDEX_OFFSET + 8].try_into().unwrap()) as usize;
+
+    let changelog_size = 32 * (max_depth + 1) + 8;
+    let start = active_index.checked_mul(changelog_size)
+        .and_then(|offset| offset.checked_add(TREE_CHANGELOG_OFFSET))
+        .ok_or(StacheError::InvalidNft)?;
+    require!(data.len() >= start + 32, StacheError::InvalidNft);
+
+    Ok(data[start..start + 32].try_into().unwrap())
+}
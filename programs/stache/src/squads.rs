@@ -0,0 +1,242 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program::invoke;
+use anchor_lang::solana_program::sysvar::instructions::{load_current_index_checked, load_instruction_at_checked};
+
+use crate::account::{Vault, VaultType};
+use crate::error::StacheError;
+
+// squads v3 (squads-mpl) program
+declare_id!("SMPLecH534NA9acpos4G6x7uf3LWbCAwZQE9e8ZekMu");
+
+pub const SQUAD: &str = "squad";
+pub const MULTISIG: &str = "multisig";
+pub const TRANSACTION: &str = "transaction";
+pub const AUTHORITY: &str = "authority";
+
+// the multisig's default vault authority (squads creates authority 1 as "the vault")
+pub const DEFAULT_AUTHORITY_INDEX: u32 = 1;
+
+// the pda squads signs for when it executes a transaction for the multisig's default authority
+pub fn find_authority(multisig: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[SQUAD.as_bytes().as_ref(), multisig.as_ref(), &DEFAULT_AUTHORITY_INDEX.to_le_bytes(), AUTHORITY.as_bytes().as_ref()],
+        &ID,
+    )
+}
+
+// mirror of the squads v3 multisig account (Ms) - we only ever read it, so no need to pull in the whole squads crate
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct Ms {
+    pub threshold: u16,
+    pub authority_index: u16,
+    pub transaction_index: u32,
+    pub ms_change_index: u32,
+    pub bump: u8,
+    pub create_key: Pubkey,
+    pub allow_external_execute: bool,
+    pub keys: Vec<Pubkey>,
+}
+
+impl Ms {
+
+    // anchor account discriminator for the squads Ms account
+    pub fn discriminator() -> [u8; 8] {
+        let mut discriminator = [0u8; 8];
+        discriminator.copy_from_slice(&hash(b"account:Ms").to_bytes()[..8]);
+        discriminator
+    }
+
+    // loads the multisig and verifies it's a real squads multisig: owned by squads and derived from
+    // seeds = [b"squad", create_key.as_ref(), b"multisig"], bump
+    pub fn load(multisig: &AccountInfo) -> Result<Ms> {
+        require!(multisig.owner == &ID, StacheError::InvalidMultisig);
+
+        let data = multisig.try_borrow_data()?;
+        require!(data.len() > 8 && data[..8] == Self::discriminator(), StacheError::InvalidMultisig);
+        let ms: Ms = AnchorDeserialize::deserialize(&mut &data[8..])?;
+
+        let derived = Pubkey::create_program_address(
+            &[SQUAD.as_bytes().as_ref(), ms.create_key.as_ref(), MULTISIG.as_bytes().as_ref(), &[ms.bump]],
+            &ID,
+        ).map_err(|_| error!(StacheError::InvalidMultisig))?;
+        require!(derived == multisig.key(), StacheError::InvalidMultisig);
+
+        Ok(ms)
+    }
+
+    pub fn is_member(&self, key: &Pubkey) -> bool {
+        self.keys.contains(key)
+    }
+}
+
+// loads the multisig backing a squads vault; None for any other vault type
+pub fn load_vault_multisig(vault: &Vault, multisig: &Option<UncheckedAccount>) -> Result<Option<Ms>> {
+    match &vault.vault_type {
+        VaultType::Squads { multisig: vault_multisig, .. } => {
+            let multisig = multisig.as_ref().ok_or(StacheError::MissingAccount)?;
+            require!(multisig.key() == *vault_multisig, StacheError::InvalidMultisig);
+            Ok(Some(Ms::load(&multisig.to_account_info())?))
+        }
+        _ => Ok(None),
+    }
+}
+
+/////// TRANSACTIONS ///////
+
+/*
+squads vaults can also be run through squads itself: propose_to_squads puts an action in a squads transaction (w/a
+single execute_squads_action instruction for the multisig's default authority), the members approve it in squads, and
+executing it there has the squads authority sign into stache, which releases the action once the squads transaction
+has the approvals the vault needs
+ */
+
+// mirror of the squads v3 transaction account (MsTransaction), again read only
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub enum MsTransactionStatus {
+    Draft,
+    Active,
+    ExecuteReady,
+    Executed,
+    Rejected,
+    Cancelled,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct MsTransaction {
+    pub creator: Pubkey,
+    pub ms: Pubkey,
+    pub transaction_index: u32,
+    pub authority_index: u32,
+    pub authority_bump: u8,
+    pub status: MsTransactionStatus,
+    pub instruction_index: u8,
+    pub bump: u8,
+    pub approved: Vec<Pubkey>,
+    pub rejected: Vec<Pubkey>,
+    pub cancelled: Vec<Pubkey>,
+    pub executed_index: u8,
+}
+
+impl MsTransaction {
+
+    pub fn discriminator() -> [u8; 8] {
+        let mut discriminator = [0u8; 8];
+        discriminator.copy_from_slice(&hash(b"account:MsTransaction").to_bytes()[..8]);
+        discriminator
+    }
+
+    // loads a transaction of the given multisig, verified the same way as the multisig: owned by squads and derived
+    // from seeds = [b"squad", multisig, transaction_index, b"transaction"], bump
+    pub fn load(transaction: &AccountInfo, multisig: &Pubkey) -> Result<MsTransaction> {
+        require!(transaction.owner == &ID, StacheError::InvalidMultisig);
+
+        let data = transaction.try_borrow_data()?;
+        require!(data.len() > 8 && data[..8] == Self::discriminator(), StacheError::InvalidMultisig);
+        let ms_transaction: MsTransaction = AnchorDeserialize::deserialize(&mut &data[8..])?;
+        require!(ms_transaction.ms == *multisig, StacheError::InvalidMultisig);
+
+        let derived = Pubkey::create_program_address(
+            &[SQUAD.as_bytes().as_ref(), multisig.as_ref(), &ms_transaction.transaction_index.to_le_bytes(),
+                TRANSACTION.as_bytes().as_ref(), &[ms_transaction.bump]],
+            &ID,
+        ).map_err(|_| error!(StacheError::InvalidMultisig))?;
+        require!(derived == transaction.key(), StacheError::InvalidMultisig);
+
+        Ok(ms_transaction)
+    }
+
+    // squads only signs for its authority while executing a transaction, but any of the multisig's ready transactions
+    // could be passed in - so it has to be the one the top-level squads instruction is executing (multisig, transaction, ..)
+    pub fn check_executing(transaction: &AccountInfo, sysvar_instructions: &AccountInfo) -> Result<()> {
+        let current = load_instruction_at_checked(usize::from(load_current_index_checked(sysvar_instructions)?), sysvar_instructions)?;
+        require!(current.program_id == ID, StacheError::InvalidMultisig);
+        require!(current.accounts.get(1).map(|meta| meta.pubkey) == Some(transaction.key()), StacheError::InvalidMultisig);
+        Ok(())
+    }
+}
+
+// what squads' add_instruction takes
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct MsAccountMeta {
+    pub pubkey: Pubkey,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct IncomingInstruction {
+    pub program_id: Pubkey,
+    pub keys: Vec<MsAccountMeta>,
+    pub data: Vec<u8>,
+}
+
+impl From<Instruction> for IncomingInstruction {
+    fn from(instruction: Instruction) -> Self {
+        IncomingInstruction {
+            program_id: instruction.program_id,
+            keys: instruction.accounts.into_iter().map(|meta| MsAccountMeta {
+                pubkey: meta.pubkey,
+                is_signer: meta.is_signer,
+                is_writable: meta.is_writable,
+            }).collect(),
+            data: instruction.data,
+        }
+    }
+}
+
+// the squads instructions propose_to_squads goes through. the creator (a member) signs the stache instruction, and that
+// signature carries over into these
+pub struct SquadsTransaction<'a, 'info> {
+    pub multisig: &'a AccountInfo<'info>,
+    pub transaction: &'a AccountInfo<'info>,
+    pub instruction: &'a AccountInfo<'info>,
+    pub creator: &'a AccountInfo<'info>,
+    pub system_program: &'a AccountInfo<'info>,
+    pub squads_program: &'a AccountInfo<'info>,
+}
+
+impl<'a, 'info> SquadsTransaction<'a, 'info> {
+
+    // create_transaction + add_instruction + activate_transaction, so the members can start approving right away
+    pub fn propose(&self, incoming_instruction: IncomingInstruction) -> Result<()> {
+        self.invoke("create_transaction",
+                    DEFAULT_AUTHORITY_INDEX.try_to_vec()?,
+                    vec![
+                        AccountMeta::new(self.multisig.key(), false),
+                        AccountMeta::new(self.transaction.key(), false),
+                        AccountMeta::new(self.creator.key(), true),
+                        AccountMeta::new_readonly(self.system_program.key(), false),
+                    ])?;
+        self.invoke("add_instruction",
+                    incoming_instruction.try_to_vec()?,
+                    vec![
+                        AccountMeta::new_readonly(self.multisig.key(), false),
+                        AccountMeta::new(self.transaction.key(), false),
+                        AccountMeta::new(self.instruction.key(), false),
+                        AccountMeta::new(self.creator.key(), true),
+                        AccountMeta::new_readonly(self.system_program.key(), false),
+                    ])?;
+        self.invoke("activate_transaction",
+                    Vec::new(),
+                    vec![
+                        AccountMeta::new_readonly(self.multisig.key(), false),
+                        AccountMeta::new(self.transaction.key(), false),
+                        AccountMeta::new(self.creator.key(), true),
+                    ])?;
+        msg!("proposed squads transaction {}", self.transaction.key());
+        Ok(())
+    }
+
+    fn invoke(&self, name: &str, args: Vec<u8>, accounts: Vec<AccountMeta>) -> Result<()> {
+        require!(self.squads_program.key() == ID, StacheError::InvalidMultisig);
+
+        let mut data = hash(format!("global:{}", name).as_bytes()).to_bytes()[..8].to_vec();
+        data.extend(args);
+        invoke(&Instruction { program_id: ID, accounts, data },
+               &[self.multisig.clone(), self.transaction.clone(), self.instruction.clone(), self.creator.clone(),
+                   self.system_program.clone(), self.squads_program.clone()])?;
+        Ok(())
+    }
+}
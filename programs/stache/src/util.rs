@@ -1,3 +1,17 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{
+        transfer_fee::{TransferFeeAmount, TransferFeeConfig},
+        transfer_hook::TransferHook,
+        BaseStateWithExtensions, StateWithExtensions,
+    },
+    state,
+};
+use crate::error::StacheError;
+use chrono::DateTime;
+use clockwork_cron::Schedule;
+use std::str::FromStr;
 
 // checks that a given string contains only lowercase letters and numbers
 pub fn is_valid_name(s: &str, require_lower: bool) -> bool {
@@ -7,3 +21,53 @@ pub fn is_valid_name(s: &str, require_lower: bool) -> bool {
     }
     s.chars().all(|c| !c.is_whitespace()  && (c.is_ascii_lowercase() || c.is_ascii_digit() || (!require_lower && c.is_ascii_uppercase())))
 }
+
+// the token-2022 transfer fee that comes out of transferring the given amount (0 for regular spl mints)
+pub fn transfer_fee(mint: &AccountInfo, amount: u64) -> Result<u64> {
+    if mint.owner != &spl_token_2022::ID {
+        return Ok(0);
+    }
+    let data = mint.try_borrow_data()?;
+    let mint = StateWithExtensions::<state::Mint>::unpack(&data)?;
+    let fee = match mint.get_extension::<TransferFeeConfig>() {
+        Ok(fee_config) => fee_config.calculate_epoch_fee(Clock::get()?.epoch, amount).unwrap_or(0),
+        Err(_) => 0,
+    };
+    Ok(fee)
+}
+
+// token-2022 transfer hooks need the hook program's extra accounts passed along on every transfer, which stache doesn't
+// do, so mints w/a hook get turned away up front instead of failing somewhere inside the token program
+pub fn reject_transfer_hook(mint: &AccountInfo) -> Result<()> {
+    if mint.owner != &spl_token_2022::ID {
+        return Ok(());
+    }
+    let data = mint.try_borrow_data()?;
+    let mint = StateWithExtensions::<state::Mint>::unpack(&data)?;
+    if let Ok(transfer_hook) = mint.get_extension::<TransferHook>() {
+        require!(Option::<Pubkey>::from(transfer_hook.program_id).is_none(), StacheError::TransferHookNotSupported);
+    }
+    Ok(())
+}
+
+// fees withheld in a token-2022 account - an account can't be closed until they're harvested
+pub fn withheld_fees(token_account: &AccountInfo) -> Result<u64> {
+    if token_account.owner != &spl_token_2022::ID {
+        return Ok(0);
+    }
+    let data = token_account.try_borrow_data()?;
+    let account = StateWithExtensions::<state::Account>::unpack(&data)?;
+    let withheld = match account.get_extension::<TransferFeeAmount>() {
+        Ok(fee_amount) => u64::from(fee_amount.withheld_amount),
+        Err(_) => 0,
+    };
+    Ok(withheld)
+}
+
+// the next time a clockwork cron schedule fires after the given unix timestamp (same parsing clockwork uses for its
+// cron triggers, so we agree with the thread on when it's due)
+pub fn next_cron_timestamp(schedule: &str, after: i64) -> Option<i64> {
+    let schedule = Schedule::from_str(schedule).ok()?;
+    let after = DateTime::from_timestamp(after, 0)?;
+    schedule.next_after(&after).map(|next| next.timestamp())
+}
@@ -1,6 +1,7 @@
-import * as anchor from "@project-serum/anchor";
-import {Idl, Program, Wallet, web3} from "@project-serum/anchor";
+import * as anchor from "@coral-xyz/anchor";
+import {Idl, Program, Wallet, web3} from "@coral-xyz/anchor";
 import { Stache } from "../target/types/stache";
+import { SquadsMock } from "../target/types/squads_mock";
 
 import { execSync } from "child_process";
 
@@ -12,22 +13,25 @@ import {
   PublicKey,
   sendAndConfirmTransaction,
   SystemProgram,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
   Transaction
 } from "@solana/web3.js";
 import {
-  createNFTMint, createTokenMint,
+  createNFTMint, createTokenMint, createTransferFeeMint, createProgrammableNft,
+  findMetadataPda, findEditionPda, findTokenRecordPda, TokenMetadataProgId,
   findStachePda,
   findDomainPda,
   findDomainStatePda,
   findKeychainKeyPda,
   findKeychainPda,
-  findKeychainStatePda, findVaultPda, findAutoPda, findThreadPda
+  findKeychainStatePda, findVaultPda, findAutoPda, findThreadPda, findSquadsMultisigPda, findUnwrapPda
 } from "./utils";
 import * as assert from "assert";
 import {
   ASSOCIATED_TOKEN_PROGRAM_ID,
   createAssociatedTokenAccount,
   createAssociatedTokenAccountInstruction,
+  createAssociatedTokenAccountIdempotentInstruction,
   createMint,
   createMintToCheckedInstruction,
   createTransferCheckedInstruction,
@@ -35,7 +39,7 @@ import {
   getAccount,
   getAssociatedTokenAddress,
   getAssociatedTokenAddressSync,
-  mintToChecked, TOKEN_PROGRAM_ID,
+  mintToChecked, NATIVE_MINT, TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID,
   transferChecked
 } from "@solana/spl-token";
 import {expect} from "chai";
@@ -81,6 +85,7 @@ describe("stache", () => {
   anchor.setProvider(provider);
 
   const stacheProgram = anchor.workspace.Stache as Program<Stache>;
+  const squadsProgram = anchor.workspace.SquadsMock as Program<SquadsMock>;
   const keychainProgram = new Program(KeychainIdl, KeychainIdl.metadata.address, provider);
 
   let userKeychainPda: PublicKey;
@@ -97,6 +102,9 @@ describe("stache", () => {
   let vaultPdaBump: number;
   let vaultAta: PublicKey;
   let easyVaultAta: PublicKey;
+  let squadsVaultPda: PublicKey;
+  let squadsVaultAta: PublicKey;
+  let squadsMultisigPda: PublicKey;
   let key2: Keypair = Keypair.generate();
 
   // for admin stuff
@@ -302,9 +310,10 @@ describe("stache", () => {
       mint: mint.publicKey,
       owner: provider.wallet.publicKey,
       toToken: userAta,
+      unwrapToken: null,
+      systemProgram: SystemProgram.programId,
       tokenProgram: TOKEN_PROGRAM_ID,
       associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
-      rent: anchor.web3.SYSVAR_RENT_PUBKEY,
     }).transaction();
 
     txid = await provider.sendAndConfirm(tx);
@@ -334,6 +343,120 @@ describe("stache", () => {
 
   });
 
+  it('wraps and unwraps sol', async () => {
+
+    const stacheWsolAta = getAssociatedTokenAddressSync(NATIVE_MINT, stachePda, true);
+    const [unwrapPda] = findUnwrapPda(stachePda, stacheProgram.programId);
+
+    // no from token: the sol gets wrapped into the stache's wsol ata (created if needed)
+    await stacheProgram.methods.stash(new anchor.BN(2 * LAMPORTS_PER_SOL)).accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      stacheAta: stacheWsolAta,
+      mint: NATIVE_MINT,
+      owner: provider.wallet.publicKey,
+      fromToken: null,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+    }).rpc();
+
+    let wsolBalance = await connection.getTokenAccountBalance(stacheWsolAta);
+    expect(wsolBalance.value.uiAmount).to.equal(2);
+
+    const unwrap = (lamports: number, unwrapToken: PublicKey | null) => stacheProgram.methods.unstash(new anchor.BN(lamports)).accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      stacheAta: stacheWsolAta,
+      mint: NATIVE_MINT,
+      owner: provider.wallet.publicKey,
+      toToken: null,
+      unwrapToken,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+    }).rpc();
+
+    // partial unwrap goes through the temporary unwrap account, which gets closed right away
+    let userBalance = await connection.getBalance(provider.wallet.publicKey);
+    await unwrap(LAMPORTS_PER_SOL, unwrapPda);
+    wsolBalance = await connection.getTokenAccountBalance(stacheWsolAta);
+    expect(wsolBalance.value.uiAmount).to.equal(1);
+    expect(await connection.getAccountInfo(unwrapPda)).to.be.null;
+    expect(await connection.getBalance(provider.wallet.publicKey)).to.be.greaterThan(userBalance + 0.99 * LAMPORTS_PER_SOL);
+
+    // can't do a partial unwrap w/out the unwrap account
+    try {
+      await unwrap(LAMPORTS_PER_SOL / 2, null);
+      assert.fail("shouldn't be able to partially unwrap w/out the unwrap account");
+    } catch (e) {
+      expect(e.error.errorCode.code).to.equal('MissingAccount');
+    }
+
+    // unwrapping everything closes the stache's wsol ata
+    await unwrap(LAMPORTS_PER_SOL, null);
+    expect(await connection.getAccountInfo(stacheWsolAta)).to.be.null;
+  });
+
+  it('enforces stache spending limits', async () => {
+
+    // 10 tokens a day
+    await stacheProgram.methods.setSpendingLimit(mint.publicKey, new anchor.BN(10 * 1e9), 24 * 60 * 60).accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      vault: null,
+      authority: provider.wallet.publicKey,
+      cosigner: null,
+    }).rpc();
+
+    const unstash = (amount: number) => stacheProgram.methods.unstash(new anchor.BN(amount * 1e9)).accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      stacheAta: stacheMintAta,
+      mint: mint.publicKey,
+      owner: provider.wallet.publicKey,
+      toToken: userAta,
+      unwrapToken: null,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+    }).rpc();
+
+    await unstash(6);
+    try {
+      await unstash(6);
+      assert.fail("shouldn't be able to go over the spending limit");
+    } catch (e) {
+      expect(e.error.errorCode.code).to.equal('SpendingLimitExceeded');
+    }
+
+    // loosening the limit needs a 2nd key
+    try {
+      await stacheProgram.methods.removeSpendingLimit(mint.publicKey).accounts({
+        stache: stachePda,
+        keychain: userKeychainPda,
+        vault: null,
+        authority: provider.wallet.publicKey,
+        cosigner: null,
+      }).rpc();
+      assert.fail("shouldn't be able to remove a spending limit without a cosigner");
+    } catch (e) {
+      expect(e.error.errorCode.code).to.equal('InvalidCosigner');
+    }
+
+    await stacheProgram.methods.removeSpendingLimit(mint.publicKey).accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      vault: null,
+      authority: provider.wallet.publicKey,
+      cosigner: key2.publicKey,
+    }).signers([key2]).rpc();
+
+    await unstash(6);
+    let stache = await stacheProgram.account.currentStache.fetch(stachePda);
+    expect(stache.spendingLimits.length).to.equal(0);
+  });
+
   it('creates vaults', async () => {
 
       // first vault index = 1
@@ -348,6 +471,7 @@ describe("stache", () => {
         keychain: userKeychainPda,
         vault: vaultPda,
         authority: provider.wallet.publicKey,
+        multisig: null,
         systemProgram: SystemProgram.programId,
       }).rpc();
 
@@ -358,6 +482,7 @@ describe("stache", () => {
         keychain: userKeychainPda,
         vault: easyVaultPda,
         authority: provider.wallet.publicKey,
+        multisig: null,
         systemProgram: SystemProgram.programId,
       }).rpc();
 
@@ -389,19 +514,226 @@ describe("stache", () => {
       vaultTokenBalance = await connection.getTokenAccountBalance(easyVaultAta);
       console.log(`easy vault token balance: ${vaultTokenBalance.value.uiAmount}`);
 
-      // mimic squads vault creation
+      // squads vault, backed by a 2 of 2 multisig (created w/the squads stand-in program)
       let squadsVaultName = 'squads';
-      let squadsPda = Keypair.generate().publicKey;
-      let [squadsVaultPda] = findVaultPda(3, username, domainPda, stacheProgram.programId);
-      txid = await stacheProgram.methods.createVault(squadsVaultName, {squads: {multisig: squadsPda, sigs: 2}}).accounts({
+      let createKey = Keypair.generate().publicKey;
+      [squadsMultisigPda] = findSquadsMultisigPda(createKey);
+      txid = await squadsProgram.methods.create(2, createKey, [key2.publicKey, admin.publicKey]).accounts({
+        multisig: squadsMultisigPda,
+        creator: provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      }).rpc();
+
+      console.log(`created squads multisig @ ${squadsMultisigPda} in tx: ${txid}`);
+
+      [squadsVaultPda] = findVaultPda(3, username, domainPda, stacheProgram.programId);
+      squadsVaultAta = getAssociatedTokenAddressSync(mint.publicKey, squadsVaultPda, true);
+      txid = await stacheProgram.methods.createVault(squadsVaultName, {squads: {multisig: squadsMultisigPda, sigs: 2}}).accounts({
         stache: stachePda,
         keychain: userKeychainPda,
         vault: squadsVaultPda,
         authority: provider.wallet.publicKey,
+        multisig: squadsMultisigPda,
         systemProgram: SystemProgram.programId,
       }).rpc();
 
-      console.log(`created squads vault for ms @ ${squadsPda} >>>> ${vaultPda} <<<< bump: ${vaultPdaBump} in tx: ${txid}`);
+      console.log(`created squads vault for ms @ ${squadsMultisigPda} >>>> ${squadsVaultPda} <<<< in tx: ${txid}`);
+
+      tx = new Transaction().add(
+          createAssociatedTokenAccountInstruction(provider.wallet.publicKey, squadsVaultAta, squadsVaultPda, mint.publicKey),
+          createTransferCheckedInstruction(userAta, mint.publicKey, squadsVaultAta, provider.wallet.publicKey, 5 * 1e9, 9)
+      );
+      txid = await provider.sendAndConfirm(tx);
+      console.log(`deposited 5 tokens into squads vault, txid: ${txid}`);
+
+      // a squads vault pointing at something that isn't a squads multisig should fail
+      let [badVaultPda] = findVaultPda(4, username, domainPda, stacheProgram.programId);
+      let fakeMultisig = Keypair.generate().publicKey;
+      try {
+        await stacheProgram.methods.createVault('badsquads', {squads: {multisig: fakeMultisig, sigs: 2}}).accounts({
+          stache: stachePda,
+          keychain: userKeychainPda,
+          vault: badVaultPda,
+          authority: provider.wallet.publicKey,
+          multisig: fakeMultisig,
+          systemProgram: SystemProgram.programId,
+        }).rpc();
+        assert.fail("shouldn't be able to create a squads vault without a squads multisig");
+      } catch (e) {
+        expect(e.error.errorCode.code).to.equal('InvalidMultisig');
+      }
+  });
+
+  it('deposits into a vault', async () => {
+
+    const deposit = (fromToken: PublicKey, amount: number) => stacheProgram.methods.depositToVault(new anchor.BN(amount * 1e9)).accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      vault: easyVaultPda,
+      vaultAta: easyVaultAta,
+      mint: mint.publicKey,
+      authority: provider.wallet.publicKey,
+      fromToken,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+    }).rpc();
+
+    // from the user's wallet, and then from the stache
+    await deposit(userAta, 2);
+    await deposit(stacheMintAta, 1);
+    let vaultTokenBalance = await connection.getTokenAccountBalance(easyVaultAta);
+    expect(vaultTokenBalance.value.uiAmount).to.equal(8);
+
+    // only allow some other mint
+    const setMints = (mints: PublicKey[]) => stacheProgram.methods.setVaultMints(mints).accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      vault: easyVaultPda,
+      authority: provider.wallet.publicKey,
+    }).rpc();
+
+    await setMints([Keypair.generate().publicKey]);
+    try {
+      await deposit(userAta, 1);
+      assert.fail("shouldn't be able to deposit a mint that isn't allowed");
+    } catch (e) {
+      expect(e.error.errorCode.code).to.equal('MintNotAllowed');
+    }
+
+    await setMints([mint.publicKey]);
+    await deposit(userAta, 1);
+    await setMints([]);
+
+    // back to where we were
+    await stacheProgram.methods.withdrawFromVault(new anchor.BN(4 * 1e9)).accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      vault: easyVaultPda,
+      authority: provider.wallet.publicKey,
+      vaultAta: easyVaultAta,
+      mint: mint.publicKey,
+      toToken: userAta,
+      multisig: null,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+    }).rpc();
+  });
+
+  it('moves tokens between the stache and vaults', async () => {
+
+    const internalTransfer = (fromVault: PublicKey | null, fromToken: PublicKey, toVault: PublicKey | null, toToken: PublicKey, amount: number) =>
+        stacheProgram.methods.internalTransfer(new anchor.BN(amount * 1e9)).accounts({
+          stache: stachePda,
+          keychain: userKeychainPda,
+          fromVault,
+          toVault,
+          fromToken,
+          toToken,
+          mint: mint.publicKey,
+          authority: provider.wallet.publicKey,
+          multisig: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        }).rpc();
+
+    // stache -> easy vault -> stache is immediate
+    await internalTransfer(null, stacheMintAta, easyVaultPda, easyVaultAta, 1);
+    let vaultTokenBalance = await connection.getTokenAccountBalance(easyVaultAta);
+    expect(vaultTokenBalance.value.uiAmount).to.equal(6);
+    await internalTransfer(easyVaultPda, easyVaultAta, null, stacheMintAta, 1);
+    vaultTokenBalance = await connection.getTokenAccountBalance(easyVaultAta);
+    expect(vaultTokenBalance.value.uiAmount).to.equal(5);
+
+    // leaving the 2-sig vault becomes a vault action
+    await internalTransfer(vaultPda, vaultAta, easyVaultPda, easyVaultAta, 1);
+    let vault = await stacheProgram.account.vault.fetch(vaultPda);
+    expect(vault.actions.length).to.equal(1);
+    vaultTokenBalance = await connection.getTokenAccountBalance(vaultAta);
+    expect(vaultTokenBalance.value.uiAmount).to.equal(5);
+
+    await stacheProgram.methods.denyAction(vault.actions[0].actionIndex).accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      vault: vaultPda,
+      authority: provider.wallet.publicKey,
+    }).rpc();
+  });
+
+  it('handles token-2022 mints with transfer fees', async () => {
+
+    // 1% transfer fee
+    const feeMint = await createTransferFeeMint(connection, admin, provider.wallet.publicKey, 100);
+    const userFeeAta = await createAssociatedTokenAccount(connection, admin, feeMint.publicKey, provider.wallet.publicKey, undefined, TOKEN_2022_PROGRAM_ID);
+    await provider.sendAndConfirm(new Transaction().add(
+        createMintToCheckedInstruction(feeMint.publicKey, userFeeAta, provider.wallet.publicKey, 1000 * 1e9, 9, [], TOKEN_2022_PROGRAM_ID)
+    ));
+
+    const stacheFeeAta = getAssociatedTokenAddressSync(feeMint.publicKey, stachePda, true, TOKEN_2022_PROGRAM_ID);
+    const vaultFeeAta = getAssociatedTokenAddressSync(feeMint.publicKey, easyVaultPda, true, TOKEN_2022_PROGRAM_ID);
+
+    // stash 100: the stache gets 99
+    await stacheProgram.methods.stash(new anchor.BN(100 * 1e9)).accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      stacheAta: stacheFeeAta,
+      mint: feeMint.publicKey,
+      owner: provider.wallet.publicKey,
+      fromToken: userFeeAta,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_2022_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+    }).rpc();
+    let balance = await connection.getTokenAccountBalance(stacheFeeAta);
+    expect(balance.value.uiAmount).to.equal(99);
+
+    // into the easy vault: 50 in, 49.5 arrives
+    await stacheProgram.methods.depositToVault(new anchor.BN(50 * 1e9)).accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      vault: easyVaultPda,
+      vaultAta: vaultFeeAta,
+      mint: feeMint.publicKey,
+      authority: provider.wallet.publicKey,
+      fromToken: stacheFeeAta,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_2022_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+    }).rpc();
+    balance = await connection.getTokenAccountBalance(vaultFeeAta);
+    expect(balance.value.uiAmount).to.equal(49.5);
+
+    // emptying the vault ata: the fees withheld from the deposit keep it from being closed
+    await stacheProgram.methods.withdrawFromVault(new anchor.BN(49.5 * 1e9)).accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      vault: easyVaultPda,
+      authority: provider.wallet.publicKey,
+      vaultAta: vaultFeeAta,
+      mint: feeMint.publicKey,
+      toToken: userFeeAta,
+      multisig: null,
+      tokenProgram: TOKEN_2022_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+    }).rpc();
+    balance = await connection.getTokenAccountBalance(vaultFeeAta);
+    expect(balance.value.uiAmount).to.equal(0);
+    expect(await connection.getAccountInfo(vaultFeeAta)).to.not.be.null;
+
+    // and out of the stache
+    await stacheProgram.methods.unstash(new anchor.BN(49 * 1e9)).accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      stacheAta: stacheFeeAta,
+      mint: feeMint.publicKey,
+      owner: provider.wallet.publicKey,
+      toToken: userFeeAta,
+      unwrapToken: null,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_2022_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+    }).rpc();
+    balance = await connection.getTokenAccountBalance(stacheFeeAta);
+    expect(balance.value.uiAmount).to.equal(0);
   });
 
   it('withdraws tokens from a easy vault', async () => {
@@ -414,6 +746,7 @@ describe("stache", () => {
       vaultAta: easyVaultAta,
       mint: mint.publicKey,
       toToken: userAta,
+      multisig: null,
       tokenProgram: TOKEN_PROGRAM_ID,
       associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
     }).rpc();
@@ -428,6 +761,103 @@ describe("stache", () => {
     expect(vaultTokenBalance.value.uiAmount).to.equal(2);
   });
 
+  it('time-locks an easy vault and delays its withdrawals', async () => {
+
+    // savings vault - next vault index
+    let stache = await stacheProgram.account.currentStache.fetch(stachePda);
+    let [savingsVaultPda] = findVaultPda(stache.nextVaultIndex, username, domainPda, stacheProgram.programId);
+    let savingsVaultAta = getAssociatedTokenAddressSync(mint.publicKey, savingsVaultPda, true);
+
+    await stacheProgram.methods.createVault('savings', {easy: {}}).accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      vault: savingsVaultPda,
+      authority: provider.wallet.publicKey,
+      multisig: null,
+      systemProgram: SystemProgram.programId,
+    }).rpc();
+
+    await provider.sendAndConfirm(new Transaction().add(
+        createAssociatedTokenAccountInstruction(provider.wallet.publicKey, savingsVaultAta, savingsVaultPda, mint.publicKey),
+        createTransferCheckedInstruction(userAta, mint.publicKey, savingsVaultAta, provider.wallet.publicKey, 2 * 1e9, 9)
+    ));
+
+    const withdraw = () => stacheProgram.methods.withdrawFromVault(new anchor.BN(1e9)).accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      vault: savingsVaultPda,
+      authority: provider.wallet.publicKey,
+      vaultAta: savingsVaultAta,
+      mint: mint.publicKey,
+      toToken: userAta,
+      multisig: null,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+    });
+
+    // lock it for a couple seconds
+    let now = Math.floor(Date.now() / 1000);
+    await stacheProgram.methods.lockVaultUntil(new anchor.BN(now + 2)).accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      vault: savingsVaultPda,
+      authority: provider.wallet.publicKey,
+    }).rpc();
+
+    try {
+      await withdraw().rpc();
+      assert.fail("shouldn't be able to withdraw from a time-locked vault");
+    } catch (e) {
+      expect(e.error.errorCode.code).to.equal('VaultLocked');
+    }
+
+    // wait out the time-lock, then put a 1 day cooldown on withdrawals
+    await new Promise(resolve => setTimeout(resolve, 4000));
+    await stacheProgram.methods.setVaultDelay(24 * 60 * 60).accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      vault: savingsVaultPda,
+      authority: provider.wallet.publicKey,
+    }).rpc();
+
+    // withdrawals now get queued
+    await withdraw().rpc();
+    let vault = await stacheProgram.account.vault.fetch(savingsVaultPda);
+    expect(vault.actions.length).to.equal(1);
+    let actionIndex = vault.actions[0].actionIndex;
+
+    try {
+      await stacheProgram.methods.executeAction(actionIndex).accounts({
+        stache: stachePda,
+        keychain: userKeychainPda,
+        vault: savingsVaultPda,
+        authority: provider.wallet.publicKey,
+        multisig: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      }).remainingAccounts([
+        {pubkey: savingsVaultAta, isWritable: true, isSigner: false},
+        {pubkey: userAta, isWritable: true, isSigner: false},
+        {pubkey: mint.publicKey, isWritable: false, isSigner: false},
+      ]).rpc();
+      assert.fail("shouldn't be able to execute before the cooldown is up");
+    } catch (e) {
+      expect(e.error.errorCode.code).to.equal('ActionNotExecutable');
+    }
+
+    // any key on the keychain can cancel it during the cooldown
+    await stacheProgram.methods.denyAction(actionIndex).accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      vault: savingsVaultPda,
+      authority: key2.publicKey,
+    }).signers([key2]).rpc();
+
+    vault = await stacheProgram.account.vault.fetch(savingsVaultPda);
+    expect(vault.actions.length).to.equal(0);
+    let vaultTokenBalance = await connection.getTokenAccountBalance(savingsVaultAta);
+    expect(vaultTokenBalance.value.uiAmount).to.equal(2);
+  });
+
   it('withdraws tokens from a 2-sig vault', async () => {
 
     let withdrawAmount = 5*1e9;  // the amount we deposited into the vault
@@ -439,6 +869,7 @@ describe("stache", () => {
       vaultAta,
       mint: mint.publicKey,
       toToken: userAta,
+      multisig: null,
       tokenProgram: TOKEN_PROGRAM_ID,
       associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
     }).rpc();
@@ -452,17 +883,19 @@ describe("stache", () => {
 
     // now we need to approve the action with the other key
 
-    txid = await stacheProgram.methods.approveAction(1).accounts({
+    txid = await stacheProgram.methods.approveAction(vault.actions[0].actionIndex).accounts({
       stache: stachePda,
       keychain: userKeychainPda,
       vault: vaultPda,
       authority: key2.publicKey,
+      multisig: null,
       tokenProgram: TOKEN_PROGRAM_ID,
       associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
     }).remainingAccounts([
-        // withdrawal expects 2 more accounts: from vault ata, to token account
+        // withdrawal expects 3 more accounts: from vault ata, to token account, mint
         {pubkey: vaultAta, isWritable: true, isSigner: false},
         {pubkey: userAta, isWritable: true, isSigner: false},
+        {pubkey: mint.publicKey, isWritable: false, isSigner: false},
     ]).signers([key2]).rpc();
 
     console.log(`approved withdraw action, txid: ${txid}`);
@@ -481,6 +914,392 @@ describe("stache", () => {
 
   });
 
+  it('holds and releases sol in vaults', async () => {
+
+    const depositSol = (vault: PublicKey, lamports: number) => stacheProgram.methods.depositSolToVault(new anchor.BN(lamports)).accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      vault,
+      authority: provider.wallet.publicKey,
+      systemProgram: SystemProgram.programId,
+    }).rpc();
+
+    const withdrawSol = (vault: PublicKey, lamports: number) => stacheProgram.methods.withdrawSolFromVault(new anchor.BN(lamports)).accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      vault,
+      to: admin.publicKey,
+      authority: provider.wallet.publicKey,
+      multisig: null,
+    }).rpc();
+
+    // easy vault: straight in and out
+    await depositSol(easyVaultPda, LAMPORTS_PER_SOL);
+    let adminBalance = await connection.getBalance(admin.publicKey);
+    await withdrawSol(easyVaultPda, LAMPORTS_PER_SOL);
+    expect(await connection.getBalance(admin.publicKey)).to.equal(adminBalance + LAMPORTS_PER_SOL);
+
+    // can't dip into the vault's rent
+    try {
+      await withdrawSol(easyVaultPda, 1);
+      assert.fail("shouldn't be able to withdraw the vault's rent");
+    } catch (e) {
+      expect(e.error.errorCode.code).to.equal('InsufficientFunds');
+    }
+
+    // 2-sig vault: the withdraw becomes an action that the 2nd key approves
+    await depositSol(vaultPda, LAMPORTS_PER_SOL);
+
+    try {
+      await stacheProgram.methods.destroyVault().accounts({
+        stache: stachePda,
+        keychain: userKeychainPda,
+        vault: vaultPda,
+        authority: provider.wallet.publicKey,
+      }).rpc();
+      assert.fail("shouldn't be able to destroy a vault holding sol");
+    } catch (e) {
+      expect(e.error.errorCode.code).to.equal('VaultNotEmpty');
+    }
+
+    adminBalance = await connection.getBalance(admin.publicKey);
+    await withdrawSol(vaultPda, LAMPORTS_PER_SOL);
+    expect(await connection.getBalance(admin.publicKey)).to.equal(adminBalance);
+
+    let vault = await stacheProgram.account.vault.fetch(vaultPda);
+    expect(vault.actions.length).to.equal(1);
+
+    await stacheProgram.methods.approveAction(vault.actions[0].actionIndex).accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      vault: vaultPda,
+      authority: key2.publicKey,
+      multisig: null,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+    }).remainingAccounts([
+        // sol withdrawal expects 1 more account: where the lamports go
+        {pubkey: admin.publicKey, isWritable: true, isSigner: false},
+    ]).signers([key2]).rpc();
+
+    vault = await stacheProgram.account.vault.fetch(vaultPda);
+    expect(vault.actions.length).to.equal(0);
+    expect(await connection.getBalance(admin.publicKey)).to.equal(adminBalance + LAMPORTS_PER_SOL);
+  });
+
+  it('holds programmable nfts in the stache and vaults', async () => {
+
+    const nft = await createProgrammableNft(connection, admin, provider.wallet.publicKey);
+    const [metadata] = findMetadataPda(nft.publicKey);
+    const [edition] = findEditionPda(nft.publicKey);
+    const userNftAta = getAssociatedTokenAddressSync(nft.publicKey, provider.wallet.publicKey);
+    const stacheNftAta = getAssociatedTokenAddressSync(nft.publicKey, stachePda, true);
+    const vaultNftAta = getAssociatedTokenAddressSync(nft.publicKey, vaultPda, true);
+    const tokenRecord = (token: PublicKey) => findTokenRecordPda(nft.publicKey, token)[0];
+
+    const nftAccounts = {
+      mint: nft.publicKey,
+      metadata,
+      edition,
+      authorizationRules: null,
+      authorizationRulesProgram: null,
+      tokenMetadataProgram: TokenMetadataProgId,
+      sysvarInstructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+    };
+
+    // into the stache and back out
+    await stacheProgram.methods.stashNft().accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      vault: null,
+      owner: provider.wallet.publicKey,
+      fromToken: userNftAta,
+      destinationToken: stacheNftAta,
+      tokenRecord: tokenRecord(userNftAta),
+      destinationTokenRecord: tokenRecord(stacheNftAta),
+      ...nftAccounts,
+    }).rpc();
+    let balance = await connection.getTokenAccountBalance(stacheNftAta);
+    expect(balance.value.uiAmount).to.equal(1);
+
+    await stacheProgram.methods.unstashNft().accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      owner: provider.wallet.publicKey,
+      stacheToken: stacheNftAta,
+      destinationToken: userNftAta,
+      tokenRecord: tokenRecord(stacheNftAta),
+      destinationTokenRecord: tokenRecord(userNftAta),
+      ...nftAccounts,
+    }).rpc();
+    balance = await connection.getTokenAccountBalance(userNftAta);
+    expect(balance.value.uiAmount).to.equal(1);
+
+    // straight into the 2-sig vault: getting it out takes the 2nd key
+    await stacheProgram.methods.stashNft().accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      vault: vaultPda,
+      owner: provider.wallet.publicKey,
+      fromToken: userNftAta,
+      destinationToken: vaultNftAta,
+      tokenRecord: tokenRecord(userNftAta),
+      destinationTokenRecord: tokenRecord(vaultNftAta),
+      ...nftAccounts,
+    }).rpc();
+
+    await stacheProgram.methods.withdrawNftFromVault().accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      vault: vaultPda,
+      authority: provider.wallet.publicKey,
+      to: provider.wallet.publicKey,
+      vaultToken: vaultNftAta,
+      destinationToken: userNftAta,
+      multisig: null,
+      tokenRecord: tokenRecord(vaultNftAta),
+      destinationTokenRecord: tokenRecord(userNftAta),
+      ...nftAccounts,
+    }).rpc();
+
+    let vault = await stacheProgram.account.vault.fetch(vaultPda);
+    expect(vault.actions.length).to.equal(1);
+    balance = await connection.getTokenAccountBalance(vaultNftAta);
+    expect(balance.value.uiAmount).to.equal(1);
+
+    await stacheProgram.methods.approveAction(vault.actions[0].actionIndex).accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      vault: vaultPda,
+      authority: key2.publicKey,
+      multisig: null,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+    }).remainingAccounts([
+      // nft transfers go through metaplex, so they need everything metaplex needs (the token metadata program stands in for unused accounts)
+      {pubkey: vaultNftAta, isWritable: true, isSigner: false},
+      {pubkey: userNftAta, isWritable: true, isSigner: false},
+      {pubkey: provider.wallet.publicKey, isWritable: false, isSigner: false},
+      {pubkey: nft.publicKey, isWritable: false, isSigner: false},
+      {pubkey: metadata, isWritable: true, isSigner: false},
+      {pubkey: edition, isWritable: false, isSigner: false},
+      {pubkey: tokenRecord(vaultNftAta), isWritable: true, isSigner: false},
+      {pubkey: tokenRecord(userNftAta), isWritable: true, isSigner: false},
+      {pubkey: TokenMetadataProgId, isWritable: false, isSigner: false},
+      {pubkey: SystemProgram.programId, isWritable: false, isSigner: false},
+      {pubkey: SYSVAR_INSTRUCTIONS_PUBKEY, isWritable: false, isSigner: false},
+      {pubkey: ASSOCIATED_TOKEN_PROGRAM_ID, isWritable: false, isSigner: false},
+      {pubkey: TokenMetadataProgId, isWritable: false, isSigner: false},
+      {pubkey: TokenMetadataProgId, isWritable: false, isSigner: false},
+    ]).signers([key2]).rpc();
+
+    vault = await stacheProgram.account.vault.fetch(vaultPda);
+    expect(vault.actions.length).to.equal(0);
+    balance = await connection.getTokenAccountBalance(userNftAta);
+    expect(balance.value.uiAmount).to.equal(1);
+  });
+
+  it('locks and unlocks vaults', async () => {
+
+    const lock = (vault: PublicKey) => stacheProgram.methods.lockVault().accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      vault,
+      authority: provider.wallet.publicKey,
+    }).rpc();
+
+    const unlock = (vault: PublicKey, authority: Keypair) => stacheProgram.methods.unlockVault().accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      vault,
+      authority: authority.publicKey,
+      multisig: null,
+    }).signers([authority]).rpc();
+
+    // easy vault: the key that locked it can't unlock it
+    await lock(easyVaultPda);
+    try {
+      await stacheProgram.methods.unlockVault().accounts({
+        stache: stachePda,
+        keychain: userKeychainPda,
+        vault: easyVaultPda,
+        authority: provider.wallet.publicKey,
+        multisig: null,
+      }).rpc();
+      assert.fail("the locking key shouldn't be able to unlock an easy vault");
+    } catch (e) {
+      expect(e.error.errorCode.code).to.equal('NotAuthorized');
+    }
+
+    await unlock(easyVaultPda, key2);
+    let vault = await stacheProgram.account.vault.fetch(easyVaultPda);
+    expect(vault.locked).to.be.false;
+
+    // 2-sig vault: unlocking is a pending action that needs the 2nd sig
+    await lock(vaultPda);
+    await unlock(vaultPda, key2);
+    vault = await stacheProgram.account.vault.fetch(vaultPda);
+    expect(vault.locked).to.be.true;
+    expect(vault.actions.length).to.equal(1);
+
+    await stacheProgram.methods.approveAction(vault.actions[0].actionIndex).accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      vault: vaultPda,
+      authority: provider.wallet.publicKey,
+      multisig: null,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+    }).rpc();
+
+    vault = await stacheProgram.account.vault.fetch(vaultPda);
+    expect(vault.locked).to.be.false;
+    expect(vault.actions.length).to.equal(0);
+  });
+
+  it('withdraws tokens from a squads vault', async () => {
+
+    let withdrawAmount = 5*1e9;
+    let txid = await stacheProgram.methods.withdrawFromVault(new anchor.BN(withdrawAmount)).accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      vault: squadsVaultPda,
+      authority: provider.wallet.publicKey,
+      vaultAta: squadsVaultAta,
+      mint: mint.publicKey,
+      toToken: userAta,
+      multisig: squadsMultisigPda,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+    }).rpc();
+
+    // the proposer isn't a member of the multisig, so this is just a pending action
+    let vault = await stacheProgram.account.vault.fetch(squadsVaultPda);
+    expect(vault.actions.length).to.equal(1);
+    let actionIndex = vault.actions[0].actionIndex;
+
+    const approve = (approver: Keypair) => stacheProgram.methods.approveAction(actionIndex).accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      vault: squadsVaultPda,
+      authority: approver.publicKey,
+      multisig: squadsMultisigPda,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+    }).remainingAccounts([
+      {pubkey: squadsVaultAta, isWritable: true, isSigner: false},
+      {pubkey: userAta, isWritable: true, isSigner: false},
+      {pubkey: mint.publicKey, isWritable: false, isSigner: false},
+    ]).signers([approver]);
+
+    // 1st member approval - still short of the 2 sigs
+    txid = await approve(key2).rpc();
+    console.log(`1st multisig member approved squads withdrawal, txid: ${txid}`);
+    let vaultAtaInfo = await connection.getAccountInfo(squadsVaultAta);
+    expect(vaultAtaInfo).to.exist;
+
+    // 2nd member approval executes the withdrawal (and closes the emptied vault ata)
+    txid = await approve(admin).rpc();
+    console.log(`2nd multisig member approved squads withdrawal, txid: ${txid}`);
+    vaultAtaInfo = await connection.getAccountInfo(squadsVaultAta);
+    expect(vaultAtaInfo).to.be.null;
+
+    vault = await stacheProgram.account.vault.fetch(squadsVaultPda);
+    expect(vault.actions.length).to.equal(0);
+
+    // done with it, free up the vault slot
+    await stacheProgram.methods.destroyVault().accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      vault: squadsVaultPda,
+      authority: provider.wallet.publicKey,
+    }).rpc();
+  });
+
+  it('withdraws tokens from a 2 of 3 threshold vault', async () => {
+
+    let stache = await stacheProgram.account.currentStache.fetch(stachePda);
+    let [thresholdVaultPda] = findVaultPda(stache.nextVaultIndex, username, domainPda, stacheProgram.programId);
+    let thresholdVaultAta = getAssociatedTokenAddressSync(mint.publicKey, thresholdVaultPda, true);
+
+    // the user submits, and any 2 of user/key2/admin approve (submitting counts as the user's approval)
+    let txid = await stacheProgram.methods.createVault('treasury', {threshold: {
+        approvers: [provider.wallet.publicKey, key2.publicKey, admin.publicKey].map(key => ({key, enabled: true})),
+        submitters: [{key: provider.wallet.publicKey, enabled: true}],
+        threshold: 2,
+      }}).accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      vault: thresholdVaultPda,
+      authority: provider.wallet.publicKey,
+      multisig: null,
+      systemProgram: SystemProgram.programId,
+    }).rpc();
+
+    console.log(`created 2 of 3 threshold vault >>>> ${thresholdVaultPda} <<<< in tx: ${txid}`);
+
+    let tx = new Transaction().add(
+        createAssociatedTokenAccountInstruction(provider.wallet.publicKey, thresholdVaultAta, thresholdVaultPda, mint.publicKey),
+        createTransferCheckedInstruction(userAta, mint.publicKey, thresholdVaultAta, provider.wallet.publicKey, 5 * 1e9, 9)
+    );
+    await provider.sendAndConfirm(tx);
+
+    const withdraw = (submitter: PublicKey) => stacheProgram.methods.withdrawFromVault(new anchor.BN(5 * 1e9)).accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      vault: thresholdVaultPda,
+      authority: submitter,
+      vaultAta: thresholdVaultAta,
+      mint: mint.publicKey,
+      toToken: userAta,
+      multisig: null,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+    });
+
+    // admin is an approver but not a submitter
+    try {
+      await withdraw(admin.publicKey).signers([admin]).rpc();
+      assert.fail("non-submitter shouldn't be able to propose a withdrawal");
+    } catch (e) {
+      expect(e.error.errorCode.code).to.equal('NotAuthorized');
+    }
+
+    txid = await withdraw(provider.wallet.publicKey).rpc();
+    let vault = await stacheProgram.account.vault.fetch(thresholdVaultPda);
+    expect(vault.actions.length).to.equal(1);
+
+    // 2nd approval hits the threshold and executes
+    txid = await stacheProgram.methods.approveAction(vault.actions[0].actionIndex).accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      vault: thresholdVaultPda,
+      authority: admin.publicKey,
+      multisig: null,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+    }).remainingAccounts([
+      {pubkey: thresholdVaultAta, isWritable: true, isSigner: false},
+      {pubkey: userAta, isWritable: true, isSigner: false},
+      {pubkey: mint.publicKey, isWritable: false, isSigner: false},
+    ]).signers([admin]).rpc();
+
+    console.log(`approved threshold vault withdrawal, txid: ${txid}`);
+    let vaultAtaInfo = await connection.getAccountInfo(thresholdVaultAta);
+    expect(vaultAtaInfo).to.be.null;
+
+    // done with it, free up the vault slot
+    await stacheProgram.methods.destroyVault().accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      vault: thresholdVaultPda,
+      authority: provider.wallet.publicKey,
+    }).rpc();
+  });
+
   it('creates and fires an automation', async () => {
 
     // first vault index = 1
@@ -516,8 +1335,7 @@ describe("stache", () => {
     // transfer 5 tokens to the vault
     let transferAmount = new anchor.BN(5*1e9);
 
-    // set the action
-    txid = await stacheProgram.methods.setAutoAction(transferAmount).accounts({
+    const actionAccounts = {
       stache: stachePda,
       keychain: userKeychainPda,
       auto: autoPda,
@@ -525,73 +1343,257 @@ describe("stache", () => {
       fromToken: stacheMintAta,
       toToken: easyVaultAta,
       mint: mint.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
       associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      vault: null,
+    };
+
+    // percentages are in basis points, so can't go over 10000
+    try {
+      await stacheProgram.methods.addAutoAction(new anchor.BN(20000), {percent: {}}).accounts(actionAccounts).rpc();
+      assert.fail("shouldn't be able to transfer more than 100%");
+    } catch (e) {
+      expect(e.error.errorCode.code).to.equal('InvalidAction');
+    }
+
+    // actions run in order, all or nothing: 5 tokens to the vault, then 10% of what's left back to the user
+    await stacheProgram.methods.addAutoAction(transferAmount, {fixed: {}}).accounts(actionAccounts).rpc();
+    await stacheProgram.methods.addAutoAction(new anchor.BN(1000), {percent: {}}).accounts({
+      ...actionAccounts,
+      toToken: userAta,
     }).rpc();
+    let auto = await stacheProgram.account.auto.fetch(autoPda);
+    expect(auto.actions.length).to.equal(2);
+    expect(auto.actions[1].to.toBase58()).to.equal(userAta.toBase58());
 
-    console.log(`set automation action to transfer ${transferAmount.toString()} from ${stacheMintAta.toString()} to ${easyVaultAta.toString()} in tx: ${txid}`);
-
-    let [threadPda, threadBump] = findThreadPda(autoName, autoPda);
-
-    // now activate the automation - needs to be on devnet for automation
-
-    /*
-    await stacheProgram.methods.activateAuto().accounts({
+    // start over w/just the transfer to the vault
+    await stacheProgram.methods.clearAutoActions().accounts({
       stache: stachePda,
       keychain: userKeychainPda,
       auto: autoPda,
       authority: provider.wallet.publicKey,
-      // these are needed
-      thread: null,
-      clockwork: null,
-      systemProgram: null,
     }).rpc();
+    txid = await stacheProgram.methods.addAutoAction(transferAmount, {fixed: {}}).accounts(actionAccounts).rpc();
+    auto = await stacheProgram.account.auto.fetch(autoPda);
+    expect(auto.actions.length).to.equal(1);
+
+    console.log(`set automation action to transfer ${transferAmount.toString()} from ${stacheMintAta.toString()} to ${easyVaultAta.toString()} in tx: ${txid}`);
+
+    // activating needs clockwork (devnet), but the automation can be dry run + fired by hand w/out it.
+    // remaining accounts: the trigger account, then each action's from, to, mint, token program
+    const fireAccounts = [
+      {pubkey: stacheMintAta, isWritable: false, isSigner: false},
+      {pubkey: stacheMintAta, isWritable: true, isSigner: false},
+      {pubkey: easyVaultAta, isWritable: true, isSigner: false},
+      {pubkey: mint.publicKey, isWritable: false, isSigner: false},
+      {pubkey: TOKEN_PROGRAM_ID, isWritable: false, isSigner: false},
+    ];
+    const dryRun = async () => {
+      const result = await stacheProgram.methods.dryRunAuto().accounts({
+        stache: stachePda,
+        keychain: userKeychainPda,
+        auto: autoPda,
+        authority: provider.wallet.publicKey,
+      }).remainingAccounts(fireAccounts).simulate();
+      return result.events.find((e) => e.name === 'AutoDryRun').data;
+    };
 
-    console.log(`activated automation ${autoName} for ${username} >>>> ${autoPda} <<<< in tx: ${txid}`);
+    // balance isn't above the trigger yet
+    let dryRunResult = await dryRun();
+    expect(dryRunResult.outcome).to.deep.equal({wouldNotExecute: {}});
 
-    // now transfer some tokens to the vault
+    // now transfer some tokens to the stache
     let tx = new Transaction().add(
         createTransferCheckedInstruction(userAta, mint.publicKey, stacheMintAta, provider.wallet.publicKey, 15 * 1e9, 9)
     );
-    txid = await provider.sendAndConfirm(tx)
+    await provider.sendAndConfirm(tx);
+
+    dryRunResult = await dryRun();
+    expect(dryRunResult.outcome).to.deep.equal({wouldExecute: {}});
+    expect(dryRunResult.amounts[0].toString()).to.equal(transferAmount.toString());
 
     stacheMintTokenBalance = await connection.getTokenAccountBalance(stacheMintAta);
-    console.log(`new stache mint token balance after transfer in: ${stacheMintTokenBalance.value.uiAmount}`);
     let easyVaultMintBalance = await connection.getTokenAccountBalance(easyVaultAta);
-    console.log(`easy vault token balance after transfer in (before automation fire): ${easyVaultMintBalance.value.uiAmount}`);
+    console.log(`easy vault token balance before automation fire: ${easyVaultMintBalance.value.uiAmount}`);
+
+    // fire the automation manually (no thread)
+    txid = await stacheProgram.methods.fireAuto().accounts({
+      stache: stachePda,
+      auto: autoPda,
+      thread: null,
+      keychain: userKeychainPda,
+      authority: provider.wallet.publicKey,
+    }).remainingAccounts(fireAccounts).rpc();
+
+    console.log(`fired automation ${autoName} for ${username} >>>> ${autoPda} <<<< in tx: ${txid}`);
+
+    let newStacheMintTokenBalance = await connection.getTokenAccountBalance(stacheMintAta);
+    let newEasyVaultMintBalance = await connection.getTokenAccountBalance(easyVaultAta);
+    expect(newStacheMintTokenBalance.value.uiAmount).to.equal(stacheMintTokenBalance.value.uiAmount - 5);
+    expect(newEasyVaultMintBalance.value.uiAmount).to.equal(easyVaultMintBalance.value.uiAmount + 5);
+
+    auto = await stacheProgram.account.auto.fetch(autoPda);
+    expect(auto.numExecs).to.equal(1);
+
+    // the fire + execution show up as events
+    const fireTx = await connection.getTransaction(txid, {commitment: 'confirmed', maxSupportedTransactionVersion: 0});
+    const eventParser = new anchor.EventParser(stacheProgram.programId, stacheProgram.coder);
+    const events = [...eventParser.parseLogs(fireTx.meta.logMessages)];
+    expect(events.find((e) => e.name === 'AutoFired').data.triggerMet).to.be.true;
+    const executed = events.find((e) => e.name === 'AutoExecuted').data;
+    expect(executed.numExecs).to.equal(1);
+    expect(executed.amounts[0].toString()).to.equal(transferAmount.toString());
+
+    // only keys on the keychain can fire it by hand
+    const outsider = Keypair.generate();
+    try {
+      await stacheProgram.methods.fireAuto().accounts({
+        stache: stachePda,
+        auto: autoPda,
+        thread: null,
+        keychain: userKeychainPda,
+        authority: outsider.publicKey,
+      }).remainingAccounts(fireAccounts).signers([outsider]).rpc();
+      assert.fail("shouldn't be able to fire someone else's automation");
+    } catch (e) {
+      expect(e.error.errorCode.code).to.equal('NotAuthorized');
+    }
+  });
+
+
+  it('sets time-based automation triggers', async () => {
+
+    const [cronAutoPda] = findAutoPda(2, username, domainPda, stacheProgram.programId);
+    await stacheProgram.methods.createAuto(randomName()).accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      auto: cronAutoPda,
+      authority: provider.wallet.publicKey,
+      systemProgram: SystemProgram.programId,
+    }).rpc();
+
+    const triggerAccounts = {
+      stache: stachePda,
+      keychain: userKeychainPda,
+      auto: cronAutoPda,
+      authority: provider.wallet.publicKey,
+      token: null,
+    };
+
+    try {
+      await stacheProgram.methods.setAutoCronTrigger("every 1st of the month").accounts(triggerAccounts).rpc();
+      assert.fail("shouldn't be able to set an invalid schedule");
+    } catch (e) {
+      expect(e.error.errorCode.code).to.equal('InvalidSchedule');
+    }
+
+    // noon on the 1st of every month
+    await stacheProgram.methods.setAutoCronTrigger("0 0 12 1 * * *").accounts(triggerAccounts).rpc();
+    let auto = await stacheProgram.account.auto.fetch(cronAutoPda);
+    expect(auto.triggerType).to.deep.equal({cron: {}});
+
+    // or once a day, starting now
+    const start = new anchor.BN(Math.floor(Date.now() / 1000));
+    await stacheProgram.methods.setAutoIntervalTrigger(new anchor.BN(86400), start).accounts(triggerAccounts).rpc();
+    auto = await stacheProgram.account.auto.fetch(cronAutoPda);
+    expect(auto.triggerType).to.deep.equal({interval: {}});
+
+    try {
+      await stacheProgram.methods.setAutoIntervalTrigger(new anchor.BN(0), start).accounts(triggerAccounts).rpc();
+      assert.fail("shouldn't be able to set an empty interval");
+    } catch (e) {
+      expect(e.error.errorCode.code).to.equal('InvalidSchedule');
+    }
+  });
+
+  it('limits automations', async () => {
 
-    // fire the automation manually
-    txid = await stacheProgram.methods.fireAuto(true, true).accounts({
+    const limitAccounts = {
       stache: stachePda,
       keychain: userKeychainPda,
       auto: autoPda,
       authority: provider.wallet.publicKey,
-      fromToken: stacheMintAta,
-      toToken: easyVaultAta,
+    };
+
+    try {
+      await stacheProgram.methods.setAutoLimits(0, null, null).accounts(limitAccounts).rpc();
+      assert.fail("shouldn't be able to cap an automation at 0 executions");
+    } catch (e) {
+      expect(e.error.errorCode.code).to.equal('InvalidAutomationLimit');
+    }
+
+    // at most 10 executions / 500 tokens, for the next 30 days
+    const expiresAt = new anchor.BN(Math.floor(Date.now() / 1000) + 30 * 86400);
+    await stacheProgram.methods.setAutoLimits(10, new anchor.BN(500 * 1e9), expiresAt).accounts(limitAccounts).rpc();
+
+    let auto = await stacheProgram.account.auto.fetch(autoPda);
+    expect(auto.maxExecs).to.equal(10);
+    expect(auto.maxTotalAmount.toString()).to.equal(new anchor.BN(500 * 1e9).toString());
+    expect(auto.expiresAt.toString()).to.equal(expiresAt.toString());
+    expect(auto.totalAmount.toNumber()).to.equal(0);
+  });
+
+  it('authorizes automations on vaults', async () => {
+
+    // the time-based automation: sweep anything above 1 token out of the 2-sig vault back to the user
+    const [vaultAutoPda] = findAutoPda(2, username, domainPda, stacheProgram.programId);
+    await provider.sendAndConfirm(new Transaction().add(
+        createAssociatedTokenAccountIdempotentInstruction(provider.wallet.publicKey, vaultAta, vaultPda, mint.publicKey)
+    ));
+
+    await stacheProgram.methods.addAutoAction(new anchor.BN(1e9), {sweepAbove: {}}).accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      auto: vaultAutoPda,
+      authority: provider.wallet.publicKey,
+      fromToken: vaultAta,
+      toToken: userAta,
+      mint: mint.publicKey,
       tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      vault: vaultPda,
+    }).rpc();
 
-      // not needed since the automation flag is false
-      thread: null,
+    // a 2-sig vault needs the 2nd key to sign off on it
+    await stacheProgram.methods.authorizeVaultAuto(0).accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      vault: vaultPda,
+      auto: vaultAutoPda,
+      authority: provider.wallet.publicKey,
+      multisig: null,
     }).rpc();
-     */
 
-    // if the balance balance trigger account is NOT a to/from, then we pass it in this way, but since in this
-    // case it is, we don't want to pass it in twice, so we set the use_ref and use_from params (todo: cleaner way later)
+    let vault = await stacheProgram.account.vault.fetch(vaultPda);
+    expect(vault.authorizedAutos.length).to.equal(0);
+    expect(vault.actions.length).to.equal(1);
 
-       /*
-        .remainingAccounts([
-        // this is the account whose balance we're checking (specified in the balance trigger)
-      {pubkey: stacheMintAta, isWritable: false, isSigner: false},
-    ]).rpc();
+    await stacheProgram.methods.approveAction(vault.actions[0].actionIndex).accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      vault: vaultPda,
+      authority: key2.publicKey,
+      multisig: null,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+    }).signers([key2]).rpc();
 
+    vault = await stacheProgram.account.vault.fetch(vaultPda);
+    expect(vault.actions.length).to.equal(0);
+    expect(vault.authorizedAutos.length).to.equal(1);
+    expect(vault.authorizedAutos[0].auto.toBase58()).to.equal(vaultAutoPda.toBase58());
 
-    console.log(`fired automation ${autoName} for ${username} >>>> ${autoPda} <<<< in tx: ${txid}`);
-    stacheMintTokenBalance = await connection.getTokenAccountBalance(stacheMintAta);
-    console.log(`new stache mint token balance after automation fire: ${stacheMintTokenBalance.value.uiAmount}`);
-    easyVaultMintBalance = await connection.getTokenAccountBalance(easyVaultAta);
-    console.log(`easy vault token balance after automation fire: ${easyVaultMintBalance.value.uiAmount}`);
-        */
-  });
+    // and revoking it is instant
+    await stacheProgram.methods.revokeVaultAuto(vaultAutoPda).accounts({
+      stache: stachePda,
+      keychain: userKeychainPda,
+      vault: vaultPda,
+      authority: provider.wallet.publicKey,
+    }).rpc();
 
+    vault = await stacheProgram.account.vault.fetch(vaultPda);
+    expect(vault.authorizedAutos.length).to.equal(0);
+  });
 
   it('destroys a vault', async () => {
 
@@ -621,18 +1623,61 @@ describe("stache", () => {
 
 
   it("destroys a stache", async () => {
-    let tx = await stacheProgram.methods.destroyStache().accounts({
+
+    const destroyStache = (cosigner: Keypair) => stacheProgram.methods.destroyStache(true).accounts({
       stache: stachePda,
       keychain: userKeychainPda,
       authority: provider.wallet.publicKey,
+      cosigner: cosigner.publicKey,
+      destination: provider.wallet.publicKey,
+      tokenProgram: TOKEN_PROGRAM_ID,
       keychainProgram: keychainProgram.programId,
       systemProgram: SystemProgram.programId,
-    }).rpc();
+    }).remainingAccounts([
+      // sweep the stache's tokens back to the user: (stache ata, destination token account, mint)
+      {pubkey: stacheMintAta, isWritable: true, isSigner: false},
+      {pubkey: userAta, isWritable: true, isSigner: false},
+      {pubkey: mint.publicKey, isWritable: false, isSigner: false},
+    ]).signers([cosigner]);
+
+    // can't destroy while there are still vaults/automations
+    try {
+      await destroyStache(key2).rpc();
+      assert.fail("shouldn't be able to destroy a stache that still has vaults");
+    } catch (e) {
+      expect(e.error.errorCode.code).to.equal('StacheNotEmpty');
+    }
+
+    let stache = await stacheProgram.account.currentStache.fetch(stachePda);
+    for (const vaultIndex of stache.vaults) {
+      let [pda] = findVaultPda(vaultIndex, username, domainPda, stacheProgram.programId);
+      await stacheProgram.methods.destroyVault().accounts({
+        stache: stachePda,
+        keychain: userKeychainPda,
+        vault: pda,
+        authority: provider.wallet.publicKey,
+      }).rpc();
+    }
+    for (const autoIndex of stache.autos) {
+      let [pda] = findAutoPda(autoIndex, username, domainPda, stacheProgram.programId);
+      await stacheProgram.methods.destroyAuto().accounts({
+        stache: stachePda,
+        keychain: userKeychainPda,
+        auto: pda,
+        authority: provider.wallet.publicKey,
+        thread: null,
+        clockworkProgram: null,
+      }).rpc();
+    }
+
+    let tx = await destroyStache(key2).rpc();
 
     console.log(`destroyed stache for ${username} in tx: ${tx}`);
 
-    let stache = await stacheProgram.account.currentStache.fetchNullable(stachePda);
+    stache = await stacheProgram.account.currentStache.fetchNullable(stachePda);
     expect(stache).to.be.null;
+    let stacheAtaInfo = await connection.getAccountInfo(stacheMintAta);
+    expect(stacheAtaInfo).to.be.null;
   });
 
 });
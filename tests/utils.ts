@@ -1,12 +1,32 @@
-import {Connection, Keypair, PublicKey, sendAndConfirmTransaction, SystemProgram, Transaction} from "@solana/web3.js";
-import * as anchor from "@project-serum/anchor";
 import {
+  Connection,
+  Keypair,
+  PublicKey,
+  sendAndConfirmTransaction,
+  SystemProgram,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
+  Transaction
+} from "@solana/web3.js";
+import * as anchor from "@coral-xyz/anchor";
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
   createInitializeMint2Instruction,
+  createInitializeTransferFeeConfigInstruction,
+  ExtensionType,
   getMinimumBalanceForRentExemptMint,
+  getMintLen,
   MINT_SIZE,
+  TOKEN_2022_PROGRAM_ID,
+  getAssociatedTokenAddressSync,
   TOKEN_PROGRAM_ID
 } from "@solana/spl-token";
-import {Program} from "@project-serum/anchor";
+import {
+  createCreateInstruction,
+  createMintInstruction,
+  PROGRAM_ID as TokenMetadataProgId,
+  TokenStandard,
+} from "@metaplex-foundation/mpl-token-metadata";
+import {Program} from "@coral-xyz/anchor";
 
 export const DOMAIN = 'domination';
 export const KEYCHAIN = 'keychain';
@@ -21,6 +41,10 @@ export const STACHE = 'stache';
 export const BEARD_SPACE = 'beards';
 export const VAULT_SPACE = 'vaults';
 export const AUTOMATIONS_SPACE = 'automations';
+export const UNWRAP = 'unwrap';
+
+// squads v3 (the squads-mock stand-in gets loaded here for local testing)
+export const SquadsProgId = new PublicKey('SMPLecH534NA9acpos4G6x7uf3LWbCAwZQE9e8ZekMu');
 
 // devnet v2
 export const ThreadProgId = new PublicKey('CLoCKyJ6DXBJqqu2VWx9RLbgnwwR6BMHHuyasVmfMzBh');
@@ -67,6 +91,117 @@ export async function createNFTMint(connection: Connection, payer: Keypair, auth
   return mintKey;
 }
 
+// token-2022 mint w/a transfer fee (in basis points)
+export async function createTransferFeeMint(connection: Connection, payer: Keypair, authority: PublicKey, feeBasisPoints: number): Promise<Keypair> {
+
+  const mintLen = getMintLen([ExtensionType.TransferFeeConfig]);
+  const lamports = await connection.getMinimumBalanceForRentExemption(mintLen);
+  const mintKey = anchor.web3.Keypair.generate();
+
+  const transaction = new Transaction().add(
+      SystemProgram.createAccount({
+        fromPubkey: payer.publicKey,
+        newAccountPubkey: mintKey.publicKey,
+        space: mintLen,
+        lamports,
+        programId: TOKEN_2022_PROGRAM_ID,
+      }),
+      createInitializeTransferFeeConfigInstruction(mintKey.publicKey, authority, authority, feeBasisPoints, BigInt(1e18), TOKEN_2022_PROGRAM_ID),
+      createInitializeMint2Instruction(mintKey.publicKey, 9, authority, authority, TOKEN_2022_PROGRAM_ID),
+  );
+
+  await sendAndConfirmTransaction(connection, transaction, [payer, mintKey]);
+  return mintKey;
+}
+
+///// metaplex
+
+export {TokenMetadataProgId};
+
+export const findMetadataPda = (mint: PublicKey): [PublicKey, number] => {
+  return anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from('metadata'), TokenMetadataProgId.toBuffer(), mint.toBuffer()],
+      TokenMetadataProgId,
+  );
+};
+
+export const findEditionPda = (mint: PublicKey): [PublicKey, number] => {
+  return anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from('metadata'), TokenMetadataProgId.toBuffer(), mint.toBuffer(), Buffer.from('edition')],
+      TokenMetadataProgId,
+  );
+};
+
+export const findTokenRecordPda = (mint: PublicKey, token: PublicKey): [PublicKey, number] => {
+  return anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from('metadata'), TokenMetadataProgId.toBuffer(), mint.toBuffer(), Buffer.from('token_record'), token.toBuffer()],
+      TokenMetadataProgId,
+  );
+};
+
+// creates a programmable nft and mints it to the owner's ata
+export async function createProgrammableNft(connection: Connection, payer: Keypair, owner: PublicKey): Promise<Keypair> {
+  const mintKey = anchor.web3.Keypair.generate();
+  const [metadata] = findMetadataPda(mintKey.publicKey);
+  const [masterEdition] = findEditionPda(mintKey.publicKey);
+  const token = getAssociatedTokenAddressSync(mintKey.publicKey, owner);
+  const [tokenRecord] = findTokenRecordPda(mintKey.publicKey, token);
+
+  const createIx = createCreateInstruction({
+    metadata,
+    masterEdition,
+    mint: mintKey.publicKey,
+    authority: payer.publicKey,
+    payer: payer.publicKey,
+    updateAuthority: payer.publicKey,
+    systemProgram: SystemProgram.programId,
+    sysvarInstructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+    splTokenProgram: TOKEN_PROGRAM_ID,
+  }, {
+    createArgs: {
+      __kind: 'V1',
+      assetData: {
+        name: 'stache pnft',
+        symbol: '',
+        uri: '',
+        sellerFeeBasisPoints: 0,
+        creators: null,
+        primarySaleHappened: false,
+        isMutable: true,
+        tokenStandard: TokenStandard.ProgrammableNonFungible,
+        collection: null,
+        uses: null,
+        collectionDetails: null,
+        ruleSet: null,
+      },
+      decimals: 0,
+      printSupply: {__kind: 'Zero'},
+    },
+  });
+  // the mint gets created in this ix, so it has to sign
+  createIx.keys.find((key) => key.pubkey.equals(mintKey.publicKey)).isSigner = true;
+
+  const mintIx = createMintInstruction({
+    token,
+    tokenOwner: owner,
+    metadata,
+    masterEdition,
+    tokenRecord,
+    mint: mintKey.publicKey,
+    authority: payer.publicKey,
+    payer: payer.publicKey,
+    systemProgram: SystemProgram.programId,
+    sysvarInstructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+    splTokenProgram: TOKEN_PROGRAM_ID,
+    splAtaProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+  }, {
+    mintArgs: {__kind: 'V1', amount: 1, authorizationData: null},
+  });
+
+  await sendAndConfirmTransaction(connection, new Transaction().add(createIx, mintIx), [payer, mintKey]);
+  return mintKey;
+}
+
 export const findDomainPda = (domain: string, keychainprogid: PublicKey): [PublicKey, number] => {
   return anchor.web3.PublicKey.findProgramAddressSync(
       [Buffer.from(anchor.utils.bytes.utf8.encode(domain)),
@@ -167,6 +302,17 @@ export const findAutoPda = (autoIndex: number, stacheid: string, domainPda: Publ
   );
 };
 
+// temporary wsol account used when partially unwrapping sol out of a stache
+export const findUnwrapPda = (stachePda: PublicKey, stacheprogid: PublicKey): [PublicKey, number] => {
+  return anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from(anchor.utils.bytes.utf8.encode(UNWRAP)),
+        stachePda.toBuffer(),
+      ],
+      stacheprogid,
+  );
+};
+
 // get the thread pda for the given thread id and authority (program that will get executed/owns the thread)
 export const findThreadPda = (id: string, threadAuthority: PublicKey): [PublicKey, number] => {
   return anchor.web3.PublicKey.findProgramAddressSync(
@@ -179,3 +325,15 @@ export const findThreadPda = (id: string, threadAuthority: PublicKey): [PublicKe
   );
 
 }
+
+// squads multisig pda: seeds = [b"squad", create_key, b"multisig"]
+export const findSquadsMultisigPda = (createKey: PublicKey): [PublicKey, number] => {
+  return anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from(anchor.utils.bytes.utf8.encode('squad')),
+        createKey.toBuffer(),
+        Buffer.from(anchor.utils.bytes.utf8.encode('multisig')),
+      ],
+      SquadsProgId,
+  );
+}